@@ -0,0 +1,60 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use ut325f_rs::{FrameDecoder, Reading};
+
+fn frame() -> [u8; Reading::N_BYTES] {
+    let mut frame = [0u8; Reading::N_BYTES];
+    frame[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+    frame[5..9].copy_from_slice(&26.5f32.to_le_bytes());
+    let sum = frame[..Reading::N_BYTES - 2]
+        .iter()
+        .fold(0u16, |sum, &b| sum.wrapping_add(u16::from(b)));
+    frame[Reading::N_BYTES - 2..].copy_from_slice(&sum.to_be_bytes());
+    frame
+}
+
+/// About 1 MiB of frames, every tenth preceded by a few bytes of noise.
+fn capture() -> Vec<u8> {
+    let frame = frame();
+    let mut bytes = Vec::new();
+    for i in 0..(1 << 20) / Reading::N_BYTES {
+        if i % 10 == 0 {
+            bytes.extend_from_slice(&[0x00, 0xaa, 0x13]);
+        }
+        bytes.extend_from_slice(&frame);
+    }
+    bytes
+}
+
+fn decode(bytes: &[u8], chunk_size: usize) -> usize {
+    let mut decoder = FrameDecoder::new();
+    let mut frames = 0;
+    for chunk in bytes.chunks(chunk_size) {
+        decoder.push(chunk);
+        while let Some(frame) = decoder.next_frame() {
+            black_box(Reading::parse(&frame).unwrap());
+            frames += 1;
+        }
+    }
+    frames
+}
+
+fn benches(c: &mut Criterion) {
+    let frame = frame();
+    c.bench_function("parse", |b| {
+        b.iter(|| Reading::parse(black_box(&frame)).unwrap())
+    });
+
+    let bytes = capture();
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    // Live transports deliver small chunks; offline decoding pushes
+    // whole files.
+    group.bench_function("20 byte chunks", |b| b.iter(|| decode(&bytes, 20)));
+    group.bench_function("whole capture", |b| b.iter(|| decode(&bytes, bytes.len())));
+    group.finish();
+}
+
+criterion_group!(decode_benches, benches);
+criterion_main!(decode_benches);
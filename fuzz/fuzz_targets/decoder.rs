@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ut325f_rs::{FrameDecoder, Reading};
+
+// The first byte picks a chunk size, so frames are also exercised when
+// split across pushes.
+fuzz_target!(|data: &[u8]| {
+    let Some((&chunk_size, bytes)) = data.split_first() else {
+        return;
+    };
+    let mut decoder = FrameDecoder::new();
+    for chunk in bytes.chunks(usize::from(chunk_size).max(1)) {
+        decoder.push(chunk);
+        while let Some(frame) = decoder.next_frame() {
+            assert!(Reading::validate_frame(&frame));
+            Reading::parse(&frame).expect("validated frame must parse");
+        }
+    }
+});
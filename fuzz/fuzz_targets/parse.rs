@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ut325f_rs::Reading;
+
+fuzz_target!(|frame: [u8; Reading::N_BYTES]| {
+    let _ = Reading::parse(&frame);
+    let _ = Reading::parse_tolerant(&frame);
+});
@@ -0,0 +1,471 @@
+use alloc::vec::Vec;
+
+use crate::error::{Error, FrameError, Result};
+use crate::reading::Reading;
+
+/// Incremental decoder that reassembles the meter's fixed-size frames
+/// from an arbitrarily chunked byte stream.
+///
+/// Transports deliver bytes with no alignment guarantees; the decoder
+/// scans for the sync header and yields only frames whose checksum
+/// validates. A corrupted or truncated frame is skipped one byte at a
+/// time, so a genuine frame embedded after a false or damaged sync is
+/// still found.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    sync: [u8; Reading::N_SYNC_BYTES],
+    /// Bytes of `buf` before this have been consumed. Advancing an
+    /// offset instead of draining keeps decoding a large push linear;
+    /// the consumed prefix is dropped on the next push.
+    start: usize,
+    discarded_bytes: u64,
+    rejected_frames: u64,
+    /// The discarded bytes themselves, if kept.
+    discarded: Option<Vec<u8>>,
+    /// The last candidate [`next_candidate`](Self::next_candidate)
+    /// rejected.
+    rejected: Option<[u8; Reading::N_BYTES]>,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            sync: Reading::SYNC,
+            start: 0,
+            discarded_bytes: 0,
+            rejected_frames: 0,
+            discarded: None,
+            rejected: None,
+        }
+    }
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks for frames starting with `sync` instead of
+    /// [`Reading::SYNC`], for firmware that sends another header (e.g.
+    /// a different command byte). Parse its frames with the matching
+    /// `_with_sync` functions of [`Reading`].
+    pub fn with_sync(mut self, sync: [u8; Reading::N_SYNC_BYTES]) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// The header frames are expected to start with.
+    pub fn sync(&self) -> &[u8; Reading::N_SYNC_BYTES] {
+        &self.sync
+    }
+
+    /// Keeps the bytes the decoder discards, for inspection with
+    /// [`take_discarded`](Self::take_discarded).
+    pub fn with_discarded_kept(mut self) -> Self {
+        self.discarded = Some(Vec::new());
+        self
+    }
+
+    /// Returns the bytes discarded since the last call; always empty
+    /// unless the decoder was built
+    /// [`with_discarded_kept`](Self::with_discarded_kept).
+    pub fn take_discarded(&mut self) -> Vec<u8> {
+        self.discarded
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Appends received bytes to the decoder.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.drain(..self.start);
+        self.start = 0;
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Returns the next validated frame, discarding any bytes that do
+    /// not begin one. Returns `None` until a full valid frame is
+    /// buffered.
+    pub fn next_frame(&mut self) -> Option<[u8; Reading::N_BYTES]> {
+        loop {
+            match self.next_candidate()? {
+                Ok(frame) => return Some(frame),
+                Err(_) => continue,
+            }
+        }
+    }
+
+    /// Like [`next_frame`](Self::next_frame), but returns the error for
+    /// each rejected candidate (e.g. a checksum mismatch) instead of
+    /// skipping it silently. Decoding carries on after an error.
+    pub fn next_candidate(&mut self) -> Option<Result<[u8; Reading::N_BYTES]>> {
+        Some(self.next_checked()?.map_err(|e| e.error))
+    }
+
+    /// Like [`next_candidate`](Self::next_candidate), with the rejected
+    /// candidate in the error.
+    pub(crate) fn next_checked(
+        &mut self,
+    ) -> Option<core::result::Result<[u8; Reading::N_BYTES], FrameError>> {
+        let candidate = self.next_synced()?;
+        if Reading::validate_frame_with_sync(&candidate, &self.sync) {
+            self.consume(Reading::N_BYTES);
+            return Some(Ok(candidate));
+        }
+        let error = Reading::parse_with_sync(&candidate, &self.sync)
+            .err()
+            .unwrap_or(Error::ChecksumMismatch);
+        self.reject(candidate);
+        Some(Err(FrameError::new(error, &candidate).with_sync(self.sync)))
+    }
+
+    /// Like [`next_frame`](Self::next_frame), but also returns a
+    /// candidate that fails validation, for
+    /// [`Reading::parse_tolerant`] to flag. A candidate with another
+    /// sync header inside it is still skipped: it is most likely a
+    /// truncated frame, with the next one starting at that header.
+    pub fn next_frame_tolerant(&mut self) -> Option<[u8; Reading::N_BYTES]> {
+        loop {
+            let candidate = self.next_synced()?;
+            if Reading::validate_frame_with_sync(&candidate, &self.sync)
+                || find_sync(&candidate[1..], &self.sync).is_none()
+            {
+                self.consume(Reading::N_BYTES);
+                return Some(candidate);
+            }
+            self.reject(candidate);
+        }
+    }
+
+    /// Discards bytes up to the next sync header and returns the
+    /// candidate frame starting there, without consuming it. Returns
+    /// `None` until the whole candidate is buffered.
+    fn next_synced(&mut self) -> Option<[u8; Reading::N_BYTES]> {
+        let pending = self.pending();
+        let Some(offset) = find_sync(pending, &self.sync) else {
+            // No sync found; keep only a partial-sync tail.
+            let skip = pending.len().saturating_sub(Reading::N_SYNC_BYTES - 1);
+            self.discard(skip);
+            return None;
+        };
+        self.discard(offset);
+        // Wait for the rest of the candidate frame.
+        self.pending().first_chunk().copied()
+    }
+
+    /// Bad candidate (corruption or a false sync): advance past the
+    /// first sync byte and rescan.
+    fn reject(&mut self, candidate: [u8; Reading::N_BYTES]) {
+        self.rejected = Some(candidate);
+        self.discard(1);
+        self.rejected_frames += 1;
+    }
+
+    /// The candidate frame behind the last error from
+    /// [`next_candidate`](Self::next_candidate), e.g. for a
+    /// [`FrameError`](crate::FrameError).
+    pub fn last_rejected(&self) -> Option<&[u8; Reading::N_BYTES]> {
+        self.rejected.as_ref()
+    }
+
+    fn pending(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.start += n;
+    }
+
+    fn discard(&mut self, n: usize) {
+        if let Some(discarded) = &mut self.discarded {
+            discarded.extend_from_slice(&self.buf[self.start..self.start + n]);
+        }
+        self.consume(n);
+        self.discarded_bytes += n as u64;
+    }
+
+    /// Bytes skipped so far while looking for a valid frame.
+    pub fn discarded_bytes(&self) -> u64 {
+        self.discarded_bytes
+    }
+
+    /// Frame candidates so far that began with a sync header but failed
+    /// validation.
+    pub fn rejected_frames(&self) -> u64 {
+        self.rejected_frames
+    }
+}
+
+/// Returns the offset of the first sync header in `bytes`.
+pub(crate) fn find_sync(bytes: &[u8], sync: &[u8; Reading::N_SYNC_BYTES]) -> Option<usize> {
+    let [first, rest @ ..] = *sync;
+    let mut from = 0;
+    while let Some(i) = bytes[from..].iter().position(|&b| b == first) {
+        let at = from + i;
+        let tail = &bytes[at + 1..];
+        if tail.len() < rest.len() {
+            return None;
+        }
+        if tail.starts_with(&rest) {
+            return Some(at);
+        }
+        from = at + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_frame() -> [u8; Reading::N_BYTES] {
+        let mut frame = [0u8; Reading::N_BYTES];
+        frame[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+        frame[Reading::N_BYTES - 4] = 0xee;
+        Reading::write_checksum(&mut frame);
+        frame
+    }
+
+    #[test]
+    fn test_whole_frame() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&test_frame());
+        assert_eq!(decoder.next_frame(), Some(test_frame()));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_split_frame() {
+        let mut decoder = FrameDecoder::new();
+        let frame = test_frame();
+        decoder.push(&frame[..20]);
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(&frame[20..]);
+        assert_eq!(decoder.next_frame(), Some(frame));
+    }
+
+    #[test]
+    fn test_garbage_before_sync() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&[0x00, 0xaa, 0x55, 0x12]);
+        decoder.push(&test_frame());
+        assert_eq!(decoder.next_frame(), Some(test_frame()));
+    }
+
+    #[test]
+    fn test_two_frames_in_one_chunk() {
+        let mut decoder = FrameDecoder::new();
+        let mut bytes = test_frame().to_vec();
+        bytes.extend_from_slice(&test_frame());
+        decoder.push(&bytes);
+        assert_eq!(decoder.next_frame(), Some(test_frame()));
+        assert_eq!(decoder.next_frame(), Some(test_frame()));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_garbage_only_is_discarded() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&[0x12; 1024]);
+        assert_eq!(decoder.next_frame(), None);
+        // Buffer must not grow without bound on garbage input.
+        assert!(decoder.pending().len() < Reading::N_SYNC_BYTES);
+    }
+
+    #[test]
+    fn test_sync_split_across_chunks() {
+        let mut decoder = FrameDecoder::new();
+        let frame = test_frame();
+        decoder.push(&[0x99]);
+        decoder.push(&frame[..2]);
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(&frame[2..]);
+        assert_eq!(decoder.next_frame(), Some(frame));
+    }
+
+    #[test]
+    fn test_truncated_frame_does_not_swallow_next() {
+        // Frame A loses its last 6 bytes in transit; frame B arrives
+        // complete. B's sync falls inside what a naive decoder would
+        // consume as A's payload; the decoder must still yield B.
+        let mut decoder = FrameDecoder::new();
+        let frame = test_frame();
+        decoder.push(&frame[..Reading::N_BYTES - 6]);
+        decoder.push(&frame);
+        assert_eq!(decoder.next_frame(), Some(frame));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_checksum_collision_does_not_swallow_embedded_frame() {
+        // Construct 20 bytes of "noise" starting with a sync pattern,
+        // followed by a real frame, such that the first 56-byte
+        // candidate passes the checksum by collision but carries an
+        // invalid hold type. The decoder must reject that candidate and
+        // still find the embedded real frame.
+        let mut good = [0u8; Reading::N_BYTES];
+        good[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+        good[33] = 0x77; // Candidate's hold-type byte: invalid.
+        good[34] = 0x0f; // Candidate's stored checksum: 0x0fa0.
+        good[35] = 0xa0;
+        Reading::write_checksum(&mut good);
+
+        let mut noise = [0u8; 20];
+        noise[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+        // Tune the filler bytes so the candidate's sum equals 0x0fa0.
+        let candidate_sum = |noise: &[u8; 20]| -> u32 {
+            noise.iter().chain(&good[..34]).map(|&b| u32::from(b)).sum()
+        };
+        let mut deficit = 0x0fa0 - candidate_sum(&noise);
+        for byte in &mut noise[Reading::N_SYNC_BYTES..] {
+            let add = deficit.min(255);
+            *byte = add as u8;
+            deficit -= add;
+        }
+        assert_eq!(candidate_sum(&noise), 0x0fa0);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&noise);
+        decoder.push(&good);
+        assert_eq!(decoder.next_frame(), Some(good));
+    }
+
+    #[test]
+    fn test_corrupted_frame_is_skipped() {
+        let mut decoder = FrameDecoder::new();
+        let mut corrupted = test_frame();
+        corrupted[10] ^= 0x01;
+        decoder.push(&corrupted);
+        decoder.push(&test_frame());
+        assert_eq!(decoder.next_frame(), Some(test_frame()));
+        assert_eq!(decoder.next_frame(), None);
+        assert_eq!(decoder.rejected_frames(), 1);
+        assert_eq!(decoder.discarded_bytes(), Reading::N_BYTES as u64);
+    }
+
+    #[test]
+    fn test_next_candidate_reports_rejections() {
+        let mut decoder = FrameDecoder::new();
+        let mut corrupted = test_frame();
+        corrupted[10] ^= 0x01;
+        decoder.push(&corrupted);
+        decoder.push(&test_frame());
+        assert_eq!(decoder.next_candidate(), Some(Err(Error::ChecksumMismatch)));
+        assert_eq!(decoder.last_rejected(), Some(&corrupted));
+        assert_eq!(decoder.next_candidate(), Some(Ok(test_frame())));
+        assert_eq!(decoder.next_candidate(), None);
+    }
+
+    #[test]
+    fn test_next_frame_tolerant() {
+        let mut decoder = FrameDecoder::new();
+        let mut corrupted = test_frame();
+        corrupted[10] ^= 0x01;
+        let truncated = &test_frame()[..Reading::N_BYTES - 6];
+        decoder.push(&corrupted);
+        decoder.push(truncated);
+        decoder.push(&test_frame());
+        assert_eq!(decoder.next_frame_tolerant(), Some(corrupted));
+        assert_eq!(decoder.next_frame_tolerant(), Some(test_frame()));
+        assert_eq!(decoder.next_frame_tolerant(), None);
+        assert_eq!(decoder.rejected_frames(), 1);
+    }
+
+    #[test]
+    fn test_with_sync() {
+        let sync = [0xaa, 0x55, 0x00, 0x34, 0x02];
+        let mut frame = test_frame();
+        frame[..Reading::N_SYNC_BYTES].copy_from_slice(&sync);
+        Reading::write_checksum(&mut frame);
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&frame);
+        assert_eq!(decoder.next_frame(), None);
+        let mut decoder = FrameDecoder::new().with_sync(sync);
+        decoder.push(&test_frame());
+        decoder.push(&frame);
+        assert_eq!(decoder.next_frame(), Some(frame));
+        assert!(Reading::parse_with_sync(&frame, &sync).is_ok());
+        assert_eq!(Reading::parse(&frame), Err(Error::BadSyncHeader));
+    }
+
+    #[test]
+    fn test_discarded_kept() {
+        let mut decoder = FrameDecoder::new().with_discarded_kept();
+        decoder.push(&[0x01, 0x02]);
+        decoder.push(&test_frame());
+        assert_eq!(decoder.next_frame(), Some(test_frame()));
+        assert_eq!(decoder.take_discarded(), [0x01, 0x02]);
+        assert!(decoder.take_discarded().is_empty());
+    }
+
+    #[test]
+    fn test_false_sync_inside_garbage() {
+        // A sync pattern appears in noise with no valid frame behind
+        // it, followed by a real frame.
+        let mut decoder = FrameDecoder::new();
+        let mut noise = Reading::SYNC.to_vec();
+        noise.extend_from_slice(&[0x5a; 51]);
+        decoder.push(&noise);
+        decoder.push(&test_frame());
+        assert_eq!(decoder.next_frame(), Some(test_frame()));
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn test_arbitrary_bytes_never_panic(
+                chunks in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..200), 0..20)
+            ) {
+                let mut decoder = FrameDecoder::new();
+                for chunk in &chunks {
+                    decoder.push(chunk);
+                    while let Some(frame) = decoder.next_frame() {
+                        prop_assert!(Reading::validate_frame(&frame));
+                        prop_assert!(Reading::parse(&frame).is_ok());
+                    }
+                }
+                // Whatever is left cannot hold a whole frame yet.
+                prop_assert!(decoder.pending().len() < Reading::N_BYTES);
+            }
+
+            #[test]
+            fn test_frame_found_in_noise(
+                before in prop::collection::vec(any::<u8>(), 0..200),
+                after in prop::collection::vec(any::<u8>(), 0..200),
+                split in 0..Reading::N_BYTES,
+            ) {
+                // Noise ahead of the frame may itself begin a frame (even
+                // a checksum-valid one by collision), so only require
+                // that the real frame comes out eventually.
+                let frame = test_frame();
+                let mut bytes = before;
+                let start = bytes.len();
+                bytes.extend_from_slice(&frame);
+                bytes.extend_from_slice(&after);
+                let mut decoder = FrameDecoder::new();
+                decoder.push(&bytes[..start + split]);
+                let mut frames = Vec::new();
+                frames.extend(std::iter::from_fn(|| decoder.next_frame()));
+                decoder.push(&bytes[start + split..]);
+                frames.extend(std::iter::from_fn(|| decoder.next_frame()));
+                prop_assert!(frames.contains(&frame));
+            }
+
+            #[test]
+            fn test_bit_flip_never_yields_invalid_frame(byte in 0..Reading::N_BYTES, bit in 0..8u8) {
+                let mut frame = test_frame();
+                frame[byte] ^= 1 << bit;
+                let mut decoder = FrameDecoder::new();
+                decoder.push(&frame);
+                // A single flipped bit always breaks the checksum (or
+                // the sync header), so the frame must be dropped.
+                prop_assert_eq!(decoder.next_frame(), None);
+            }
+        }
+    }
+}
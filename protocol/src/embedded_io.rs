@@ -0,0 +1,141 @@
+use embedded_io_async::{ErrorKind, Read};
+
+use crate::machine::{Event, Protocol};
+use crate::reading::Reading;
+
+/// Why [`EmbeddedIoReader::read`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ReadError {
+    /// The reader failed.
+    #[error("read failed: {0}")]
+    Io(ErrorKind),
+
+    /// The reader reached end of input.
+    #[error("end of input")]
+    EndOfInput,
+}
+
+/// Reads the meter from an `embedded-io-async` byte source, such as an
+/// Embassy UART wired to the meter's serial output.
+///
+/// Needs only `alloc`. It runs the same framing and parsing as the
+/// `ut325f-rs` `Meter` on a fixed receive buffer, without a read
+/// timeout; wrap [`read`](Self::read) in your executor's timeout if you
+/// need one. Without the `std` feature readings carry no timestamp, so
+/// take the time from your own clock when `read` returns.
+pub struct EmbeddedIoReader<R> {
+    reader: R,
+    protocol: Protocol,
+}
+
+impl<R: Read> EmbeddedIoReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            protocol: Protocol::new(),
+        }
+    }
+
+    /// Reads frames starting with `sync` instead of [`Reading::SYNC`];
+    /// see [`FrameDecoder::with_sync`](crate::FrameDecoder::with_sync).
+    pub fn with_sync(mut self, sync: [u8; Reading::N_SYNC_BYTES]) -> Self {
+        self.protocol = core::mem::take(&mut self.protocol).with_sync(sync);
+        self
+    }
+
+    /// Returns the next reading, skipping corrupted frames.
+    pub async fn read(&mut self) -> Result<Reading, ReadError> {
+        let mut buf = [0u8; 64];
+        loop {
+            while let Some(event) = self.protocol.next_event() {
+                if let Event::Reading { reading, .. } = event {
+                    return Ok(reading);
+                }
+            }
+            let n = self
+                .reader
+                .read(&mut buf)
+                .await
+                .map_err(|e| ReadError::Io(embedded_io_async::Error::kind(&e)))?;
+            if n == 0 {
+                return Err(ReadError::EndOfInput);
+            }
+            self.protocol.push(&buf[..n]);
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_io_async::ErrorType;
+
+    /// Hands out `chunks` a read at a time, then fails with `error`.
+    struct MockReader<'a> {
+        chunks: &'a [&'a [u8]],
+        error: ErrorKind,
+    }
+
+    impl ErrorType for MockReader<'_> {
+        type Error = ErrorKind;
+    }
+
+    impl Read for MockReader<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            let Some((chunk, rest)) = self.chunks.split_first() else {
+                return Err(self.error);
+            };
+            self.chunks = rest;
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    /// Runs `future`, which the mock never leaves pending, to completion.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("mock reader left a read pending"),
+        }
+    }
+
+    #[test]
+    fn test_read_from_mock() {
+        let frame = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .meter_temp_c(24.0)
+            .build()
+            .to_frame();
+        let (head, tail) = frame.split_at(20);
+        let chunks: &[&[u8]] = &[&[0x55, 0x00], head, tail, &frame];
+        let mut reader = EmbeddedIoReader::new(MockReader {
+            chunks,
+            error: ErrorKind::TimedOut,
+        });
+        let reading = block_on(reader.read()).unwrap();
+        assert_eq!(reading.current_temps_c[0], 21.5);
+        block_on(reader.read()).unwrap();
+        assert_eq!(
+            block_on(reader.read()),
+            Err(ReadError::Io(ErrorKind::TimedOut))
+        );
+    }
+
+    #[test]
+    fn test_read_end_of_input() {
+        let mut reader = EmbeddedIoReader::new(&[0x55u8, 0x00][..]);
+        assert_eq!(block_on(reader.read()), Err(ReadError::EndOfInput));
+    }
+}
@@ -0,0 +1,111 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::reading::Reading;
+
+/// Reasons a frame fails to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("bad sync header")]
+    BadSyncHeader,
+
+    #[error("checksum mismatch")]
+    ChecksumMismatch,
+
+    #[error("invalid hold type {0:#04x}")]
+    InvalidHoldType(u8),
+
+    /// Strict parsing only: the frame's unidentified bytes, always zero
+    /// so far, hold something else.
+    #[error("unexpected unknown bytes {0:02x?}")]
+    UnexpectedUnknownBytes([u8; 4]),
+
+    /// Strict parsing only: a field holds a value the meter is not
+    /// known to send.
+    #[error("frame anomaly: {0}")]
+    Anomaly(&'static str),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// How much of the frame around the bytes at fault a [`FrameError`]
+/// shows.
+const CONTEXT_BYTES: usize = 4;
+
+/// A frame that failed to parse, with the bytes at fault, so a report
+/// of it says where the frame went wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameError {
+    pub error: Error,
+    pub frame: [u8; Reading::N_BYTES],
+    /// The sync header the frame was expected to start with.
+    sync: [u8; Reading::N_SYNC_BYTES],
+}
+
+impl FrameError {
+    pub fn new(error: Error, frame: &[u8; Reading::N_BYTES]) -> Self {
+        Self {
+            error,
+            frame: *frame,
+            sync: Reading::SYNC,
+        }
+    }
+
+    /// For a frame read with `sync` instead of [`Reading::SYNC`], so a
+    /// bad sync header is reported against the one expected.
+    pub fn with_sync(mut self, sync: [u8; Reading::N_SYNC_BYTES]) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// The bytes at fault.
+    pub fn bytes(&self) -> Range<usize> {
+        Reading::fault(&self.frame, &self.error, &self.sync)
+    }
+
+    /// The name of the field holding the bytes at fault, from
+    /// [`Reading::FIELDS`].
+    pub fn field(&self) -> &'static str {
+        let offset = self.bytes().start;
+        Reading::FIELDS
+            .iter()
+            .find(|(_, range)| range.contains(&offset))
+            .map_or("frame", |(name, _)| name)
+    }
+}
+
+impl fmt::Display for FrameError {
+    /// E.g. `checksum mismatch at byte 54 (checksum): 00 00 00 01 [12 34]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.bytes();
+        let hex = |range: Range<usize>| {
+            self.frame[range]
+                .iter()
+                .map(|b| alloc::format!("{b:02x}"))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        write!(
+            f,
+            "{} at byte {} ({}): ",
+            self.error,
+            bytes.start,
+            self.field()
+        )?;
+        let before = bytes.start.saturating_sub(CONTEXT_BYTES)..bytes.start;
+        let after = bytes.end..(bytes.end + CONTEXT_BYTES).min(Reading::N_BYTES);
+        if !before.is_empty() {
+            write!(f, "{} ", hex(before))?;
+        }
+        write!(f, "[{}]", hex(bytes))?;
+        if !after.is_empty() {
+            write!(f, " {}", hex(after))?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for FrameError {}
@@ -0,0 +1,31 @@
+//! The UT325F's wire protocol: reassembling frames from a byte stream
+//! and converting between frames and [`Reading`]s, with no I/O or
+//! runtime dependencies.
+//!
+//! Builds for `no_std` targets with `alloc` when the default `std`
+//! feature is off; readings then carry no timestamp.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod decoder;
+#[cfg(feature = "embedded-io")]
+mod embedded_io;
+mod error;
+mod machine;
+mod reading;
+#[cfg(feature = "uom")]
+mod units;
+mod utils;
+
+pub use decoder::FrameDecoder;
+#[cfg(feature = "embedded-io")]
+pub use embedded_io::{EmbeddedIoReader, ReadError};
+pub use error::{Error, FrameError, Result};
+pub use machine::{Event, Mode, Protocol};
+pub use reading::{HoldType, Quality, Reading, ReadingBuilder, Validity};
+/// The `uom` version the typed temperature accessors use.
+#[cfg(feature = "uom")]
+pub use uom;
+pub use utils::{round_temp, to_millidegrees};
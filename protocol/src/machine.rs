@@ -0,0 +1,228 @@
+use alloc::vec::Vec;
+
+use crate::decoder::FrameDecoder;
+use crate::error::FrameError;
+use crate::reading::Reading;
+
+/// How a [`Protocol`] treats frames that fail a check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Skip them ([`Reading::parse`]).
+    #[default]
+    Lenient,
+    /// Report each as [`Event::Invalid`] ([`Reading::parse_strict`]).
+    Strict,
+    /// Keep them, noting the failed checks in the reading's `validity`
+    /// ([`Reading::parse_tolerant`]).
+    Tolerant,
+}
+
+/// What a [`Protocol`] made of the bytes fed to it.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A frame decoded into a reading.
+    Reading {
+        reading: Reading,
+        frame: [u8; Reading::N_BYTES],
+    },
+    /// Bytes skipped while looking for a frame: line noise, or the first
+    /// byte of a rejected candidate.
+    Discarded(u64),
+    /// A checksum-valid frame that failed to parse and was skipped.
+    Skipped(FrameError),
+    /// Strict mode only: a frame that failed a check.
+    Invalid(FrameError),
+}
+
+/// The meter's protocol as a state machine with no I/O: feed it the
+/// bytes received, however chunked, and it returns what they amount to.
+/// A transport layer, async or not, only has to move bytes in (and, were
+/// the meter to take commands, out through
+/// [`poll_transmit`](Self::poll_transmit)).
+#[derive(Debug, Default)]
+pub struct Protocol {
+    decoder: FrameDecoder,
+    mode: Mode,
+    /// The event found while reporting the bytes discarded before it.
+    queued: Option<Event>,
+}
+
+impl Protocol {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// See [`FrameDecoder::with_sync`].
+    pub fn with_sync(mut self, sync: [u8; Reading::N_SYNC_BYTES]) -> Self {
+        self.decoder = self.decoder.with_sync(sync);
+        self
+    }
+
+    /// See [`FrameDecoder::with_discarded_kept`].
+    pub fn with_discarded_kept(mut self) -> Self {
+        self.decoder = self.decoder.with_discarded_kept();
+        self
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The frame decoder, for its counters and settings.
+    pub fn decoder(&self) -> &FrameDecoder {
+        &self.decoder
+    }
+
+    /// See [`FrameDecoder::take_discarded`].
+    pub fn take_discarded(&mut self) -> Vec<u8> {
+        self.decoder.take_discarded()
+    }
+
+    /// Feeds received bytes in and returns the events they complete.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        self.push(bytes);
+        core::iter::from_fn(|| self.next_event()).collect()
+    }
+
+    /// Feeds received bytes in without decoding them yet; take the
+    /// events one at a time with [`next_event`](Self::next_event).
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.decoder.push(bytes);
+    }
+
+    /// Returns the next event from the bytes fed in so far, or `None`
+    /// once they are used up.
+    pub fn next_event(&mut self) -> Option<Event> {
+        if let Some(event) = self.queued.take() {
+            return Some(event);
+        }
+        let discarded_before = self.decoder.discarded_bytes();
+        let event = self.decode();
+        let discarded = self.decoder.discarded_bytes() - discarded_before;
+        if discarded > 0 {
+            self.queued = event;
+            return Some(Event::Discarded(discarded));
+        }
+        event
+    }
+
+    /// Bytes to send to the meter, if any. The UT325F takes no
+    /// commands, so there never are yet; transport layers that call this
+    /// need no change once the protocol grows some.
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn decode(&mut self) -> Option<Event> {
+        let sync = *self.decoder.sync();
+        let (frame, parsed) = match self.mode {
+            Mode::Lenient => {
+                let frame = self.decoder.next_frame()?;
+                (frame, Reading::parse_with_sync(&frame, &sync))
+            }
+            Mode::Strict => match self.decoder.next_checked()? {
+                Ok(frame) => (frame, Reading::parse_strict_with_sync(&frame, &sync)),
+                Err(e) => return Some(Event::Invalid(e)),
+            },
+            Mode::Tolerant => {
+                let frame = self.decoder.next_frame_tolerant()?;
+                (frame, Reading::parse_tolerant_with_sync(&frame, &sync))
+            }
+        };
+        Some(match parsed {
+            Ok(reading) => Event::Reading { reading, frame },
+            Err(e) if self.mode == Mode::Strict => {
+                Event::Invalid(FrameError::new(e, &frame).with_sync(sync))
+            }
+            Err(e) => Event::Skipped(FrameError::new(e, &frame).with_sync(sync)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::reading::Validity;
+
+    fn frames() -> Vec<u8> {
+        let mut corrupt = Reading::builder().meter_temp_c(24.0).build().to_frame();
+        corrupt[20] ^= 0x01;
+        let good = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .meter_temp_c(24.0)
+            .build()
+            .to_frame();
+        [&[0x00, 0x13][..], &corrupt, &good].concat()
+    }
+
+    fn summary(events: &[Event]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                Event::Reading { reading, .. } => format!("reading {}", reading.validity),
+                Event::Discarded(n) => format!("discarded {n}"),
+                Event::Skipped(e) => format!("skipped {}", e.error),
+                Event::Invalid(e) => format!("invalid {}", e.error),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_feed() {
+        let bytes = frames();
+        let mut protocol = Protocol::new();
+        // Events come out as soon as the bytes for them are in.
+        let mut events = protocol.feed(&bytes[..30]);
+        assert_eq!(summary(&events), ["discarded 2"]);
+        events.extend(protocol.feed(&bytes[30..]));
+        assert_eq!(
+            summary(&events),
+            ["discarded 2", "discarded 56", "reading ok"]
+        );
+        assert_eq!(protocol.poll_transmit(), None);
+    }
+
+    #[test]
+    fn test_modes() {
+        let mut strict = Protocol::new().with_mode(Mode::Strict);
+        assert_eq!(
+            summary(&strict.feed(&frames())),
+            [
+                "discarded 3",
+                "invalid checksum mismatch",
+                "discarded 55",
+                "reading ok"
+            ]
+        );
+        let mut tolerant = Protocol::new().with_mode(Mode::Tolerant);
+        let events = tolerant.feed(&frames());
+        assert_eq!(
+            summary(&events),
+            ["discarded 2", "reading checksum", "reading ok"]
+        );
+        assert!(matches!(
+            &events[1],
+            Event::Reading { reading, .. } if reading.validity == Validity::CHECKSUM
+        ));
+
+        let mut unknown = Reading::builder().meter_temp_c(24.0).build().to_frame();
+        unknown[Reading::N_BYTES - 6] = 0x01;
+        Reading::write_checksum(&mut unknown);
+        let mut lenient = Protocol::new();
+        let events = lenient.feed(&unknown);
+        assert_eq!(summary(&events), ["reading ok"]);
+        let mut strict = Protocol::new().with_mode(Mode::Strict);
+        let events = strict.feed(&unknown);
+        assert!(matches!(
+            &events[..],
+            [Event::Invalid(e)] if matches!(e.error, Error::UnexpectedUnknownBytes(_))
+        ));
+    }
+}
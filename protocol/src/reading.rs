@@ -0,0 +1,1125 @@
+use core::fmt;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+use crate::decoder::find_sync;
+use crate::error::{Error, Result};
+#[cfg(feature = "std")]
+use crate::utils::system_time_to_unix_seconds;
+use crate::utils::{round_temp, to_millidegrees};
+
+// Layout after the sync header: current temperatures and error flags,
+// held temperatures and error flags, meter temperature, four unknown
+// bytes, hold type, and the checksum.
+const CURRENT: usize = Reading::N_SYNC_BYTES;
+const HELD: usize = CURRENT + 20;
+const METER: usize = HELD + 20;
+const UNKNOWN: usize = METER + 4;
+const HOLD_TYPE: usize = UNKNOWN + 4;
+const _: () = assert!(HOLD_TYPE + 1 == Reading::N_CHECKSUMMED_BYTES);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HoldType {
+    Current = 0,
+    Maximum = 1,
+    Minimum = 2,
+    Average = 3,
+}
+
+impl TryFrom<u8> for HoldType {
+    type Error = ();
+
+    fn try_from(value: u8) -> core::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Current),
+            1 => Ok(Self::Maximum),
+            2 => Ok(Self::Minimum),
+            3 => Ok(Self::Average),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The checks a reading's frame failed, as a set of flags. Only
+/// [`Reading::parse_tolerant`] lets such a frame through; every other
+/// reading is [`VALID`](Self::VALID).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Validity(u8);
+
+impl Validity {
+    pub const VALID: Self = Self(0);
+    /// The checksum did not match.
+    pub const CHECKSUM: Self = Self(1);
+    /// The hold type was unknown; the reading says `Current`.
+    pub const HOLD_TYPE: Self = Self(1 << 1);
+    /// The unidentified bytes were not zero.
+    pub const UNKNOWN_BYTES: Self = Self(1 << 2);
+    /// A temperature was non-finite without an error flag.
+    pub const ANOMALY: Self = Self(1 << 3);
+    /// Each flag with the name `Display` gives it.
+    pub const FLAGS: [(&'static str, Self); 4] = [
+        ("checksum", Self::CHECKSUM),
+        ("hold_type", Self::HOLD_TYPE),
+        ("unknown_bytes", Self::UNKNOWN_BYTES),
+        ("anomaly", Self::ANOMALY),
+    ];
+
+    pub fn is_valid(self) -> bool {
+        self == Self::VALID
+    }
+
+    /// Whether every flag of `other` is set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for Validity {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl core::ops::BitOrAssign for Validity {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+/// `ok`, or the names of the flags set joined by `+`, e.g.
+/// `checksum+hold_type`.
+impl fmt::Display for Validity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return f.write_str("ok");
+        }
+        let names = Self::FLAGS
+            .iter()
+            .filter(|&&(_, flag)| self.contains(flag))
+            .map(|&(name, _)| name);
+        for (i, name) in names.enumerate() {
+            if i > 0 {
+                f.write_str("+")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a reading arrived, for auditing the quality of a log. Parsing
+/// fills in the checksum status; a `Meter` and its plausibility filter
+/// fill in the rest.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quality {
+    /// Whether the frame's checksum matched: false only for a frame
+    /// kept by [`Reading::parse_tolerant`].
+    pub checksum_ok: bool,
+    /// Bytes skipped looking for this frame after the one before: 0
+    /// on a clean link.
+    pub resync_bytes: u64,
+    /// How much the time since the previous frame differed from the
+    /// time between the two before it, in seconds: near 0 for a steady
+    /// stream. `None` for the first two frames.
+    pub jitter_s: Option<f32>,
+    /// Channels the plausibility filter blanked in this reading.
+    pub filtered_channels: u8,
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self {
+            checksum_ok: true,
+            resync_bytes: 0,
+            jitter_s: None,
+            filtered_channels: 0,
+        }
+    }
+}
+
+/// A reading from the Uni-T UT325F meter.
+///
+/// `Display` gives a compact line such as
+/// `T1 26.7 °C  T2 open  T3 25.1 °C  T4 open  (meter 25.3 °C)`, with one
+/// decimal (the meter's resolution) unless a precision is given, e.g.
+/// `{reading:.3}`. Held temperatures follow when the meter is holding a
+/// maximum, minimum or average.
+///
+/// Fields may be added in minor releases; build readings with
+/// [`Reading::builder`] rather than a struct literal.
+#[derive(Copy, Clone)]
+#[non_exhaustive]
+pub struct Reading {
+    /// When the reading was parsed; absent without the `std` feature,
+    /// where the caller keeps time.
+    #[cfg(feature = "std")]
+    pub timestamp: SystemTime,
+    pub current_temps_c: [f32; 4],
+    pub held_temps_c: [f32; 4],
+    pub hold_type: HoldType,
+    pub meter_temp_c: f32,
+    /// The raw error flag sent with each current temperature: 0 for a
+    /// valid value, otherwise a code saying why the temperature is NaN
+    /// ([`Reading::OPEN_PROBE`] for a missing thermocouple; other codes
+    /// appear e.g. over range).
+    pub current_errors: [u8; 4],
+    /// The raw error flag sent with each held temperature.
+    pub held_errors: [u8; 4],
+    /// The checks the frame failed; always valid except from
+    /// [`Reading::parse_tolerant`].
+    pub validity: Validity,
+    pub quality: Quality,
+}
+
+impl Reading {
+    pub const N_BYTES: usize = 56;
+    /// The header frames start with: a 0xaa55 marker, the 52-byte
+    /// payload length and a command byte. Firmware revisions that send
+    /// another one are read with the `_with_sync` functions and
+    /// [`FrameDecoder::with_sync`](crate::FrameDecoder::with_sync).
+    pub const SYNC: [u8; 5] = [0xaa, 0x55, 0x00, 0x34, 0x01];
+    pub const N_SYNC_BYTES: usize = Self::SYNC.len();
+    const N_CHECKSUMMED_BYTES: usize = Self::N_BYTES - 2;
+    /// The error flag the meter sends for a channel with no
+    /// thermocouple.
+    pub const OPEN_PROBE: u8 = 0x30;
+    /// The frame's fields in order, as name and byte range, for tools
+    /// that annotate raw frames.
+    pub const FIELDS: [(&'static str, Range<usize>); 9] = [
+        ("sync", 0..CURRENT),
+        ("current", CURRENT..CURRENT + 16),
+        ("current flags", CURRENT + 16..HELD),
+        ("held", HELD..HELD + 16),
+        ("held flags", HELD + 16..METER),
+        ("meter", METER..UNKNOWN),
+        ("unknown", UNKNOWN..HOLD_TYPE),
+        ("hold type", HOLD_TYPE..Self::N_CHECKSUMMED_BYTES),
+        ("checksum", Self::N_CHECKSUMMED_BYTES..Self::N_BYTES),
+    ];
+
+    /// Returns true if `buf` is a parseable frame: sync header,
+    /// matching checksum, and a known hold type. Checking everything
+    /// `parse` rejects lets the decoder discard a bad candidate
+    /// byte-by-byte instead of consuming a real frame embedded in it.
+    pub fn validate_frame(buf: &[u8; Self::N_BYTES]) -> bool {
+        Self::validate_frame_with_sync(buf, &Self::SYNC)
+    }
+
+    /// Like [`validate_frame`](Self::validate_frame), for firmware
+    /// whose frames start with `sync` instead of [`SYNC`](Self::SYNC).
+    pub fn validate_frame_with_sync(
+        buf: &[u8; Self::N_BYTES],
+        sync: &[u8; Self::N_SYNC_BYTES],
+    ) -> bool {
+        buf[..Self::N_SYNC_BYTES] == *sync
+            && Self::checksum_ok(buf)
+            && HoldType::try_from(buf[Self::N_BYTES - 3]).is_ok()
+    }
+
+    /// The frame's last two bytes are a big-endian u16 checksum: the
+    /// wrapping sum of all preceding bytes, including the sync header.
+    fn checksum(buf: &[u8; Self::N_BYTES]) -> u16 {
+        buf[..Self::N_CHECKSUMMED_BYTES]
+            .iter()
+            .fold(0u16, |sum, &b| sum.wrapping_add(u16::from(b)))
+    }
+
+    fn checksum_ok(buf: &[u8; Self::N_BYTES]) -> bool {
+        let stored = u16::from_be_bytes([
+            buf[Self::N_CHECKSUMMED_BYTES],
+            buf[Self::N_CHECKSUMMED_BYTES + 1],
+        ]);
+        Self::checksum(buf) == stored
+    }
+
+    /// Overwrites the frame's trailing checksum with the correct value,
+    /// for tools that build or edit frames.
+    pub fn write_checksum(buf: &mut [u8; Self::N_BYTES]) {
+        let sum = Self::checksum(buf);
+        buf[Self::N_CHECKSUMMED_BYTES..].copy_from_slice(&sum.to_be_bytes());
+    }
+
+    /// Reads the little-endian f32 at `offset`.
+    fn f32_at(buf: &[u8; Self::N_BYTES], offset: usize) -> f32 {
+        f32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Reads the four error flags that follow the temperatures at
+    /// `offset`.
+    fn errors_at(buf: &[u8; Self::N_BYTES], offset: usize) -> [u8; 4] {
+        buf[offset + 16..offset + 20].try_into().unwrap()
+    }
+
+    /// Reads four temperatures at `offset`, followed by their four
+    /// error flags; a flagged channel (e.g. no thermocouple) is NaN.
+    fn temps_at(buf: &[u8; Self::N_BYTES], offset: usize) -> [f32; 4] {
+        core::array::from_fn(|channel| {
+            if buf[offset + 16 + channel] != 0 {
+                f32::NAN
+            } else {
+                Self::f32_at(buf, offset + 4 * channel)
+            }
+        })
+    }
+
+    pub fn parse(buf: &[u8; Self::N_BYTES]) -> Result<Self> {
+        Self::parse_with_sync(buf, &Self::SYNC)
+    }
+
+    /// Parses every frame in `bytes`, e.g. a whole capture loaded into
+    /// memory, skipping the junk between frames as [`FrameDecoder`]
+    /// does but without copying the bytes. Yields an error for each
+    /// candidate that starts with the sync header but fails to parse;
+    /// `.filter_map(Result::ok)` keeps just the readings. A truncated
+    /// frame at the end is ignored.
+    ///
+    /// [`FrameDecoder`]: crate::FrameDecoder
+    pub fn parse_all(bytes: &[u8]) -> impl Iterator<Item = Result<Self>> + '_ {
+        Self::parse_all_with_sync(bytes, &Self::SYNC)
+    }
+
+    /// Like [`parse_all`](Self::parse_all), for firmware whose frames
+    /// start with `sync` instead of [`SYNC`](Self::SYNC).
+    pub fn parse_all_with_sync<'a>(
+        bytes: &'a [u8],
+        sync: &[u8; Self::N_SYNC_BYTES],
+    ) -> impl Iterator<Item = Result<Self>> + use<'a> {
+        let sync = *sync;
+        let mut rest = bytes;
+        core::iter::from_fn(move || {
+            let offset = find_sync(rest, &sync)?;
+            let candidate = rest[offset..].first_chunk::<{ Self::N_BYTES }>()?;
+            // Past a valid frame, or a byte into a rejected one, as the
+            // decoder rescans.
+            let valid = Self::validate_frame_with_sync(candidate, &sync);
+            rest = &rest[offset + if valid { Self::N_BYTES } else { 1 }..];
+            Some(Self::parse_with_sync(candidate, &sync))
+        })
+    }
+
+    /// Like [`parse`](Self::parse), for firmware whose frames start
+    /// with `sync` instead of [`SYNC`](Self::SYNC).
+    pub fn parse_with_sync(
+        buf: &[u8; Self::N_BYTES],
+        sync: &[u8; Self::N_SYNC_BYTES],
+    ) -> Result<Self> {
+        if buf[..Self::N_SYNC_BYTES] != *sync {
+            return Err(Error::BadSyncHeader);
+        }
+        if !Self::checksum_ok(buf) {
+            return Err(Error::ChecksumMismatch);
+        }
+        let hold_type = HoldType::try_from(buf[HOLD_TYPE])
+            .map_err(|_| Error::InvalidHoldType(buf[HOLD_TYPE]))?;
+        Ok(Self::decode(buf, hold_type, Validity::VALID))
+    }
+
+    fn decode(buf: &[u8; Self::N_BYTES], hold_type: HoldType, validity: Validity) -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            timestamp: SystemTime::now(),
+            current_temps_c: Self::temps_at(buf, CURRENT),
+            held_temps_c: Self::temps_at(buf, HELD),
+            hold_type,
+            meter_temp_c: Self::f32_at(buf, METER),
+            current_errors: Self::errors_at(buf, CURRENT),
+            held_errors: Self::errors_at(buf, HELD),
+            validity,
+            quality: Quality {
+                checksum_ok: !validity.contains(Validity::CHECKSUM),
+                ..Quality::default()
+            },
+        }
+    }
+
+    /// Like [`parse`](Self::parse), but also rejects a frame the meter
+    /// is not known to send: non-zero unknown bytes, a non-finite
+    /// temperature without an error flag, or a non-finite meter
+    /// temperature. For validating firmware behavior; `parse` accepts
+    /// such frames.
+    pub fn parse_strict(buf: &[u8; Self::N_BYTES]) -> Result<Self> {
+        Self::parse_strict_with_sync(buf, &Self::SYNC)
+    }
+
+    /// Like [`parse_strict`](Self::parse_strict), for firmware whose
+    /// frames start with `sync` instead of [`SYNC`](Self::SYNC).
+    pub fn parse_strict_with_sync(
+        buf: &[u8; Self::N_BYTES],
+        sync: &[u8; Self::N_SYNC_BYTES],
+    ) -> Result<Self> {
+        let reading = Self::parse_with_sync(buf, sync)?;
+        let unknown = Self::unknown_bytes(buf);
+        if unknown != [0; 4] {
+            return Err(Error::UnexpectedUnknownBytes(unknown));
+        }
+        match Self::anomaly(buf) {
+            Some(anomaly) => Err(Error::Anomaly(anomaly)),
+            None => Ok(reading),
+        }
+    }
+
+    /// Like [`parse`](Self::parse), but keeps a frame that fails a
+    /// check instead of rejecting it, noting each failed check in the
+    /// reading's [`validity`](Self::validity): a checksum mismatch, an
+    /// unknown hold type (read as `Current`), and whatever
+    /// [`parse_strict`](Self::parse_strict) rejects. Only a frame
+    /// without the sync header is an error. For noisy links, where
+    /// marginal data beats none.
+    pub fn parse_tolerant(buf: &[u8; Self::N_BYTES]) -> Result<Self> {
+        Self::parse_tolerant_with_sync(buf, &Self::SYNC)
+    }
+
+    /// Like [`parse_tolerant`](Self::parse_tolerant), for firmware
+    /// whose frames start with `sync` instead of [`SYNC`](Self::SYNC).
+    pub fn parse_tolerant_with_sync(
+        buf: &[u8; Self::N_BYTES],
+        sync: &[u8; Self::N_SYNC_BYTES],
+    ) -> Result<Self> {
+        if buf[..Self::N_SYNC_BYTES] != *sync {
+            return Err(Error::BadSyncHeader);
+        }
+        let mut validity = Validity::VALID;
+        if !Self::checksum_ok(buf) {
+            validity |= Validity::CHECKSUM;
+        }
+        let hold_type = HoldType::try_from(buf[HOLD_TYPE]).unwrap_or_else(|()| {
+            validity |= Validity::HOLD_TYPE;
+            HoldType::Current
+        });
+        if Self::unknown_bytes(buf) != [0; 4] {
+            validity |= Validity::UNKNOWN_BYTES;
+        }
+        if Self::anomaly(buf).is_some() {
+            validity |= Validity::ANOMALY;
+        }
+        Ok(Self::decode(buf, hold_type, validity))
+    }
+
+    fn unknown_bytes(buf: &[u8; Self::N_BYTES]) -> [u8; 4] {
+        buf[UNKNOWN..HOLD_TYPE].try_into().unwrap()
+    }
+
+    /// What [`parse_strict`](Self::parse_strict) finds wrong with the
+    /// frame's values, if anything.
+    fn anomaly(buf: &[u8; Self::N_BYTES]) -> Option<&'static str> {
+        for offset in [CURRENT, HELD] {
+            let errors = Self::errors_at(buf, offset);
+            if (0..4).any(|channel| {
+                errors[channel] == 0 && !Self::f32_at(buf, offset + 4 * channel).is_finite()
+            }) {
+                return Some("non-finite temperature without an error flag");
+            }
+        }
+        if !Self::f32_at(buf, METER).is_finite() {
+            return Some("non-finite meter temperature");
+        }
+        None
+    }
+
+    /// The bytes of `buf` behind `error`, as returned by
+    /// [`parse_strict`](Self::parse_strict) for it: the first bad byte
+    /// of the sync header, compared with `sync`, or the field (or
+    /// channel) at fault.
+    pub(crate) fn fault(
+        buf: &[u8; Self::N_BYTES],
+        error: &Error,
+        sync: &[u8; Self::N_SYNC_BYTES],
+    ) -> Range<usize> {
+        let first_differing = |range: Range<usize>, expected: &[u8]| {
+            let start = range.start;
+            buf[range]
+                .iter()
+                .zip(expected)
+                .position(|(a, b)| a != b)
+                .map_or(start, |i| start + i)
+        };
+        match error {
+            Error::BadSyncHeader => {
+                let at = first_differing(0..CURRENT, sync);
+                at..at + 1
+            }
+            Error::ChecksumMismatch => Self::N_CHECKSUMMED_BYTES..Self::N_BYTES,
+            Error::InvalidHoldType(_) => HOLD_TYPE..HOLD_TYPE + 1,
+            Error::UnexpectedUnknownBytes(_) => UNKNOWN..HOLD_TYPE,
+            _ => {
+                // An anomaly: the first non-finite temperature without
+                // an error flag, else the meter temperature.
+                [CURRENT, HELD]
+                    .into_iter()
+                    .flat_map(|offset| (0..4).map(move |channel| (offset, channel)))
+                    .find(|&(offset, channel)| {
+                        buf[offset + 16 + channel] == 0
+                            && !Self::f32_at(buf, offset + 4 * channel).is_finite()
+                    })
+                    .map_or(METER..UNKNOWN, |(offset, channel)| {
+                        offset + 4 * channel..offset + 4 * channel + 4
+                    })
+            }
+        }
+    }
+
+    /// Encodes the reading as the meter would send it, e.g. for a
+    /// simulator. A NaN channel is sent with its error flag, or
+    /// [`Reading::OPEN_PROBE`] if it has none; the timestamp is not part
+    /// of the frame.
+    pub fn to_frame(&self) -> [u8; Self::N_BYTES] {
+        let mut buf = [0u8; Self::N_BYTES];
+        buf[..Self::N_SYNC_BYTES].copy_from_slice(&Self::SYNC);
+        Self::put_temps(
+            &mut buf,
+            CURRENT,
+            &self.current_temps_c,
+            &self.current_errors,
+        );
+        Self::put_temps(&mut buf, HELD, &self.held_temps_c, &self.held_errors);
+        buf[METER..METER + 4].copy_from_slice(&self.meter_temp_c.to_le_bytes());
+        buf[HOLD_TYPE] = self.hold_type as u8;
+        Self::write_checksum(&mut buf);
+        buf
+    }
+
+    /// The inverse of [`temps_at`](Self::temps_at) and
+    /// [`errors_at`](Self::errors_at).
+    fn put_temps(buf: &mut [u8; Self::N_BYTES], offset: usize, temps: &[f32; 4], errors: &[u8; 4]) {
+        for (channel, (&temp, &error)) in temps.iter().zip(errors).enumerate() {
+            if error != 0 {
+                buf[offset + 16 + channel] = error;
+            } else if temp.is_nan() {
+                buf[offset + 16 + channel] = Self::OPEN_PROBE;
+            } else {
+                let at = offset + 4 * channel;
+                buf[at..at + 4].copy_from_slice(&temp.to_le_bytes());
+            }
+        }
+    }
+
+    /// Current temperatures in integer millidegrees C; `None` for a
+    /// disconnected channel.
+    pub fn current_temps_mc(&self) -> [Option<i32>; 4] {
+        self.current_temps_c.map(to_millidegrees)
+    }
+
+    /// Held temperatures in integer millidegrees C; `None` for a
+    /// disconnected channel.
+    pub fn held_temps_mc(&self) -> [Option<i32>; 4] {
+        self.held_temps_c.map(to_millidegrees)
+    }
+
+    /// The meter's internal temperature in integer millidegrees C.
+    pub fn meter_temp_mc(&self) -> Option<i32> {
+        to_millidegrees(self.meter_temp_c)
+    }
+
+    /// Writes the timestamp and current temperatures as one line.
+    #[cfg(feature = "std")]
+    pub fn write_current_temps(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        self.write_current_temps_rounded(writer, 3)
+    }
+
+    /// Like [`write_current_temps`](Self::write_current_temps), with
+    /// temperatures rounded to `decimals` places.
+    #[cfg(feature = "std")]
+    pub fn write_current_temps_rounded(
+        &self,
+        writer: &mut impl io::Write,
+        decimals: usize,
+    ) -> io::Result<()> {
+        write!(writer, "{:.3}", system_time_to_unix_seconds(self.timestamp))?;
+        write_temps(writer, &self.current_temps_c, decimals)?;
+        writeln!(writer)
+    }
+
+    /// Writes the timestamp, current temperatures, hold type, and held
+    /// temperatures as one line.
+    #[cfg(feature = "std")]
+    pub fn write_all_temps(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        self.write_all_temps_rounded(writer, 3)
+    }
+
+    /// Like [`write_all_temps`](Self::write_all_temps), with
+    /// temperatures rounded to `decimals` places.
+    #[cfg(feature = "std")]
+    pub fn write_all_temps_rounded(
+        &self,
+        writer: &mut impl io::Write,
+        decimals: usize,
+    ) -> io::Result<()> {
+        write!(writer, "{:.3}", system_time_to_unix_seconds(self.timestamp))?;
+        write_temps(writer, &self.current_temps_c, decimals)?;
+        write!(writer, " {:?}", self.hold_type)?;
+        write_temps(writer, &self.held_temps_c, decimals)?;
+        writeln!(writer)
+    }
+}
+
+/// Compares temperatures so that NaN (a disconnected channel) equals
+/// NaN, as two readings of the same open channel should. Ignores
+/// `quality`, which describes how a reading arrived rather than what it
+/// says.
+impl PartialEq for Reading {
+    fn eq(&self, other: &Self) -> bool {
+        let temp_eq = |a: &f32, b: &f32| a == b || (a.is_nan() && b.is_nan());
+        let temps_eq = |a: &[f32; 4], b: &[f32; 4]| a.iter().zip(b).all(|(a, b)| temp_eq(a, b));
+        #[cfg(feature = "std")]
+        if self.timestamp != other.timestamp {
+            return false;
+        }
+        temps_eq(&self.current_temps_c, &other.current_temps_c)
+            && temps_eq(&self.held_temps_c, &other.held_temps_c)
+            && self.hold_type == other.hold_type
+            && temp_eq(&self.meter_temp_c, &other.meter_temp_c)
+            && self.current_errors == other.current_errors
+            && self.held_errors == other.held_errors
+            && self.validity == other.validity
+    }
+}
+
+impl Reading {
+    /// Starts building a reading, e.g. for tests and simulators. Every
+    /// channel starts open (NaN, flagged [`Reading::OPEN_PROBE`]), the
+    /// hold type `Current`, and the timestamp now. Setting a channel's
+    /// temperature keeps its error flag in step: a value clears it and
+    /// NaN marks the channel open.
+    pub fn builder() -> ReadingBuilder {
+        ReadingBuilder {
+            reading: Reading {
+                #[cfg(feature = "std")]
+                timestamp: SystemTime::now(),
+                current_temps_c: [f32::NAN; 4],
+                held_temps_c: [f32::NAN; 4],
+                hold_type: HoldType::Current,
+                meter_temp_c: f32::NAN,
+                current_errors: [Reading::OPEN_PROBE; 4],
+                held_errors: [Reading::OPEN_PROBE; 4],
+                validity: Validity::VALID,
+                quality: Quality::default(),
+            },
+        }
+    }
+}
+
+/// Builds a [`Reading`]; see [`Reading::builder`].
+#[derive(Debug, Clone)]
+pub struct ReadingBuilder {
+    reading: Reading,
+}
+
+impl ReadingBuilder {
+    #[cfg(feature = "std")]
+    pub fn timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.reading.timestamp = timestamp;
+        self
+    }
+
+    pub fn current_temps_c(mut self, temps: [f32; 4]) -> Self {
+        self.reading.current_temps_c = temps;
+        self.reading.current_errors = temps.map(error_for);
+        self
+    }
+
+    /// Sets one channel's current temperature (`channel` is 0-based).
+    ///
+    /// # Panics
+    ///
+    /// If `channel` is 4 or more.
+    pub fn current_temp_c(mut self, channel: usize, temp: f32) -> Self {
+        self.reading.current_temps_c[channel] = temp;
+        self.reading.current_errors[channel] = error_for(temp);
+        self
+    }
+
+    /// Marks one current channel NaN with a raw error flag, e.g. to
+    /// simulate an over-range probe.
+    ///
+    /// # Panics
+    ///
+    /// If `channel` is 4 or more.
+    pub fn current_error(mut self, channel: usize, error: u8) -> Self {
+        self.reading.current_temps_c[channel] = f32::NAN;
+        self.reading.current_errors[channel] = error;
+        self
+    }
+
+    pub fn held_temps_c(mut self, temps: [f32; 4]) -> Self {
+        self.reading.held_temps_c = temps;
+        self.reading.held_errors = temps.map(error_for);
+        self
+    }
+
+    pub fn hold_type(mut self, hold_type: HoldType) -> Self {
+        self.reading.hold_type = hold_type;
+        self
+    }
+
+    pub fn meter_temp_c(mut self, temp: f32) -> Self {
+        self.reading.meter_temp_c = temp;
+        self
+    }
+
+    pub fn validity(mut self, validity: Validity) -> Self {
+        self.reading.validity = validity;
+        self
+    }
+
+    pub fn build(self) -> Reading {
+        self.reading
+    }
+}
+
+/// The error flag that goes with a temperature set through the builder.
+fn error_for(temp: f32) -> u8 {
+    if temp.is_nan() {
+        Reading::OPEN_PROBE
+    } else {
+        0
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(1);
+        let write_temps = |f: &mut fmt::Formatter<'_>, temps: &[f32; 4]| {
+            for (channel, temp) in temps.iter().enumerate() {
+                if channel > 0 {
+                    f.write_str("  ")?;
+                }
+                if temp.is_nan() {
+                    write!(f, "T{} open", channel + 1)?;
+                } else {
+                    let temp = round_temp((*temp).into(), precision);
+                    write!(f, "T{} {temp:.precision$} °C", channel + 1)?;
+                }
+            }
+            Ok(())
+        };
+        write_temps(f, &self.current_temps_c)?;
+        if self.hold_type != HoldType::Current {
+            write!(f, "  [{:?}: ", self.hold_type)?;
+            write_temps(f, &self.held_temps_c)?;
+            f.write_str("]")?;
+        }
+        let meter_temp = round_temp(self.meter_temp_c.into(), precision);
+        write!(f, "  (meter {meter_temp:.precision$} °C)")
+    }
+}
+
+/// Includes the Unix time and which channels have a thermocouple,
+/// which the raw fields leave to the reader to work out.
+impl fmt::Debug for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Reading");
+        #[cfg(feature = "std")]
+        f.field("timestamp", &self.timestamp)
+            .field("unix_time", &system_time_to_unix_seconds(self.timestamp));
+        f.field("current_temps_c", &self.current_temps_c)
+            .field("connected", &self.current_temps_c.map(|t| !t.is_nan()))
+            .field("held_temps_c", &self.held_temps_c)
+            .field("hold_type", &self.hold_type)
+            .field("meter_temp_c", &self.meter_temp_c)
+            .field("current_errors", &self.current_errors)
+            .field("held_errors", &self.held_errors)
+            .field("validity", &self.validity)
+            .field("quality", &self.quality)
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+fn write_temps(writer: &mut impl io::Write, temps: &[f32; 4], decimals: usize) -> io::Result<()> {
+    for &temp in temps {
+        write!(writer, " {:7.decimals$}", round_temp(temp.into(), decimals))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reading_from_bytes() -> Result<()> {
+        #[rustfmt::skip]
+        let test_bytes: [u8; Reading::N_BYTES] = [
+            0xaa, 0x55, 0x00, 0x34, 0x01, // Sync header
+            0x98, 0x94, 0xd5, 0x41,       // current_temps_c[0]
+            0x00, 0x00, 0x00, 0x00,       // current_temps_c[1]
+            0x2d, 0x02, 0xd5, 0x41,       // current_temps_c[2]
+            0x6c, 0x25, 0x85, 0x42,       // current_temps_c[3]
+            0x00, 0x30, 0x30, 0x30,       // current_temp_errors
+            0x98, 0x94, 0xd5, 0x41,       // held_temps_c[0]
+            0x00, 0x00, 0x00, 0x00,       // held_temps_c[1]
+            0x2d, 0x02, 0xd5, 0x41,       // held_temps_c[2]
+            0x6c, 0x25, 0x85, 0x42,       // held_temps_c[3]
+            0x00, 0x00, 0x00, 0x00,       // held_temp_errors
+            0x00, 0x80, 0xd2, 0x41,       // meter_temp_c
+            0x00, 0x00, 0x00, 0x00,       // unknown
+            0x00,                         // hold_type
+            0x0d, 0x15,                   // checksum
+        ];
+
+        let reading_result = Reading::parse(&test_bytes)?;
+
+        assert_eq!(reading_result.current_temps_c[0], 26.697556);
+        assert!(reading_result.current_temps_c[1].is_nan());
+        assert!(reading_result.current_temps_c[2].is_nan());
+        assert!(reading_result.current_temps_c[3].is_nan());
+
+        assert_eq!(reading_result.held_temps_c[0], 26.697556);
+        assert_eq!(reading_result.held_temps_c[1], 0.0);
+        assert_eq!(reading_result.held_temps_c[2], 26.626062);
+        assert_eq!(reading_result.held_temps_c[3], 66.57309);
+
+        assert_eq!(reading_result.meter_temp_c, 26.3125);
+        assert_eq!(reading_result.hold_type, HoldType::Current);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bad_sync() -> Result<()> {
+        let mut buffer = [0u8; Reading::N_BYTES];
+        buffer[0] = 0x00; // Corrupt the sync header
+        let reading_result = Reading::parse(&buffer);
+        assert!(matches!(reading_result, Err(Error::BadSyncHeader)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_hold_type() -> Result<()> {
+        let mut buffer = [0u8; Reading::N_BYTES];
+        buffer[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+        buffer[Reading::N_BYTES - 3] = 0xff; // Invalid HoldType value
+        Reading::write_checksum(&mut buffer);
+        let reading_result = Reading::parse(&buffer);
+        assert!(matches!(reading_result, Err(Error::InvalidHoldType(0xff))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bad_checksum() -> Result<()> {
+        let mut buffer = [0u8; Reading::N_BYTES];
+        buffer[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+        Reading::write_checksum(&mut buffer);
+        buffer[10] ^= 0x01; // Corrupt one payload byte
+        let reading_result = Reading::parse(&buffer);
+        assert!(matches!(reading_result, Err(Error::ChecksumMismatch)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let frame = |temp| {
+            Reading::builder()
+                .current_temp_c(0, temp)
+                .build()
+                .to_frame()
+        };
+        let mut corrupt = frame(2.0);
+        corrupt[20] ^= 0x01;
+        let mut bytes = vec![0x13, 0x55];
+        bytes.extend_from_slice(&frame(1.0));
+        bytes.extend_from_slice(&corrupt);
+        bytes.extend_from_slice(&frame(3.0));
+        bytes.extend_from_slice(&frame(4.0)[..30]);
+        let parsed: Vec<_> = Reading::parse_all(&bytes)
+            .map(|result| result.map(|reading| reading.current_temps_c[0]))
+            .collect();
+        assert!(matches!(
+            parsed[..],
+            [Ok(1.0), Err(Error::ChecksumMismatch), Ok(3.0)]
+        ));
+        assert_eq!(Reading::parse_all(&[]).count(), 0);
+
+        let sync = [0xaa, 0x55, 0x00, 0x34, 0x02];
+        let mut other = frame(5.0);
+        other[..Reading::N_SYNC_BYTES].copy_from_slice(&sync);
+        Reading::write_checksum(&mut other);
+        let mut bytes = frame(1.0).to_vec();
+        bytes.extend_from_slice(&other);
+        let parsed: Vec<_> = Reading::parse_all_with_sync(&bytes, &sync)
+            .map(|result| result.map(|reading| reading.current_temps_c[0]))
+            .collect();
+        assert!(matches!(parsed[..], [Ok(5.0)]));
+    }
+
+    #[test]
+    fn test_validate_frame() {
+        let mut buffer = [0u8; Reading::N_BYTES];
+        buffer[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+        Reading::write_checksum(&mut buffer);
+        assert!(Reading::validate_frame(&buffer));
+
+        let mut corrupted = buffer;
+        corrupted[20] ^= 0x40;
+        assert!(!Reading::validate_frame(&corrupted));
+
+        let mut bad_sync = buffer;
+        bad_sync[0] = 0x00;
+        assert!(!Reading::validate_frame(&bad_sync));
+    }
+
+    #[test]
+    fn test_write_current_temps_rounded() -> io::Result<()> {
+        let reading = Reading {
+            timestamp: std::time::UNIX_EPOCH,
+            current_temps_c: [1.25, f32::NAN, -3.04, 100.0],
+            held_temps_c: [0.0; 4],
+            hold_type: HoldType::Current,
+            meter_temp_c: 25.0,
+            current_errors: [0; 4],
+            held_errors: [0; 4],
+            validity: Validity::VALID,
+            quality: Quality::default(),
+        };
+        assert_eq!(
+            reading.current_temps_mc(),
+            [Some(1250), None, Some(-3040), Some(100000)]
+        );
+        let mut line = Vec::new();
+        reading.write_current_temps_rounded(&mut line, 1)?;
+        assert_eq!(line, b"0.000     1.3     NaN    -3.0   100.0\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() {
+        let mut reading = Reading {
+            timestamp: std::time::UNIX_EPOCH,
+            current_temps_c: [26.66, f32::NAN, 25.1, f32::NAN],
+            held_temps_c: [30.0, f32::NAN, 25.0, f32::NAN],
+            hold_type: HoldType::Current,
+            meter_temp_c: 25.3,
+            current_errors: [0; 4],
+            held_errors: [0; 4],
+            validity: Validity::VALID,
+            quality: Quality::default(),
+        };
+        assert_eq!(
+            reading.to_string(),
+            "T1 26.7 °C  T2 open  T3 25.1 °C  T4 open  (meter 25.3 °C)"
+        );
+        reading.hold_type = HoldType::Maximum;
+        assert_eq!(
+            format!("{reading:.2}"),
+            "T1 26.66 °C  T2 open  T3 25.10 °C  T4 open  \
+             [Maximum: T1 30.00 °C  T2 open  T3 25.00 °C  T4 open]  (meter 25.30 °C)"
+        );
+        assert!(format!("{reading:?}").contains("connected: [true, false, true, false]"));
+    }
+
+    #[test]
+    fn test_builder_and_nan_equality() {
+        let build = || {
+            Reading::builder()
+                .timestamp(std::time::UNIX_EPOCH)
+                .current_temp_c(0, 21.5)
+                .hold_type(HoldType::Average)
+                .build()
+        };
+        let reading = build();
+        assert_eq!(reading.current_temps_c[0], 21.5);
+        assert!(reading.current_temps_c[1].is_nan());
+        assert_eq!(reading.hold_type, HoldType::Average);
+        assert_eq!(reading, build());
+        assert_ne!(reading, Reading::builder().build());
+    }
+
+    #[test]
+    fn test_to_frame_round_trip() -> Result<()> {
+        let reading = Reading::builder()
+            .current_temps_c([26.5, f32::NAN, -3.25, 1200.0])
+            .held_temps_c([30.0, f32::NAN, f32::NAN, 0.0])
+            .hold_type(HoldType::Maximum)
+            .meter_temp_c(25.0)
+            .build();
+        let frame = reading.to_frame();
+        assert!(Reading::validate_frame(&frame));
+        let parsed = Reading::parse(&frame)?;
+        assert_eq!(
+            parsed,
+            Reading {
+                timestamp: parsed.timestamp,
+                ..reading
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fields_cover_frame() {
+        let mut next = 0;
+        for (_, range) in Reading::FIELDS {
+            assert_eq!(range.start, next);
+            next = range.end;
+        }
+        assert_eq!(next, Reading::N_BYTES);
+    }
+
+    #[test]
+    fn test_error_flags() -> Result<()> {
+        let reading = Reading::builder()
+            .current_temps_c([26.5, f32::NAN, 0.0, 0.0])
+            .current_error(2, 0x31)
+            .build();
+        assert_eq!(reading.current_errors, [0, Reading::OPEN_PROBE, 0x31, 0]);
+        assert!(reading.current_temps_c[2].is_nan());
+        let parsed = Reading::parse(&reading.to_frame())?;
+        assert_eq!(parsed.current_errors, reading.current_errors);
+        assert_eq!(parsed.held_errors, [Reading::OPEN_PROBE; 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        let frame = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .meter_temp_c(25.0)
+            .build()
+            .to_frame();
+        assert!(Reading::parse_strict(&frame).is_ok());
+        let mut unknown = frame;
+        unknown[UNKNOWN + 1] = 0x12;
+        Reading::write_checksum(&mut unknown);
+        assert!(Reading::parse(&unknown).is_ok());
+        assert_eq!(
+            Reading::parse_strict(&unknown),
+            Err(Error::UnexpectedUnknownBytes([0, 0x12, 0, 0]))
+        );
+        let mut unflagged_nan = frame;
+        unflagged_nan[CURRENT + 4..CURRENT + 8].copy_from_slice(&f32::NAN.to_le_bytes());
+        unflagged_nan[CURRENT + 16 + 1] = 0;
+        Reading::write_checksum(&mut unflagged_nan);
+        assert!(matches!(
+            Reading::parse_strict(&unflagged_nan),
+            Err(Error::Anomaly(_))
+        ));
+        let no_meter_temp = Reading::builder().build().to_frame();
+        assert!(matches!(
+            Reading::parse_strict(&no_meter_temp),
+            Err(Error::Anomaly(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_tolerant() -> Result<()> {
+        let frame = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .meter_temp_c(25.0)
+            .build()
+            .to_frame();
+        assert!(Reading::parse_tolerant(&frame)?.validity.is_valid());
+        let mut damaged = frame;
+        damaged[HOLD_TYPE] = 0x07;
+        damaged[UNKNOWN] = 0x12;
+        let reading = Reading::parse_tolerant(&damaged)?;
+        assert_eq!(
+            reading.validity,
+            Validity::CHECKSUM | Validity::HOLD_TYPE | Validity::UNKNOWN_BYTES
+        );
+        assert_eq!(
+            reading.validity.to_string(),
+            "checksum+hold_type+unknown_bytes"
+        );
+        assert_eq!(reading.hold_type, HoldType::Current);
+        assert!(!reading.quality.checksum_ok);
+        assert_eq!(reading.current_temps_c[0], 21.5);
+        let no_meter_temp = Reading::builder().build().to_frame();
+        assert_eq!(
+            Reading::parse_tolerant(&no_meter_temp)?.validity,
+            Validity::ANOMALY
+        );
+        damaged[0] = 0x00;
+        assert_eq!(Reading::parse_tolerant(&damaged), Err(Error::BadSyncHeader));
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_error() {
+        use crate::error::FrameError;
+
+        let frame = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .meter_temp_c(25.0)
+            .build()
+            .to_frame();
+        let mut corrupted = frame;
+        corrupted[10] ^= 0x01;
+        let error = FrameError::new(Reading::parse(&corrupted).unwrap_err(), &corrupted);
+        assert_eq!(error.bytes(), 54..56);
+        assert_eq!(error.field(), "checksum");
+        let [.., hi, lo] = corrupted;
+        assert_eq!(
+            error.to_string(),
+            format!("checksum mismatch at byte 54 (checksum): 00 00 00 00 [{hi:02x} {lo:02x}]")
+        );
+
+        let mut bad_sync = frame;
+        bad_sync[2] = 0x01;
+        let error = FrameError::new(Error::BadSyncHeader, &bad_sync);
+        assert_eq!(error.bytes(), 2..3);
+        assert!(
+            error
+                .to_string()
+                .starts_with("bad sync header at byte 2 (sync): aa 55 [01] 34")
+        );
+        // Against a configured header, the byte that differs from it.
+        let error =
+            FrameError::new(Error::BadSyncHeader, &frame).with_sync([0xaa, 0x55, 0x00, 0x34, 0x02]);
+        assert_eq!(error.bytes(), 4..5);
+        assert_eq!(error.field(), "sync");
+
+        let mut unflagged_nan = frame;
+        unflagged_nan[HELD + 8..HELD + 12].copy_from_slice(&f32::NAN.to_le_bytes());
+        unflagged_nan[HELD + 16 + 2] = 0;
+        Reading::write_checksum(&mut unflagged_nan);
+        let error = Reading::parse_strict(&unflagged_nan).unwrap_err();
+        let error = FrameError::new(error, &unflagged_nan);
+        assert_eq!(error.bytes(), HELD + 8..HELD + 12);
+        assert_eq!(error.field(), "held");
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn test_parse_arbitrary_frame_never_panics(
+                body in prop::collection::vec(any::<u8>(), Reading::N_BYTES - Reading::N_SYNC_BYTES)
+            ) {
+                let mut frame = [0u8; Reading::N_BYTES];
+                frame[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+                frame[Reading::N_SYNC_BYTES..].copy_from_slice(&body);
+                let _ = Reading::parse(&frame);
+                Reading::write_checksum(&mut frame);
+                prop_assert_eq!(
+                    Reading::parse(&frame).is_ok(),
+                    Reading::validate_frame(&frame)
+                );
+            }
+
+            #[test]
+            fn test_to_frame_parses_back(
+                current in prop::array::uniform4(-200.0f32..1400.0),
+                held in prop::array::uniform4(-200.0f32..1400.0),
+                hold in 0..4u8,
+                meter_temp in -20.0f32..60.0,
+            ) {
+                let reading = Reading::builder()
+                    .current_temps_c(current)
+                    .held_temps_c(held)
+                    .hold_type(HoldType::try_from(hold).unwrap())
+                    .meter_temp_c(meter_temp)
+                    .build();
+                let parsed = Reading::parse(&reading.to_frame()).unwrap();
+                prop_assert_eq!(parsed, Reading { timestamp: parsed.timestamp, ..reading });
+            }
+        }
+    }
+}
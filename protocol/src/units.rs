@@ -0,0 +1,45 @@
+use uom::si::f32::ThermodynamicTemperature;
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+use crate::reading::Reading;
+
+fn celsius(temp_c: f32) -> Option<ThermodynamicTemperature> {
+    (!temp_c.is_nan()).then(|| ThermodynamicTemperature::new::<degree_celsius>(temp_c))
+}
+
+/// Typed views of a reading's temperatures, so consuming code cannot
+/// mix up units.
+impl Reading {
+    /// Current temperatures; `None` for a disconnected channel.
+    pub fn current_temps(&self) -> [Option<ThermodynamicTemperature>; 4] {
+        self.current_temps_c.map(celsius)
+    }
+
+    /// Held temperatures; `None` for a disconnected channel.
+    pub fn held_temps(&self) -> [Option<ThermodynamicTemperature>; 4] {
+        self.held_temps_c.map(celsius)
+    }
+
+    /// The meter's internal temperature.
+    pub fn meter_temp(&self) -> Option<ThermodynamicTemperature> {
+        celsius(self.meter_temp_c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::thermodynamic_temperature::kelvin;
+
+    #[test]
+    fn test_current_temps() {
+        let reading = Reading::builder()
+            .current_temps_c([0.0, f32::NAN, 100.0, -40.0])
+            .meter_temp_c(25.0)
+            .build();
+        let temps = reading.current_temps();
+        assert_eq!(temps[0].map(|t| t.get::<kelvin>()), Some(273.15));
+        assert!(temps[1].is_none());
+        assert_eq!(temps[2].map(|t| t.get::<degree_celsius>()), Some(100.0));
+    }
+}
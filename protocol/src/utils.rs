@@ -0,0 +1,25 @@
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "std")]
+pub fn system_time_to_unix_seconds(time: SystemTime) -> f64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    }
+}
+
+/// Rounds a temperature half away from zero to `decimals` places. The
+/// crate's formatting helpers round with this rather than leaving it
+/// to `format!`, which rounds ties to even, so every output shows the
+/// same digits for the same value.
+pub fn round_temp(value: f64, decimals: usize) -> f64 {
+    let scale = libm::pow(10.0, decimals as f64);
+    libm::round(value * scale) / scale
+}
+
+/// Converts a temperature to integer millidegrees, rounding half away
+/// from zero; `None` for NaN.
+pub fn to_millidegrees(temp_c: f32) -> Option<i32> {
+    (!temp_c.is_nan()).then(|| libm::round(f64::from(temp_c) * 1000.0) as i32)
+}
@@ -0,0 +1,417 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use crate::Reading;
+use crate::error::Error;
+use crate::expr::Expr;
+
+/// Which side of its limit trips a threshold.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Above,
+    Below,
+}
+
+/// A limit on one channel's current temperature, written as e.g.
+/// `T1>80` or `T3<-5` (channels are numbered 1 to 4), optionally
+/// followed by a clear level and a dwell time: `T1>80 clear=75 for=10s`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Threshold {
+    /// Zero-based channel index.
+    pub channel: usize,
+    pub direction: Direction,
+    pub limit_c: f32,
+    /// Once raised, the alarm clears only back past this level instead
+    /// of the limit, so a temperature hovering at the limit does not
+    /// make it chatter.
+    pub clear_c: Option<f32>,
+    /// How long the temperature must stay beyond the limit (or the
+    /// clear level) before the alarm raises (or clears).
+    pub dwell: Duration,
+}
+
+impl Threshold {
+    fn tripped(&self, temp_c: f32) -> bool {
+        match self.direction {
+            Direction::Above => temp_c > self.limit_c,
+            Direction::Below => temp_c < self.limit_c,
+        }
+    }
+
+    fn cleared(&self, temp_c: f32) -> bool {
+        let clear_c = self.clear_c.unwrap_or(self.limit_c);
+        match self.direction {
+            Direction::Above => temp_c <= clear_c,
+            Direction::Below => temp_c >= clear_c,
+        }
+    }
+}
+
+impl FromStr for Threshold {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAlarm(s.to_owned());
+        // The limit itself may contain spaces (`T1 > 80`); options are
+        // the `key=value` words after it.
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let n_limit = words.iter().take_while(|w| !w.contains('=')).count();
+        let (limit, options) = words.split_at(n_limit);
+        let limit = limit.concat();
+        let rest = limit.strip_prefix(['T', 't']).ok_or_else(invalid)?;
+        let split = rest.find(['>', '<']).ok_or_else(invalid)?;
+        let channel = match rest[..split].trim().parse::<usize>() {
+            Ok(n @ 1..=4) => n - 1,
+            _ => return Err(invalid()),
+        };
+        let direction = if rest.as_bytes()[split] == b'>' {
+            Direction::Above
+        } else {
+            Direction::Below
+        };
+        let limit_c = rest[split + 1..].trim().parse().map_err(|_| invalid())?;
+        let mut threshold = Self {
+            channel,
+            direction,
+            limit_c,
+            clear_c: None,
+            dwell: Duration::ZERO,
+        };
+        for option in options {
+            match option.split_once('=') {
+                Some(("clear", value)) => {
+                    threshold.clear_c = Some(value.parse().map_err(|_| invalid())?)
+                }
+                Some(("for", value)) => {
+                    threshold.dwell = humantime::parse_duration(value).map_err(|_| invalid())?
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        // A clear level beyond the limit would clear an alarm that is
+        // still tripping.
+        if threshold
+            .clear_c
+            .is_some_and(|clear_c| threshold.tripped(clear_c))
+        {
+            return Err(invalid());
+        }
+        Ok(threshold)
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.direction {
+            Direction::Above => '>',
+            Direction::Below => '<',
+        };
+        write!(f, "T{}{}{}", self.channel + 1, op, self.limit_c)?;
+        if let Some(clear_c) = self.clear_c {
+            write!(f, " clear={clear_c}")?;
+        }
+        if !self.dwell.is_zero() {
+            write!(f, " for={}", humantime::format_duration(self.dwell))?;
+        }
+        Ok(())
+    }
+}
+
+/// An alarm over several channels: an [`Expr`] that raises the alarm
+/// while true, optionally followed by a dwell time, e.g.
+/// `t1 > 80 && t2 - t3 > 5 for 30s`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub expr: Expr,
+    /// How long the condition must hold (or not) before the alarm
+    /// raises (or clears).
+    pub dwell: Duration,
+}
+
+impl FromStr for Condition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAlarm(s.to_owned());
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (expr, dwell) = match words[..] {
+            [ref expr @ .., "for", dwell] => (expr, Some(dwell)),
+            [ref expr @ .., last] if last.starts_with("for=") => (expr, last.strip_prefix("for=")),
+            ref expr => (expr, None),
+        };
+        let dwell = dwell
+            .map(humantime::parse_duration)
+            .transpose()
+            .map_err(|_| invalid())?;
+        let expr: Expr = expr.join(" ").parse()?;
+        // Without a channel, e.g. a mistyped `1>80`, it could never
+        // change.
+        if !expr.uses_channels() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            expr,
+            dwell: dwell.unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+        if !self.dwell.is_zero() {
+            write!(f, " for {}", humantime::format_duration(self.dwell))?;
+        }
+        Ok(())
+    }
+}
+
+/// What an alarm watches: one channel against a limit, or a condition
+/// over several. Parses as a [`Threshold`] where it can, else as a
+/// [`Condition`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Threshold(Threshold),
+    Condition(Condition),
+}
+
+impl Rule {
+    fn dwell(&self) -> Duration {
+        match self {
+            Self::Threshold(threshold) => threshold.dwell,
+            Self::Condition(condition) => condition.dwell,
+        }
+    }
+}
+
+impl From<Threshold> for Rule {
+    fn from(threshold: Threshold) -> Self {
+        Self::Threshold(threshold)
+    }
+}
+
+impl From<Condition> for Rule {
+    fn from(condition: Condition) -> Self {
+        Self::Condition(condition)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(threshold) => Ok(Self::Threshold(threshold)),
+            Err(_) => s.parse().map(Self::Condition),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Threshold(threshold) => threshold.fmt(f),
+            Self::Condition(condition) => condition.fmt(f),
+        }
+    }
+}
+
+/// An alarm starting or ceasing to trip.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AlarmEvent {
+    pub rule: Rule,
+    /// True when the alarm was raised, false when it cleared.
+    pub raised: bool,
+    /// The temperature that changed the alarm's state; NaN for a
+    /// condition.
+    pub temp_c: f32,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct AlarmState {
+    active: bool,
+    /// When the temperature first called for a change of state, while
+    /// waiting out the threshold's dwell time.
+    pending_since: Option<SystemTime>,
+}
+
+/// Tracks a set of alarm rules across readings and reports when each
+/// one raises or clears. A disconnected channel (NaN) leaves the alarms
+/// that depend on it as they were.
+#[derive(Debug, Clone, Default)]
+pub struct Alarms {
+    rules: Vec<Rule>,
+    states: Vec<AlarmState>,
+}
+
+impl Alarms {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let states = vec![AlarmState::default(); rules.len()];
+        Self { rules, states }
+    }
+
+    /// Evaluates `reading` and returns the alarms that changed state.
+    pub fn update(&mut self, reading: &Reading) -> Vec<AlarmEvent> {
+        let mut events = Vec::new();
+        for (rule, state) in self.rules.iter().zip(&mut self.states) {
+            let (temp_c, change) = match rule {
+                Rule::Threshold(threshold) => {
+                    let temp_c = reading.current_temps_c[threshold.channel];
+                    let change = if state.active {
+                        threshold.cleared(temp_c)
+                    } else {
+                        threshold.tripped(temp_c)
+                    };
+                    (temp_c, (!temp_c.is_nan()).then_some(change))
+                }
+                Rule::Condition(condition) => {
+                    let value = condition.expr.eval(&reading.current_temps_c);
+                    let change = (value != 0.0) != state.active;
+                    (f32::NAN, (!value.is_nan()).then_some(change))
+                }
+            };
+            let Some(change) = change else {
+                continue;
+            };
+            if !change {
+                state.pending_since = None;
+                continue;
+            }
+            let since = *state.pending_since.get_or_insert(reading.timestamp);
+            let waited = reading.timestamp.duration_since(since).unwrap_or_default();
+            if waited >= rule.dwell() {
+                state.active = !state.active;
+                state.pending_since = None;
+                events.push(AlarmEvent {
+                    rule: rule.clone(),
+                    raised: state.active,
+                    temp_c,
+                    timestamp: reading.timestamp,
+                });
+            }
+        }
+        events
+    }
+
+    /// Returns true if any alarm is currently raised.
+    pub fn any_active(&self) -> bool {
+        self.states.iter().any(|state| state.active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(t1: f32) -> Reading {
+        reading_at(0, t1)
+    }
+
+    fn reading_at(seconds: u64, t1: f32) -> Reading {
+        at(seconds, [t1, 0.0, 0.0, 0.0])
+    }
+
+    fn at(seconds: u64, temps: [f32; 4]) -> Reading {
+        Reading::builder()
+            .timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .current_temps_c(temps)
+            .held_temps_c([0.0; 4])
+            .meter_temp_c(25.0)
+            .build()
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        let threshold: Threshold = "T1>80".parse().unwrap();
+        assert_eq!(
+            threshold,
+            Threshold {
+                channel: 0,
+                direction: Direction::Above,
+                limit_c: 80.0,
+                clear_c: None,
+                dwell: Duration::ZERO,
+            }
+        );
+        assert_eq!(threshold.to_string(), "T1>80");
+        let threshold: Threshold = "t4 < -5.5".parse().unwrap();
+        assert_eq!(threshold.channel, 3);
+        assert_eq!(threshold.direction, Direction::Below);
+        assert_eq!(threshold.limit_c, -5.5);
+        let threshold: Threshold = "T1 > 80 clear=75 for=10s".parse().unwrap();
+        assert_eq!(threshold.clear_c, Some(75.0));
+        assert_eq!(threshold.dwell, Duration::from_secs(10));
+        assert_eq!(threshold.to_string(), "T1>80 clear=75 for=10s");
+        for bad in [
+            "T5>1",
+            "T0>1",
+            "1>80",
+            "T1=80",
+            "T1>",
+            "T1>x",
+            "T1>80 clear=85",
+            "T1>80 for=x",
+            "T1>80 hold=1",
+        ] {
+            assert!(bad.parse::<Threshold>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_raise_and_clear() {
+        let mut alarms = Alarms::new(vec!["T1>80".parse().unwrap()]);
+        assert!(alarms.update(&reading(79.0)).is_empty());
+        let events = alarms.update(&reading(81.0));
+        assert_eq!(events.len(), 1);
+        assert!(events[0].raised);
+        assert_eq!(events[0].temp_c, 81.0);
+        assert!(alarms.any_active());
+        assert!(alarms.update(&reading(82.0)).is_empty());
+        assert!(alarms.update(&reading(f32::NAN)).is_empty());
+        let events = alarms.update(&reading(80.0));
+        assert_eq!(events.len(), 1);
+        assert!(!events[0].raised);
+        assert!(!alarms.any_active());
+    }
+
+    #[test]
+    fn test_condition() {
+        let rule: Rule = "t1 > 80 && t2 - t3 > 5 for 30s".parse().unwrap();
+        let Rule::Condition(condition) = &rule else {
+            panic!("expected a condition");
+        };
+        assert_eq!(condition.dwell, Duration::from_secs(30));
+        assert_eq!(rule.to_string(), "t1 > 80 && t2 - t3 > 5 for 30s");
+        assert!(matches!("T1 > 80".parse(), Ok(Rule::Threshold(_))));
+        assert!("1 > 80".parse::<Rule>().is_err());
+        assert!("t1 > 80 for soon".parse::<Rule>().is_err());
+
+        let mut alarms = Alarms::new(vec!["t1 > 80 && t2 < 5".parse().unwrap()]);
+        let both = |t1, t2| at(0, [t1, t2, 0.0, 0.0]);
+        assert!(alarms.update(&both(81.0, 6.0)).is_empty());
+        let events = alarms.update(&both(81.0, 4.0));
+        assert!(events[0].raised);
+        assert!(events[0].temp_c.is_nan());
+        assert!(alarms.update(&both(f32::NAN, 6.0)).is_empty());
+        assert!(!alarms.update(&both(79.0, 4.0))[0].raised);
+    }
+
+    #[test]
+    fn test_hysteresis_and_dwell() {
+        let mut alarms = Alarms::new(vec!["T1>80 clear=75 for=10s".parse().unwrap()]);
+        assert!(alarms.update(&reading_at(0, 81.0)).is_empty());
+        // Dipping back resets the dwell timer.
+        assert!(alarms.update(&reading_at(5, 79.0)).is_empty());
+        assert!(alarms.update(&reading_at(6, 81.0)).is_empty());
+        assert!(alarms.update(&reading_at(15, 81.0)).is_empty());
+        assert!(alarms.update(&reading_at(16, 81.0))[0].raised);
+        // Below the limit but above the clear level: still raised.
+        assert!(alarms.update(&reading_at(17, 78.0)).is_empty());
+        assert!(alarms.update(&reading_at(40, 78.0)).is_empty());
+        assert!(alarms.update(&reading_at(41, 74.0)).is_empty());
+        assert!(!alarms.update(&reading_at(51, 74.0))[0].raised);
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::Write;
+
+use ut325f_rs::AlarmEvent;
+
+use crate::color::Paint;
+
+/// What to do, besides reporting on stderr, when an alarm changes
+/// state.
+#[derive(Default)]
+pub struct AlarmActions {
+    /// Ring the terminal bell when an alarm is raised.
+    pub bell: bool,
+    /// Show a desktop notification for every raise and clear.
+    pub notify: bool,
+    /// Send webhook and/or email messages.
+    #[cfg(any(feature = "webhook", feature = "email"))]
+    pub remote: Option<crate::remote::RemoteNotifier>,
+}
+
+impl AlarmActions {
+    pub fn fire(&mut self, event: &AlarmEvent) {
+        let message = describe(event);
+        let paint = if event.raised {
+            Paint::Red
+        } else {
+            Paint::Green
+        };
+        tracing::warn!(
+            kind = "alarm",
+            alarm = %event.rule,
+            state = if event.raised { "raised" } else { "cleared" },
+            temp_c = event.temp_c,
+            "{}",
+            paint.apply(&message, crate::color::stderr())
+        );
+        if event.raised {
+            crate::exit::alarm_raised();
+        }
+        if self.bell && event.raised {
+            let mut stderr = std::io::stderr();
+            let _ = stderr.write_all(b"\x07");
+            let _ = stderr.flush();
+        }
+        #[cfg(any(feature = "webhook", feature = "email"))]
+        if let Some(remote) = &mut self.remote {
+            remote.send(event, &message);
+        }
+        if self.notify {
+            notify(message);
+        }
+    }
+}
+
+fn describe(event: &AlarmEvent) -> String {
+    let state = if event.raised { "raised" } else { "cleared" };
+    // A condition over several channels has no one temperature.
+    match event.temp_c.is_nan() {
+        true => format!("Alarm {state}: {}", event.rule),
+        false => format!("Alarm {state}: {} ({:.1} °C)", event.rule, event.temp_c),
+    }
+}
+
+#[cfg(feature = "notify")]
+fn notify(message: String) {
+    // Showing a notification is a blocking D-Bus round trip; keep it
+    // off the reader. Failures are not worth interrupting a session.
+    tokio::task::spawn_blocking(move || {
+        let _ = notify_rust::Notification::new()
+            .summary("UT325F")
+            .body(&message)
+            .show();
+    });
+}
+
+#[cfg(not(feature = "notify"))]
+fn notify(_message: String) {}
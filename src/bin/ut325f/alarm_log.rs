@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use ut325f_rs::{AlarmEvent, Direction, Reading, Rule};
+
+use crate::format::json_number;
+use crate::records::unix_seconds;
+
+/// An alarm raised and not yet cleared.
+struct Excursion {
+    rule: Rule,
+    raised_at: SystemTime,
+    /// The furthest a threshold's channel went past its limit; NaN for
+    /// a condition.
+    peak_c: f32,
+}
+
+/// The --alarm-log: every alarm raised and cleared, one JSON line each,
+/// appended to a file of its own so the history outlives rotated logs.
+/// A clear carries how long the alarm was raised and its peak.
+pub struct AlarmLog {
+    file: std::fs::File,
+    /// By meter and alarm.
+    open: HashMap<(Option<String>, String), Excursion>,
+}
+
+impl AlarmLog {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening {}", path.display()))?;
+        Ok(Self {
+            file,
+            open: HashMap::new(),
+        })
+    }
+
+    /// Follows the peaks of raised alarms through `reading`, then logs
+    /// `events`, the alarm changes it brought.
+    pub fn update(
+        &mut self,
+        meter: Option<&str>,
+        reading: &Reading,
+        events: &[AlarmEvent],
+    ) -> Result<()> {
+        for event in events.iter().filter(|event| event.raised) {
+            self.open.insert(
+                (meter.map(str::to_owned), event.rule.to_string()),
+                Excursion {
+                    rule: event.rule.clone(),
+                    raised_at: event.timestamp,
+                    peak_c: f32::NAN,
+                },
+            );
+        }
+        for ((tag, _), excursion) in &mut self.open {
+            if tag.as_deref() != meter {
+                continue;
+            }
+            if let Rule::Threshold(threshold) = &excursion.rule {
+                let temp_c = reading.current_temps_c[threshold.channel];
+                let further = match threshold.direction {
+                    Direction::Above => temp_c > excursion.peak_c,
+                    Direction::Below => temp_c < excursion.peak_c,
+                };
+                if further || excursion.peak_c.is_nan() {
+                    excursion.peak_c = temp_c;
+                }
+            }
+        }
+        for event in events {
+            let mut line = json!({
+                "alarm": event.rule.to_string(),
+                "state": if event.raised { "raised" } else { "cleared" },
+                "temp_c": json_number(event.temp_c.into()),
+                "timestamp": unix_seconds(event.timestamp),
+            });
+            if let Some(meter) = meter {
+                line["meter"] = meter.into();
+            }
+            let key = (meter.map(str::to_owned), event.rule.to_string());
+            if !event.raised
+                && let Some(excursion) = self.open.remove(&key)
+            {
+                line["peak_c"] = json_number(excursion.peak_c.into());
+                line["raised_at"] = unix_seconds(excursion.raised_at).into();
+                line["duration_s"] = event
+                    .timestamp
+                    .duration_since(excursion.raised_at)
+                    .unwrap_or_default()
+                    .as_secs_f64()
+                    .into();
+            }
+            // One write per line, so a crash cannot leave half of one.
+            self.file.write_all(format!("{line}\n").as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use ut325f_rs::Alarms;
+
+    #[test]
+    fn test_alarm_log() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("ut325f-alarms-{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut log = AlarmLog::open(&path)?;
+        let mut alarms = Alarms::new(vec!["T1>80".parse()?, "T2<0".parse()?]);
+        for (second, t1) in [(0, 70.0), (1, 85.0), (2, 95.0), (3, 90.0), (4, 75.0)] {
+            let reading = Reading::builder()
+                .timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(second))
+                .current_temp_c(0, t1)
+                .current_temp_c(1, 20.0)
+                .build();
+            let events = alarms.update(&reading);
+            log.update(Some("kiln"), &reading, &events)?;
+        }
+        drop(log);
+        let text = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["state"], "raised");
+        assert_eq!(lines[0]["meter"], "kiln");
+        assert!(lines[0].get("peak_c").is_none());
+        assert_eq!(lines[1]["state"], "cleared");
+        assert_eq!(lines[1]["temp_c"], 75.0);
+        assert_eq!(lines[1]["peak_c"], 95.0);
+        assert_eq!(lines[1]["raised_at"], 1.0);
+        assert_eq!(lines[1]["duration_s"], 3.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use std::io::{BufRead, IsTerminal};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Semaphore, broadcast};
+use ut325f_rs::Annotation;
+
+use crate::interactive;
+
+/// The note of a mark made without one.
+const DEFAULT_NOTE: &str = "mark";
+
+/// Annotations waiting to be written; a session this far behind loses
+/// the oldest.
+const QUEUE_LEN: usize = 16;
+
+/// The largest request the listener accepts.
+const MAX_REQUEST: usize = 16 << 10;
+
+/// How long a connection to the listener has to send its request in
+/// full and take the response, however slowly it trickles in.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections the listener serves at once; more wait to be accepted.
+const MAX_CONNECTIONS: usize = 8;
+
+const BAD_REQUEST: &str = "400 Bad Request";
+
+/// Marks made while a session runs, written to every meter's outputs:
+/// a line typed on the terminal, SIGUSR1, or an HTTP `POST /annotate`
+/// whose body is the note.
+pub struct Annotations {
+    marks: broadcast::Sender<Annotation>,
+}
+
+impl Annotations {
+    /// Starts taking marks: from stdin if `stdin` and it is a terminal,
+    /// from SIGUSR1, and over HTTP at `listen` if given. With `keys`, and
+    /// stdout a terminal too, stdin takes single keys that also control
+    /// the display (see [`interactive`]); otherwise each line typed is a
+    /// note.
+    pub async fn start(stdin: bool, keys: bool, listen: Option<SocketAddr>) -> Result<Self> {
+        let (marks, _) = broadcast::channel(QUEUE_LEN);
+        if let Some(addr) = listen {
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("listening on {addr}"))?;
+            tokio::spawn(serve(listener, marks.clone()));
+        }
+        let terminal = stdin && std::io::stdin().is_terminal();
+        let keys = terminal
+            && keys
+            && std::io::stdout().is_terminal()
+            && interactive::start(marks.clone());
+        if terminal && !keys {
+            let marks = marks.clone();
+            // A thread of its own rather than tokio's stdin, whose
+            // blocked read would hold up exiting until Enter is pressed.
+            std::thread::spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    let Ok(line) = line else { break };
+                    mark(&marks, &line);
+                }
+            });
+        }
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            let mut signals = signal(SignalKind::user_defined1())?;
+            let marks = marks.clone();
+            tokio::spawn(async move {
+                while signals.recv().await.is_some() {
+                    mark(&marks, "");
+                }
+            });
+        }
+        Ok(Self { marks })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Annotation> {
+        self.marks.subscribe()
+    }
+}
+
+/// The next mark from `marks`; never, once they end.
+pub async fn next(marks: &mut Option<broadcast::Receiver<Annotation>>) -> Annotation {
+    while let Some(receiver) = marks {
+        match receiver.recv().await {
+            Ok(annotation) => return annotation,
+            Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => *marks = None,
+        }
+    }
+    std::future::pending().await
+}
+
+pub fn mark(marks: &broadcast::Sender<Annotation>, note: &str) {
+    let note = match note.trim() {
+        "" => DEFAULT_NOTE,
+        note => note,
+    };
+    let annotation = Annotation::new(SystemTime::now(), note);
+    tracing::info!(kind = "annotation", note = %annotation.note, "Marked: {}", annotation.note);
+    let _ = marks.send(annotation);
+}
+
+async fn serve(listener: TcpListener, marks: broadcast::Sender<Annotation>) {
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        let Ok(permit) = connections.clone().acquire_owned().await else {
+            return;
+        };
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("Accepting an annotation request failed: {e}");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let marks = marks.clone();
+        tokio::spawn(async move {
+            let responded = tokio::time::timeout(REQUEST_TIMEOUT, respond(stream, &marks)).await;
+            match responded {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::debug!("Annotation request from {peer}: {e}"),
+                Err(_) => tracing::debug!("Annotation request from {peer}: timed out"),
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, marks: &broadcast::Sender<Annotation>) -> Result<()> {
+    let mut request = Vec::new();
+    let parsed = loop {
+        if let Some(parsed) = parse_request(&request) {
+            break parsed;
+        }
+        if request.len() > MAX_REQUEST {
+            break Err("413 Content Too Large");
+        }
+        let mut buf = [0; 4096];
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break Err(BAD_REQUEST);
+        }
+        request.extend_from_slice(&buf[..n]);
+    };
+    let status = match parsed {
+        Ok(note) => {
+            mark(marks, &note);
+            "204 No Content"
+        }
+        Err(status) => status,
+    };
+    let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+/// The note an HTTP request to the listener carries, or the status to
+/// refuse it with; `None` while more of it is to come. The body is the
+/// note, as plain text or as JSON `{"note": "..."}`.
+fn parse_request(request: &[u8]) -> Option<std::result::Result<String, &'static str>> {
+    let end = request.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let Ok(head) = std::str::from_utf8(&request[..end]) else {
+        return Some(Err(BAD_REQUEST));
+    };
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (method, target) = (request_line.next(), request_line.next());
+    if target.and_then(|target| target.split('?').next()) != Some("/annotate") {
+        return Some(Err("404 Not Found"));
+    }
+    if method != Some("POST") {
+        return Some(Err("405 Method Not Allowed"));
+    }
+    let length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>());
+    let length = match length {
+        None => 0,
+        Some(Ok(length)) if length <= MAX_REQUEST => length,
+        Some(Ok(_)) => return Some(Err("413 Content Too Large")),
+        Some(Err(_)) => return Some(Err(BAD_REQUEST)),
+    };
+    let body = request.get(end + 4..end + 4 + length)?;
+    let Ok(body) = std::str::from_utf8(body) else {
+        return Some(Err(BAD_REQUEST));
+    };
+    let json = serde_json::from_str::<serde_json::Value>(body).ok();
+    match json.as_ref().and_then(|json| json.get("note")) {
+        Some(serde_json::Value::String(note)) => Some(Ok(note.clone())),
+        Some(_) => Some(Err(BAD_REQUEST)),
+        None => Some(Ok(body.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let post = |body: &str| {
+            format!(
+                "POST /annotate HTTP/1.1\r\nHost: x\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+        };
+        let parse = |request: &str| parse_request(request.as_bytes());
+        assert_eq!(
+            parse(&post("opened door")),
+            Some(Ok("opened door".to_owned()))
+        );
+        assert_eq!(
+            parse(&post(r#"{"note":"opened door"}"#)),
+            Some(Ok("opened door".to_owned()))
+        );
+        assert_eq!(parse(&post(r#"{"note":1}"#)), Some(Err(BAD_REQUEST)));
+        assert_eq!(
+            parse("POST /annotate HTTP/1.1\r\n\r\n"),
+            Some(Ok(String::new()))
+        );
+        // Incomplete: the head, then the body.
+        assert_eq!(parse("POST /annotate HTTP/1.1\r\n"), None);
+        assert_eq!(parse(&post("opened door")[..60]), None);
+        assert_eq!(
+            parse("GET /annotate HTTP/1.1\r\n\r\n"),
+            Some(Err("405 Method Not Allowed"))
+        );
+        assert_eq!(parse("POST / HTTP/1.1\r\n\r\n"), Some(Err("404 Not Found")));
+    }
+
+    #[tokio::test]
+    async fn test_listener() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (marks, _) = broadcast::channel(QUEUE_LEN);
+        let mut received = Some(marks.subscribe());
+        tokio::spawn(serve(listener, marks));
+        let mut client = TcpStream::connect(addr).await?;
+        client
+            .write_all(b"POST /annotate HTTP/1.1\r\nContent-Length: 4\r\n\r\ndoor")
+            .await?;
+        let mut response = String::new();
+        client.read_to_string(&mut response).await?;
+        assert!(response.starts_with("HTTP/1.1 204 "));
+        assert_eq!(next(&mut received).await.note, "door");
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_listener_drops_slow_requests() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (marks, _) = broadcast::channel(QUEUE_LEN);
+        tokio::spawn(serve(listener, marks));
+        let mut client = TcpStream::connect(addr).await?;
+        // A byte at a time, each well within the timeout, until the
+        // listener gives up on the request as a whole.
+        let request = b"POST /annotate HTTP/1.1\r\nContent-Length: 4\r\n\r\ndoor";
+        let mut sent = 0;
+        for byte in request {
+            if client.write_all(&[*byte]).await.is_err() {
+                break;
+            }
+            sent += 1;
+            tokio::time::sleep(REQUEST_TIMEOUT / 4).await;
+        }
+        assert!(sent < request.len());
+        let mut response = Vec::new();
+        let _ = client.read_to_end(&mut response).await;
+        assert!(response.is_empty());
+        Ok(())
+    }
+}
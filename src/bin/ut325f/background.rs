@@ -0,0 +1,396 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::oneshot;
+
+/// When data written to a log file is handed on to be written out: once
+/// `capacity` bytes have collected, or `interval` after the last
+/// hand-off, whichever comes first, and in any case after every
+/// `records` records if set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushPolicy {
+    pub interval: Duration,
+    pub capacity: usize,
+    pub records: Option<u64>,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            capacity: 64 << 10,
+            records: None,
+        }
+    }
+}
+
+/// How many ops the background task may fall behind by before more
+/// wait in the [`Backlog`].
+const QUEUE: usize = 64;
+
+/// How many bytes of data may wait in the [`Backlog`] before more are
+/// dropped.
+const BACKLOG: usize = 16 << 20;
+
+/// Work to do once a file has been created, such as deleting old
+/// segments.
+pub type Then = Pin<Box<dyn Future<Output = io::Result<()>> + Send>>;
+
+enum Op {
+    /// Closes the current file, fsyncing it, and continues with this
+    /// one.
+    Open(std::fs::File),
+    /// Like `Open`, creating the file at `path` first and then running
+    /// `then`.
+    Create {
+        path: PathBuf,
+        then: Option<Then>,
+    },
+    Data(Vec<u8>),
+    Flush,
+    Close(oneshot::Sender<io::Result<()>>),
+}
+
+impl Op {
+    fn len(&self) -> usize {
+        match self {
+            Self::Data(bytes) => bytes.len(),
+            _ => 0,
+        }
+    }
+}
+
+/// Ops that found the channel full, sent in order before any later
+/// one. Data beyond [`BACKLOG`] bytes is dropped and counted rather
+/// than held, so a stalled disk cannot take all the memory; other ops
+/// are always held.
+#[derive(Default)]
+struct Backlog {
+    ops: VecDeque<Op>,
+    bytes: usize,
+    /// Bytes dropped so far, reported by [`Files::close`].
+    dropped: u64,
+    /// Dropping since the backlog last emptied; warned about once.
+    dropping: bool,
+}
+
+impl Backlog {
+    /// Sends what it can, returning whether it emptied.
+    fn send(&mut self, ops: &mpsc::Sender<Op>) -> bool {
+        while let Some(op) = self.pop() {
+            match ops.try_send(op) {
+                Ok(()) => {}
+                Err(TrySendError::Full(op)) => {
+                    self.bytes += op.len();
+                    self.ops.push_front(op);
+                    return false;
+                }
+                // The task has ended; there is nowhere left to send.
+                Err(TrySendError::Closed(_)) => {
+                    self.ops.clear();
+                    self.bytes = 0;
+                }
+            }
+        }
+        self.dropping = false;
+        true
+    }
+
+    fn hold(&mut self, op: Op) {
+        if matches!(op, Op::Data(_)) && self.bytes + op.len() > BACKLOG {
+            if !self.dropping {
+                tracing::warn!("Log writing is falling behind the disk; dropping data");
+                self.dropping = true;
+            }
+            self.dropped += op.len() as u64;
+            return;
+        }
+        self.bytes += op.len();
+        self.ops.push_back(op);
+    }
+
+    fn pop(&mut self) -> Option<Op> {
+        let op = self.ops.pop_front()?;
+        self.bytes -= op.len();
+        Some(op)
+    }
+}
+
+/// The first write error the background task met, reported by the next
+/// write or close.
+type Failure = Arc<Mutex<Option<io::Error>>>;
+
+/// The sending end, shared by [`Files`] and its writers.
+struct Queue {
+    ops: mpsc::Sender<Op>,
+    backlog: Mutex<Backlog>,
+    failure: Failure,
+}
+
+impl Queue {
+    /// Sends `op` without waiting, holding it in the backlog if the
+    /// channel is full.
+    fn push(&self, op: Op) {
+        let mut backlog = self.backlog.lock().unwrap();
+        if !backlog.send(&self.ops) {
+            return backlog.hold(op);
+        }
+        if let Err(TrySendError::Full(op)) = self.ops.try_send(op) {
+            backlog.hold(op);
+        }
+    }
+
+    /// Waits until the whole backlog has been sent.
+    async fn drain(&self) {
+        loop {
+            let Some(op) = self.backlog.lock().unwrap().pop() else {
+                return;
+            };
+            if self.ops.send(op).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn check(&self) -> io::Result<()> {
+        match self.failure.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes a log's files on a background task with `tokio::fs`, so a
+/// slow disk or SD card holds up that task rather than the meter's
+/// reader, whose frames would otherwise back up and be lost. Only one
+/// file is open at a time: opening the next (on rotation) fsyncs and
+/// closes the last.
+#[derive(Clone)]
+pub struct Files {
+    queue: Arc<Queue>,
+    policy: FlushPolicy,
+}
+
+impl Files {
+    /// Starts the task; needs a tokio runtime.
+    pub fn spawn(policy: FlushPolicy) -> Self {
+        let (ops, received) = mpsc::channel(QUEUE);
+        let failure = Failure::default();
+        tokio::spawn(run(received, failure.clone()));
+        let queue = Arc::new(Queue {
+            ops,
+            backlog: Mutex::default(),
+            failure,
+        });
+        Self { queue, policy }
+    }
+
+    /// Switches to `file`, returning the writer for it. Creating the
+    /// file is left to the caller, so errors such as a missing
+    /// directory surface at once.
+    pub fn open(&self, file: std::fs::File) -> FileWriter {
+        self.queue.push(Op::Open(file));
+        self.writer()
+    }
+
+    /// Switches to a new file at `path`, returning the writer for it.
+    /// The task creates the file and then runs `then`; errors surface
+    /// on a later write or the close.
+    pub fn create(&self, path: PathBuf, then: Option<Then>) -> FileWriter {
+        self.queue.push(Op::Create { path, then });
+        self.writer()
+    }
+
+    fn writer(&self) -> FileWriter {
+        FileWriter {
+            queue: self.queue.clone(),
+            policy: self.policy,
+            pending: Vec::new(),
+            sent: Instant::now(),
+        }
+    }
+
+    /// Waits until everything written has reached the disk and closes
+    /// the file. Fails if the task met an error or data was dropped.
+    pub async fn close(&self) -> io::Result<()> {
+        let (done, closed) = oneshot::channel();
+        self.queue.push(Op::Close(done));
+        self.queue.drain().await;
+        let closed = closed
+            .await
+            .unwrap_or_else(|_| Err(io::Error::other("log writer task ended")));
+        match std::mem::take(&mut self.queue.backlog.lock().unwrap().dropped) {
+            0 => closed,
+            dropped => closed.and(Err(io::Error::other(format!(
+                "{dropped} bytes of the log were dropped: the disk could not keep up"
+            )))),
+        }
+    }
+}
+
+/// Data for the current file, collected per the [`FlushPolicy`] and
+/// handed to the background task.
+pub struct FileWriter {
+    queue: Arc<Queue>,
+    policy: FlushPolicy,
+    pending: Vec<u8>,
+    sent: Instant,
+}
+
+impl FileWriter {
+    fn send(&mut self) {
+        if !self.pending.is_empty() {
+            self.queue.push(Op::Data(std::mem::take(&mut self.pending)));
+        }
+        self.sent = Instant::now();
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.queue.check()?;
+        self.pending.extend_from_slice(buf);
+        if self.pending.len() >= self.policy.capacity || self.sent.elapsed() >= self.policy.interval
+        {
+            self.send();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.queue.check()?;
+        self.send();
+        self.queue.push(Op::Flush);
+        Ok(())
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        self.send();
+    }
+}
+
+async fn run(mut ops: mpsc::Receiver<Op>, failure: Failure) {
+    let mut file = None;
+    while let Some(op) = ops.recv().await {
+        let result = match op {
+            Op::Open(next) => {
+                let closed = close(file.take()).await;
+                file = Some(tokio::fs::File::from_std(next));
+                closed
+            }
+            Op::Create { path, then } => {
+                let closed = close(file.take()).await;
+                let created = match tokio::fs::File::create(&path).await {
+                    Ok(created) => {
+                        file = Some(created);
+                        match then {
+                            Some(then) => then.await,
+                            None => Ok(()),
+                        }
+                    }
+                    Err(e) => Err(io::Error::new(
+                        e.kind(),
+                        format!("creating {}: {e}", path.display()),
+                    )),
+                };
+                closed.and(created)
+            }
+            Op::Data(bytes) => match &mut file {
+                Some(file) => file.write_all(&bytes).await,
+                None => Ok(()),
+            },
+            Op::Flush => match &mut file {
+                Some(file) => file.flush().await,
+                None => Ok(()),
+            },
+            Op::Close(done) => {
+                let earlier = failure.lock().unwrap().take();
+                let closed = close(file.take()).await;
+                let _ = done.send(earlier.map_or(closed, Err));
+                continue;
+            }
+        };
+        if let Err(e) = result {
+            failure.lock().unwrap().get_or_insert(e);
+        }
+    }
+    // Every sender is gone without a close; still finish the file.
+    let _ = close(file).await;
+}
+
+async fn close(file: Option<tokio::fs::File>) -> io::Result<()> {
+    let Some(mut file) = file else {
+        return Ok(());
+    };
+    file.flush().await?;
+    file.sync_all().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_flush_policy() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("ut325f-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let files = Files::spawn(FlushPolicy {
+            interval: Duration::from_secs(3600),
+            capacity: 8,
+            records: None,
+        });
+        let (a, b, c) = (dir.join("a"), dir.join("b"), dir.join("c"));
+        let mut writer = files.open(std::fs::File::create(&a)?);
+        writer.write_all(b"1234")?;
+        writer.write_all(b"5678")?;
+        writer.write_all(b"9")?;
+        drop(writer);
+        let mut writer = files.open(std::fs::File::create(&b)?);
+        writer.write_all(b"next")?;
+        // Held back: under capacity, and the interval has not passed.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(std::fs::read(&b)?, b"");
+        writer.flush()?;
+        drop(writer);
+        let mut writer = files.create(c.clone(), None);
+        writer.write_all(b"created")?;
+        drop(writer);
+        files.close().await?;
+        assert_eq!(std::fs::read(&a)?, b"123456789");
+        assert_eq!(std::fs::read(&b)?, b"next");
+        assert_eq!(std::fs::read(&c)?, b"created");
+        std::fs::remove_dir_all(&dir)
+    }
+
+    #[test]
+    fn test_backlog_holds_then_drops() {
+        let (ops, mut received) = mpsc::channel(1);
+        let queue = Queue {
+            ops,
+            backlog: Mutex::default(),
+            failure: Failure::default(),
+        };
+        queue.push(Op::Data(vec![0]));
+        queue.push(Op::Data(vec![0; BACKLOG]));
+        // Over the limit: dropped, while the flush is still held.
+        queue.push(Op::Data(vec![0]));
+        queue.push(Op::Flush);
+        {
+            let backlog = queue.backlog.lock().unwrap();
+            assert_eq!((backlog.ops.len(), backlog.dropped), (2, 1));
+        }
+        assert!(matches!(received.try_recv(), Ok(Op::Data(bytes)) if bytes.len() == 1));
+        // Room again: held ops go first, in order.
+        queue.push(Op::Data(vec![0; 2]));
+        assert!(matches!(received.try_recv(), Ok(Op::Data(bytes)) if bytes.len() == BACKLOG));
+        assert_eq!(queue.backlog.lock().unwrap().ops.len(), 2);
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::cli::ConnectArgs;
+use crate::connect;
+use crate::stats::ChannelStats;
+
+/// A reference temperature and the mean the channel read at it.
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    reference_c: f64,
+    measured_c: f64,
+}
+
+/// The gain and offset that map the two measured temperatures onto
+/// their references: `corrected = gain * measured + offset`.
+fn two_point(low: Point, high: Point) -> Result<(f64, f64)> {
+    let span = high.measured_c - low.measured_c;
+    if span.abs() < 1.0 || (high.reference_c - low.reference_c).abs() < 1.0 {
+        return Err(anyhow!(
+            "the two points must be at least 1 °C apart, as given and as read"
+        ));
+    }
+    let gain = (high.reference_c - low.reference_c) / span;
+    Ok((gain, low.reference_c - gain * low.measured_c))
+}
+
+/// Asks on stderr for the reference temperature of point `n`.
+fn prompt_reference(channel: usize, n: usize) -> Result<f64> {
+    let stdin = std::io::stdin();
+    loop {
+        eprint!(
+            "Put the T{channel} probe at reference point {n}, let it settle, and enter the reference temperature in °C: "
+        );
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Err(anyhow!("no reference temperature given"));
+        }
+        match line.trim().parse::<f64>() {
+            Ok(reference) if reference.is_finite() => return Ok(reference),
+            _ => eprintln!("'{}' is not a temperature", line.trim()),
+        }
+    }
+}
+
+/// Averages `channel` (zero-based) over `window` of readings.
+async fn measure(
+    connect_args: &ConnectArgs,
+    channel: usize,
+    window: Duration,
+) -> Result<ChannelStats> {
+    let mut meter = connect::open(connect_args).await?;
+    let mut temps = Vec::new();
+    let result: Result<Result<(), ut325f_rs::Error>, _> = tokio::time::timeout(window, async {
+        loop {
+            let reading = meter.read().await?;
+            temps.push(reading.current_temps_c[channel]);
+        }
+    })
+    .await;
+    let torn_down = connect::teardown(meter, connect_args.disconnect).await;
+    if let Ok(Err(e)) = result {
+        return Err(e.into());
+    }
+    torn_down?;
+    Ok(ChannelStats::new(temps.into_iter()))
+}
+
+/// Runs `calibrate`: captures `channel` (one-based) at two reference
+/// temperatures and prints the correction.
+pub async fn run(connect_args: &ConnectArgs, channel: u8, window: Duration) -> Result<()> {
+    let index = usize::from(channel) - 1;
+    let mut points = Vec::new();
+    for n in 1..=2 {
+        let reference_c = prompt_reference(index + 1, n)?;
+        let stats = measure(connect_args, index, window).await?;
+        if stats.count == 0 {
+            return Err(anyhow!("T{channel} gave no value at point {n}"));
+        }
+        eprintln!(
+            "T{channel} read {:.2} °C (min {:.2}, max {:.2}, {} readings)",
+            stats.mean, stats.min, stats.max, stats.count
+        );
+        points.push(Point {
+            reference_c,
+            measured_c: f64::from(stats.mean),
+        });
+    }
+    let (gain, offset) = two_point(points[0], points[1]).context("Calibration failed")?;
+    let mut out = std::io::stdout();
+    writeln!(out, "Gain:    {gain:.5}")?;
+    writeln!(out, "Offset:  {offset:+.3} °C")?;
+    let sign = if offset < 0.0 { '-' } else { '+' };
+    writeln!(
+        out,
+        "Apply with: --derive 't{channel}_cal = {gain:.5} * t{channel} {sign} {:.3}'",
+        offset.abs()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_point() {
+        let point = |reference_c, measured_c| Point {
+            reference_c,
+            measured_c,
+        };
+        let (gain, offset) = two_point(point(0.0, 0.5), point(100.0, 99.5)).unwrap();
+        assert!((gain * 0.5 + offset).abs() < 1e-9);
+        assert!((gain * 99.5 + offset - 100.0).abs() < 1e-9);
+        assert!(two_point(point(0.0, 20.0), point(100.0, 20.2)).is_err());
+    }
+}
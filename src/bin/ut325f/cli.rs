@@ -0,0 +1,1197 @@
+use anyhow::{Result, anyhow};
+use clap::ValueHint;
+use clap_derive::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use ut325f_rs::{Reading, Rule, ThermocoupleType};
+
+use crate::{
+    background, color, duration, format, hexdump, merge, relay, resample, schedule, schema, sink,
+};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Cli {
+    /// Output format [default: pretty on a terminal, text when piped or
+    /// written to a file; csv for log].
+    #[arg(short, long, value_enum, global = true, alias = "to")]
+    pub format: Option<format::Format>,
+
+    /// Report more on stderr: -v adds link quality every minute of a
+    /// live or replayed session, -vv debugging detail, -vvv every byte
+    /// received.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Print nothing but data; warnings and errors are only counted,
+    /// and the count reported at exit.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Write warnings, errors and alarms on stderr as JSON lines, for
+    /// supervisory tooling; stdout keeps the data.
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    pub errors_json: bool,
+
+    /// Color the pretty format on stdout and alarms on stderr. auto
+    /// colors a terminal unless the NO_COLOR environment variable is
+    /// set.
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t)]
+    pub color: color::ColorChoice,
+
+    #[command(flatten)]
+    pub style: StyleArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// The checks clap's derive cannot express, as a usage error.
+    pub fn check(&self) -> Result<(), clap::Error> {
+        let connect = match &self.command {
+            Command::Monitor(MonitorArgs { connect, .. })
+            | Command::Log(LogArgs { connect, .. })
+            | Command::Info(connect)
+            | Command::Selftest(connect)
+            | Command::Snapshot { connect, .. }
+            | Command::Calibrate { connect, .. } => connect,
+            _ => return Ok(()),
+        };
+        connect.check().map_err(|e| {
+            let mut command = <Self as clap::CommandFactory>::command().bin_name("ut325f");
+            command.error(clap::error::ErrorKind::ArgumentConflict, e)
+        })
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Read the meter and print each reading, or write it to --output.
+    /// On a terminal, keys pause the display, switch units and more;
+    /// press ? to list them.
+    Monitor(MonitorArgs),
+
+    /// Record the meter to a file; like monitor, but the file is
+    /// required and the format defaults to CSV.
+    Log(LogArgs),
+
+    /// Play a raw capture of the meter's byte stream through the same
+    /// filtering, alarms, hooks and output as monitor. With `-`, decode
+    /// raw bytes from stdin as they arrive (e.g. piped from socat).
+    #[command(visible_alias = "decode")]
+    Replay(ReplayArgs),
+
+    /// Read one reading and show the meter's state and link health.
+    Info(ConnectArgs),
+
+    /// Read a few readings, print each channel's average over them
+    /// (ignoring NaN) as one record in --format, and exit: the
+    /// temperature right now, for scripts.
+    Snapshot {
+        /// How many readings to average.
+        #[arg(long, default_value_t = 10,
+              value_parser = clap::value_parser!(u32).range(1..))]
+        samples: u32,
+
+        #[command(flatten)]
+        connect: ConnectArgs,
+    },
+
+    /// List the serial ports on this host.
+    ListPorts,
+
+    /// Discover meters over Bluetooth LE and list them.
+    Discover {
+        /// How long to scan (e.g. 8s, 1m).
+        #[arg(long, value_name = "DURATION", default_value = "8s",
+              value_parser = duration::parse_scan_time)]
+        scan_time: std::time::Duration,
+    },
+
+    /// Check the frame decoder and, given a meter, read from it for a
+    /// few seconds and check the link.
+    Selftest(ConnectArgs),
+
+    /// Work out a channel's gain and offset from readings at two known
+    /// temperatures (an ice bath and boiling water, or a dry block),
+    /// prompting for each, and print them as a --derive expression.
+    Calibrate {
+        /// The channel to calibrate, 1 to 4.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=4))]
+        channel: u8,
+
+        /// How long to average the channel at each point.
+        #[arg(long, value_name = "DURATION", default_value = "10s",
+              value_parser = duration::parse_positive)]
+        average: std::time::Duration,
+
+        #[command(flatten)]
+        connect: ConnectArgs,
+    },
+
+    /// Summarize a recorded log: per-channel min/max/mean/p95, duration,
+    /// and gaps.
+    Stats {
+        /// Log written by this tool, or raw capture
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+
+        /// Intervals between records longer than this count as gaps.
+        #[arg(long, value_name = "DURATION", default_value = "2s",
+              value_parser = duration::parse)]
+        gap: std::time::Duration,
+
+        /// Also fit each channel's slow trend, in °C per day, apart from
+        /// any daily cycle once the log spans two days.
+        #[arg(long)]
+        drift: bool,
+
+        /// The header bytes frames in a raw capture start with, as for
+        /// monitor --sync.
+        #[arg(long, value_name = "HEX", value_parser = parse_sync)]
+        sync: Option<[u8; Reading::N_SYNC_BYTES]>,
+    },
+
+    /// Time-align several logs into one wide table. Rows follow the
+    /// first log; each holds its temperatures followed by the other
+    /// logs' temperatures at that time (NaN if none within tolerance).
+    Merge {
+        /// Logs written by this tool, or raw captures
+        #[arg(required = true, num_args = 2.., value_hint = ValueHint::FilePath)]
+        paths: Vec<PathBuf>,
+
+        /// Write to FILE instead of stdout.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+
+        /// How to align the other logs' records to the first's.
+        #[arg(long, value_enum, default_value_t = merge::Alignment::Nearest)]
+        align: merge::Alignment,
+
+        /// Maximum distance in time to a record used for alignment.
+        #[arg(long, value_name = "DURATION", default_value = "500ms",
+              value_parser = duration::parse)]
+        tolerance: std::time::Duration,
+
+        /// The header bytes frames in a raw capture start with, as for
+        /// monitor --sync.
+        #[arg(long, value_name = "HEX", value_parser = parse_sync)]
+        sync: Option<[u8; Reading::N_SYNC_BYTES]>,
+    },
+
+    /// Downsample a log onto a regular time grid.
+    Resample {
+        /// Log written by this tool, or raw capture
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+
+        /// Write to FILE instead of stdout.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+
+        /// Grid spacing (e.g. 30s, 1m, 2h).
+        #[arg(long, value_parser = duration::parse_positive)]
+        period: std::time::Duration,
+
+        /// How to combine the samples in each period; NaNs are ignored.
+        #[arg(long, value_enum, default_value_t = resample::Aggregation::Mean)]
+        agg: resample::Aggregation,
+
+        /// The header bytes frames in a raw capture start with, as for
+        /// monitor --sync.
+        #[arg(long, value_name = "HEX", value_parser = parse_sync)]
+        sync: Option<[u8; Reading::N_SYNC_BYTES]>,
+    },
+
+    /// Print a shell completion script, e.g.
+    /// `ut325f completions bash > /etc/bash_completion.d/ut325f`.
+    Completions { shell: clap_complete::Shell },
+
+    /// Convert a log or a raw capture of the meter's byte stream to the
+    /// format given by --format. Raw captures carry no timing; their
+    /// records are stamped with the time of conversion. Logs and raw
+    /// captures are converted a piece at a time, however large.
+    #[command(alias = "convert")]
+    Export {
+        /// Log written by this tool, or raw capture
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+
+        /// Write to FILE instead of stdout.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+
+        /// Report how much of the input has been converted on stderr.
+        #[arg(long)]
+        progress: bool,
+
+        /// The header bytes frames in a raw capture start with, as for
+        /// monitor --sync.
+        #[arg(long, value_name = "HEX", value_parser = parse_sync)]
+        sync: Option<[u8; Reading::N_SYNC_BYTES]>,
+    },
+
+    /// Extract the meter's byte stream from a third-party capture (a
+    /// pcap or pcapng USB capture from usbmon or USBPcap, or a Saleae
+    /// Logic async serial CSV export) as a raw capture. Other commands
+    /// read these captures directly; importing drops their timing.
+    Import {
+        /// Third-party capture
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+
+        /// Write to FILE instead of stdout (compressed per its
+        /// extension, as for --output).
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+
+        /// The header bytes the meter's frames start with, as for
+        /// monitor --sync; a capture holding no such frame is refused.
+        #[arg(long, value_name = "HEX", value_parser = parse_sync)]
+        sync: Option<[u8; Reading::N_SYNC_BYTES]>,
+    },
+
+    /// Print the JSON Schema of the NDJSON or json-flat records (for
+    /// the --format, --label and --millidegrees given), the --webhook
+    /// alarm message, the --report session report, or a
+    /// --daily-summary line.
+    Schema {
+        #[arg(value_enum, default_value_t = schema::Kind::Record)]
+        kind: schema::Kind,
+    },
+}
+
+/// Which meter to talk to.
+#[derive(Args, Debug, Clone)]
+pub struct ConnectArgs {
+    /// The serial port to use, or ble://ADDRESS (ble:// alone for the
+    /// only meter discovered) to connect over Bluetooth LE. A pattern
+    /// such as '/dev/ttyUSB*' or several ports read every meter given
+    /// at once (monitor and log only)
+    #[arg(conflicts_with = "ble", value_hint = ValueHint::FilePath)]
+    pub port: Vec<String>,
+
+    /// Connect over Bluetooth LE, either to ADDRESS
+    /// (e.g. E8:26:CF:F1:23:61) or, with no address, to the only meter
+    /// discovered
+    #[arg(short, long, value_name = "ADDRESS", num_args = 0..=1)]
+    pub ble: Option<Option<String>>,
+
+    /// Disconnect the meter on exit. By default it is left connected:
+    /// a connected meter stays awake and the next run finds it without
+    /// a scan.
+    #[arg(long)]
+    pub disconnect: bool,
+
+    /// How long to scan for a meter (e.g. 8s), for --ble without an
+    /// address [default: 8s].
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse_scan_time)]
+    pub scan_time: Option<std::time::Duration>,
+
+    /// Tag every record (and Grafana point) with NAME, so the meter's
+    /// data stays attributable once merged with other meters'. Several
+    /// meters read at once are tagged with the USB serial numbers of
+    /// their adapters, or else their port names.
+    #[arg(long, value_name = "NAME")]
+    pub name: Option<String>,
+
+    /// Try the usual baud rates in turn until frames decode, for clones
+    /// and firmware that do not run at 115200
+    #[arg(long, conflicts_with = "ble")]
+    pub auto_baud: bool,
+
+    /// The serial port's baud rate [default: 115200]
+    #[arg(long, value_name = "RATE", conflicts_with_all = ["ble", "auto_baud"])]
+    pub baud: Option<u32>,
+
+    /// The serial port's parity [default: none]
+    #[arg(long, value_enum, conflicts_with = "ble")]
+    pub parity: Option<Parity>,
+
+    /// The serial port's stop bits [default: 1]
+    #[arg(long, value_name = "BITS", value_parser = clap::value_parser!(u8).range(1..=2),
+          conflicts_with = "ble")]
+    pub stop_bits: Option<u8>,
+
+    /// Keep retrying a serial port that fails to open, e.g. one whose
+    /// adapter is still being plugged in, for up to DURATION
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse,
+          conflicts_with = "ble")]
+    pub open_timeout: Option<std::time::Duration>,
+}
+
+/// The scheme of a PORT that names a Bluetooth meter.
+const BLE_SCHEME: &str = "ble://";
+
+fn is_ble_port(port: &str) -> bool {
+    port.starts_with(BLE_SCHEME)
+}
+
+/// A serial port's parity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+impl ConnectArgs {
+    /// True if a port or --ble was given.
+    pub fn is_given(&self) -> bool {
+        !self.port.is_empty() || self.ble.is_some()
+    }
+
+    /// The Bluetooth meter given by --ble or a single ble:// port: its
+    /// address, or `None` for the only meter discovered.
+    pub fn ble_target(&self) -> Option<Option<&str>> {
+        if let Some(address) = &self.ble {
+            return Some(address.as_deref());
+        }
+        match &self.port[..] {
+            [port] => {
+                let address = port.strip_prefix(BLE_SCHEME)?;
+                Some(Some(address).filter(|address| !address.is_empty()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Rejects the options that fit none of the meters given, which
+    /// clap cannot tell apart when BLE comes as a ble:// port.
+    pub fn check(&self) -> Result<(), String> {
+        let any_ble = self.ble.is_some() || self.port.iter().any(|port| is_ble_port(port));
+        let any_serial = self.port.iter().any(|port| !is_ble_port(port));
+        let serial = [
+            ("--auto-baud", self.auto_baud),
+            ("--baud", self.baud.is_some()),
+            ("--parity", self.parity.is_some()),
+            ("--stop-bits", self.stop_bits.is_some()),
+            ("--open-timeout", self.open_timeout.is_some()),
+        ];
+        let ble = [
+            ("--disconnect", self.disconnect),
+            ("--scan-time", self.scan_time.is_some()),
+        ];
+        if let Some((flag, _)) = serial.iter().find(|(_, given)| *given && !any_serial) {
+            return Err(format!("{flag} needs a serial port"));
+        }
+        if let Some((flag, _)) = ble.iter().find(|(_, given)| *given && !any_ble) {
+            return Err(format!("{flag} needs --ble or a ble:// port"));
+        }
+        Ok(())
+    }
+
+    /// Describes the meter's connection for reports.
+    pub fn source(&self) -> String {
+        match (self.ble_target(), &self.port[..]) {
+            (Some(Some(address)), _) => format!("ble:{address}"),
+            (Some(None), _) => "ble".to_owned(),
+            (None, []) => String::new(),
+            (None, ports) => format!("serial:{}", ports.join(",")),
+        }
+    }
+
+    /// The serial line settings given, over the meter's defaults.
+    #[cfg(feature = "serial")]
+    pub fn serial_config(&self) -> ut325f_rs::SerialConfig {
+        let mut config = ut325f_rs::SerialConfig::new();
+        if let Some(baud) = self.baud {
+            config = config.with_baud_rate(baud);
+        }
+        if let Some(parity) = self.parity {
+            config = config.with_parity(match parity {
+                Parity::None => ut325f_rs::Parity::None,
+                Parity::Odd => ut325f_rs::Parity::Odd,
+                Parity::Even => ut325f_rs::Parity::Even,
+            });
+        }
+        if let Some(stop_bits) = self.stop_bits {
+            config = config.with_stop_bits(match stop_bits {
+                2 => ut325f_rs::StopBits::Two,
+                _ => ut325f_rs::StopBits::One,
+            });
+        }
+        if let Some(timeout) = self.open_timeout {
+            config = config.with_open_timeout(timeout);
+        }
+        config
+    }
+
+    /// The same connection, to `port` alone.
+    pub fn with_port(&self, port: String) -> Self {
+        Self {
+            port: vec![port],
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct MonitorArgs {
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// Write to FILE instead of stdout, compressed if FILE ends in .gz
+    /// or .zst (requires the gzip or zstd feature); may be repeated.
+    /// Append ,format=FORMAT to override --format for this output and
+    /// ,every=INTERVAL to write at most one reading per interval, e.g.
+    /// -o - -o 'minutes.csv,format=csv,every=1m'. `-` is stdout.
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub output: Vec<sink::OutputSpec>,
+
+    #[command(flatten)]
+    pub rotation: RotationArgs,
+
+    #[command(flatten)]
+    pub buffering: BufferArgs,
+
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+
+    #[command(flatten)]
+    pub pipeline: PipelineArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// The log file, compressed if it ends in .gz or .zst (requires the
+    /// gzip or zstd feature); may be repeated, with the same options as
+    /// monitor's --output.
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath, required = true)]
+    pub output: Vec<sink::OutputSpec>,
+
+    #[command(flatten)]
+    pub rotation: RotationArgs,
+
+    #[command(flatten)]
+    pub buffering: BufferArgs,
+
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+
+    #[command(flatten)]
+    pub pipeline: PipelineArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// Raw capture of the meter's byte stream, optionally compressed,
+    /// or - for stdin
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub path: PathBuf,
+
+    /// Write to FILE instead of stdout; may be repeated, with the same
+    /// options as monitor's --output.
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub output: Vec<sink::OutputSpec>,
+
+    /// Reproduce a timestamped capture's original timing, FACTOR times
+    /// faster (1 for real time), instead of replaying it at once.
+    #[arg(long, value_name = "FACTOR", value_parser = parse_speed)]
+    pub speed: Option<f64>,
+
+    #[command(flatten)]
+    pub rotation: RotationArgs,
+
+    #[command(flatten)]
+    pub buffering: BufferArgs,
+
+    #[command(flatten)]
+    pub pipeline: PipelineArgs,
+}
+
+/// Splitting a live log into files by time.
+#[derive(Args, Debug)]
+pub struct RotationArgs {
+    /// Start a new output file every INTERVAL (e.g. 1h, 1d), aligned to
+    /// UTC. Each file is named after its --output with its start time
+    /// inserted, e.g. session-20250101T000000Z.csv.
+    #[arg(long, value_name = "INTERVAL", requires = "output",
+          value_parser = duration::parse_interval)]
+    pub rotate: Option<std::time::Duration>,
+
+    /// With --rotate, keep only the newest N files.
+    #[arg(long, value_name = "N", requires = "rotate",
+          value_parser = clap::value_parser!(u64).range(1..))]
+    pub keep: Option<u64>,
+
+    /// With --rotate, delete the oldest files while all of them
+    /// together exceed SIZE (e.g. 500M, 2G).
+    #[arg(long, value_name = "SIZE", requires = "rotate", value_parser = sink::parse_size)]
+    pub max_total_size: Option<u64>,
+}
+
+impl RotationArgs {
+    pub fn rotation(&self) -> Option<sink::Rotation> {
+        self.rotate.map(|interval| sink::Rotation {
+            interval,
+            keep: self.keep.map(|keep| keep as usize),
+            max_total_size: self.max_total_size,
+        })
+    }
+}
+
+/// How a live log's output files are buffered.
+#[derive(Args, Debug)]
+pub struct BufferArgs {
+    /// Write buffered output to the file at least this often (default
+    /// 1s). Files are written on a background task, so a slow disk does
+    /// not hold up reading the meter; each file is fsynced when closed
+    /// or rotated.
+    #[arg(long, value_name = "INTERVAL", value_parser = duration::parse)]
+    pub flush_interval: Option<std::time::Duration>,
+
+    /// Flush stdout and output files after every record, for scripts
+    /// watching the output live (e.g. through tee or grep).
+    #[arg(long, conflicts_with = "flush_every")]
+    pub line_buffered: bool,
+
+    /// Flush stdout and output files after every N records; stdout is
+    /// otherwise line-buffered.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub flush_every: Option<u64>,
+}
+
+impl BufferArgs {
+    pub fn flush_policy(&self) -> background::FlushPolicy {
+        let policy = background::FlushPolicy::default();
+        background::FlushPolicy {
+            interval: self.flush_interval.unwrap_or(policy.interval),
+            records: if self.line_buffered {
+                Some(1)
+            } else {
+                self.flush_every
+            },
+            ..policy
+        }
+    }
+}
+
+/// Reading a live meter only at times.
+#[derive(Args, Debug)]
+pub struct ScheduleArgs {
+    /// Read the meter only for DURATION (e.g. 10s) at the start of
+    /// every --sample-every, closing the port (disconnecting a BLE
+    /// meter) in between to save power on battery-powered loggers.
+    #[arg(long, value_name = "DURATION", requires = "sample_every",
+          value_parser = duration::parse_positive)]
+    pub sample_for: Option<std::time::Duration>,
+
+    /// How often to read the meter for --sample-for (e.g. 5m), aligned
+    /// to UTC.
+    #[arg(long, value_name = "INTERVAL", requires = "sample_for",
+          value_parser = duration::parse_interval)]
+    pub sample_every: Option<std::time::Duration>,
+
+    /// Read the meter only between START and END each day, e.g.
+    /// 08:00-18:00 (or 22:00-06:00 overnight), in local time unless
+    /// --utc-offset is given; may be repeated.
+    #[arg(long, value_name = "START-END", conflicts_with_all = ["sample_for", "sample_every"])]
+    pub between: Vec<schedule::DailyWindow>,
+
+    /// The offset from UTC of the times given to --between or
+    /// --schedule, and of the midnight that ends a --daily-summary day,
+    /// e.g. +01:00 or -05:00. It is fixed: daylight saving time is not
+    /// followed, as it is by --between's default of local time.
+    #[arg(long, value_name = "OFFSET", allow_hyphen_values = true,
+          value_parser = schedule::parse_utc_offset)]
+    pub utc_offset: Option<i64>,
+
+    /// Keep the meter open outside the --between or --sample-for
+    /// windows, discarding its readings, instead of closing the port
+    /// (or disconnecting a BLE meter) until the next one.
+    #[arg(long)]
+    pub hold_port: bool,
+
+    /// Start a session of --duration at the times a cron expression
+    /// gives, e.g. '0 */6 * * *' for every six hours, in UTC unless
+    /// --utc-offset is given. Each session writes its own output files
+    /// and --report, named with the session's start time inserted as
+    /// with --rotate.
+    #[arg(long = "schedule", value_name = "CRON", requires = "duration",
+          conflicts_with_all = ["between", "sample_for", "sample_every", "hold_port", "rotate"])]
+    pub cron: Option<schedule::Cron>,
+
+    /// End the session after DURATION (e.g. 10m); with --schedule,
+    /// each session.
+    #[arg(long, value_name = "DURATION", conflicts_with_all = ["between", "sample_for"],
+          value_parser = duration::parse_positive)]
+    pub duration: Option<std::time::Duration>,
+
+    /// At each midnight, in UTC unless --utc-offset is given, and at
+    /// the end of the session, append a JSON line summarizing the day
+    /// to FILE: each channel's min, max and mean, alarms raised, uptime,
+    /// and the percentage of seconds up that brought a reading.
+    #[arg(long, value_name = "FILE")]
+    pub daily_summary: Option<PathBuf>,
+
+    /// Write one reading, then close the meter and exit; the exit
+    /// status still reports an --alarm it raised.
+    #[arg(long, conflicts_with_all = ["between", "sample_for", "sample_every", "cron", "duration"])]
+    pub once: bool,
+}
+
+impl ScheduleArgs {
+    pub fn schedule(&self) -> Result<Option<schedule::Schedule>> {
+        if self.utc_offset.is_some()
+            && self.between.is_empty()
+            && self.cron.is_none()
+            && self.daily_summary.is_none()
+        {
+            return Err(anyhow!(
+                "--utc-offset needs --between, --schedule or --daily-summary"
+            ));
+        }
+        if !self.between.is_empty() {
+            return Ok(Some(schedule::Schedule::Daily {
+                windows: self.between.clone(),
+                utc_offset: self.utc_offset,
+            }));
+        }
+        let (Some(window), Some(every)) = (self.sample_for, self.sample_every) else {
+            if self.hold_port {
+                return Err(anyhow!("--hold-port needs --between or --sample-for"));
+            }
+            return Ok(None);
+        };
+        if window.is_zero() || window >= every {
+            return Err(anyhow!(
+                "--sample-for must be positive and shorter than --sample-every"
+            ));
+        }
+        Ok(Some(schedule::Schedule::Periodic { window, every }))
+    }
+}
+
+/// What happens to each reading between the meter and the output.
+#[derive(Args, Debug)]
+pub struct PipelineArgs {
+    /// Print the held temperatures as well.
+    #[arg(short = 'H', long)]
+    pub held_temps: bool,
+
+    /// Stop with an error on a corrupted frame or one the meter is not
+    /// known to send (non-zero unknown bytes, unflagged non-finite
+    /// values), instead of skipping it; for validating firmware.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Keep frames that fail their checksum or hold values the meter is
+    /// not known to send, instead of skipping them, and add a validity
+    /// column saying which checks each reading's frame failed; for
+    /// noisy links, where marginal data beats none.
+    #[arg(long, conflicts_with = "strict")]
+    pub tolerant: bool,
+
+    /// The five header bytes frames start with, in hex (e.g.
+    /// aa:55:00:34:02), for firmware revisions that send another
+    /// command byte than the usual aa:55:00:34:01.
+    #[arg(long, value_name = "HEX", value_parser = parse_sync)]
+    pub sync: Option<[u8; Reading::N_SYNC_BYTES]>,
+
+    /// Add a quality object to NDJSON and json-flat records, for
+    /// auditing a log: whether the checksum matched, bytes skipped to
+    /// find the frame, jitter in its arrival, and channels the
+    /// plausibility filter blanked.
+    #[arg(long)]
+    pub quality: bool,
+
+    /// While no readings arrive, write a heartbeat to NDJSON outputs
+    /// every INTERVAL (e.g. 10s) and keep waiting, instead of giving up
+    /// after 5 s without data.
+    #[arg(long, value_name = "INTERVAL", value_parser = duration::parse_positive)]
+    pub heartbeat: Option<std::time::Duration>,
+
+    /// Print each accepted frame as annotated hex on stdout, before its
+    /// decoded record (alongside, the default) or instead of the
+    /// records stdout would otherwise get (--raw-hex=only).
+    #[arg(long, value_enum, value_name = "MODE", num_args = 0..=1,
+          require_equals = true, default_missing_value = "alongside")]
+    pub raw_hex: Option<hexdump::RawHex>,
+
+    /// With --raw-hex, also dump the bytes skipped between frames
+    /// (line noise and rejected frames).
+    #[arg(long, requires = "raw_hex")]
+    pub raw_hex_skipped: bool,
+
+    /// Treat temperatures below C as corrupt: log NaN instead and warn.
+    #[arg(long, value_name = "C", allow_negative_numbers = true)]
+    pub plausible_min: Option<f32>,
+
+    /// Treat temperatures above C as corrupt: log NaN instead and warn.
+    #[arg(long, value_name = "C", allow_negative_numbers = true)]
+    pub plausible_max: Option<f32>,
+
+    /// Treat a change of more than C from a channel's previous sample as
+    /// corrupt, unless it persists for three samples.
+    #[arg(long, value_name = "C")]
+    pub max_step: Option<f32>,
+
+    /// The thermocouple type on a channel, e.g. T1=K; may be repeated.
+    /// Temperatures the type cannot produce are treated as corrupt, and
+    /// --report lists the types.
+    #[arg(long, value_name = "CHANNEL=TYPE", value_parser = parse_thermocouple)]
+    pub thermocouple: Vec<(usize, ThermocoupleType)>,
+
+    /// Compare the other channels against CHANNEL, e.g. a calibrated
+    /// probe on T1: their bias, drift and largest deviation from it are
+    /// logged with -v every minute and at the end, and go in --report.
+    #[arg(long, value_name = "CHANNEL", value_parser = parse_channel)]
+    pub reference: Option<usize>,
+
+    /// Fit each channel's slow trend, in °C per day with a 95%
+    /// confidence interval, apart from any daily cycle once readings
+    /// span two days; logged with -v every minute and at the end.
+    #[arg(long)]
+    pub drift: bool,
+
+    /// Raise an alarm while a channel is beyond a limit, e.g. T1>80 or
+    /// T2<5, optionally clearing only past another level and after a
+    /// dwell time, e.g. 'T1>80 clear=75 for=10s', or while a condition
+    /// over several channels holds, written as for --derive with
+    /// comparisons and && || !, e.g. 't1 > 80 && t2 - t3 > 5 for 30s';
+    /// may be repeated. Alarms are reported on stderr.
+    #[arg(long, value_name = "RULE")]
+    pub alarm: Vec<Rule>,
+
+    /// Ring the terminal bell when an alarm is raised.
+    #[arg(long, requires = "alarm")]
+    pub bell: bool,
+
+    /// Show a desktop notification when an alarm is raised or cleared.
+    #[arg(long, requires = "alarm")]
+    pub notify: bool,
+
+    /// Switch a relay on while any alarm is raised, and off when all
+    /// have cleared: a GPIO line by sysfs number (gpio:17), or an LCUS
+    /// USB relay board (serial:/dev/ttyUSB1, or serial:/dev/ttyUSB1:2
+    /// for its second relay). It is switched off at the start.
+    #[arg(long, value_name = "RELAY", requires = "alarm")]
+    pub relay: Option<relay::RelaySpec>,
+
+    /// POST each alarm raise and clear as JSON to URL (e.g. a Slack or
+    /// Teams incoming webhook).
+    #[arg(long, value_name = "URL", requires = "alarm", value_hint = ValueHint::Url)]
+    pub webhook: Option<String>,
+
+    /// Email each alarm raise and clear to ADDRESS; may be repeated.
+    /// The SMTP password, if any, is read from UT325F_SMTP_PASSWORD.
+    #[arg(long, value_name = "ADDRESS", requires_all = ["alarm", "smtp_server", "email_from"],
+          value_hint = ValueHint::EmailAddress)]
+    pub email_to: Vec<String>,
+
+    /// Sender address for alarm emails.
+    #[arg(long, value_name = "ADDRESS", value_hint = ValueHint::EmailAddress)]
+    pub email_from: Option<String>,
+
+    /// SMTP server for alarm emails, reached over TLS.
+    #[arg(long, value_name = "HOST", value_hint = ValueHint::Hostname)]
+    pub smtp_server: Option<String>,
+
+    /// SMTP user name.
+    #[arg(long, value_name = "USER", value_hint = ValueHint::Username)]
+    pub smtp_user: Option<String>,
+
+    /// Send at most one webhook or email message per alarm per INTERVAL;
+    /// changes in between are counted in the next message.
+    #[arg(long, value_name = "INTERVAL", default_value = "5m",
+          value_parser = duration::parse)]
+    pub alarm_interval: std::time::Duration,
+
+    /// Run CMD through the shell on each alarm raise and clear, with
+    /// the reading in UT325F_T1..UT325F_T4 etc. and the alarm in
+    /// UT325F_ALARM, UT325F_ALARM_STATE (raised or cleared),
+    /// UT325F_ALARM_CHANNEL and UT325F_ALARM_TEMP.
+    #[arg(long, value_name = "CMD", requires = "alarm", value_hint = ValueHint::CommandString)]
+    pub on_alarm: Option<String>,
+
+    /// Run CMD through the shell on each reading, with UT325F_TIMESTAMP,
+    /// UT325F_T1..UT325F_T4 and, where known, UT325F_METER_TEMP,
+    /// UT325F_HOLD_TYPE and UT325F_HELD1..UT325F_HELD4 set. A reading
+    /// arriving while CMD is still running is skipped.
+    #[arg(long, value_name = "CMD", value_hint = ValueHint::CommandString)]
+    pub on_reading: Option<String>,
+
+    /// Push each reading to a Grafana Live stream at URL, e.g.
+    /// http://localhost:3000/api/live/push/ut325f. The API token is
+    /// read from UT325F_GRAFANA_TOKEN.
+    #[arg(long, value_name = "URL", value_hint = ValueHint::Url)]
+    pub grafana: Option<String>,
+
+    /// On exit, write a JSON report of the session to FILE: source,
+    /// start and end times, per-channel statistics, alarm events, and
+    /// link error counters.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub report: Option<PathBuf>,
+
+    /// Append every alarm raised and cleared to FILE as a JSON line,
+    /// apart from the data and never rotated; a clear adds how long the
+    /// alarm was raised and the peak temperature it reached.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub alarm_log: Option<PathBuf>,
+
+    /// Listen for HTTP at ADDR (e.g. 127.0.0.1:8325) and mark the data
+    /// with the note each POST /annotate carries as its body, as plain
+    /// text or JSON {"note": "..."}. A line typed on the terminal, or
+    /// SIGUSR1, marks it too. Anyone who can reach ADDR can add notes,
+    /// unauthenticated, so keep it on a loopback address.
+    #[arg(long, value_name = "ADDR")]
+    pub annotate_listen: Option<std::net::SocketAddr>,
+}
+
+impl PipelineArgs {
+    /// Rejects options that need a feature this build lacks.
+    pub fn check_features(&self) -> Result<()> {
+        #[cfg(not(feature = "notify"))]
+        if self.notify {
+            return Err(anyhow!(
+                "Built without notification support; rebuild with `--features notify`"
+            ));
+        }
+        #[cfg(not(feature = "webhook"))]
+        if self.webhook.is_some() {
+            return Err(anyhow!(
+                "Built without webhook support; rebuild with `--features webhook`"
+            ));
+        }
+        #[cfg(not(feature = "grafana"))]
+        if self.grafana.is_some() {
+            return Err(anyhow!(
+                "Built without Grafana support; rebuild with `--features grafana`"
+            ));
+        }
+        #[cfg(not(feature = "email"))]
+        if !self.email_to.is_empty() {
+            return Err(anyhow!(
+                "Built without email support; rebuild with `--features email`"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Number formatting options for commands that write records.
+#[derive(Args, Debug)]
+pub struct StyleArgs {
+    /// How to write NaN temperatures: nan, null, empty (CSV only), or a
+    /// sentinel number such as -9999 [default: nan, or null for JSON].
+    #[arg(
+        long,
+        value_name = "REPR",
+        global = true,
+        allow_negative_numbers = true
+    )]
+    pub nan: Option<format::NanRepr>,
+
+    /// Decimal places for temperatures. The meter resolves 0.1 °C.
+    #[arg(long, value_name = "N", global = true, default_value_t = 3,
+          value_parser = clap::value_parser!(u8).range(0..=6))]
+    pub precision: u8,
+
+    /// Write temperatures as integer millidegrees C. CSV columns and
+    /// NDJSON fields get an _mc suffix; text logs carry no units, so
+    /// subcommands read them back as degrees.
+    #[arg(long, global = true, conflicts_with = "precision")]
+    pub millidegrees: bool,
+
+    /// Name a channel, e.g. T1=oven, for the json-flat format's field
+    /// names [default: t1..t4]; may be repeated.
+    #[arg(long, value_name = "CHANNEL=NAME", global = true)]
+    pub label: Vec<format::ChannelLabel>,
+
+    /// Add a channel computed from each reading's temperatures, e.g.
+    /// 'delta = t1 - t2' or 'avg_all = mean(t1..t4)', written after the
+    /// measured ones; may be repeated. Expressions take t1..t4, numbers,
+    /// the operators + - * / and parentheses, abs(), and mean(), min()
+    /// and max() over channels, ranges and expressions, skipping open
+    /// channels.
+    #[arg(long, value_name = "NAME=EXPR", global = true)]
+    pub derive: Vec<ut325f_rs::DerivedChannel>,
+
+    /// The CSV field separator.
+    #[arg(long, value_enum, value_name = "DELIMITER", global = true,
+          default_value_t = format::Delimiter::Comma)]
+    pub csv_delimiter: format::Delimiter,
+
+    /// Enclose every CSV field in double quotes.
+    #[arg(long, global = true)]
+    pub csv_quote: bool,
+
+    /// Write CSV numbers with a decimal comma, as spreadsheets set up
+    /// for many European locales expect; needs --csv-delimiter
+    /// semicolon or tab, or --csv-quote.
+    #[arg(long, global = true)]
+    pub decimal_comma: bool,
+}
+
+impl StyleArgs {
+    pub fn style(&self, format: format::Format) -> Result<format::Style> {
+        if format == format::Format::Parquet {
+            return Err(anyhow!("parquet is written by export alone"));
+        }
+        if let Some(nan) = self.nan {
+            nan.check(format).map_err(|e| anyhow!("--nan: {e}"))?;
+        }
+        let mut style = format::Style {
+            nan: self.nan,
+            precision: self.precision.into(),
+            millidegrees: self.millidegrees,
+            csv: format::CsvDialect {
+                delimiter: self.csv_delimiter,
+                quote: self.csv_quote,
+                decimal_comma: self.decimal_comma,
+            },
+            ..format::Style::default()
+        };
+        style
+            .csv
+            .check()
+            .map_err(|e| anyhow!("--decimal-comma: {e}"))?;
+        for label in &self.label {
+            style.labels[label.channel] = label.name.clone();
+        }
+        let reserved = [
+            "timestamp",
+            "meter",
+            "seq",
+            "hold_type",
+            "meter_temp",
+            "validity",
+            "quality",
+        ];
+        for (i, label) in style.labels.iter().enumerate() {
+            if reserved.contains(&label.as_str()) || style.labels[..i].contains(label) {
+                return Err(anyhow!("--label: '{label}' is used twice or reserved"));
+            }
+        }
+        // Derived channels share the field namespace of every format.
+        for (i, derived) in self.derive.iter().enumerate() {
+            let name = derived.name.as_str();
+            let taken = reserved.contains(&name)
+                || ["temps", "held_temps"].contains(&name)
+                || (1..=4).any(|n| name == format!("t{n}") || name == format!("held{n}"))
+                || style.labels.iter().any(|label| label == name)
+                || self.derive[..i].iter().any(|other| other.name == name);
+            if taken {
+                return Err(anyhow!("--derive: '{name}' is used twice or reserved"));
+            }
+        }
+        style.derived = self.derive.clone();
+        Ok(style)
+    }
+}
+
+/// Parses `T1` into a zero-based channel.
+fn parse_channel(s: &str) -> std::result::Result<usize, String> {
+    match s.trim() {
+        "T1" | "t1" => Ok(0),
+        "T2" | "t2" => Ok(1),
+        "T3" | "t3" => Ok(2),
+        "T4" | "t4" => Ok(3),
+        other => Err(format!("unknown channel '{other}'; expected T1 to T4")),
+    }
+}
+
+/// Parses `T1=K` into a zero-based channel and a type.
+fn parse_thermocouple(s: &str) -> std::result::Result<(usize, ThermocoupleType), String> {
+    let (channel, kind) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected CHANNEL=TYPE, e.g. T1=K, got '{s}'"))?;
+    let channel = parse_channel(channel)?;
+    let kind = kind.parse().map_err(|e: ut325f_rs::Error| e.to_string())?;
+    Ok((channel, kind))
+}
+
+/// Parses a frame header such as `aa:55:00:34:02` or `aa55003402`.
+fn parse_sync(s: &str) -> std::result::Result<[u8; Reading::N_SYNC_BYTES], String> {
+    let digits: String = s.chars().filter(|c| !matches!(c, ':' | ' ')).collect();
+    let bytes = (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            digits
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format!("'{s}' is not hex bytes"))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        format!(
+            "expected {} bytes, got {}",
+            Reading::N_SYNC_BYTES,
+            bytes.len()
+        )
+    })
+}
+
+fn parse_speed(s: &str) -> std::result::Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => Err("must be a positive number".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_parse_sync() {
+        assert_eq!(
+            parse_sync("aa:55:00:34:02"),
+            Ok([0xaa, 0x55, 0x00, 0x34, 0x02])
+        );
+        assert_eq!(parse_sync("AA55003402"), Ok([0xaa, 0x55, 0x00, 0x34, 0x02]));
+        assert!(parse_sync("aa:55:00:34").is_err());
+        assert!(parse_sync("aa:55:00:34:0").is_err());
+        assert!(parse_sync("zz:55:00:34:02").is_err());
+    }
+
+    #[test]
+    fn test_raw_hex_takes_no_separate_value() {
+        let cli = Cli::try_parse_from(["ut325f", "replay", "--raw-hex", "a.raw"]).unwrap();
+        let Command::Replay(replay) = cli.command else {
+            panic!("expected replay");
+        };
+        assert_eq!(replay.pipeline.raw_hex, Some(hexdump::RawHex::Alongside));
+        assert_eq!(replay.path, PathBuf::from("a.raw"));
+        assert!(Cli::try_parse_from(["ut325f", "replay", "--raw-hex=only", "a.raw"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_monitor() {
+        let cli = Cli::try_parse_from([
+            "ut325f",
+            "monitor",
+            "/dev/ttyUSB0",
+            "--alarm",
+            "T1>80",
+            "--format",
+            "csv",
+        ])
+        .unwrap();
+        assert_eq!(cli.format, Some(format::Format::Csv));
+        let Command::Monitor(monitor) = cli.command else {
+            panic!("expected monitor");
+        };
+        assert_eq!(monitor.connect.source(), "serial:/dev/ttyUSB0");
+        assert_eq!(monitor.pipeline.alarm.len(), 1);
+    }
+
+    #[cfg(feature = "serial")]
+    #[test]
+    fn test_serial_config() {
+        let cli = Cli::try_parse_from([
+            "ut325f",
+            "info",
+            "/dev/ttyUSB0",
+            "--baud",
+            "9600",
+            "--parity",
+            "even",
+            "--stop-bits",
+            "2",
+            "--open-timeout",
+            "5s",
+        ])
+        .unwrap();
+        let Command::Info(connect) = cli.command else {
+            panic!("expected info");
+        };
+        assert_eq!(
+            connect.serial_config(),
+            ut325f_rs::SerialConfig::new()
+                .with_baud_rate(9600)
+                .with_parity(ut325f_rs::Parity::Even)
+                .with_stop_bits(ut325f_rs::StopBits::Two)
+                .with_open_timeout(std::time::Duration::from_secs(5))
+        );
+        assert!(
+            Cli::try_parse_from(["ut325f", "info", "--stop-bits", "3", "/dev/ttyUSB0"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_from([
+                "ut325f",
+                "info",
+                "--baud",
+                "9600",
+                "--auto-baud",
+                "/dev/ttyUSB0"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ble_port() {
+        let connect = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["ut325f", "info"].iter().chain(args)).unwrap();
+            let Command::Info(connect) = cli.command else {
+                panic!("expected info");
+            };
+            connect
+        };
+        let url = connect(&["ble://E8:26:CF:F1:23:61", "--disconnect"]);
+        assert_eq!(url.ble_target(), Some(Some("E8:26:CF:F1:23:61")));
+        assert_eq!(url.source(), "ble:E8:26:CF:F1:23:61");
+        assert_eq!(url.check(), Ok(()));
+        let scan = connect(&["ble://", "--scan-time", "20s"]);
+        assert_eq!(scan.ble_target(), Some(None));
+        assert_eq!(scan.source(), "ble");
+        assert_eq!(connect(&["-b"]).ble_target(), Some(None));
+        let serial = connect(&["/dev/ttyUSB0"]);
+        assert_eq!(serial.ble_target(), None);
+        assert_eq!(serial.check(), Ok(()));
+        assert!(connect(&["/dev/ttyUSB0", "--disconnect"]).check().is_err());
+        assert!(connect(&["ble://", "--baud", "9600"]).check().is_err());
+        let mixed = connect(&["/dev/ttyUSB0", "ble://", "--baud", "9600", "--disconnect"]);
+        assert_eq!(mixed.ble_target(), None);
+        assert_eq!(mixed.check(), Ok(()));
+    }
+
+    #[test]
+    fn test_log_requires_output() {
+        assert!(Cli::try_parse_from(["ut325f", "log", "/dev/ttyUSB0"]).is_err());
+        assert!(Cli::try_parse_from(["ut325f", "log", "/dev/ttyUSB0", "-o", "a.csv"]).is_ok());
+    }
+
+    #[test]
+    fn test_export_accepts_to() {
+        let cli = Cli::try_parse_from(["ut325f", "convert", "a.raw", "--to", "ndjson"]).unwrap();
+        assert_eq!(cli.format, Some(format::Format::Ndjson));
+        assert!(matches!(cli.command, Command::Export { .. }));
+    }
+
+    #[test]
+    fn test_completions() {
+        use clap::CommandFactory;
+        let mut script = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::Bash,
+            &mut Cli::command(),
+            "ut325f",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("list-ports"));
+        assert!(script.contains("ndjson"));
+    }
+
+    #[test]
+    fn test_decode_stdin() {
+        let cli = Cli::try_parse_from(["ut325f", "decode", "-", "-f", "ndjson"]).unwrap();
+        let Command::Replay(replay) = cli.command else {
+            panic!("expected replay");
+        };
+        assert_eq!(replay.path, PathBuf::from("-"));
+    }
+
+    #[test]
+    fn test_rotate_requires_output() {
+        assert!(Cli::try_parse_from(["ut325f", "monitor", "p", "--rotate", "1h"]).is_err());
+    }
+}
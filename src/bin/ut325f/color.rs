@@ -0,0 +1,83 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// When to color output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum ColorChoice {
+    /// On a terminal, unless the NO_COLOR environment variable is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color a stream that is, or is not, a terminal.
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            Self::Auto => terminal && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+
+    pub fn stdout(self) -> bool {
+        self.enabled(std::io::stdout().is_terminal())
+    }
+}
+
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Decides once whether diagnostics on stderr are colored; never as
+/// JSON lines.
+pub fn init(choice: ColorChoice, json: bool) {
+    let enabled = !json && choice.enabled(std::io::stderr().is_terminal());
+    STDERR.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether diagnostics on stderr are colored.
+pub fn stderr() -> bool {
+    STDERR.load(Ordering::Relaxed)
+}
+
+/// An ANSI SGR color or attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Paint {
+    Bold,
+    Dim,
+    Red,
+    Green,
+}
+
+impl Paint {
+    fn code(self) -> u8 {
+        match self {
+            Self::Bold => 1,
+            Self::Dim => 2,
+            Self::Red => 31,
+            Self::Green => 32,
+        }
+    }
+
+    /// `text` in this color if `enabled`, else as it is.
+    pub fn apply(self, text: &str, enabled: bool) -> String {
+        match enabled {
+            true => format!("\x1b[{}m{text}\x1b[0m", self.code()),
+            false => text.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice() {
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+        assert_eq!(Paint::Red.apply("T1>80", true), "\x1b[31mT1>80\x1b[0m");
+        assert_eq!(Paint::Red.apply("T1>80", false), "T1>80");
+    }
+}
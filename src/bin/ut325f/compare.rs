@@ -0,0 +1,140 @@
+use crate::records::Record;
+
+/// How the channels deviate from a reference channel over a session,
+/// for --reference.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    reference: usize,
+    start: Option<f64>,
+    channels: [Deviation; 4],
+}
+
+/// Running sums of one channel's deviation `d` from the reference at
+/// time `t` (seconds since the first record), enough for a mean and a
+/// least-squares slope.
+#[derive(Debug, Clone, Copy, Default)]
+struct Deviation {
+    count: usize,
+    sum_d: f64,
+    sum_t: f64,
+    sum_tt: f64,
+    sum_td: f64,
+    max_abs: f64,
+}
+
+/// One channel's deviation from the reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    /// Mean deviation, in °C.
+    pub bias: f64,
+    /// How fast the deviation changes, in °C per hour; `None` until
+    /// readings span some time.
+    pub drift_per_h: Option<f64>,
+    /// The largest deviation either way, in °C.
+    pub max_deviation: f64,
+}
+
+impl Comparison {
+    /// Compares channels against `reference` (zero-based).
+    pub fn new(reference: usize) -> Self {
+        Self {
+            reference,
+            start: None,
+            channels: [Deviation::default(); 4],
+        }
+    }
+
+    pub fn reference(&self) -> usize {
+        self.reference
+    }
+
+    pub fn push(&mut self, record: &Record) {
+        let reference = f64::from(record.temps_c[self.reference]);
+        if reference.is_nan() {
+            return;
+        }
+        let t = record.timestamp - *self.start.get_or_insert(record.timestamp);
+        for (channel, &temp) in record.temps_c.iter().enumerate() {
+            if channel == self.reference || temp.is_nan() {
+                continue;
+            }
+            let d = f64::from(temp) - reference;
+            let deviation = &mut self.channels[channel];
+            deviation.count += 1;
+            deviation.sum_d += d;
+            deviation.sum_t += t;
+            deviation.sum_tt += t * t;
+            deviation.sum_td += t * d;
+            deviation.max_abs = deviation.max_abs.max(d.abs());
+        }
+    }
+
+    /// The deviation of `channel`, or `None` for the reference itself
+    /// or a channel that never had a value alongside it.
+    pub fn summary(&self, channel: usize) -> Option<Summary> {
+        let deviation = self.channels[channel];
+        if channel == self.reference || deviation.count == 0 {
+            return None;
+        }
+        let n = deviation.count as f64;
+        let variance = deviation.sum_tt - deviation.sum_t * deviation.sum_t / n;
+        let drift_per_h = (variance > 1e-9).then(|| {
+            let covariance = deviation.sum_td - deviation.sum_t * deviation.sum_d / n;
+            covariance / variance * 3600.0
+        });
+        Some(Summary {
+            count: deviation.count,
+            bias: deviation.sum_d / n,
+            drift_per_h,
+            max_deviation: deviation.max_abs,
+        })
+    }
+
+    /// Logs each channel's deviation so far.
+    pub fn log(&self) {
+        for channel in 0..4 {
+            let Some(summary) = self.summary(channel) else {
+                continue;
+            };
+            let drift = summary
+                .drift_per_h
+                .map_or("n/a".to_owned(), |drift| format!("{drift:+.3} °C/h"));
+            tracing::info!(
+                "T{} vs T{}: bias {:+.3} °C, drift {drift}, max deviation {:.3} °C over {} readings",
+                channel + 1,
+                self.reference + 1,
+                summary.bias,
+                summary.max_deviation,
+                summary.count
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparison() {
+        let mut comparison = Comparison::new(0);
+        for minute in 0..=60 {
+            let t2 = 100.5 + minute as f32 * 0.01;
+            comparison.push(&Record::new(
+                f64::from(minute) * 60.0,
+                [100.0, t2, f32::NAN, 99.0],
+            ));
+        }
+        assert_eq!(comparison.summary(0), None);
+        assert_eq!(comparison.summary(2), None);
+        let t2 = comparison.summary(1).unwrap();
+        assert_eq!(t2.count, 61);
+        assert!((t2.bias - 0.8).abs() < 1e-3);
+        assert!((t2.drift_per_h.unwrap() - 0.6).abs() < 1e-3);
+        assert!((t2.max_deviation - 1.1).abs() < 1e-3);
+        let t4 = comparison.summary(3).unwrap();
+        assert!((t4.bias + 1.0).abs() < 1e-9);
+        assert!(t4.drift_per_h.unwrap().abs() < 1e-9);
+    }
+}
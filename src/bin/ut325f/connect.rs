@@ -0,0 +1,246 @@
+use anyhow::{Context, Result, anyhow};
+use std::path::Path;
+use ut325f_rs::{Meter, Transport};
+
+use crate::cli::ConnectArgs;
+
+#[cfg(not(any(feature = "bluebus", feature = "btleplug")))]
+pub const NO_BLE_SUPPORT: &str =
+    "Built without Bluetooth support; rebuild with `--features bluebus` or `--features btleplug`";
+
+/// Whichever transport the command line selected, so commands need not
+/// be generic over it.
+pub enum AnyTransport {
+    #[cfg(feature = "serial")]
+    Serial(ut325f_rs::SerialTransport),
+    #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+    Ble(Box<ut325f_rs::BleTransport>),
+}
+
+impl Transport for AnyTransport {
+    async fn recv(&mut self) -> ut325f_rs::Result<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "serial")]
+            Self::Serial(ref mut transport) => transport.recv().await,
+            #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+            Self::Ble(ref mut transport) => transport.recv().await,
+        }
+    }
+
+    async fn close(self) -> ut325f_rs::Result<()> {
+        match self {
+            #[cfg(feature = "serial")]
+            Self::Serial(transport) => transport.close().await,
+            #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+            Self::Ble(transport) => (*transport).close().await,
+        }
+    }
+
+    async fn detach(self) -> ut325f_rs::Result<()> {
+        match self {
+            #[cfg(feature = "serial")]
+            Self::Serial(transport) => transport.detach().await,
+            #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+            Self::Ble(transport) => (*transport).detach().await,
+        }
+    }
+}
+
+/// Opens the meter given on the command line.
+pub async fn open(connect: &ConnectArgs) -> Result<Meter<AnyTransport>> {
+    if let Some(address) = connect.ble_target() {
+        #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+        {
+            let transport = match address {
+                Some(address) => ut325f_rs::BleTransport::open(address).await?,
+                None => {
+                    let scan_time = connect
+                        .scan_time
+                        .unwrap_or(std::time::Duration::from_secs(8));
+                    ut325f_rs::BleTransport::open_only(scan_time).await?
+                }
+            };
+            return Ok(Meter::new(AnyTransport::Ble(Box::new(transport))));
+        }
+        #[cfg(not(any(feature = "bluebus", feature = "btleplug")))]
+        {
+            let _ = address;
+            return Err(anyhow!(NO_BLE_SUPPORT));
+        }
+    }
+    let ports = expand_ports(&connect.port)?;
+    let port = match &ports[..] {
+        [] => return Err(anyhow!("No meter given; pass a serial PORT or --ble")),
+        [port] => port,
+        _ => return Err(anyhow!("{} ports given; this reads one meter", ports.len())),
+    };
+    #[cfg(feature = "serial")]
+    {
+        let config = connect.serial_config();
+        if !connect.auto_baud {
+            let transport = ut325f_rs::SerialTransport::open_with(port, &config).await?;
+            return Ok(Meter::new(AnyTransport::Serial(transport)));
+        }
+        let (transport, rate, sync) = ut325f_rs::SerialTransport::open_auto_baud(
+            port,
+            &config,
+            &ut325f_rs::SerialTransport::AUTO_BAUD_RATES,
+        )
+        .await?;
+        tracing::info!("{port}: {rate} baud");
+        // Read frames like the one found, whatever their command byte,
+        // unless --sync says otherwise.
+        Ok(Meter::new(AnyTransport::Serial(transport)).with_sync(sync))
+    }
+    #[cfg(not(feature = "serial"))]
+    {
+        let _ = port;
+        Err(anyhow!(
+            "Built without serial support; rebuild with `--features serial`"
+        ))
+    }
+}
+
+/// `ports` with each pattern such as `/dev/ttyUSB*` replaced by the
+/// ports it matches, in order. `*` and `?` match within the last path
+/// component only.
+pub fn expand_ports(ports: &[String]) -> Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    for port in ports {
+        if !port.contains(['*', '?']) {
+            expanded.push(port.clone());
+            continue;
+        }
+        let path = Path::new(port);
+        let pattern = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut matches: Vec<String> = std::fs::read_dir(dir)
+            .with_context(|| format!("listing {}", dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| glob_match(&pattern, &entry.file_name().to_string_lossy()))
+            .map(|entry| entry.path().to_string_lossy().into_owned())
+            .collect();
+        if matches.is_empty() {
+            return Err(anyhow!("No port matches {port}"));
+        }
+        matches.sort();
+        expanded.extend(matches);
+    }
+    Ok(expanded)
+}
+
+/// A short name for `port` to tag records and name files with, e.g.
+/// `ttyUSB0` for `/dev/ttyUSB0`.
+pub fn port_name(port: &str) -> String {
+    Path::new(port).file_name().map_or_else(
+        || port.to_owned(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// A stable name for the meter on `port`: the USB serial number of its
+/// adapter where it reports one, which survives the port being
+/// renumbered, else the port's name.
+pub fn meter_id(port: &str) -> String {
+    #[cfg(feature = "serial")]
+    if let Some(serial_number) = usb_serial_number(port) {
+        return serial_number;
+    }
+    port_name(port)
+}
+
+#[cfg(feature = "serial")]
+fn usb_serial_number(port: &str) -> Option<String> {
+    // The port may be given through a symlink, e.g. /dev/serial/by-id.
+    let canonical = std::fs::canonicalize(port).ok();
+    tokio_serial::available_ports()
+        .ok()?
+        .into_iter()
+        .find_map(|info| {
+            let same = info.port_name == port
+                || canonical.is_some() && std::fs::canonicalize(&info.port_name).ok() == canonical;
+            match info.port_type {
+                tokio_serial::SerialPortType::UsbPort(usb) if same => usb.serial_number,
+                _ => None,
+            }
+        })
+        .filter(|serial_number| !serial_number.is_empty())
+}
+
+/// Matches `name` against a pattern of literal characters, `*` (any
+/// run) and `?` (any one character).
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Backtracking to the last `*` suffices: it can always absorb more.
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Releases the meter as the command line asked: closing disconnects a
+/// BLE meter, detaching leaves it connected.
+pub async fn teardown<T: Transport>(meter: Meter<T>, disconnect: bool) -> Result<()> {
+    if disconnect {
+        meter.close().await?;
+    } else {
+        meter.detach().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("ttyUSB*", "ttyUSB0"));
+        assert!(glob_match("ttyUSB*", "ttyUSB"));
+        assert!(glob_match("tty?SB1", "ttyUSB1"));
+        assert!(glob_match("*USB*", "ttyUSB12"));
+        assert!(!glob_match("ttyUSB*", "ttyACM0"));
+        assert!(!glob_match("ttyUSB?", "ttyUSB10"));
+    }
+
+    #[test]
+    fn test_expand_ports() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("ut325f-ports-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        for name in ["ttyUSB1", "ttyUSB0", "ttyACM0"] {
+            std::fs::write(dir.join(name), "")?;
+        }
+        let pattern = dir.join("ttyUSB*").to_string_lossy().into_owned();
+        let ports = expand_ports(&[pattern, "COM3".to_owned()]);
+        std::fs::remove_dir_all(&dir)?;
+        let names: Vec<_> = ports?.iter().map(|port| port_name(port)).collect();
+        assert_eq!(names, ["ttyUSB0", "ttyUSB1", "COM3"]);
+        Ok(())
+    }
+}
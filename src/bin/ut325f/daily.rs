@@ -0,0 +1,227 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use crate::records::{Record, unix_seconds};
+use crate::schedule::{DAY, midnight};
+
+/// The digest of each day of a long session for `--daily-summary`,
+/// appended to a file as a JSON line at every midnight and when the
+/// session ends.
+pub struct DailySummary {
+    path: PathBuf,
+    utc_offset: i64,
+    /// The midnight that began the current day.
+    day: SystemTime,
+    /// When the session started, or the current day did if later.
+    since: SystemTime,
+    readings: usize,
+    channels: [Running; 4],
+    /// Whole seconds, since the epoch, in which a reading arrived.
+    seconds: usize,
+    last_second: Option<i64>,
+    alarms: usize,
+}
+
+impl DailySummary {
+    pub fn new(path: PathBuf, utc_offset: i64, now: SystemTime) -> Self {
+        Self {
+            path,
+            utc_offset,
+            day: midnight(now, utc_offset),
+            since: now,
+            readings: 0,
+            channels: [Running::default(); 4],
+            seconds: 0,
+            last_second: None,
+            alarms: 0,
+        }
+    }
+
+    /// The midnight that ends the current day.
+    pub fn due(&self) -> SystemTime {
+        self.day + DAY
+    }
+
+    /// Writes the summary of every day that ended by `now`.
+    pub fn roll(&mut self, now: SystemTime) -> Result<()> {
+        while now >= self.due() {
+            let end = self.due();
+            self.write(end, false)?;
+            *self = Self::new(std::mem::take(&mut self.path), self.utc_offset, end);
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, record: &Record) {
+        self.readings += 1;
+        for (channel, temp) in self.channels.iter_mut().zip(record.temps_c) {
+            channel.add(temp);
+        }
+        let second = record.timestamp.floor() as i64;
+        if self.last_second != Some(second) {
+            self.seconds += 1;
+            self.last_second = Some(second);
+        }
+    }
+
+    /// Counts an alarm raised.
+    pub fn alarm(&mut self) {
+        self.alarms += 1;
+    }
+
+    /// Writes the summary of the day so far, at the end of a session.
+    pub fn finish(&mut self, now: SystemTime) -> Result<()> {
+        self.roll(now)?;
+        match now > self.since {
+            true => self.write(now, true),
+            false => Ok(()),
+        }
+    }
+
+    fn write(&self, end: SystemTime, partial: bool) -> Result<()> {
+        let uptime = end.duration_since(self.since).unwrap_or_default();
+        let completeness = (!uptime.is_zero())
+            .then(|| (self.seconds as f64 * 100.0 / uptime.as_secs_f64()).min(100.0));
+        let channels: Vec<_> = self
+            .channels
+            .iter()
+            .enumerate()
+            .map(|(channel, stats)| {
+                json!({
+                    "channel": format!("T{}", channel + 1),
+                    "count": stats.count,
+                    "missing": stats.missing,
+                    "min": crate::format::json_number(stats.min.into()),
+                    "max": crate::format::json_number(stats.max.into()),
+                    "mean": crate::format::json_number(stats.sum / stats.count as f64),
+                })
+            })
+            .collect();
+        // The local midnight written as if it were UTC gives the date.
+        let local = unix_seconds(self.day) as i64 + self.utc_offset;
+        let local = UNIX_EPOCH + Duration::from_secs(local.max(0) as u64);
+        let date = humantime::format_rfc3339_seconds(local).to_string();
+        let summary = json!({
+            "date": &date[..10],
+            "start": unix_seconds(self.since),
+            "end": unix_seconds(end),
+            "partial": partial,
+            "uptime_s": uptime.as_secs_f64(),
+            "readings": self.readings,
+            "completeness_pct": completeness.map(crate::format::json_number),
+            "alarms": self.alarms,
+            "channels": channels,
+        });
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("opening {}", self.path.display()))?;
+        writeln!(file, "{summary}")?;
+        Ok(())
+    }
+}
+
+/// One channel's statistics for the day, kept as readings arrive so a
+/// day's worth need not be held.
+#[derive(Debug, Clone, Copy)]
+struct Running {
+    count: usize,
+    missing: usize,
+    min: f32,
+    max: f32,
+    sum: f64,
+}
+
+impl Default for Running {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            missing: 0,
+            min: f32::NAN,
+            max: f32::NAN,
+            sum: 0.0,
+        }
+    }
+}
+
+impl Running {
+    fn add(&mut self, temp: f32) {
+        if temp.is_nan() {
+            self.missing += 1;
+            return;
+        }
+        // `f32::min` and `max` ignore the NaN they start from.
+        self.min = self.min.min(temp);
+        self.max = self.max.max(temp);
+        self.sum += f64::from(temp);
+        self.count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: f64, t1: f32) -> Record {
+        Record::new(timestamp, [t1, f32::NAN, 0.0, 0.0])
+    }
+
+    #[test]
+    fn test_daily_summary() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("ut325f-daily-{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let hour = 3600.0;
+        // 22:00 on 2 January 1970 at UTC+02:00.
+        let start = UNIX_EPOCH + Duration::from_secs(44 * 3600);
+        let mut summary = DailySummary::new(path.clone(), 2 * 3600, start);
+        assert_eq!(summary.due(), start + Duration::from_secs(2 * 3600));
+        for (offset, t1) in [(0.0, 10.0), (0.5, 20.0), (1.0, 30.0)] {
+            summary.record(&record(44.0 * hour + offset, t1));
+        }
+        summary.alarm();
+        summary.roll(summary.due())?;
+        summary.record(&record(46.0 * hour, 5.0));
+        summary.finish(start + Duration::from_secs(2 * 3600 + 10))?;
+
+        let text = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let days: Vec<serde_json::Value> = text
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0]["date"], "1970-01-02");
+        assert_eq!(days[0]["partial"], false);
+        assert_eq!(days[0]["uptime_s"], 7200.0);
+        assert_eq!(days[0]["readings"], 3);
+        assert_eq!(days[0]["completeness_pct"], 0.028);
+        assert_eq!(days[0]["alarms"], 1);
+        assert_eq!(days[0]["channels"][0]["mean"], 20.0);
+        assert_eq!(days[0]["channels"][0]["min"], 10.0);
+        assert_eq!(days[0]["channels"][0]["max"], 30.0);
+        assert_eq!(days[0]["channels"][1]["count"], 0);
+        assert_eq!(days[0]["channels"][1]["missing"], 3);
+        assert_eq!(days[1]["date"], "1970-01-03");
+        assert_eq!(days[1]["partial"], true);
+        assert_eq!(days[1]["completeness_pct"], 10.0);
+        assert_eq!(days[1]["alarms"], 0);
+
+        let schema = crate::schema::schema(
+            crate::schema::Kind::DailySummary,
+            crate::format::Format::Ndjson,
+            &crate::format::Style::default(),
+        );
+        for key in days[0].as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{key}");
+        }
+        for key in schema["required"].as_array().unwrap() {
+            assert!(days[0].get(key.as_str().unwrap()).is_some(), "{key}");
+        }
+        Ok(())
+    }
+}
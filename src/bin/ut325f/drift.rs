@@ -0,0 +1,211 @@
+use std::f64::consts::TAU;
+use std::io;
+
+use crate::records::Record;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// Readings must span this long, in days, before a trend is fitted.
+const MIN_SPAN_DAYS: f64 = 1.0 / 24.0;
+
+/// ... and this long before a daily cycle is fitted alongside it.
+const MIN_CYCLE_SPAN_DAYS: f64 = 2.0;
+
+/// Fits each channel's slow trend, for `stats --drift` and live
+/// `--drift`.
+#[derive(Debug, Clone, Default)]
+pub struct Drift {
+    channels: [Trend; 4],
+}
+
+/// Least-squares sums for `temp = a + b·t + c·cos(ωt) + s·sin(ωt)`,
+/// with `t` in days from the first sample and `ω` one turn a day, so
+/// that the trend `b` is not confused with the ambient daily cycle.
+/// Temperatures are taken relative to the first sample.
+#[derive(Debug, Clone, Copy, Default)]
+struct Trend {
+    origin: Option<(f64, f64)>,
+    n: usize,
+    span_days: f64,
+    xtx: [[f64; 4]; 4],
+    xty: [f64; 4],
+    yy: f64,
+}
+
+/// A channel's fitted trend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit {
+    /// The trend, in °C per day.
+    pub per_day: f64,
+    /// Half the width of the trend's 95% confidence interval, assuming
+    /// independent noise.
+    pub ci95: f64,
+    /// The amplitude of the daily cycle in °C, once readings span two
+    /// days.
+    pub daily_amplitude: Option<f64>,
+    pub span_days: f64,
+}
+
+impl Trend {
+    fn push(&mut self, timestamp: f64, temp_c: f64) {
+        let (t0, y0) = *self.origin.get_or_insert((timestamp, temp_c));
+        let t = (timestamp - t0) / SECONDS_PER_DAY;
+        let y = temp_c - y0;
+        let x = [1.0, t, (TAU * t).cos(), (TAU * t).sin()];
+        for (row, &xi) in x.iter().enumerate() {
+            for (col, &xj) in x.iter().enumerate() {
+                self.xtx[row][col] += xi * xj;
+            }
+            self.xty[row] += xi * y;
+        }
+        self.yy += y * y;
+        self.n += 1;
+        self.span_days = self.span_days.max(t);
+    }
+
+    fn fit(&self) -> Option<Fit> {
+        if self.span_days < MIN_SPAN_DAYS {
+            return None;
+        }
+        let k = if self.span_days >= MIN_CYCLE_SPAN_DAYS {
+            4
+        } else {
+            2
+        };
+        if self.n <= k {
+            return None;
+        }
+        let xtx: Vec<Vec<f64>> = self.xtx[..k].iter().map(|row| row[..k].to_vec()).collect();
+        let inverse = invert(xtx)?;
+        let beta: Vec<f64> = inverse
+            .iter()
+            .map(|row| row.iter().zip(&self.xty).map(|(a, b)| a * b).sum())
+            .collect();
+        let explained: f64 = beta.iter().zip(&self.xty).map(|(b, xy)| b * xy).sum();
+        let variance = ((self.yy - explained) / (self.n - k) as f64).max(0.0);
+        Some(Fit {
+            per_day: beta[1],
+            ci95: 1.96 * (variance * inverse[1][1]).sqrt(),
+            daily_amplitude: (k == 4).then(|| beta[2].hypot(beta[3])),
+            span_days: self.span_days,
+        })
+    }
+}
+
+/// Inverts a small symmetric matrix by Gauss-Jordan elimination, or
+/// `None` if it is singular.
+fn invert(mut m: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let k = m.len();
+    let mut inverse: Vec<Vec<f64>> = (0..k)
+        .map(|row| (0..k).map(|col| f64::from(u8::from(row == col))).collect())
+        .collect();
+    for col in 0..k {
+        let pivot = (col..k).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+        if m[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        m.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let scale = m[col][col];
+        for j in 0..k {
+            m[col][j] /= scale;
+            inverse[col][j] /= scale;
+        }
+        for row in 0..k {
+            let factor = m[row][col];
+            if row == col || factor == 0.0 {
+                continue;
+            }
+            for j in 0..k {
+                m[row][j] -= factor * m[col][j];
+                inverse[row][j] -= factor * inverse[col][j];
+            }
+        }
+    }
+    Some(inverse)
+}
+
+impl Drift {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: &Record) {
+        for (trend, &temp) in self.channels.iter_mut().zip(&record.temps_c) {
+            if !temp.is_nan() {
+                trend.push(record.timestamp, f64::from(temp));
+            }
+        }
+    }
+
+    /// The trend of `channel` (zero-based), once readings span an hour.
+    pub fn fit(&self, channel: usize) -> Option<Fit> {
+        self.channels[channel].fit()
+    }
+
+    /// Logs each channel's trend so far.
+    pub fn log(&self) {
+        for channel in 0..4 {
+            if let Some(fit) = self.fit(channel) {
+                let cycle = fit
+                    .daily_amplitude
+                    .map_or(String::new(), |a| format!(", daily cycle ±{a:.3} °C"));
+                tracing::info!(
+                    "T{} drift {:+.4} ± {:.4} °C/day over {:.2} days{cycle}",
+                    channel + 1,
+                    fit.per_day,
+                    fit.ci95,
+                    fit.span_days
+                );
+            }
+        }
+    }
+
+    pub fn write(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        writeln!(writer, "channel  drift °C/day   ±95%  daily cycle °C")?;
+        for channel in 0..4 {
+            match self.fit(channel) {
+                Some(fit) => writeln!(
+                    writer,
+                    "T{:<6} {:+13.4} {:7.4} {:>15}",
+                    channel + 1,
+                    fit.per_day,
+                    fit.ci95,
+                    fit.daily_amplitude
+                        .map_or("n/a".to_owned(), |a| format!("{a:.3}"))
+                )?,
+                None => writeln!(writer, "T{:<6} {:>13}", channel + 1, "n/a")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_separated_from_daily_cycle() {
+        let mut drift = Drift::new();
+        // Four days every ten minutes: 0.05 °C/day of drift under a
+        // 2 °C daily swing, and a short-lived channel.
+        for i in 0..=576 {
+            let t = f64::from(i) * 600.0;
+            let day = t / SECONDS_PER_DAY;
+            let t1 = 25.0 + 0.05 * day + 2.0 * (TAU * day + 1.0).sin();
+            let t3 = if i < 3 { 20.0 } else { f64::NAN };
+            drift.push(&Record::new(
+                1.7e9 + t,
+                [t1 as f32, f32::NAN, t3 as f32, 20.0],
+            ));
+        }
+        let t1 = drift.fit(0).unwrap();
+        assert!((t1.per_day - 0.05).abs() < 0.01, "{t1:?}");
+        assert!((t1.daily_amplitude.unwrap() - 2.0).abs() < 0.01);
+        assert!(t1.ci95 < 0.01);
+        assert_eq!(drift.fit(1), None);
+        assert_eq!(drift.fit(2), None);
+        assert!(drift.fit(3).unwrap().per_day.abs() < 1e-9);
+    }
+}
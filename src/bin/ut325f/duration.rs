@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+/// Parses a duration given on the command line: humantime syntax
+/// (`90s`, `2h30m`, `1d`, `500ms`) or a bare number of seconds (`1.5`).
+pub fn parse(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).map_err(|_| format!("invalid duration '{s}'"));
+    }
+    humantime::parse_duration(s).map_err(|e| format!("invalid duration '{s}': {e}"))
+}
+
+/// Like [`parse`], but rejects zero.
+pub fn parse_positive(s: &str) -> Result<Duration, String> {
+    match parse(s)? {
+        Duration::ZERO => Err("must be positive".to_owned()),
+        duration => Ok(duration),
+    }
+}
+
+/// Like [`parse`], for intervals that files or windows are aligned to:
+/// at least a second.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let interval = parse(s)?;
+    if interval < Duration::from_secs(1) {
+        return Err("must be at least 1s".to_owned());
+    }
+    Ok(interval)
+}
+
+/// Like [`parse`], for Bluetooth scans: from a second to an hour.
+pub fn parse_scan_time(s: &str) -> Result<Duration, String> {
+    let scan_time = parse(s)?;
+    if !(Duration::from_secs(1)..=Duration::from_secs(3600)).contains(&scan_time) {
+        return Err("must be from 1s to 1h".to_owned());
+    }
+    Ok(scan_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse("2h30m"), Ok(Duration::from_secs(9000)));
+        assert_eq!(parse("1d"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse("8"), Ok(Duration::from_secs(8)));
+        assert!(parse("-1").is_err());
+        assert!(parse("soon").is_err());
+        assert!(parse_positive("0s").is_err());
+        assert!(parse_interval("500ms").is_err());
+        assert!(parse_scan_time("2h").is_err());
+        assert_eq!(parse_scan_time("30"), Ok(Duration::from_secs(30)));
+    }
+}
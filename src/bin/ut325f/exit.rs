@@ -0,0 +1,123 @@
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ut325f_rs::Error;
+
+/// The command succeeded.
+pub const OK: u8 = 0;
+/// Any failure not listed below.
+pub const FAILURE: u8 = 1;
+/// The meter could not be opened: no such port, or no Bluetooth
+/// connection.
+#[cfg_attr(
+    not(any(
+        feature = "serial",
+        feature = "serial-async-io",
+        feature = "bluebus",
+        feature = "btleplug"
+    )),
+    expect(dead_code)
+)]
+pub const OPEN_FAILED: u8 = 2;
+/// The meter stopped sending.
+pub const TIMEOUT: u8 = 3;
+/// The meter sent frames that could not be decoded (with --strict).
+pub const PROTOCOL: u8 = 4;
+/// The session ended normally, but an alarm was raised during it.
+pub const ALARM: u8 = 5;
+/// The connection to the meter was lost.
+pub const DISCONNECTED: u8 = 6;
+/// The command line was invalid.
+pub const USAGE: u8 = 64;
+
+static ALARM_RAISED: AtomicBool = AtomicBool::new(false);
+
+/// Notes that an alarm was raised, so a clean exit reports [`ALARM`].
+pub fn alarm_raised() {
+    ALARM_RAISED.store(true, Ordering::Relaxed);
+}
+
+/// The exit status for the outcome of a command.
+pub fn code(result: &anyhow::Result<()>) -> ExitCode {
+    let code = match result {
+        Ok(()) if ALARM_RAISED.load(Ordering::Relaxed) => ALARM,
+        Ok(()) => OK,
+        Err(e) => e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Error>())
+            .map_or(FAILURE, error_code),
+    };
+    ExitCode::from(code)
+}
+
+/// Whether `error` comes of stdout's or another output's reader having
+/// gone away, which ends a command cleanly.
+pub fn downstream_closed(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| match cause.downcast_ref::<Error>() {
+            Some(Error::SinksClosed) => true,
+            Some(Error::Io(e)) => e.kind() == std::io::ErrorKind::BrokenPipe,
+            _ => cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe),
+        })
+}
+
+fn error_code(error: &Error) -> u8 {
+    match error {
+        Error::ReadTimeout => TIMEOUT,
+        Error::BadSyncHeader
+        | Error::ChecksumMismatch
+        | Error::InvalidHoldType(_)
+        | Error::MalformedFrame(_)
+        | Error::UnexpectedUnknownBytes(_)
+        | Error::InvalidFrame(_) => PROTOCOL,
+        Error::Disconnected(_) => DISCONNECTED,
+        #[cfg(any(feature = "serial", feature = "serial-async-io"))]
+        Error::SerialOpen { .. } => OPEN_FAILED,
+        #[cfg(feature = "serial")]
+        Error::NoBaudRate { .. } => OPEN_FAILED,
+        #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+        Error::ConnectTimeout(_)
+        | Error::ConnectFailed { .. }
+        | Error::DeviceNotKnown(_)
+        | Error::CharacteristicNotFound { .. }
+        | Error::NoMetersFound
+        | Error::MultipleMetersFound(_)
+        | Error::NoUsableAdapter
+        | Error::AdapterUnusable { .. } => OPEN_FAILED,
+        #[cfg(feature = "btleplug")]
+        Error::InvalidAddress(_) | Error::DeviceSearchIncomplete { .. } => OPEN_FAILED,
+        _ => FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_code() {
+        let read: anyhow::Result<()> = Err(Error::ReadTimeout).context("Error reading data");
+        assert_eq!(code(&read), ExitCode::from(TIMEOUT));
+        assert_eq!(error_code(&Error::ChecksumMismatch), PROTOCOL);
+        assert_eq!(error_code(&Error::Disconnected("gone")), DISCONNECTED);
+        assert_eq!(
+            code(&Err(anyhow::anyhow!("bad log"))),
+            ExitCode::from(FAILURE)
+        );
+    }
+
+    #[test]
+    fn test_downstream_closed() {
+        let pipe = || std::io::Error::from(std::io::ErrorKind::BrokenPipe);
+        assert!(downstream_closed(
+            &anyhow::Error::new(pipe()).context("Error writing stats")
+        ));
+        assert!(downstream_closed(&Error::Io(pipe()).into()));
+        assert!(downstream_closed(&Error::SinksClosed.into()));
+        assert!(!downstream_closed(&Error::ReadTimeout.into()));
+    }
+}
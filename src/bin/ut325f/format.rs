@@ -0,0 +1,967 @@
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use ut325f_rs::{DerivedChannel, Quality, round_temp, to_millidegrees};
+
+use crate::color::Paint;
+use crate::records::Record;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum Format {
+    /// Space-separated columns, as read by every subcommand.
+    Text,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// One JSON object per line; NaN temperatures are null.
+    Ndjson,
+    /// One flat JSON object per line with a field per channel, named
+    /// by --label, for consumers that handle nested arrays poorly.
+    JsonFlat,
+    /// Labelled columns with units and UTC times, for reading on a
+    /// terminal; not read back by other subcommands.
+    Pretty,
+    /// Apache Parquet, a column per channel; written by export only,
+    /// when built with the `parquet` feature, and not read back.
+    Parquet,
+}
+
+impl Format {
+    /// The format for stdout when none is given: pretty on a terminal,
+    /// text for a pipe or file.
+    pub fn for_stdout() -> Self {
+        if io::stdout().is_terminal() {
+            Self::Pretty
+        } else {
+            Self::Text
+        }
+    }
+
+    fn is_json(self) -> bool {
+        matches!(self, Self::Ndjson | Self::JsonFlat)
+    }
+}
+
+/// A `--label` naming a channel, e.g. `T1=oven`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelLabel {
+    /// 0-based.
+    pub channel: usize,
+    pub name: String,
+}
+
+impl FromStr for ChannelLabel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid label '{s}'; expected e.g. T1=oven");
+        let (channel, name) = s.split_once('=').ok_or_else(invalid)?;
+        let channel = match channel.to_ascii_uppercase().as_str() {
+            "T1" => 0,
+            "T2" => 1,
+            "T3" => 2,
+            "T4" => 3,
+            _ => return Err(invalid()),
+        };
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            channel,
+            name: name.to_owned(),
+        })
+    }
+}
+
+/// How a NaN temperature (a disconnected channel) is written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NanRepr {
+    /// The literal `NaN`; the text and CSV default.
+    Nan,
+    /// JSON `null`, or the literal `null` in text and CSV; the NDJSON
+    /// default.
+    Null,
+    /// An empty CSV field.
+    Empty,
+    /// A number that cannot be a real reading, e.g. -9999.
+    Sentinel(f64),
+}
+
+impl FromStr for NanRepr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nan" | "NaN" => Ok(Self::Nan),
+            "null" => Ok(Self::Null),
+            "empty" => Ok(Self::Empty),
+            _ => s
+                .parse()
+                .ok()
+                .filter(|v: &f64| v.is_finite())
+                .map(Self::Sentinel)
+                .ok_or_else(|| "expected nan, null, empty, or a number".to_owned()),
+        }
+    }
+}
+
+impl NanRepr {
+    /// Checks that `format` can represent this choice.
+    pub fn check(self, format: Format) -> Result<(), String> {
+        match (self, format) {
+            (Self::Empty, Format::Text | Format::Pretty) => {
+                Err("empty NaNs would shift the text format's columns".to_owned())
+            }
+            (Self::Empty | Self::Nan, Format::Ndjson | Format::JsonFlat) => {
+                Err("JSON can only write NaNs as null or a number".to_owned())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The field separator of CSV output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum Delimiter {
+    Comma,
+    Semicolon,
+    Tab,
+}
+
+impl Delimiter {
+    fn as_char(self) -> char {
+        match self {
+            Self::Comma => ',',
+            Self::Semicolon => ';',
+            Self::Tab => '\t',
+        }
+    }
+}
+
+/// How CSV output is punctuated, e.g. for spreadsheets set up for a
+/// locale that writes decimal commas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvDialect {
+    pub delimiter: Delimiter,
+    /// Enclose every field in double quotes.
+    pub quote: bool,
+    /// Write numbers with a decimal comma.
+    pub decimal_comma: bool,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: Delimiter::Comma,
+            quote: false,
+            decimal_comma: false,
+        }
+    }
+}
+
+impl CsvDialect {
+    /// Checks that fields cannot be confused with the separators
+    /// between them.
+    pub fn check(&self) -> Result<(), String> {
+        if self.decimal_comma && self.delimiter == Delimiter::Comma && !self.quote {
+            return Err("decimal commas need another delimiter or quoting".to_owned());
+        }
+        Ok(())
+    }
+
+    fn join(&self, fields: Vec<String>) -> String {
+        let fields: Vec<String> = match self.quote {
+            true => fields.into_iter().map(|f| format!("\"{f}\"")).collect(),
+            false => fields,
+        };
+        fields.join(&self.delimiter.as_char().to_string())
+    }
+}
+
+/// The unit the pretty format shows temperatures in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TempUnit {
+    /// `temp_c` in this unit.
+    fn convert(self, temp_c: f32) -> f32 {
+        match self {
+            Self::Celsius => temp_c,
+            Self::Fahrenheit => temp_c * 1.8 + 32.0,
+            Self::Kelvin => temp_c + 273.15,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+            Self::Kelvin => "K",
+        }
+    }
+
+    /// The unit after this one, for switching between them in turn.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Celsius => Self::Fahrenheit,
+            Self::Fahrenheit => Self::Kelvin,
+            Self::Kelvin => Self::Celsius,
+        }
+    }
+}
+
+/// Number formatting options shared by all formats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    /// How NaN temperatures are written; `None` for the format's
+    /// default.
+    pub nan: Option<NanRepr>,
+    /// Decimal places for temperatures. Timestamps always carry
+    /// milliseconds.
+    pub precision: usize,
+    /// Write temperatures as integer millidegrees instead, with `_mc`
+    /// field names in CSV and NDJSON.
+    pub millidegrees: bool,
+    /// Channel field names for the flat JSON format.
+    pub labels: [String; 4],
+    pub csv: CsvDialect,
+    /// Tags every record with the meter it came from, when several are
+    /// read at once.
+    pub meter: Option<String>,
+    /// Computed channels written after the measured ones.
+    pub derived: Vec<DerivedChannel>,
+    /// Adds each reading's validity flags, read with `--tolerant`.
+    pub validity: bool,
+    /// Adds each reading's quality to JSON records.
+    pub quality: bool,
+    /// Colors the pretty format.
+    pub color: bool,
+    /// The pretty format's unit, unless in millidegrees.
+    pub unit: TempUnit,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            nan: None,
+            precision: 3,
+            millidegrees: false,
+            labels: ["t1", "t2", "t3", "t4"].map(str::to_owned),
+            csv: CsvDialect::default(),
+            meter: None,
+            derived: Vec::new(),
+            validity: false,
+            quality: false,
+            color: false,
+            unit: TempUnit::Celsius,
+        }
+    }
+}
+
+/// Writes records in one format. The CSV header is derived from the
+/// first record, so a log's records must all carry the same fields.
+pub struct RecordWriter<W: io::Write> {
+    writer: W,
+    format: Format,
+    style: Style,
+    started: bool,
+}
+
+impl<W: io::Write> RecordWriter<W> {
+    pub fn new(writer: W, format: Format, style: Style) -> Self {
+        Self {
+            writer,
+            format,
+            style,
+            started: false,
+        }
+    }
+
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        match self.format {
+            Format::Text => self.write_text(record)?,
+            Format::Csv => self.write_csv(record)?,
+            Format::Ndjson => self.write_ndjson(record)?,
+            Format::JsonFlat => self.write_json_flat(record)?,
+            Format::Pretty => self.write_pretty(record)?,
+            Format::Parquet => unreachable!("parquet is written by export alone"),
+        }
+        self.started = true;
+        Ok(())
+    }
+
+    /// Writes a heartbeat (see [`ut325f_rs::Heartbeat`]) as a JSON
+    /// line; the other formats have no place for one and skip it.
+    pub fn write_heartbeat(&mut self, timestamp: f64, last_reading: Option<f64>) -> io::Result<()> {
+        if !self.format.is_json() {
+            return Ok(());
+        }
+        let mut object = serde_json::Map::new();
+        object.insert("timestamp".into(), json_number(timestamp));
+        object.insert("heartbeat".into(), true.into());
+        object.insert(
+            "last_reading".into(),
+            last_reading.map_or(serde_json::Value::Null, json_number),
+        );
+        serde_json::to_writer(&mut self.writer, &object)?;
+        writeln!(self.writer)
+    }
+
+    /// Writes an annotation (see [`ut325f_rs::Annotation`]): a JSON
+    /// line in the JSON formats, and a line starting with `#`, which
+    /// readers of the log skip, in text and pretty. CSV has no place
+    /// for one that plain CSV readers would take, so skips it.
+    pub fn write_annotation(&mut self, timestamp: f64, note: &str) -> io::Result<()> {
+        let meter = self.style.meter.as_deref();
+        match self.format {
+            Format::Ndjson | Format::JsonFlat => {
+                let mut object = serde_json::Map::new();
+                object.insert("timestamp".into(), json_number(timestamp));
+                if let Some(meter) = meter {
+                    object.insert("meter".into(), meter.into());
+                }
+                object.insert("annotation".into(), note.into());
+                serde_json::to_writer(&mut self.writer, &object)?;
+                writeln!(self.writer)
+            }
+            Format::Csv | Format::Parquet => Ok(()),
+            Format::Text => {
+                let meter = meter.map(|meter| format!(" {meter}")).unwrap_or_default();
+                writeln!(self.writer, "# {timestamp:.3}{meter} {note}")
+            }
+            Format::Pretty => {
+                let time = Duration::try_from_secs_f64(timestamp).unwrap_or_default();
+                let mut line =
+                    humantime::format_rfc3339_millis(SystemTime::UNIX_EPOCH + time).to_string();
+                if let Some(meter) = meter {
+                    line.push_str(&format!("  {meter}"));
+                }
+                let note = Paint::Bold.apply(&format!("# {note}"), self.style.color);
+                writeln!(self.writer, "{line}  {note}")
+            }
+        }
+    }
+
+    pub fn set_unit(&mut self, unit: TempUnit) {
+        self.style.unit = unit;
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Formats a temperature for the text and CSV formats.
+    fn temp(&self, temp: f32) -> String {
+        let precision = if self.style.millidegrees {
+            0
+        } else {
+            self.style.precision
+        };
+        if let Some(mc) = to_millidegrees(temp).filter(|_| self.style.millidegrees) {
+            return mc.to_string();
+        }
+        if !temp.is_nan() {
+            return format!("{:.precision$}", round_temp(temp.into(), precision));
+        }
+        match self.style.nan.unwrap_or(NanRepr::Nan) {
+            NanRepr::Nan => "NaN".to_owned(),
+            NanRepr::Null => "null".to_owned(),
+            NanRepr::Empty => String::new(),
+            NanRepr::Sentinel(value) => format!("{value:.precision$}"),
+        }
+    }
+
+    fn json_temp(&self, temp: f32) -> serde_json::Value {
+        match (temp.is_nan(), self.style.nan) {
+            (true, Some(NanRepr::Sentinel(value))) => value.into(),
+            _ if self.style.millidegrees => to_millidegrees(temp).into(),
+            _ => round_temp(temp.into(), self.style.precision).into(),
+        }
+    }
+
+    fn write_text(&mut self, record: &Record) -> io::Result<()> {
+        let mut line = format!("{:.3}", record.timestamp);
+        if let Some(meter) = &self.style.meter {
+            line.push_str(&format!(" {meter}"));
+        }
+        for &temp in &record.temps_c {
+            line.push_str(&format!(" {:>7}", self.temp(temp)));
+        }
+        if let Some((hold_type, held_temps_c)) = &record.held {
+            line.push_str(&format!(" {hold_type:?}"));
+            for &temp in held_temps_c {
+                line.push_str(&format!(" {:>7}", self.temp(temp)));
+            }
+        }
+        for derived in &self.style.derived {
+            let value = derived.expr.eval(&record.temps_c);
+            line.push_str(&format!(" {:>7}", self.temp(value)));
+        }
+        writeln!(self.writer, "{line}")
+    }
+
+    fn write_pretty(&mut self, record: &Record) -> io::Result<()> {
+        let unit = match self.style.millidegrees {
+            true => TempUnit::Celsius,
+            false => self.style.unit,
+        };
+        let symbol = match self.style.millidegrees {
+            true => "m°C",
+            false => unit.symbol(),
+        };
+        let color = self.style.color;
+        let temp = |temp: f32| match (temp.is_nan(), self.style.nan) {
+            (true, None) => format!("{}   ", Paint::Dim.apply(&format!("{:>7}", "--"), color)),
+            _ => format!("{:>7} {symbol}", self.temp(unit.convert(temp))),
+        };
+        let time = Duration::try_from_secs_f64(record.timestamp).unwrap_or_default();
+        let mut line = humantime::format_rfc3339_millis(SystemTime::UNIX_EPOCH + time).to_string();
+        if let Some(meter) = &self.style.meter {
+            line.push_str(&format!("  {meter}"));
+        }
+        for (channel, (label, &value)) in self.style.labels.iter().zip(&record.temps_c).enumerate()
+        {
+            let label = match label == &format!("t{}", channel + 1) {
+                true => label.to_uppercase(),
+                false => label.clone(),
+            };
+            let label = Paint::Bold.apply(&label, color);
+            line.push_str(&format!("  {label} {}", temp(value)));
+        }
+        if let Some((hold_type, held_temps_c)) = &record.held {
+            line.push_str(&format!("  {hold_type:?}"));
+            for &value in held_temps_c {
+                line.push_str(&format!(" {}", temp(value)));
+            }
+        }
+        for derived in &self.style.derived {
+            let value = derived.expr.eval(&record.temps_c);
+            line.push_str(&format!("  {} {}", derived.name, temp(value)));
+        }
+        if let Some(validity) = record.validity.filter(|validity| !validity.is_valid()) {
+            let validity = format!("[{validity}]");
+            line.push_str(&format!("  {}", Paint::Red.apply(&validity, color)));
+        }
+        writeln!(self.writer, "{}", line.trim_end())
+    }
+
+    fn write_csv(&mut self, record: &Record) -> io::Result<()> {
+        let mut fields = vec![format!("{:.3}", record.timestamp)];
+        if let Some(seq) = record.seq {
+            fields.push(seq.to_string());
+        }
+        for &temp in &record.temps_c {
+            fields.push(self.temp(temp));
+        }
+        if let Some((hold_type, held_temps_c)) = &record.held {
+            fields.push(format!("{hold_type:?}"));
+            for &temp in held_temps_c {
+                fields.push(self.temp(temp));
+            }
+        }
+        if let Some(meter_temp_c) = record.meter_temp_c {
+            fields.push(self.temp(meter_temp_c));
+        }
+        for derived in &self.style.derived {
+            fields.push(self.temp(derived.expr.eval(&record.temps_c)));
+        }
+        if let Some(validity) = record.validity {
+            fields.push(validity.to_string());
+        }
+        if !self.started {
+            let unit = if self.style.millidegrees { "_mc" } else { "" };
+            let mut header = vec!["timestamp".to_owned()];
+            if self.style.meter.is_some() {
+                header.push("meter".to_owned());
+            }
+            if record.seq.is_some() {
+                header.push("seq".to_owned());
+            }
+            for channel in 1..=4 {
+                header.push(format!("t{channel}{unit}"));
+            }
+            if record.held.is_some() {
+                header.push("hold_type".to_owned());
+                for channel in 1..=4 {
+                    header.push(format!("held{channel}{unit}"));
+                }
+            }
+            if record.meter_temp_c.is_some() {
+                header.push(format!("meter_temp{unit}"));
+            }
+            for derived in &self.style.derived {
+                header.push(format!("{}{unit}", derived.name));
+            }
+            if record.validity.is_some() {
+                header.push("validity".to_owned());
+            }
+            let header = self.style.csv.join(header);
+            writeln!(self.writer, "{header}")?;
+        }
+        if self.style.csv.decimal_comma {
+            for field in &mut fields {
+                *field = field.replace('.', ",");
+            }
+        }
+        if let Some(meter) = &self.style.meter {
+            fields.insert(1, meter.clone());
+        }
+        let line = self.style.csv.join(fields);
+        writeln!(self.writer, "{line}")
+    }
+
+    fn write_ndjson(&mut self, record: &Record) -> io::Result<()> {
+        let temps = |temps: &[f32; 4]| temps.map(|t| self.json_temp(t)).to_vec();
+        let unit = if self.style.millidegrees { "mc" } else { "c" };
+        let mut object = serde_json::Map::new();
+        object.insert("timestamp".into(), json_number(record.timestamp));
+        if let Some(meter) = &self.style.meter {
+            object.insert("meter".into(), meter.clone().into());
+        }
+        if let Some(seq) = record.seq {
+            object.insert("seq".into(), seq.into());
+        }
+        object.insert(format!("temps_{unit}"), temps(&record.temps_c).into());
+        if let Some((hold_type, held_temps_c)) = &record.held {
+            object.insert("hold_type".into(), format!("{hold_type:?}").into());
+            object.insert(format!("held_temps_{unit}"), temps(held_temps_c).into());
+        }
+        if let Some(meter_temp_c) = record.meter_temp_c {
+            object.insert(format!("meter_temp_{unit}"), self.json_temp(meter_temp_c));
+        }
+        for derived in &self.style.derived {
+            let value = self.json_temp(derived.expr.eval(&record.temps_c));
+            object.insert(format!("{}_{unit}", derived.name), value);
+        }
+        if let Some(validity) = record.validity {
+            object.insert("validity".into(), validity.to_string().into());
+        }
+        if let Some(quality) = &record.quality {
+            object.insert("quality".into(), quality_json(quality));
+        }
+        serde_json::to_writer(&mut self.writer, &object)?;
+        writeln!(self.writer)
+    }
+
+    fn write_json_flat(&mut self, record: &Record) -> io::Result<()> {
+        let unit = if self.style.millidegrees { "_mc" } else { "" };
+        let mut object = serde_json::Map::new();
+        object.insert("timestamp".into(), json_number(record.timestamp));
+        if let Some(meter) = &self.style.meter {
+            object.insert("meter".into(), meter.clone().into());
+        }
+        if let Some(seq) = record.seq {
+            object.insert("seq".into(), seq.into());
+        }
+        for (label, &temp) in self.style.labels.iter().zip(&record.temps_c) {
+            object.insert(format!("{label}{unit}"), self.json_temp(temp));
+        }
+        if let Some((hold_type, held_temps_c)) = &record.held {
+            object.insert("hold_type".into(), format!("{hold_type:?}").into());
+            for (label, &temp) in self.style.labels.iter().zip(held_temps_c) {
+                object.insert(format!("{label}_held{unit}"), self.json_temp(temp));
+            }
+        }
+        if let Some(meter_temp_c) = record.meter_temp_c {
+            object.insert(format!("meter_temp{unit}"), self.json_temp(meter_temp_c));
+        }
+        for derived in &self.style.derived {
+            let value = self.json_temp(derived.expr.eval(&record.temps_c));
+            object.insert(format!("{}{unit}", derived.name), value);
+        }
+        if let Some(validity) = record.validity {
+            object.insert("validity".into(), validity.to_string().into());
+        }
+        if let Some(quality) = &record.quality {
+            object.insert("quality".into(), quality_json(quality));
+        }
+        serde_json::to_writer(&mut self.writer, &object)?;
+        writeln!(self.writer)
+    }
+}
+
+/// A reading's [`Quality`] as a JSON object.
+pub fn quality_json(quality: &Quality) -> serde_json::Value {
+    serde_json::json!({
+        "checksum_ok": quality.checksum_ok,
+        "resync_bytes": quality.resync_bytes,
+        "jitter_s": quality.jitter_s.map(|jitter| json_number(jitter.into())),
+        "filtered_channels": quality.filtered_channels,
+    })
+}
+
+/// Rounds to the three decimals the other formats print; non-finite
+/// values become null.
+pub fn json_number(value: f64) -> serde_json::Value {
+    round_temp(value, 3).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ut325f_rs::{HoldType, Validity};
+
+    fn write_all(format: Format, records: &[Record]) -> String {
+        write_styled(format, Style::default(), records)
+    }
+
+    fn write_styled(format: Format, style: Style, records: &[Record]) -> String {
+        let mut writer = RecordWriter::new(Vec::new(), format, style);
+        for record in records {
+            writer.write(record).unwrap();
+        }
+        String::from_utf8(writer.writer).unwrap()
+    }
+
+    fn record() -> Record {
+        Record {
+            timestamp: 1.5,
+            temps_c: [1.0, f32::NAN, 3.0, 4.0],
+            held: Some((HoldType::Current, [5.0, 6.0, 7.0, 8.0])),
+            meter_temp_c: Some(25.5),
+            seq: None,
+            validity: None,
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            write_all(Format::Csv, &[record(), record()]),
+            "timestamp,t1,t2,t3,t4,hold_type,held1,held2,held3,held4,meter_temp\n\
+             1.500,1.000,NaN,3.000,4.000,Current,5.000,6.000,7.000,8.000,25.500\n\
+             1.500,1.000,NaN,3.000,4.000,Current,5.000,6.000,7.000,8.000,25.500\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_dialect() {
+        let style = Style {
+            csv: CsvDialect {
+                delimiter: Delimiter::Semicolon,
+                quote: false,
+                decimal_comma: true,
+            },
+            ..Style::default()
+        };
+        assert_eq!(
+            write_styled(Format::Csv, style, &[record()]),
+            "timestamp;t1;t2;t3;t4;hold_type;held1;held2;held3;held4;meter_temp\n\
+             1,500;1,000;NaN;3,000;4,000;Current;5,000;6,000;7,000;8,000;25,500\n"
+        );
+        let dialect = CsvDialect {
+            quote: true,
+            decimal_comma: true,
+            ..CsvDialect::default()
+        };
+        let style = Style {
+            csv: dialect,
+            ..Style::default()
+        };
+        assert!(
+            write_styled(Format::Csv, style, &[record()])
+                .ends_with("\"1,500\",\"1,000\",\"NaN\",\"3,000\",\"4,000\",\"Current\",\"5,000\",\"6,000\",\"7,000\",\"8,000\",\"25,500\"\n")
+        );
+        assert!(
+            CsvDialect {
+                quote: false,
+                ..dialect
+            }
+            .check()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_meter_tag() {
+        let style = Style {
+            meter: Some("ttyUSB1".to_owned()),
+            ..Style::default()
+        };
+        let records = [Record {
+            held: None,
+            ..record()
+        }];
+        assert_eq!(
+            write_styled(Format::Csv, style.clone(), &records),
+            "timestamp,meter,t1,t2,t3,t4,meter_temp\n\
+             1.500,ttyUSB1,1.000,NaN,3.000,4.000,25.500\n"
+        );
+        assert!(
+            write_styled(Format::Ndjson, style.clone(), &records)
+                .starts_with(r#"{"timestamp":1.5,"meter":"ttyUSB1","temps_c""#)
+        );
+        assert!(write_styled(Format::Text, style, &records).starts_with("1.500 ttyUSB1 "));
+    }
+
+    #[test]
+    fn test_derived() {
+        let style = Style {
+            derived: vec![
+                "delta = t1 - t4".parse().unwrap(),
+                "avg = mean(t1..t4)".parse().unwrap(),
+            ],
+            ..Style::default()
+        };
+        let records = [Record {
+            held: None,
+            meter_temp_c: None,
+            ..record()
+        }];
+        assert_eq!(
+            write_styled(Format::Csv, style.clone(), &records),
+            "timestamp,t1,t2,t3,t4,delta,avg\n\
+             1.500,1.000,NaN,3.000,4.000,-3.000,2.667\n"
+        );
+        assert!(
+            write_styled(Format::Ndjson, style.clone(), &records).ends_with(
+                r#""delta_c":-3.0,"avg_c":2.667}
+"#
+            )
+        );
+        assert!(write_styled(Format::JsonFlat, style, &records).ends_with(
+            r#""delta":-3.0,"avg":2.667}
+"#
+        ));
+    }
+
+    #[test]
+    fn test_ndjson() {
+        assert_eq!(
+            write_all(Format::Ndjson, &[record()]),
+            concat!(
+                r#"{"timestamp":1.5,"temps_c":[1.0,null,3.0,4.0],"hold_type":"Current","#,
+                r#""held_temps_c":[5.0,6.0,7.0,8.0],"meter_temp_c":25.5}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_flat() {
+        let mut style = Style::default();
+        style.labels[0] = "oven".to_owned();
+        assert_eq!(
+            write_styled(Format::JsonFlat, style, &[record()]),
+            concat!(
+                r#"{"timestamp":1.5,"oven":1.0,"t2":null,"t3":3.0,"t4":4.0,"hold_type":"Current","#,
+                r#""oven_held":5.0,"t2_held":6.0,"t3_held":7.0,"t4_held":8.0,"meter_temp":25.5}"#,
+                "\n"
+            )
+        );
+        assert_eq!(
+            "t2=ambient".parse(),
+            Ok(ChannelLabel {
+                channel: 1,
+                name: "ambient".to_owned()
+            })
+        );
+        assert!("T5=x".parse::<ChannelLabel>().is_err());
+    }
+
+    #[test]
+    fn test_seq() {
+        let record = Record {
+            seq: Some(7),
+            ..record()
+        };
+        assert!(
+            write_all(Format::Csv, std::slice::from_ref(&record))
+                .starts_with("timestamp,seq,t1,t2,t3,t4,hold_type,")
+        );
+        assert!(
+            write_all(Format::Ndjson, std::slice::from_ref(&record))
+                .starts_with(r#"{"timestamp":1.5,"seq":7,"temps_c""#)
+        );
+        assert_eq!(
+            write_all(Format::Text, &[record]),
+            write_all(Format::Text, &[self::record()])
+        );
+    }
+
+    #[test]
+    fn test_validity() {
+        let record = Record {
+            validity: Some(Validity::CHECKSUM | Validity::HOLD_TYPE),
+            ..record()
+        };
+        let csv = write_all(Format::Csv, std::slice::from_ref(&record));
+        assert!(csv.lines().next().unwrap().ends_with(",validity"));
+        assert!(csv.trim_end().ends_with(",checksum+hold_type"));
+        assert!(
+            write_all(Format::JsonFlat, std::slice::from_ref(&record))
+                .contains(r#""validity":"checksum+hold_type""#)
+        );
+    }
+
+    #[test]
+    fn test_quality() {
+        let record = Record {
+            quality: Some(Quality {
+                resync_bytes: 7,
+                jitter_s: Some(0.0125),
+                ..Quality::default()
+            }),
+            ..record()
+        };
+        assert!(
+            write_all(Format::Ndjson, std::slice::from_ref(&record)).contains(
+                r#""quality":{"checksum_ok":true,"resync_bytes":7,"jitter_s":0.013,"filtered_channels":0}"#
+            )
+        );
+        assert_eq!(
+            write_all(Format::Csv, std::slice::from_ref(&record)),
+            write_all(Format::Csv, &[self::record()])
+        );
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let mut writer = RecordWriter::new(Vec::new(), Format::Ndjson, Style::default());
+        writer.write_heartbeat(2.5, Some(1.25)).unwrap();
+        writer.write_heartbeat(3.5, None).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "{\"timestamp\":2.5,\"heartbeat\":true,\"last_reading\":1.25}\n\
+             {\"timestamp\":3.5,\"heartbeat\":true,\"last_reading\":null}\n"
+        );
+        let mut writer = RecordWriter::new(Vec::new(), Format::Csv, Style::default());
+        writer.write_heartbeat(2.5, None).unwrap();
+        assert!(writer.writer.is_empty());
+    }
+
+    #[test]
+    fn test_annotation() {
+        let annotate = |format| {
+            let mut writer = RecordWriter::new(Vec::new(), format, Style::default());
+            writer.write_annotation(2.5, "opened door").unwrap();
+            String::from_utf8(writer.writer).unwrap()
+        };
+        assert_eq!(
+            annotate(Format::Ndjson),
+            "{\"timestamp\":2.5,\"annotation\":\"opened door\"}\n"
+        );
+        assert_eq!(annotate(Format::Text), "# 2.500 opened door\n");
+        assert_eq!(annotate(Format::Csv), "");
+        assert_eq!(
+            annotate(Format::Pretty),
+            "1970-01-01T00:00:02.500Z  # opened door\n"
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            write_all(Format::Text, &[record()]),
+            "1.500   1.000     NaN   3.000   4.000 Current   5.000   6.000   7.000   8.000\n"
+        );
+    }
+
+    #[test]
+    fn test_pretty() {
+        let mut style = Style {
+            precision: 1,
+            ..Style::default()
+        };
+        style.labels[2] = "oven".to_owned();
+        let record = Record {
+            validity: Some(Validity::CHECKSUM),
+            ..record()
+        };
+        assert_eq!(
+            write_styled(Format::Pretty, style, &[record]),
+            "1970-01-01T00:00:01.500Z  T1     1.0 °C  T2      --     oven     3.0 °C  T4     4.0 °C  \
+             Current     5.0 °C     6.0 °C     7.0 °C     8.0 °C  [checksum]\n"
+        );
+        let style = Style {
+            color: true,
+            ..Style::default()
+        };
+        let missing = Record {
+            temps_c: [f32::NAN; 4],
+            held: None,
+            ..self::record()
+        };
+        assert!(
+            write_styled(Format::Pretty, style, &[missing])
+                .ends_with("\x1b[1mT4\x1b[0m \x1b[2m     --\x1b[0m\n")
+        );
+        let style = Style {
+            precision: 1,
+            unit: TempUnit::Fahrenheit,
+            ..Style::default()
+        };
+        let fahrenheit = Record {
+            held: None,
+            ..self::record()
+        };
+        assert!(write_styled(Format::Pretty, style, &[fahrenheit]).ends_with("T4    39.2 °F\n"));
+    }
+
+    #[test]
+    fn test_nan_styles() {
+        let style = |nan| Style {
+            nan: Some(nan),
+            ..Style::default()
+        };
+        assert!(
+            write_styled(Format::Csv, style(NanRepr::Empty), &[record()])
+                .ends_with("1.500,1.000,,3.000,4.000,Current,5.000,6.000,7.000,8.000,25.500\n")
+        );
+        assert!(
+            write_styled(
+                Format::Ndjson,
+                style(NanRepr::Sentinel(-9999.0)),
+                &[record()]
+            )
+            .contains(r#""temps_c":[1.0,-9999.0,3.0,4.0]"#)
+        );
+        assert!(NanRepr::Empty.check(Format::Text).is_err());
+        assert_eq!("-9999".parse(), Ok(NanRepr::Sentinel(-9999.0)));
+    }
+
+    #[test]
+    fn test_precision() {
+        let style = Style {
+            precision: 1,
+            ..Style::default()
+        };
+        let record = Record::new(1.5, [1.25, f32::NAN, -3.04, 4.0]);
+        assert_eq!(
+            write_styled(Format::Csv, style.clone(), std::slice::from_ref(&record)),
+            "timestamp,t1,t2,t3,t4\n1.500,1.3,NaN,-3.0,4.0\n"
+        );
+        assert!(
+            write_styled(Format::Ndjson, style, &[record])
+                .contains(r#""temps_c":[1.3,null,-3.0,4.0]"#)
+        );
+    }
+
+    #[test]
+    fn test_millidegrees() {
+        let style = Style {
+            millidegrees: true,
+            ..Style::default()
+        };
+        assert_eq!(
+            write_styled(Format::Csv, style.clone(), &[record()]),
+            "timestamp,t1_mc,t2_mc,t3_mc,t4_mc,hold_type,held1_mc,held2_mc,held3_mc,held4_mc,\
+             meter_temp_mc\n\
+             1.500,1000,NaN,3000,4000,Current,5000,6000,7000,8000,25500\n"
+        );
+        assert!(
+            write_styled(Format::Ndjson, style, &[record()])
+                .contains(r#""temps_mc":[1000,null,3000,4000]"#)
+        );
+    }
+}
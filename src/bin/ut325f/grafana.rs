@@ -0,0 +1,160 @@
+use tokio::sync::mpsc;
+use ut325f_rs::{DerivedChannel, Reading, Sink};
+
+use crate::records::Record;
+
+/// Readings waiting to be pushed; newer readings are dropped while the
+/// queue is full so a slow Grafana never stalls the meter.
+const QUEUE_LEN: usize = 64;
+
+/// Pushes readings to a Grafana Live stream
+/// (`/api/live/push/<stream>`) in Influx line protocol.
+pub struct GrafanaSink {
+    queue: mpsc::Sender<String>,
+    held_temps: bool,
+    /// Tags every point with the meter.
+    meter: Option<String>,
+    derived: Vec<DerivedChannel>,
+}
+
+impl GrafanaSink {
+    pub fn new(
+        url: String,
+        token: Option<String>,
+        held_temps: bool,
+        meter: Option<String>,
+        derived: Vec<DerivedChannel>,
+    ) -> Self {
+        let (queue, mut lines) = mpsc::channel::<String>(QUEUE_LEN);
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            // Report only the first of a run of failures.
+            let mut failing = false;
+            while let Some(line) = lines.recv().await {
+                let mut request = client.post(&url).body(line);
+                if let Some(token) = &token {
+                    request = request.bearer_auth(token);
+                }
+                match request.send().await.and_then(|r| r.error_for_status()) {
+                    Ok(_) if failing => {
+                        tracing::info!("Grafana push recovered");
+                        failing = false;
+                    }
+                    Ok(_) => {}
+                    Err(e) if !failing => {
+                        tracing::warn!("Grafana push failed: {e}");
+                        failing = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+        Self {
+            queue,
+            held_temps,
+            meter,
+            derived,
+        }
+    }
+}
+
+impl Sink for GrafanaSink {
+    fn write(&mut self, reading: &Reading) -> ut325f_rs::Result<()> {
+        let record = Record::from_reading(reading, self.held_temps);
+        if let Some(line) = line_protocol(&record, self.meter.as_deref(), &self.derived) {
+            let _ = self.queue.try_send(line);
+        }
+        Ok(())
+    }
+}
+
+/// Formats `record` as one line-protocol point with nanosecond
+/// precision, tagged with `meter` if given and with the `derived`
+/// channels as fields. Line protocol has no NaN, so open channels are
+/// left out; a record with nothing left yields `None`.
+fn line_protocol(
+    record: &Record,
+    meter: Option<&str>,
+    derived: &[DerivedChannel],
+) -> Option<String> {
+    let mut fields: Vec<String> = record
+        .temps_c
+        .iter()
+        .enumerate()
+        .filter(|(_, temp)| temp.is_finite())
+        .map(|(channel, temp)| format!("t{}={temp}", channel + 1))
+        .collect();
+    if let Some((_, held)) = &record.held {
+        fields.extend(
+            held.iter()
+                .enumerate()
+                .filter(|(_, temp)| temp.is_finite())
+                .map(|(channel, temp)| format!("held{}={temp}", channel + 1)),
+        );
+    }
+    if let Some(meter_temp) = record.meter_temp_c.filter(|t| t.is_finite()) {
+        fields.push(format!("meter_temp={meter_temp}"));
+    }
+    for derived in derived {
+        let value = derived.expr.eval(&record.temps_c);
+        if value.is_finite() {
+            fields.push(format!("{}={value}", derived.name));
+        }
+    }
+    if fields.is_empty() {
+        return None;
+    }
+    let timestamp_ns = (record.timestamp * 1e9).round() as i64;
+    let tags = match meter {
+        Some(meter) => format!(",meter={}", escape_tag(meter)),
+        None => String::new(),
+    };
+    Some(format!("ut325f{tags} {} {timestamp_ns}", fields.join(",")))
+}
+
+/// Escapes the characters line protocol gives meaning to in a tag value.
+fn escape_tag(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_protocol() {
+        let record = Record {
+            meter_temp_c: Some(25.25),
+            ..Record::new(1.5, [26.5, f32::NAN, 3.0, f32::NAN])
+        };
+        assert_eq!(
+            line_protocol(&record, None, &[]).as_deref(),
+            Some("ut325f t1=26.5,t3=3,meter_temp=25.25 1500000000")
+        );
+        assert_eq!(
+            line_protocol(&record, Some("oven a"), &[]).as_deref(),
+            Some("ut325f,meter=oven\\ a t1=26.5,t3=3,meter_temp=25.25 1500000000")
+        );
+        let derived = [
+            "delta = t1 - t3".parse().unwrap(),
+            "d2 = t2 - t1".parse().unwrap(),
+        ];
+        assert_eq!(
+            line_protocol(&record, None, &derived).as_deref(),
+            Some("ut325f t1=26.5,t3=3,meter_temp=25.25,delta=23.5 1500000000")
+        );
+    }
+
+    #[test]
+    fn test_line_protocol_all_open() {
+        let record = Record::new(1.5, [f32::NAN; 4]);
+        assert_eq!(line_protocol(&record, None, &[]), None);
+    }
+}
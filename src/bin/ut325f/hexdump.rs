@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+
+use ut325f_rs::Reading;
+
+/// What `--raw-hex` prints on stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum RawHex {
+    /// Each frame's hex dump, followed by its decoded record.
+    Alongside,
+    /// Hex dumps only; stdout gets no decoded records.
+    Only,
+}
+
+/// Bytes per line when dumping skipped bytes.
+const LINE_LEN: usize = 16;
+
+/// Writes `frame` one field per line, with the temperatures its raw
+/// bytes encode (even where an error flag says to ignore them).
+pub fn write_frame(
+    writer: &mut impl Write,
+    timestamp: f64,
+    frame: &[u8; Reading::N_BYTES],
+) -> io::Result<()> {
+    writeln!(writer, "frame at {timestamp:.3}")?;
+    for (name, range) in Reading::FIELDS {
+        let bytes = &frame[range];
+        write!(writer, "  {name:<14}{}", hex(bytes))?;
+        if matches!(name, "current" | "held" | "meter") {
+            let values: Vec<String> = bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()).to_string())
+                .collect();
+            write!(writer, "  ; {}", values.join(" "))?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Writes bytes the decoder skipped: line noise or rejected frames.
+pub fn write_skipped(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+    writeln!(writer, "skipped {} bytes", bytes.len())?;
+    for line in bytes.chunks(LINE_LEN) {
+        writeln!(writer, "  {}", hex(line))?;
+    }
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_frame() -> io::Result<()> {
+        let frame = Reading::builder()
+            .current_temp_c(0, 1.5)
+            .meter_temp_c(25.0)
+            .build()
+            .to_frame();
+        let mut out = Vec::new();
+        write_frame(&mut out, 1.0, &frame)?;
+        write_skipped(&mut out, &[0xab; 17])?;
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("frame at 1.000\n  sync          aa 55 00 34 01\n"));
+        assert!(out.contains("  current       00 00 c0 3f 00 00 00 00"));
+        assert!(out.contains("; 1.5 0 0 0\n"));
+        assert!(out.contains("  current flags 00 30 30 30\n"));
+        assert!(out.ends_with(
+            "skipped 17 bytes\n  ab ab ab ab ab ab ab ab ab ab ab ab ab ab ab ab\n  ab\n"
+        ));
+        Ok(())
+    }
+}
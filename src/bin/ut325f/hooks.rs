@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ut325f_rs::{AlarmEvent, Rule};
+
+use crate::records::Record;
+
+/// A shell command run on each reading or alarm change, with the
+/// details in `UT325F_*` environment variables.
+pub struct Hook {
+    command: String,
+    /// Set while the previous run is still going. Reading hooks are
+    /// skipped rather than queued so a slow command cannot pile up
+    /// processes at the meter's sampling rate.
+    busy: Arc<AtomicBool>,
+}
+
+impl Hook {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            busy: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn on_reading(&self, record: &Record) {
+        if self.busy.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        self.spawn(reading_env(record), Some(self.busy.clone()));
+    }
+
+    /// Alarm changes are rare and each one matters, so they are never
+    /// skipped.
+    pub fn on_alarm(&self, event: &AlarmEvent, record: &Record) {
+        let mut env = reading_env(record);
+        env.extend(alarm_env(event));
+        self.spawn(env, None);
+    }
+
+    fn spawn(&self, env: Vec<(String, String)>, busy: Option<Arc<AtomicBool>>) {
+        let mut command = shell(&self.command);
+        command.envs(env).stdin(std::process::Stdio::null());
+        let description = self.command.clone();
+        match command.spawn() {
+            Ok(mut child) => {
+                tokio::spawn(async move {
+                    match child.wait().await {
+                        Ok(status) if !status.success() => {
+                            tracing::warn!("Hook '{description}' failed: {status}")
+                        }
+                        Ok(_) => {}
+                        Err(e) => tracing::warn!("Hook '{description}' failed: {e}"),
+                    }
+                    if let Some(busy) = busy {
+                        busy.store(false, Ordering::Release);
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::warn!("Hook '{description}' failed to start: {e}");
+                if let Some(busy) = busy {
+                    busy.store(false, Ordering::Release);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn reading_env(record: &Record) -> Vec<(String, String)> {
+    let mut env = vec![(
+        "UT325F_TIMESTAMP".to_owned(),
+        format!("{:.3}", record.timestamp),
+    )];
+    for (channel, temp) in record.temps_c.iter().enumerate() {
+        env.push((format!("UT325F_T{}", channel + 1), format!("{temp:.3}")));
+    }
+    if let Some((hold_type, held)) = &record.held {
+        env.push(("UT325F_HOLD_TYPE".to_owned(), format!("{hold_type:?}")));
+        for (channel, temp) in held.iter().enumerate() {
+            env.push((format!("UT325F_HELD{}", channel + 1), format!("{temp:.3}")));
+        }
+    }
+    if let Some(meter_temp) = record.meter_temp_c {
+        env.push(("UT325F_METER_TEMP".to_owned(), format!("{meter_temp:.3}")));
+    }
+    env
+}
+
+/// The alarm's variables; a condition over several channels has no
+/// UT325F_ALARM_CHANNEL or UT325F_ALARM_TEMP.
+fn alarm_env(event: &AlarmEvent) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("UT325F_ALARM".to_owned(), event.rule.to_string()),
+        (
+            "UT325F_ALARM_STATE".to_owned(),
+            if event.raised { "raised" } else { "cleared" }.to_owned(),
+        ),
+    ];
+    if let Rule::Threshold(threshold) = &event.rule {
+        env.push((
+            "UT325F_ALARM_CHANNEL".to_owned(),
+            format!("T{}", threshold.channel + 1),
+        ));
+        env.push((
+            "UT325F_ALARM_TEMP".to_owned(),
+            format!("{:.3}", event.temp_c),
+        ));
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reading_env() {
+        let record = Record {
+            meter_temp_c: Some(25.5),
+            ..Record::new(1.5, [1.0, f32::NAN, 3.0, 4.0])
+        };
+        let env = reading_env(&record);
+        assert!(env.contains(&("UT325F_TIMESTAMP".to_owned(), "1.500".to_owned())));
+        assert!(env.contains(&("UT325F_T2".to_owned(), "NaN".to_owned())));
+        assert!(env.contains(&("UT325F_METER_TEMP".to_owned(), "25.500".to_owned())));
+        assert!(!env.iter().any(|(name, _)| name == "UT325F_HOLD_TYPE"));
+    }
+}
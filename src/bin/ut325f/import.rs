@@ -0,0 +1,438 @@
+use anyhow::{Context, Result, anyhow};
+
+/// Bytes received from the meter at one moment of a third-party capture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Unix time in seconds, or seconds since the capture started for
+    /// formats that record no absolute time.
+    pub timestamp: f64,
+    pub bytes: Vec<u8>,
+}
+
+const LINKTYPE_USB_LINUX: u32 = 189;
+const LINKTYPE_USB_LINUX_MMAPPED: u32 = 220;
+const LINKTYPE_USBPCAP: u32 = 249;
+
+const USB_TRANSFER_INTERRUPT: u8 = 1;
+const USB_TRANSFER_BULK: u8 = 3;
+const USB_DIR_IN: u8 = 0x80;
+
+/// Extracts the meter's byte stream from a third-party capture: a USB
+/// capture in pcap or pcapng format (Linux usbmon or Windows USBPcap),
+/// or a Saleae Logic async serial CSV export. Returns `None` if `bytes`
+/// is none of these. sigrok's binary UART output is already a raw
+/// capture and needs no import.
+pub fn extract(bytes: &[u8]) -> Option<Result<Vec<Chunk>>> {
+    match bytes.get(..4)? {
+        [0xd4, 0xc3, 0xb2, 0xa1] | [0xa1, 0xb2, 0xc3, 0xd4] => Some(read_pcap(bytes, false)),
+        [0x4d, 0x3c, 0xb2, 0xa1] | [0xa1, 0xb2, 0x3c, 0x4d] => Some(read_pcap(bytes, true)),
+        [0x0a, 0x0d, 0x0d, 0x0a] => Some(read_pcapng(bytes)),
+        _ => {
+            let text = std::str::from_utf8(bytes).ok()?;
+            let header = text.lines().next()?;
+            is_saleae_header(header).then(|| read_saleae(text))
+        }
+    }
+}
+
+/// Whether `head`, the start of a file, is the start of a capture
+/// [`extract`] reads.
+pub fn is_capture(head: &[u8]) -> bool {
+    match head.get(..4) {
+        Some(
+            [0xd4, 0xc3, 0xb2, 0xa1]
+            | [0xa1, 0xb2, 0xc3, 0xd4]
+            | [0x4d, 0x3c, 0xb2, 0xa1]
+            | [0xa1, 0xb2, 0x3c, 0x4d]
+            | [0x0a, 0x0d, 0x0d, 0x0a],
+        ) => true,
+        _ => head
+            .split(|&b| b == b'\n')
+            .next()
+            .and_then(|line| std::str::from_utf8(line).ok())
+            .is_some_and(is_saleae_header),
+    }
+}
+
+/// Concatenates the chunks' bytes.
+pub fn raw_bytes(chunks: &[Chunk]) -> Vec<u8> {
+    chunks
+        .iter()
+        .flat_map(|c| c.bytes.iter().copied())
+        .collect()
+}
+
+/// Reads integers in a capture's byte order.
+#[derive(Clone, Copy)]
+struct Endian {
+    big: bool,
+}
+
+impl Endian {
+    fn u16(self, bytes: &[u8], at: usize) -> Option<u16> {
+        let b: [u8; 2] = bytes.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.big {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(self, bytes: &[u8], at: usize) -> Option<u32> {
+        let b: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.big {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+}
+
+fn truncated() -> anyhow::Error {
+    anyhow!("capture is truncated")
+}
+
+fn read_pcap(bytes: &[u8], nanoseconds: bool) -> Result<Vec<Chunk>> {
+    let endian = Endian {
+        big: bytes[0] == 0xa1,
+    };
+    let linktype = endian.u32(bytes, 20).ok_or_else(truncated)? & 0x0fff_ffff;
+    let scale = if nanoseconds { 1e-9 } else { 1e-6 };
+    let mut chunks = Vec::new();
+    let mut at = 24;
+    while at < bytes.len() {
+        let seconds = endian.u32(bytes, at).ok_or_else(truncated)?;
+        let fraction = endian.u32(bytes, at + 4).ok_or_else(truncated)?;
+        let len = endian.u32(bytes, at + 8).ok_or_else(truncated)? as usize;
+        let packet = bytes.get(at + 16..at + 16 + len).ok_or_else(truncated)?;
+        let timestamp = f64::from(seconds) + f64::from(fraction) * scale;
+        if let Some(data) = usb_payload(linktype, packet)? {
+            chunks.push(Chunk {
+                timestamp,
+                bytes: data.to_vec(),
+            });
+        }
+        at += 16 + len;
+    }
+    Ok(chunks)
+}
+
+fn read_pcapng(bytes: &[u8]) -> Result<Vec<Chunk>> {
+    const SECTION_HEADER: u32 = 0x0a0d_0d0a;
+    const INTERFACE_DESCRIPTION: u32 = 1;
+    const ENHANCED_PACKET: u32 = 6;
+    const IF_TSRESOL: u16 = 9;
+
+    let mut endian = Endian { big: false };
+    // Link type and timestamp units per interface, in section order.
+    let mut interfaces: Vec<(u32, f64)> = Vec::new();
+    let mut chunks = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        let block_type = endian.u32(bytes, at).ok_or_else(truncated)?;
+        if block_type == SECTION_HEADER {
+            let magic = bytes.get(at + 8..at + 12).ok_or_else(truncated)?;
+            endian.big = magic == [0x1a, 0x2b, 0x3c, 0x4d];
+            interfaces.clear();
+        }
+        let len = endian.u32(bytes, at + 4).ok_or_else(truncated)? as usize;
+        if len < 12 {
+            return Err(anyhow!("bad pcapng block length {len}"));
+        }
+        let block = bytes.get(at..at + len).ok_or_else(truncated)?;
+        let body = &block[8..len - 4];
+        match block_type {
+            INTERFACE_DESCRIPTION => {
+                let linktype = u32::from(endian.u16(body, 0).ok_or_else(truncated)?);
+                let mut units = 1e-6;
+                let mut option = 8;
+                while let (Some(code), Some(option_len)) =
+                    (endian.u16(body, option), endian.u16(body, option + 2))
+                {
+                    let option_len = usize::from(option_len);
+                    if code == 0 {
+                        break;
+                    }
+                    if code == IF_TSRESOL
+                        && let Some(&resolution) = body.get(option + 4)
+                    {
+                        units = if resolution & 0x80 != 0 {
+                            2f64.powi(-i32::from(resolution & 0x7f))
+                        } else {
+                            10f64.powi(-i32::from(resolution))
+                        };
+                    }
+                    option += 4 + option_len.div_ceil(4) * 4;
+                }
+                interfaces.push((linktype, units));
+            }
+            ENHANCED_PACKET => {
+                let interface = endian.u32(body, 0).ok_or_else(truncated)? as usize;
+                let &(linktype, units) = interfaces
+                    .get(interface)
+                    .ok_or_else(|| anyhow!("packet on undeclared interface {interface}"))?;
+                let high = endian.u32(body, 4).ok_or_else(truncated)?;
+                let low = endian.u32(body, 8).ok_or_else(truncated)?;
+                let captured = endian.u32(body, 12).ok_or_else(truncated)? as usize;
+                let packet = body.get(20..20 + captured).ok_or_else(truncated)?;
+                let ticks = (u64::from(high) << 32) | u64::from(low);
+                if let Some(data) = usb_payload(linktype, packet)? {
+                    chunks.push(Chunk {
+                        timestamp: ticks as f64 * units,
+                        bytes: data.to_vec(),
+                    });
+                }
+            }
+            _ => {}
+        }
+        at += len;
+    }
+    Ok(chunks)
+}
+
+/// Returns the data of a completed bulk or interrupt IN transfer, the
+/// direction the meter's readings travel; `None` for anything else.
+fn usb_payload(linktype: u32, packet: &[u8]) -> Result<Option<&[u8]>> {
+    match linktype {
+        LINKTYPE_USB_LINUX | LINKTYPE_USB_LINUX_MMAPPED => {
+            // usbmon headers are in host byte order; captures come from
+            // little-endian hosts in practice.
+            let header_len = if linktype == LINKTYPE_USB_LINUX {
+                48
+            } else {
+                64
+            };
+            let header = packet.get(..header_len).ok_or_else(truncated)?;
+            let completion = header[8] == b'C';
+            let transfer = header[9];
+            let endpoint = header[10];
+            if !completion
+                || endpoint & USB_DIR_IN == 0
+                || !matches!(transfer, USB_TRANSFER_BULK | USB_TRANSFER_INTERRUPT)
+            {
+                return Ok(None);
+            }
+            Ok(Some(&packet[header_len..]))
+        }
+        LINKTYPE_USBPCAP => {
+            let endian = Endian { big: false };
+            let header_len = usize::from(endian.u16(packet, 0).ok_or_else(truncated)?);
+            let header = packet.get(..header_len.max(27)).ok_or_else(truncated)?;
+            // Bit 0 of info marks a completion travelling back up from
+            // the device.
+            let completion = header[16] & 1 != 0;
+            let endpoint = header[21];
+            let transfer = header[22];
+            if !completion
+                || endpoint & USB_DIR_IN == 0
+                || !matches!(transfer, USB_TRANSFER_BULK | USB_TRANSFER_INTERRUPT)
+            {
+                return Ok(None);
+            }
+            Ok(Some(packet.get(header_len..).ok_or_else(truncated)?))
+        }
+        _ => Err(anyhow!(
+            "unsupported pcap link type {linktype}; expected a USB capture (usbmon or USBPcap)"
+        )),
+    }
+}
+
+fn saleae_columns(header: &str) -> Vec<String> {
+    header
+        .split(',')
+        .map(|c| c.trim().trim_matches('"').to_ascii_lowercase())
+        .collect()
+}
+
+fn is_saleae_header(header: &str) -> bool {
+    let columns = saleae_columns(header);
+    let has = |name: &str| columns.iter().any(|c| c == name);
+    (has("start_time") && has("data")) || (has("time [s]") && has("value"))
+}
+
+/// Reads a Saleae Logic async serial export: Logic 2's
+/// `name,type,start_time,duration,data` or Logic 1's `Time [s],Value,...`,
+/// with bytes written in hex (0xAA), decimal, or as quoted characters.
+fn read_saleae(text: &str) -> Result<Vec<Chunk>> {
+    let mut lines = text.lines().enumerate();
+    let (_, header) = lines.next().ok_or_else(truncated)?;
+    let columns = saleae_columns(header);
+    let position = |names: &[&str]| {
+        columns
+            .iter()
+            .position(|c| names.contains(&c.as_str()))
+            .ok_or_else(|| anyhow!("missing {} column", names[0]))
+    };
+    let time = position(&["start_time", "time [s]"])?;
+    let data = position(&["data", "value"])?;
+    let kind = columns.iter().position(|c| c == "type");
+    let mut chunks = Vec::new();
+    for (index, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split(',')
+            .map(|f| f.trim().trim_matches('"'))
+            .collect();
+        let field = |column: usize| {
+            fields
+                .get(column)
+                .copied()
+                .ok_or_else(|| anyhow!("line {}: too few fields", index + 1))
+        };
+        // Logic 2 also lists framing errors and the like.
+        if let Some(kind) = kind
+            && field(kind)? != "data"
+        {
+            continue;
+        }
+        let timestamp: f64 = field(time)?
+            .parse()
+            .with_context(|| format!("line {}: bad time", index + 1))?;
+        let byte = parse_byte(field(data)?)
+            .ok_or_else(|| anyhow!("line {}: bad data '{}'", index + 1, field(data).unwrap()))?;
+        chunks.push(Chunk {
+            timestamp,
+            bytes: vec![byte],
+        });
+    }
+    Ok(chunks)
+}
+
+fn parse_byte(field: &str) -> Option<u8> {
+    if let Some(hex) = field
+        .strip_prefix("0x")
+        .or_else(|| field.strip_prefix("0X"))
+    {
+        return u8::from_str_radix(hex, 16).ok();
+    }
+    if let Some(quoted) = field.strip_prefix('\'').and_then(|f| f.strip_suffix('\'')) {
+        return quoted.parse().ok();
+    }
+    field.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usbmon_packet(kind: u8, endpoint: u8, data: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; 48];
+        packet[8] = kind;
+        packet[9] = USB_TRANSFER_BULK;
+        packet[10] = endpoint;
+        packet.extend_from_slice(data);
+        packet
+    }
+
+    #[test]
+    fn test_pcap_usbmon() -> Result<()> {
+        let mut capture = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+        capture.extend_from_slice(&[0; 8]);
+        capture.extend_from_slice(&65535u32.to_le_bytes());
+        capture.extend_from_slice(&LINKTYPE_USB_LINUX.to_le_bytes());
+        let packets = [
+            (10, usbmon_packet(b'C', 0x81, &[1, 2, 3])),
+            (11, usbmon_packet(b'S', 0x81, &[])),
+            (12, usbmon_packet(b'C', 0x02, &[9])),
+            (13, usbmon_packet(b'C', 0x81, &[4])),
+        ];
+        for (seconds, packet) in &packets {
+            capture.extend_from_slice(&(*seconds as u32).to_le_bytes());
+            capture.extend_from_slice(&500_000u32.to_le_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(packet);
+        }
+        let chunks = extract(&capture).unwrap()?;
+        assert_eq!(
+            chunks,
+            [
+                Chunk {
+                    timestamp: 10.5,
+                    bytes: vec![1, 2, 3]
+                },
+                Chunk {
+                    timestamp: 13.5,
+                    bytes: vec![4]
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pcapng_usbmon() -> Result<()> {
+        fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+            let mut body = body.to_vec();
+            body.resize(body.len().div_ceil(4) * 4, 0);
+            let len = (body.len() + 12) as u32;
+            let mut block = block_type.to_le_bytes().to_vec();
+            block.extend_from_slice(&len.to_le_bytes());
+            block.extend_from_slice(&body);
+            block.extend_from_slice(&len.to_le_bytes());
+            block
+        }
+        let mut section = 0x1a2b_3c4du32.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0]);
+        section.extend_from_slice(&u64::MAX.to_le_bytes());
+        let mut interface = (LINKTYPE_USB_LINUX_MMAPPED as u16).to_le_bytes().to_vec();
+        interface.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        // if_tsresol = 10^-9, then end of options.
+        interface.extend_from_slice(&[9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]);
+        let mut packet = vec![0u8; 64];
+        packet[8] = b'C';
+        packet[9] = USB_TRANSFER_BULK;
+        packet[10] = 0x81;
+        packet.extend_from_slice(&[0xaa, 0x55]);
+        let ticks = 2_500_000_000u64;
+        let mut enhanced = 0u32.to_le_bytes().to_vec();
+        enhanced.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+        enhanced.extend_from_slice(&(ticks as u32).to_le_bytes());
+        enhanced.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        enhanced.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        enhanced.extend_from_slice(&packet);
+        let capture = [
+            block(0x0a0d_0d0a, &section),
+            block(1, &interface),
+            block(6, &enhanced),
+        ]
+        .concat();
+        let chunks = extract(&capture).unwrap()?;
+        assert_eq!(
+            chunks,
+            [Chunk {
+                timestamp: 2.5,
+                bytes: vec![0xaa, 0x55]
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_saleae_logic2_csv() -> Result<()> {
+        let csv = "name,type,start_time,duration,data\n\
+                   \"Async Serial\",\"data\",0.5,0.0001,0xAA\n\
+                   \"Async Serial\",\"error\",0.6,0.0001,\n\
+                   \"Async Serial\",\"data\",0.7,0.0001,0x55\n";
+        let chunks = extract(csv.as_bytes()).unwrap()?;
+        assert_eq!(raw_bytes(&chunks), [0xaa, 0x55]);
+        assert_eq!(chunks[1].timestamp, 0.7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_saleae_logic1_csv() -> Result<()> {
+        let csv = "Time [s],Value,Parity Error,Framing Error\n0.1,170,,\n0.2,'85',,\n";
+        let chunks = extract(csv.as_bytes()).unwrap()?;
+        assert_eq!(raw_bytes(&chunks), [0xaa, 0x55]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_a_capture() {
+        assert!(extract(b"1.000 1 2 3 4\n").is_none());
+        assert!(extract(b"timestamp,t1,t2,t3,t4\n").is_none());
+    }
+}
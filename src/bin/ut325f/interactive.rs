@@ -0,0 +1,208 @@
+use std::io::{BufRead, Read};
+use std::sync::Mutex;
+
+use tokio::sync::broadcast;
+use ut325f_rs::Annotation;
+
+use crate::annotate::mark;
+use crate::format::TempUnit;
+
+const HELP: &str =
+    "Keys: space pause/resume, u switch units, h held temps, m mark, n mark with a note, ? help";
+
+/// What the keys of an interactive monitor have set for the display on
+/// stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Controls {
+    /// Readings go unshown, though still read, checked and logged.
+    pub paused: bool,
+    /// Shows held temperatures if --held-temps did not, and hides them
+    /// if it did.
+    pub held_flipped: bool,
+    /// The pretty format's unit.
+    pub unit: TempUnit,
+}
+
+static CONTROLS: Mutex<Controls> = Mutex::new(Controls {
+    paused: false,
+    held_flipped: false,
+    unit: TempUnit::Celsius,
+});
+
+pub fn controls() -> Controls {
+    *CONTROLS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Pause,
+    Unit,
+    Held,
+    Mark,
+    Note,
+    Help,
+}
+
+impl Key {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte.to_ascii_lowercase() {
+            b' ' | b'p' => Some(Self::Pause),
+            b'u' => Some(Self::Unit),
+            b'h' => Some(Self::Held),
+            b'm' => Some(Self::Mark),
+            b'n' => Some(Self::Note),
+            b'?' => Some(Self::Help),
+            _ => None,
+        }
+    }
+}
+
+impl Controls {
+    /// The controls after `key`, and what changed.
+    fn press(self, key: Key) -> (Self, String) {
+        match key {
+            Key::Pause => {
+                let paused = !self.paused;
+                let said = if paused { "Paused" } else { "Resumed" };
+                (Self { paused, ..self }, said.to_owned())
+            }
+            Key::Unit => {
+                let unit = self.unit.next();
+                (Self { unit, ..self }, format!("Units: {}", unit.symbol()))
+            }
+            Key::Held => {
+                let held_flipped = !self.held_flipped;
+                let said = "Held temperatures toggled";
+                (
+                    Self {
+                        held_flipped,
+                        ..self
+                    },
+                    said.to_owned(),
+                )
+            }
+            Key::Mark | Key::Note | Key::Help => (self, String::new()),
+        }
+    }
+}
+
+/// Takes single keys from the terminal on stdin, putting it in raw
+/// mode until [`restore`], or a panic. Returns false where that is
+/// unsupported.
+pub fn start(marks: broadcast::Sender<Annotation>) -> bool {
+    if let Err(e) = raw::enter() {
+        tracing::debug!("Interactive keys are unavailable: {e}");
+        return false;
+    }
+    // Otherwise a panic would leave the shell without echo.
+    let report = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        report(info);
+    }));
+    eprintln!("{HELP}");
+    std::thread::spawn(move || read_keys(&marks));
+    true
+}
+
+fn read_keys(marks: &broadcast::Sender<Annotation>) {
+    let mut stdin = std::io::stdin().lock();
+    let mut byte = [0];
+    while stdin.read(&mut byte).is_ok_and(|n| n == 1) {
+        match Key::from_byte(byte[0]) {
+            Some(Key::Mark) => mark(marks, ""),
+            Some(Key::Note) => {
+                restore();
+                eprint!("Note: ");
+                let mut note = String::new();
+                let _ = stdin.read_line(&mut note);
+                let _ = raw::enter();
+                mark(marks, &note);
+            }
+            Some(Key::Help) => eprintln!("{HELP}"),
+            Some(key) => {
+                let mut controls = CONTROLS.lock().unwrap_or_else(|e| e.into_inner());
+                let (pressed, said) = controls.press(key);
+                *controls = pressed;
+                eprintln!("{said}");
+            }
+            None => {}
+        }
+    }
+}
+
+/// Returns the terminal to the mode it was in before [`start`].
+pub fn restore() {
+    raw::leave();
+}
+
+/// Keys are read as they are pressed, without echo, but Ctrl-C still
+/// interrupts.
+#[cfg(unix)]
+mod raw {
+    use std::sync::Mutex;
+
+    static SAVED: Mutex<Option<libc::termios>> = Mutex::new(None);
+
+    pub fn enter() -> std::io::Result<()> {
+        let mut saved = SAVED.lock().unwrap_or_else(|e| e.into_inner());
+        // SAFETY: tcgetattr fills in the termios it is given, and
+        // tcsetattr only reads it.
+        unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let original = *saved.get_or_insert(termios);
+            termios = original;
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn leave() {
+        if let Some(termios) = *SAVED.lock().unwrap_or_else(|e| e.into_inner()) {
+            // SAFETY: as in `enter`.
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod raw {
+    pub fn enter() -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn leave() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        let mut controls = controls();
+        for byte in *b"pUhh u" {
+            controls = controls.press(Key::from_byte(byte).unwrap()).0;
+        }
+        assert_eq!(
+            controls,
+            Controls {
+                paused: false,
+                held_flipped: false,
+                unit: TempUnit::Kelvin,
+            }
+        );
+        assert_eq!(Key::from_byte(b'n'), Some(Key::Note));
+        assert_eq!(Key::from_byte(b'x'), None);
+    }
+}
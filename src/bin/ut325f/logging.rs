@@ -0,0 +1,118 @@
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Counts warnings and errors, which `--quiet` hides until exit.
+#[derive(Clone, Default)]
+pub struct Suppressed(Arc<AtomicU64>);
+
+impl Suppressed {
+    pub fn count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for Suppressed {
+    fn on_event(&self, _: &tracing::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Writes each event to stderr as a JSON line, for --errors-json: its
+/// time, level and message, and any fields such as `kind`.
+struct JsonLines;
+
+impl<S: tracing::Subscriber> Layer<S> for JsonLines {
+    fn on_event(&self, event: &tracing::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+        let mut fields = Map::new();
+        fields.insert(
+            "timestamp".into(),
+            crate::records::unix_seconds(SystemTime::now()).into(),
+        );
+        fields.insert(
+            "level".into(),
+            event.metadata().level().as_str().to_lowercase().into(),
+        );
+        event.record(&mut JsonFields(&mut fields));
+        let mut stderr = std::io::stderr().lock();
+        let _ = writeln!(stderr, "{}", Value::Object(fields));
+    }
+}
+
+struct JsonFields<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonFields<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0
+            .insert(field.name().into(), crate::format::json_number(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{value:?}").into());
+    }
+}
+
+/// Sends diagnostics to stderr: warnings (rejected samples, alarms,
+/// failed hooks) by default, then info, debug and trace for each
+/// `-v`; nothing with `--quiet`, which counts warnings and errors
+/// instead. Messages read as plain sentences unless `-vv` asks for
+/// levels and sources, or `json` for JSON lines.
+pub fn init(verbose: u8, quiet: bool, json: bool) -> Option<Suppressed> {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::OFF,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let detailed = verbose >= 2;
+    // Fields other than the message, such as an alarm's `kind`, are
+    // for JSON lines only.
+    let message_only =
+        tracing_subscriber::fmt::format::debug_fn(|writer, field, value| match field.name() {
+            "message" => write!(writer, "{value:?}"),
+            _ => Ok(()),
+        });
+    let display = (!json).then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(crate::color::stderr())
+            .without_time()
+            .with_level(detailed)
+            .with_target(detailed)
+            .fmt_fields(message_only)
+            .with_filter(level)
+    });
+    let suppressed = quiet.then(Suppressed::default);
+    tracing_subscriber::registry()
+        .with(display)
+        .with(json.then(|| JsonLines.with_filter(level)))
+        .with(suppressed.clone().with_filter(LevelFilter::WARN))
+        .init();
+    suppressed
+}
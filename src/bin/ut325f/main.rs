@@ -0,0 +1,1547 @@
+use anyhow::Result;
+use anyhow::{Context, anyhow};
+use std::io::Write;
+use std::path::PathBuf;
+
+use ut325f_rs::{
+    Alarms, Heartbeat, LinkStats, Meter, MeterEvent, PlausibilityFilter, Reading, Sink, Tee,
+    Transport,
+};
+
+use cli::{Cli, Command, ConnectArgs, PipelineArgs, ScheduleArgs, StyleArgs};
+
+mod actions;
+mod alarm_log;
+mod annotate;
+mod background;
+mod calibrate;
+mod cli;
+mod color;
+mod compare;
+mod connect;
+mod daily;
+mod drift;
+mod duration;
+mod exit;
+mod format;
+#[cfg(feature = "grafana")]
+mod grafana;
+mod hexdump;
+mod hooks;
+mod import;
+mod interactive;
+mod logging;
+mod merge;
+mod output;
+#[cfg(feature = "parquet")]
+mod parquet_file;
+mod records;
+mod relay;
+#[cfg(any(feature = "webhook", feature = "email"))]
+mod remote;
+mod replay;
+mod report;
+mod resample;
+mod schedule;
+mod schema;
+mod selftest;
+mod sink;
+mod snapshot;
+mod stats;
+
+fn stats(
+    path: &std::path::Path,
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    gap: std::time::Duration,
+    drift: bool,
+) -> Result<()> {
+    let records = records::read_records(path, sync)?;
+    let mut stdout = std::io::stdout().lock();
+    stats::SessionStats::new(&records, gap.as_secs_f64()).write(&mut stdout)?;
+    if drift {
+        let mut fit = drift::Drift::new();
+        for record in &records {
+            fit.push(record);
+        }
+        fit.write(&mut stdout)?;
+    }
+    Ok(())
+}
+
+fn merge(
+    paths: &[PathBuf],
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    output: Option<&std::path::Path>,
+    align: merge::Alignment,
+    tolerance: std::time::Duration,
+) -> Result<()> {
+    let mut logs = Vec::new();
+    for path in paths {
+        let mut records = records::read_records(path, sync)?;
+        records.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        logs.push(records);
+    }
+    let times: Vec<f64> = logs[0].iter().map(|r| r.timestamp).collect();
+    let aligned: Vec<_> = logs[1..]
+        .iter()
+        .map(|other| merge::align(&times, other, align, tolerance.as_secs_f64()))
+        .collect();
+    let mut writer = output::create(output)?;
+    merge::write_merged(&mut writer, &logs[0], &aligned)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn resample(
+    path: &std::path::Path,
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    output: Option<&std::path::Path>,
+    period: std::time::Duration,
+    agg: resample::Aggregation,
+    format: format::Format,
+    style: format::Style,
+) -> Result<()> {
+    let mut records = records::read_records(path, sync)?;
+    records.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    write_records(
+        &resample::resample(&records, period.as_secs_f64(), agg)?,
+        output,
+        format,
+        style,
+    )
+}
+
+/// Batches of records in flight between decoding and writing.
+const EXPORT_BATCHES: usize = 4;
+
+/// How often `export --progress` reports.
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Where `export` writes its records.
+enum Exported {
+    Records(format::RecordWriter<output::Output>),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet_file::ParquetWriter<output::Output>),
+}
+
+impl Exported {
+    fn create(
+        output: Option<&std::path::Path>,
+        to: format::Format,
+        style: &StyleArgs,
+    ) -> Result<Self> {
+        if to != format::Format::Parquet {
+            let style = style.style(to)?;
+            return Ok(Self::Records(format::RecordWriter::new(
+                output::create(output)?,
+                to,
+                style,
+            )));
+        }
+        #[cfg(feature = "parquet")]
+        return Ok(Self::Parquet(parquet_file::ParquetWriter::new(
+            output::create(output)?,
+        )?));
+        #[cfg(not(feature = "parquet"))]
+        Err(anyhow!(
+            "Built without parquet support; rebuild with `--features parquet`"
+        ))
+    }
+
+    fn write(&mut self, record: &records::Record) -> Result<()> {
+        match self {
+            Self::Records(writer) => Ok(writer.write(record)?),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(writer) => writer.write(record),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        let output = match self {
+            Self::Records(writer) => writer.into_inner(),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(writer) => writer.finish()?,
+        };
+        Ok(output.finish()?)
+    }
+}
+
+/// Converts `path` a batch of records at a time, decoding on one thread
+/// while writing on this one.
+fn export(
+    path: &std::path::Path,
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    output: Option<&std::path::Path>,
+    to: format::Format,
+    style: &StyleArgs,
+    progress: bool,
+) -> Result<()> {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .len();
+    let mut writer = Exported::create(output, to, style)?;
+    let consumed = Arc::new(AtomicU64::new(0));
+    let (send, batches) = std::sync::mpsc::sync_channel(EXPORT_BATCHES);
+    let decoder = std::thread::spawn({
+        let path = path.to_owned();
+        let consumed = consumed.clone();
+        let sync = *sync;
+        move || records::stream_records(&path, &sync, consumed, |batch| send.send(batch).is_ok())
+    });
+    let report = |consumed: u64| {
+        let percent = if size == 0 {
+            100.0
+        } else {
+            consumed as f64 * 100.0 / size as f64
+        };
+        eprint!("\r{percent:5.1}% ({consumed} of {size} bytes)");
+    };
+    let mut reported = std::time::Instant::now();
+    let mut written = Ok(());
+    for batch in batches {
+        written = batch.iter().try_for_each(|record| writer.write(record));
+        if written.is_err() {
+            break;
+        }
+        if progress && reported.elapsed() >= PROGRESS_INTERVAL {
+            report(consumed.load(Ordering::Relaxed));
+            reported = std::time::Instant::now();
+        }
+    }
+    // Dropping the receiver on a write error stops the decoder.
+    let decoded = decoder.join().expect("the decoder thread panicked");
+    written?;
+    decoded?;
+    writer.finish()?;
+    if progress {
+        report(consumed.load(Ordering::Relaxed));
+        eprintln!();
+    }
+    Ok(())
+}
+
+fn import(
+    path: &std::path::Path,
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    output: Option<&std::path::Path>,
+) -> Result<()> {
+    let bytes = output::read(path)?;
+    let chunks = import::extract(&bytes)
+        .ok_or_else(|| anyhow!("{}: not a recognized capture format", path.display()))?
+        .with_context(|| path.display().to_string())?;
+    let raw = import::raw_bytes(&chunks);
+    if !Reading::parse_all_with_sync(&raw, sync).any(|reading| reading.is_ok()) {
+        let sync: Vec<_> = sync.iter().map(|b| format!("{b:02x}")).collect();
+        return Err(anyhow!(
+            "{}: no frames starting with {} in the capture (see --sync)",
+            path.display(),
+            sync.join(":")
+        ));
+    }
+    let mut out = output::create(output)?;
+    out.write_all(&raw)?;
+    out.finish()?;
+    Ok(())
+}
+
+fn write_records(
+    records: &[records::Record],
+    output: Option<&std::path::Path>,
+    format: format::Format,
+    style: format::Style,
+) -> Result<()> {
+    let mut writer = format::RecordWriter::new(output::create(output)?, format, style);
+    for record in records {
+        writer.write(record)?;
+    }
+    writer.into_inner().finish()?;
+    Ok(())
+}
+
+/// Where and how a live or replayed session writes its readings.
+struct Session<'a> {
+    source: String,
+    /// Empty for stdout alone.
+    outputs: &'a [sink::OutputSpec],
+    rotation: Option<sink::Rotation>,
+    flush: background::FlushPolicy,
+    format: format::Format,
+    /// The format of stdout, unless its --output gives one.
+    stdout_format: format::Format,
+    /// Color stdout, if its format is pretty.
+    color: bool,
+    style: &'a StyleArgs,
+    pipeline: &'a PipelineArgs,
+    disconnect: bool,
+    /// Keep the meter open between scheduled windows.
+    hold_port: bool,
+    /// End the session after this long.
+    duration: Option<std::time::Duration>,
+    /// End the session after the first reading.
+    once: bool,
+    /// Where to write the --report.
+    report: Option<PathBuf>,
+    /// Where to append the --daily-summary.
+    daily_summary: Option<PathBuf>,
+    /// The --utc-offset of the midnight that ends a summary's day.
+    utc_offset: i64,
+    /// Tags records with the meter, when several are read at once.
+    meter: Option<String>,
+    /// The --relay, shared by every meter.
+    relay: Option<&'a std::sync::Mutex<relay::Relay>>,
+    /// The --alarm-log, shared by every meter.
+    alarm_log: Option<&'a std::sync::Mutex<alarm_log::AlarmLog>>,
+    /// Marks to write to every meter's outputs.
+    annotations: &'a annotate::Annotations,
+}
+
+impl Session<'_> {
+    /// The outputs a session writes to, in the order they join its
+    /// [`Tee`].
+    fn outputs(&self) -> &[sink::OutputSpec] {
+        const STDOUT: &[sink::OutputSpec] = &[sink::OutputSpec {
+            path: None,
+            format: None,
+            every: None,
+        }];
+        if !self.outputs.is_empty() {
+            self.outputs
+        } else if self.pipeline.raw_hex == Some(hexdump::RawHex::Only) {
+            &[]
+        } else {
+            STDOUT
+        }
+    }
+
+    /// The format `spec` is written in.
+    fn format_of(&self, spec: &sink::OutputSpec) -> format::Format {
+        spec.format.unwrap_or(match spec.path {
+            Some(_) => self.format,
+            None => self.stdout_format,
+        })
+    }
+
+    /// Opens every output, plus the Grafana push if asked for, with the
+    /// background writers of its files to close after it finishes.
+    fn tee(&self) -> Result<(Tee, Vec<background::Files>)> {
+        let outputs = self.outputs();
+        if outputs.iter().filter(|spec| spec.path.is_none()).count() > 1 {
+            return Err(anyhow!("--output - given more than once"));
+        }
+        let mut tee = Tee::new();
+        let mut files = Vec::new();
+        for spec in outputs {
+            let format = self.format_of(spec);
+            let style = format::Style {
+                meter: self.meter.clone(),
+                validity: self.pipeline.tolerant,
+                quality: self.pipeline.quality,
+                color: self.color && spec.path.is_none(),
+                ..self.style.style(format)?
+            };
+            let sink = sink::FileSink::create(
+                spec.path.as_deref(),
+                format,
+                style,
+                self.pipeline.held_temps,
+                spec.path.as_ref().and(self.rotation.clone()),
+                self.flush,
+            )?;
+            files.extend(sink.files());
+            tee = tee.with_every(sink, spec.every);
+        }
+        #[cfg(feature = "grafana")]
+        if let Some(url) = &self.pipeline.grafana {
+            tee = tee.with(grafana::GrafanaSink::new(
+                url.clone(),
+                std::env::var("UT325F_GRAFANA_TOKEN").ok(),
+                self.pipeline.held_temps,
+                self.meter.clone(),
+                self.style.derive.clone(),
+            ));
+        }
+        Ok((tee, files))
+    }
+
+    /// An empty report, if --report asked for one.
+    fn report(&self) -> Option<report::RunReport> {
+        self.report.as_ref().map(|_| {
+            report::RunReport::new(self.source.clone())
+                .with_thermocouples(&self.pipeline.thermocouple)
+                .with_reference(self.pipeline.reference)
+        })
+    }
+
+    /// The stages readings pass through, with a --daily-summary begun
+    /// now if asked for.
+    fn stages(&self) -> Stages {
+        let mut stages = Stages::new(self.pipeline);
+        stages.marks = Some(self.annotations.subscribe());
+        stages.daily = self.daily_summary.clone().map(|path| {
+            daily::DailySummary::new(path, self.utc_offset, std::time::SystemTime::now())
+        });
+        stages
+    }
+
+    /// Applies the options that change how the meter decodes.
+    fn prepare<T: Transport>(&self, meter: Meter<T>) -> Meter<T> {
+        let mut meter = meter
+            .with_strict(self.pipeline.strict)
+            .with_tolerant(self.pipeline.tolerant);
+        if let Some(sync) = self.pipeline.sync {
+            meter = meter.with_sync(sync);
+        }
+        if self.pipeline.raw_hex_skipped {
+            meter.with_discarded_kept()
+        } else {
+            meter
+        }
+    }
+
+    /// Logs the link statistics and analysis of a finished session and
+    /// writes its --report and the last --daily-summary.
+    fn conclude(
+        &self,
+        report: Option<&report::RunReport>,
+        stages: &mut Stages,
+        link: LinkStats,
+        result: Result<()>,
+    ) -> Result<()> {
+        log_link_stats(link);
+        stages.log_analysis();
+        let result = match stages.daily.as_mut() {
+            Some(daily) => result.and(daily.finish(std::time::SystemTime::now())),
+            None => result,
+        };
+        match (report, &self.report) {
+            (Some(report), Some(path)) => {
+                let written = report.write(path, link, result.as_ref().err());
+                result.and(written)
+            }
+            _ => result,
+        }
+    }
+}
+
+async fn run<T: Transport>(meter: Meter<T>, session: &Session<'_>) -> Result<()> {
+    let mut meter = session.prepare(meter);
+    let mut report = session.report();
+    let mut stages = session.stages();
+    let result = match session.tee() {
+        Ok((mut tee, files)) => {
+            // Ctrl-C must also go through teardown: dying with a
+            // connection held leaves it dangling in the Bluetooth stack
+            // instead of deliberately kept (detach) or released (close),
+            // and an unfinished compressed log is truncated.
+            let reading =
+                read_readings(&mut meter, &mut tee, report.as_mut(), session, &mut stages);
+            let result = tokio::select! {
+                result = bounded(session.duration, reading) => result,
+                interrupt = tokio::signal::ctrl_c() => interrupt.map_err(Into::into),
+            };
+            let result = result.and(ignore_broken_pipe(tee.finish()).map_err(Into::into));
+            result.and(close_files(&files).await)
+        }
+        Err(e) => Err(e),
+    };
+    let result = session.conclude(report.as_ref(), &mut stages, meter.link_stats(), result);
+    let torn_down = connect::teardown(meter, session.disconnect).await;
+    // A read error is the story; a teardown failure matters only on an
+    // otherwise clean exit.
+    result.and(torn_down)
+}
+
+/// Runs `future`, but for no longer than `limit`.
+async fn bounded(
+    limit: Option<std::time::Duration>,
+    future: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, future).await.unwrap_or(Ok(())),
+        None => future.await,
+    }
+}
+
+/// Runs a session on the meter, or each of the meters, given on the
+/// command line.
+async fn live(connect: &ConnectArgs, schedule: &ScheduleArgs, session: &Session<'_>) -> Result<()> {
+    let ports = connect::expand_ports(&connect.port)?;
+    if ports.len() < 2 {
+        return live_one(connect, schedule, session).await;
+    }
+    let stdout_csv = session
+        .outputs()
+        .iter()
+        .any(|spec| spec.path.is_none() && session.format_of(spec) == format::Format::Csv);
+    if stdout_csv {
+        return Err(anyhow!(
+            "Several meters cannot share CSV on stdout; write to files or use another format"
+        ));
+    }
+    if connect.name.is_some() {
+        return Err(anyhow!("--name names one meter, but several were given"));
+    }
+    let mut names: Vec<_> = ports.iter().map(|port| connect::meter_id(port)).collect();
+    // Adapters without distinct serial numbers fall back to port names,
+    // so no two meters share outputs.
+    if (1..names.len()).any(|i| names[..i].contains(&names[i])) {
+        names = ports.iter().map(|port| connect::port_name(port)).collect();
+    }
+    let meters: Vec<_> = ports
+        .iter()
+        .zip(names)
+        .map(|(port, name)| {
+            let outputs: Vec<_> = session
+                .outputs
+                .iter()
+                .map(|spec| sink::OutputSpec {
+                    path: spec
+                        .path
+                        .as_ref()
+                        .map(|path| sink::tagged_path(path, &name)),
+                    ..spec.clone()
+                })
+                .collect();
+            (connect.with_port(port.clone()), name, outputs)
+        })
+        .collect();
+    let sessions: Vec<_> = meters
+        .iter()
+        .map(|(connect, name, outputs)| Session {
+            source: connect.source(),
+            outputs,
+            rotation: session.rotation.clone(),
+            report: session
+                .report
+                .as_ref()
+                .map(|path| sink::tagged_path(path, name)),
+            daily_summary: session
+                .daily_summary
+                .as_ref()
+                .map(|path| sink::tagged_path(path, name)),
+            meter: Some(name.clone()),
+            ..*session
+        })
+        .collect();
+    let results = join_all(
+        meters
+            .iter()
+            .zip(&sessions)
+            .map(|((connect, ..), session)| live_one(connect, schedule, session)),
+    )
+    .await;
+    // One meter failing does not stop the others; report all failures
+    // and return the first.
+    let mut result = Ok(());
+    for ((_, name, _), outcome) in meters.iter().zip(results) {
+        if let Err(e) = outcome {
+            tracing::error!("{name}: {e:#}");
+            result = result.and(Err(e.context(name.clone())));
+        }
+    }
+    result
+}
+
+/// Polls `futures` together on the current task, so that their writes
+/// to a shared stdout never interleave within a line, and returns their
+/// outputs in order.
+async fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> Vec<F::Output> {
+    let mut futures: Vec<_> = futures.into_iter().map(|f| Some(Box::pin(f))).collect();
+    let mut outputs: Vec<_> = futures.iter().map(|_| None).collect();
+    std::future::poll_fn(|cx| {
+        let mut pending = false;
+        for (slot, output) in futures.iter_mut().zip(&mut outputs) {
+            if let Some(future) = slot {
+                match future.as_mut().poll(cx) {
+                    std::task::Poll::Ready(value) => {
+                        *output = Some(value);
+                        *slot = None;
+                    }
+                    std::task::Poll::Pending => pending = true,
+                }
+            }
+        }
+        if pending {
+            std::task::Poll::Pending
+        } else {
+            std::task::Poll::Ready(())
+        }
+    })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+/// Runs a session on one meter.
+async fn live_one(
+    connect: &ConnectArgs,
+    schedule: &ScheduleArgs,
+    session: &Session<'_>,
+) -> Result<()> {
+    if let Some(cron) = &schedule.cron {
+        return run_sessions(connect, session, cron, schedule.utc_offset.unwrap_or(0)).await;
+    }
+    match schedule.schedule()? {
+        Some(schedule) => run_scheduled(connect, session, &schedule).await,
+        None => run(connect::open(connect).await?, session).await,
+    }
+}
+
+/// Runs a session, with its own outputs and report, at each time `cron`
+/// gives.
+async fn run_sessions(
+    connect: &ConnectArgs,
+    session: &Session<'_>,
+    cron: &schedule::Cron,
+    utc_offset: i64,
+) -> Result<()> {
+    let duration = session.duration.unwrap_or_default();
+    loop {
+        let Some(start) = cron.next_after(std::time::SystemTime::now(), utc_offset) else {
+            return Ok(());
+        };
+        tracing::debug!(
+            "Waiting for the next session at {}",
+            humantime::format_rfc3339_seconds(start)
+        );
+        let wait = start
+            .duration_since(std::time::SystemTime::now())
+            .unwrap_or_default();
+        tokio::select! {
+            () = tokio::time::sleep(wait) => {}
+            interrupt = tokio::signal::ctrl_c() => return interrupt.map_err(Into::into),
+        }
+        let outputs: Vec<_> = session
+            .outputs
+            .iter()
+            .map(|spec| sink::OutputSpec {
+                path: spec
+                    .path
+                    .as_ref()
+                    .map(|path| sink::segment_path(path, start)),
+                ..spec.clone()
+            })
+            .collect();
+        let this = Session {
+            source: session.source.clone(),
+            outputs: &outputs,
+            rotation: None,
+            report: session
+                .report
+                .as_ref()
+                .map(|path| sink::segment_path(path, start)),
+            daily_summary: session.daily_summary.clone(),
+            meter: session.meter.clone(),
+            ..*session
+        };
+        tracing::info!(
+            "Session started at {}",
+            humantime::format_rfc3339_seconds(start)
+        );
+        let result = match connect::open(connect).await {
+            Ok(meter) => run(meter, &this).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            // Ctrl-C, or stdout's reader going away, ends a session
+            // early and the schedule with it.
+            Ok(()) if std::time::SystemTime::now() < start + duration => return Ok(()),
+            Ok(()) => {}
+            // Unattended, a failed session should not prevent the next.
+            Err(e) => tracing::error!(
+                "Session at {} failed: {e:#}",
+                humantime::format_rfc3339_seconds(start)
+            ),
+        }
+    }
+}
+
+/// Like [`run`], but reads the meter only during the windows of
+/// `schedule`, closing it in between unless --hold-port.
+async fn run_scheduled(
+    connect: &ConnectArgs,
+    session: &Session<'_>,
+    schedule: &schedule::Schedule,
+) -> Result<()> {
+    let mut report = session.report();
+    let mut stages = session.stages();
+    let mut link = LinkStats::default();
+    let result = match session.tee() {
+        Ok((mut tee, files)) => {
+            let result = read_windows(
+                connect,
+                session,
+                schedule,
+                &mut tee,
+                report.as_mut(),
+                &mut stages,
+                &mut link,
+            )
+            .await;
+            let result = result.and(ignore_broken_pipe(tee.finish()).map_err(Into::into));
+            result.and(close_files(&files).await)
+        }
+        Err(e) => Err(e),
+    };
+    session.conclude(report.as_ref(), &mut stages, link, result)
+}
+
+async fn read_windows(
+    connect: &ConnectArgs,
+    session: &Session<'_>,
+    schedule: &schedule::Schedule,
+    tee: &mut Tee,
+    mut report: Option<&mut report::RunReport>,
+    stages: &mut Stages,
+    link: &mut LinkStats,
+) -> Result<()> {
+    let (meter, result) = sample_windows(
+        schedule,
+        session.hold_port,
+        std::time::SystemTime::now,
+        async || Ok(session.prepare(connect::open(connect).await?)),
+        async |meter| read_readings(meter, tee, report.as_deref_mut(), session, stages).await,
+        link,
+    )
+    .await;
+    let Some(meter) = meter else {
+        return result;
+    };
+    *link += meter.link_stats();
+    let torn_down = connect::teardown(meter, !session.hold_port || session.disconnect).await;
+    result.and(torn_down)
+}
+
+/// The loop of [`read_windows`]: `read` the meter `open` gives during
+/// each window of `schedule`, by the clock `now`. Returns the meter
+/// still open at the end, if any.
+async fn sample_windows<T: Transport>(
+    schedule: &schedule::Schedule,
+    hold_port: bool,
+    now: impl Fn() -> std::time::SystemTime,
+    mut open: impl AsyncFnMut() -> Result<Meter<T>>,
+    mut read: impl AsyncFnMut(&mut Meter<T>) -> Result<()>,
+    link: &mut LinkStats,
+) -> (Option<Meter<T>>, Result<()>) {
+    let interrupt = tokio::signal::ctrl_c();
+    tokio::pin!(interrupt);
+    // Open during windows, and between them too with --hold-port.
+    let mut meter = None;
+    let result = loop {
+        let window = schedule.window(now());
+        let until = |time: std::time::SystemTime| time.duration_since(now()).unwrap_or_default();
+        // A held meter is drained while waiting, so the window starts
+        // with fresh readings rather than a backlog.
+        let drain = async {
+            match meter.as_mut() {
+                Some(meter) => discard_readings(meter).await,
+                None => std::future::pending().await,
+            }
+        };
+        if !until(window.start).is_zero() {
+            tracing::debug!(
+                "Waiting for the next window at {}",
+                humantime::format_rfc3339_seconds(window.start)
+            );
+        }
+        tokio::select! {
+            () = tokio::time::sleep(until(window.start)) => {}
+            e = drain => break Err(anyhow::Error::new(e).context("Error reading data")),
+            interrupt = &mut interrupt => break interrupt.map_err(Into::into),
+        }
+        let opened = match meter.as_mut() {
+            Some(meter) => meter,
+            None => match open().await {
+                Ok(opened) => meter.insert(opened),
+                // Unattended, a meter that fails to open should not
+                // prevent the next window.
+                Err(e) => {
+                    tracing::error!(
+                        "Window at {} skipped: {e:#}",
+                        humantime::format_rfc3339_seconds(window.start)
+                    );
+                    tokio::select! {
+                        () = tokio::time::sleep(until(window.end)) => continue,
+                        interrupt = &mut interrupt => break interrupt.map_err(Into::into),
+                    }
+                }
+            },
+        };
+        tracing::debug!("Sampling for {:.1?}", until(window.end));
+        // The window closing ends the read; anything else ends the
+        // session.
+        let ended = tokio::select! {
+            result = tokio::time::timeout(until(window.end), read(opened)) => result.ok(),
+            interrupt = &mut interrupt => Some(interrupt.map_err(Into::into)),
+        };
+        if let Some(result) = ended {
+            break result;
+        }
+        if !hold_port && let Some(closing) = meter.take() {
+            *link += closing.link_stats();
+            // Closing rather than detaching: a BLE meter left connected
+            // stays awake, which is what the windows are meant to avoid.
+            if let Err(e) = connect::teardown(closing, true).await {
+                tracing::error!("Failed to close the meter after its window: {e:#}");
+            }
+        }
+    };
+    (meter, result)
+}
+
+/// Reads and drops readings until an error other than a timeout.
+async fn discard_readings<T: Transport>(meter: &mut Meter<T>) -> ut325f_rs::Error {
+    loop {
+        match meter.read().await {
+            Ok(_) | Err(ut325f_rs::Error::ReadTimeout) => {}
+            Err(e) => return e,
+        }
+    }
+}
+
+#[cfg(any(feature = "webhook", feature = "email"))]
+fn remote_notifier(pipeline: &PipelineArgs) -> Option<remote::RemoteNotifier> {
+    if pipeline.webhook.is_none() && pipeline.email_to.is_empty() {
+        return None;
+    }
+    let notifier = remote::RemoteNotifier::new(pipeline.alarm_interval);
+    #[cfg(feature = "webhook")]
+    let notifier = match &pipeline.webhook {
+        Some(url) => notifier.with_webhook(url.clone()),
+        None => notifier,
+    };
+    #[cfg(feature = "email")]
+    let notifier = match (&pipeline.smtp_server, &pipeline.email_from) {
+        (Some(server), Some(from)) if !pipeline.email_to.is_empty() => {
+            notifier.with_email(remote::EmailConfig {
+                server: server.clone(),
+                from: from.clone(),
+                to: pipeline.email_to.clone(),
+                user: pipeline.smtp_user.clone(),
+                password: std::env::var("UT325F_SMTP_PASSWORD").ok(),
+            })
+        }
+        _ => notifier,
+    };
+    Some(notifier)
+}
+
+/// The state a session keeps from reading to reading, across sampling
+/// windows too.
+struct Stages {
+    alarms: Alarms,
+    actions: actions::AlarmActions,
+    filter: PlausibilityFilter,
+    on_alarm: Option<hooks::Hook>,
+    on_reading: Option<hooks::Hook>,
+    last_reading: Option<std::time::SystemTime>,
+    comparison: Option<compare::Comparison>,
+    drift: Option<drift::Drift>,
+    daily: Option<daily::DailySummary>,
+    marks: Option<tokio::sync::broadcast::Receiver<ut325f_rs::Annotation>>,
+}
+
+impl Stages {
+    fn new(pipeline: &PipelineArgs) -> Self {
+        let mut filter =
+            PlausibilityFilter::new().with_range(pipeline.plausible_min, pipeline.plausible_max);
+        if let Some(max_step) = pipeline.max_step {
+            filter = filter.with_max_step(max_step);
+        }
+        for &(channel, kind) in &pipeline.thermocouple {
+            filter = filter.with_thermocouple(channel, kind);
+        }
+        Self {
+            alarms: Alarms::new(pipeline.alarm.clone()),
+            actions: actions::AlarmActions {
+                bell: pipeline.bell,
+                notify: pipeline.notify,
+                #[cfg(any(feature = "webhook", feature = "email"))]
+                remote: remote_notifier(pipeline),
+            },
+            filter,
+            on_alarm: pipeline.on_alarm.clone().map(hooks::Hook::new),
+            on_reading: pipeline.on_reading.clone().map(hooks::Hook::new),
+            last_reading: None,
+            comparison: pipeline.reference.map(compare::Comparison::new),
+            drift: pipeline.drift.then(drift::Drift::new),
+            daily: None,
+            marks: None,
+        }
+    }
+
+    /// Writes the --daily-summary of every day that ended by `now`. A
+    /// failure is only logged: it should not end a long session.
+    fn roll_daily(&mut self, now: std::time::SystemTime) {
+        if let Some(Err(e)) = self.daily.as_mut().map(|daily| daily.roll(now)) {
+            tracing::warn!("Writing the daily summary failed: {e:#}");
+        }
+    }
+
+    /// Logs the --reference comparison and --drift fits so far.
+    fn log_analysis(&self) {
+        if let Some(comparison) = &self.comparison {
+            comparison.log();
+        }
+        if let Some(drift) = &self.drift {
+            drift.log();
+        }
+    }
+}
+
+async fn read_readings<T: Transport>(
+    meter: &mut Meter<T>,
+    tee: &mut Tee,
+    mut report: Option<&mut report::RunReport>,
+    session: &Session<'_>,
+    stages: &mut Stages,
+) -> Result<()> {
+    let pipeline = session.pipeline;
+    let mut last_link_report = std::time::Instant::now();
+    // The last reading or heartbeat.
+    let mut last_sign_of_life = tokio::time::Instant::now();
+    loop {
+        let due = stages.daily.as_ref().map(daily::DailySummary::due);
+        let read = async {
+            match pipeline.heartbeat {
+                Some(every) => tokio::time::timeout_at(last_sign_of_life + every, meter.read())
+                    .await
+                    .unwrap_or(Err(ut325f_rs::Error::ReadTimeout)),
+                None => meter.read().await,
+            }
+        };
+        // Midnight ends a --daily-summary day even while the meter is
+        // silent.
+        let midnight = async {
+            match due {
+                Some(due) => {
+                    let until = due
+                        .duration_since(std::time::SystemTime::now())
+                        .unwrap_or_default();
+                    tokio::time::sleep(until).await;
+                }
+                None => std::future::pending().await,
+            }
+        };
+        let result = tokio::select! {
+            result = read => result,
+            () = midnight => {
+                stages.roll_daily(std::time::SystemTime::now());
+                continue;
+            }
+            annotation = annotate::next(&mut stages.marks) => {
+                let written = tee.annotate(&annotation);
+                log_closed_sinks(tee, session);
+                ignore_broken_pipe(written)?;
+                continue;
+            }
+        };
+        let mut reading = match result {
+            Ok(reading) => reading,
+            Err(ut325f_rs::Error::ReadTimeout) if pipeline.heartbeat.is_some() => {
+                if last_sign_of_life.elapsed() >= pipeline.heartbeat.unwrap_or_default() {
+                    let heartbeat =
+                        Heartbeat::new(std::time::SystemTime::now(), stages.last_reading);
+                    let written = tee.heartbeat(&heartbeat);
+                    log_closed_sinks(tee, session);
+                    ignore_broken_pipe(written)?;
+                    last_sign_of_life = tokio::time::Instant::now();
+                }
+                continue;
+            }
+            // Only a replayed capture ends this way.
+            Err(ut325f_rs::Error::Disconnected(replay::END_OF_CAPTURE)) => return Ok(()),
+            Err(e) => return Err(anyhow::Error::new(e).context("Error reading data")),
+        };
+        last_sign_of_life = tokio::time::Instant::now();
+        stages.last_reading = Some(reading.timestamp);
+        if pipeline.raw_hex.is_some()
+            && let Err(e) = dump_hex(meter, &reading)
+        {
+            return ignore_broken_pipe(Err(e.into())).map_err(Into::into);
+        }
+        for rejection in stages.filter.apply(&mut reading) {
+            tracing::warn!(
+                kind = "rejected",
+                channel = rejection.channel + 1,
+                temp_c = rejection.temp_c,
+                reason = %rejection.reason,
+                "Rejected T{} reading {:.1} °C ({})",
+                rejection.channel + 1,
+                rejection.temp_c,
+                rejection.reason
+            );
+        }
+        let record = records::Record::from_reading(&reading, pipeline.held_temps);
+        stages.roll_daily(reading.timestamp);
+        if let Some(daily) = stages.daily.as_mut() {
+            daily.record(&record);
+        }
+        let events = stages.alarms.update(&reading);
+        if let Some(log) = session.alarm_log {
+            let logged = log.lock().unwrap_or_else(|e| e.into_inner()).update(
+                session.meter.as_deref(),
+                &reading,
+                &events,
+            );
+            if let Err(e) = logged {
+                tracing::warn!("Writing the alarm log failed: {e:#}");
+            }
+        }
+        for event in events {
+            stages.actions.fire(&event);
+            if let Some(daily) = stages.daily.as_mut().filter(|_| event.raised) {
+                daily.alarm();
+            }
+            if let Some(relay) = session.relay {
+                relay
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .update(&event);
+            }
+            if let Some(report) = report.as_deref_mut() {
+                report.alarm(&event);
+            }
+            if let Some(hook) = &stages.on_alarm {
+                hook.on_alarm(&event, &record);
+            }
+        }
+        if let Some(report) = report.as_deref_mut() {
+            report.record(&record);
+        }
+        if let Some(comparison) = stages.comparison.as_mut() {
+            comparison.push(&record);
+        }
+        if let Some(drift) = stages.drift.as_mut() {
+            drift.push(&record);
+        }
+        if let Some(hook) = &stages.on_reading {
+            hook.on_reading(&record);
+        }
+        let written = tee.write(&reading);
+        log_closed_sinks(tee, session);
+        if let Err(e) = written {
+            // Reading stops when every consumer has gone away (e.g.
+            // piped to head).
+            return ignore_broken_pipe(Err(e)).map_err(Into::into);
+        }
+        if session.once {
+            return Ok(());
+        }
+        if last_link_report.elapsed() >= LINK_REPORT_INTERVAL {
+            log_link_stats(meter.link_stats());
+            stages.log_analysis();
+            last_link_report = std::time::Instant::now();
+        }
+    }
+}
+
+/// Writes the frame behind `reading`, after any bytes skipped before
+/// it, for --raw-hex.
+fn dump_hex<T: Transport>(meter: &mut Meter<T>, reading: &Reading) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    hexdump::write_skipped(&mut stdout, &meter.take_discarded())?;
+    if let Some(frame) = meter.last_frame() {
+        let timestamp = records::Record::from_reading(reading, false).timestamp;
+        hexdump::write_frame(&mut stdout, timestamp, frame)?;
+    }
+    std::io::Write::flush(&mut stdout)
+}
+
+/// How often `-v` reports link quality during a session.
+const LINK_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+fn log_link_stats(link: LinkStats) {
+    let mean_sync = link
+        .mean_sync_acquisition_time()
+        .map_or("n/a".to_owned(), |mean| format!("{mean:.1?}"));
+    tracing::info!(
+        "Link: {} readings, {} discarded bytes, {} rejected frames, {} timeouts, \
+         mean sync time {mean_sync} over {} acquisitions",
+        link.readings,
+        link.discarded_bytes,
+        link.rejected_frames,
+        link.timeouts,
+        link.sync_acquisitions
+    );
+}
+
+/// Waits for each log file's background writer to get everything
+/// to the disk.
+async fn close_files(files: &[background::Files]) -> Result<()> {
+    let mut result = Ok(());
+    for files in files {
+        result = result.and(files.close().await.map_err(Into::into));
+    }
+    result
+}
+
+fn ignore_broken_pipe(result: ut325f_rs::Result<()>) -> ut325f_rs::Result<()> {
+    match result {
+        Err(ut325f_rs::Error::Io(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        Err(ut325f_rs::Error::SinksClosed) => Ok(()),
+        result => result,
+    }
+}
+
+/// Logs the outputs `tee` has dropped since the last call because their
+/// reader went away.
+fn log_closed_sinks(tee: &mut Tee, session: &Session<'_>) {
+    for event in tee.take_events() {
+        if let MeterEvent::SinkClosed { sink, .. } = event {
+            let name = match session.outputs().get(sink) {
+                Some(sink::OutputSpec {
+                    path: Some(path), ..
+                }) => path.display().to_string(),
+                Some(sink::OutputSpec { path: None, .. }) => "stdout".to_owned(),
+                None => "Grafana".to_owned(),
+            };
+            tracing::info!(
+                kind = "sink_closed",
+                "Output {name} closed; no longer writing to it"
+            );
+        }
+    }
+}
+
+async fn info(connect: &ConnectArgs) -> Result<()> {
+    let mut meter = connect::open(connect).await?;
+    let result = meter.read().await;
+    let link = meter.link_stats();
+    let torn_down = connect::teardown(meter, connect.disconnect).await;
+    let reading = result?;
+    torn_down?;
+    let mut out = std::io::stdout();
+    writeln!(out, "Source:      {}", connect.source())?;
+    for (channel, temp) in reading.current_temps_c.iter().enumerate() {
+        match reading.current_errors[channel] {
+            0 if !temp.is_nan() => writeln!(out, "T{}:          {temp:.1} °C", channel + 1)?,
+            Reading::OPEN_PROBE => writeln!(out, "T{}:          open", channel + 1)?,
+            error => writeln!(
+                out,
+                "T{}:          no value (error 0x{error:02x})",
+                channel + 1
+            )?,
+        }
+    }
+    writeln!(out, "Hold:        {:?}", reading.hold_type)?;
+    writeln!(out, "Meter temp:  {:.1} °C", reading.meter_temp_c)?;
+    let flags = |errors: [u8; 4]| errors.map(|e| format!("{e:02x}")).join(" ");
+    writeln!(
+        out,
+        "Error flags: current {}, held {}",
+        flags(reading.current_errors),
+        flags(reading.held_errors)
+    )?;
+    writeln!(
+        out,
+        "Link:        {} discarded bytes, {} rejected frames",
+        link.discarded_bytes, link.rejected_frames
+    )?;
+    Ok(())
+}
+
+/// Readings checked by `selftest` on a live meter, about 3 s worth.
+const SELFTEST_READINGS: usize = 10;
+
+async fn selftest(connect: &ConnectArgs) -> Result<()> {
+    selftest::check_decoder().map_err(|e| anyhow!("Decoder check failed: {e}"))?;
+    let mut out = std::io::stdout();
+    writeln!(out, "Decoder:     ok")?;
+    if !connect.is_given() {
+        return Ok(());
+    }
+    let mut meter = connect::open(connect).await?;
+    let mut result = Ok(());
+    for _ in 0..SELFTEST_READINGS {
+        if let Err(e) = meter.read().await {
+            result = Err(anyhow::Error::new(e).context("Link check failed"));
+            break;
+        }
+    }
+    let link = meter.link_stats();
+    let torn_down = connect::teardown(meter, connect.disconnect).await;
+    result?;
+    torn_down?;
+    writeln!(
+        out,
+        "Link:        ok ({} readings, {} discarded bytes, {} rejected frames)",
+        link.readings, link.discarded_bytes, link.rejected_frames
+    )?;
+    Ok(())
+}
+
+fn list_ports() -> Result<()> {
+    #[cfg(feature = "serial")]
+    {
+        let mut out = std::io::stdout();
+        for port in tokio_serial::available_ports()? {
+            match port.port_type {
+                tokio_serial::SerialPortType::UsbPort(usb) => writeln!(
+                    out,
+                    "{}  USB {:04x}:{:04x}{}",
+                    port.port_name,
+                    usb.vid,
+                    usb.pid,
+                    usb.product.map(|p| format!(" {p}")).unwrap_or_default()
+                )?,
+                _ => writeln!(out, "{}", port.port_name)?,
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(feature = "serial"))]
+    Err(anyhow!(
+        "Built without serial support; rebuild with `--features serial`"
+    ))
+}
+
+#[cfg(any(feature = "bluebus", feature = "btleplug"))]
+async fn discover(scan_time: std::time::Duration) -> Result<()> {
+    let meters = ut325f_rs::BleTransport::discover(scan_time).await?;
+    if meters.is_empty() {
+        tracing::warn!("No meters found.");
+    }
+    let mut out = std::io::stdout();
+    for meter in &meters {
+        let status = match (meter.connected, meter.rssi) {
+            (true, _) => "connected".to_owned(),
+            (false, Some(rssi)) => format!("{rssi} dBm"),
+            (false, None) => "cached".to_owned(),
+        };
+        writeln!(out, "{}  {}  [{}]", meter.address, meter.name, status)?;
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> std::process::ExitCode {
+    let cli = match <Cli as clap::Parser>::try_parse().and_then(|cli| cli.check().map(|()| cli)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return std::process::ExitCode::from(if e.use_stderr() {
+                exit::USAGE
+            } else {
+                exit::OK
+            });
+        }
+    };
+    color::init(cli.color, cli.errors_json);
+    let suppressed = logging::init(cli.verbose, cli.quiet, cli.errors_json);
+    // A reader that goes away (e.g. `| head`) has all it wanted.
+    let result = run_command(&cli).await;
+    interactive::restore();
+    let result = result.or_else(|e| {
+        if exit::downstream_closed(&e) {
+            Ok(())
+        } else {
+            Err(e)
+        }
+    });
+    if let Some(count) = suppressed.map(|s| s.count()).filter(|&n| n > 0) {
+        let plural = if count == 1 { "" } else { "s" };
+        eprintln!("--quiet suppressed {count} warning or error message{plural}");
+    }
+    match &result {
+        Err(e) if cli.errors_json => tracing::error!(kind = "fatal", "{e:#}"),
+        Err(e) => eprintln!("Error: {e:?}"),
+        Ok(()) => {}
+    }
+    exit::code(&result)
+}
+
+/// Opens the --alarm-log, if given.
+fn open_alarm_log(
+    pipeline: &PipelineArgs,
+) -> Result<Option<std::sync::Mutex<alarm_log::AlarmLog>>> {
+    let path = pipeline.alarm_log.as_deref();
+    let log = path.map(alarm_log::AlarmLog::open).transpose()?;
+    Ok(log.map(std::sync::Mutex::new))
+}
+
+/// Opens the --relay, if given.
+fn open_relay(pipeline: &PipelineArgs) -> Result<Option<std::sync::Mutex<relay::Relay>>> {
+    let relay = pipeline.relay.clone().map(relay::Relay::open).transpose()?;
+    Ok(relay.map(std::sync::Mutex::new))
+}
+
+async fn run_command(cli: &Cli) -> Result<()> {
+    let format = cli.format.unwrap_or(format::Format::Text);
+    let stdout_format = cli.format.unwrap_or_else(format::Format::for_stdout);
+    match &cli.command {
+        Command::Monitor(monitor) => {
+            monitor.pipeline.check_features()?;
+            let relay = open_relay(&monitor.pipeline)?;
+            let alarm_log = open_alarm_log(&monitor.pipeline)?;
+            let annotations =
+                annotate::Annotations::start(true, true, monitor.pipeline.annotate_listen).await?;
+            let session = Session {
+                source: monitor.connect.source(),
+                outputs: &monitor.output,
+                rotation: monitor.rotation.rotation(),
+                flush: monitor.buffering.flush_policy(),
+                format,
+                stdout_format,
+                color: cli.color.stdout(),
+                style: &cli.style,
+                pipeline: &monitor.pipeline,
+                disconnect: monitor.connect.disconnect,
+                hold_port: monitor.schedule.hold_port,
+                duration: monitor.schedule.duration,
+                once: monitor.schedule.once,
+                report: monitor.pipeline.report.clone(),
+                daily_summary: monitor.schedule.daily_summary.clone(),
+                utc_offset: monitor.schedule.utc_offset.unwrap_or(0),
+                meter: monitor.connect.name.clone(),
+                relay: relay.as_ref(),
+                alarm_log: alarm_log.as_ref(),
+                annotations: &annotations,
+            };
+            live(&monitor.connect, &monitor.schedule, &session).await
+        }
+        Command::Log(log) => {
+            log.pipeline.check_features()?;
+            let relay = open_relay(&log.pipeline)?;
+            let alarm_log = open_alarm_log(&log.pipeline)?;
+            let annotations =
+                annotate::Annotations::start(true, false, log.pipeline.annotate_listen).await?;
+            let format = cli.format.unwrap_or(format::Format::Csv);
+            let session = Session {
+                source: log.connect.source(),
+                outputs: &log.output,
+                rotation: log.rotation.rotation(),
+                flush: log.buffering.flush_policy(),
+                format,
+                stdout_format: format,
+                color: cli.color.stdout(),
+                style: &cli.style,
+                pipeline: &log.pipeline,
+                disconnect: log.connect.disconnect,
+                hold_port: log.schedule.hold_port,
+                duration: log.schedule.duration,
+                once: log.schedule.once,
+                report: log.pipeline.report.clone(),
+                daily_summary: log.schedule.daily_summary.clone(),
+                utc_offset: log.schedule.utc_offset.unwrap_or(0),
+                meter: log.connect.name.clone(),
+                relay: relay.as_ref(),
+                alarm_log: alarm_log.as_ref(),
+                annotations: &annotations,
+            };
+            live(&log.connect, &log.schedule, &session).await
+        }
+        Command::Replay(replay) => {
+            replay.pipeline.check_features()?;
+            let relay = open_relay(&replay.pipeline)?;
+            let alarm_log = open_alarm_log(&replay.pipeline)?;
+            let annotations = annotate::Annotations::start(
+                replay.path.as_os_str() != "-",
+                true,
+                replay.pipeline.annotate_listen,
+            )
+            .await?;
+            let session = Session {
+                source: format!("replay:{}", replay.path.display()),
+                outputs: &replay.output,
+                rotation: replay.rotation.rotation(),
+                flush: replay.buffering.flush_policy(),
+                format,
+                stdout_format,
+                color: cli.color.stdout(),
+                style: &cli.style,
+                pipeline: &replay.pipeline,
+                disconnect: false,
+                hold_port: false,
+                duration: None,
+                once: false,
+                report: replay.pipeline.report.clone(),
+                daily_summary: None,
+                utc_offset: 0,
+                meter: None,
+                relay: relay.as_ref(),
+                alarm_log: alarm_log.as_ref(),
+                annotations: &annotations,
+            };
+            if replay.path.as_os_str() == "-" {
+                if replay.speed.is_some() {
+                    return Err(anyhow!("--speed needs a capture file, not stdin"));
+                }
+                let stdin = ut325f_rs::StreamTransport::new(tokio::io::stdin());
+                run(Meter::new(stdin), &session).await
+            } else {
+                let mut capture = replay::CaptureTransport::open(
+                    &replay.path,
+                    &replay.pipeline.sync.unwrap_or(Reading::SYNC),
+                )?;
+                if let Some(speed) = replay.speed {
+                    capture = capture.with_speed(speed)?;
+                }
+                run(Meter::new(capture), &session).await
+            }
+        }
+        Command::Info(connect) => info(connect).await,
+        Command::Snapshot { samples, connect } => {
+            let record = snapshot::read(connect, *samples as usize).await?;
+            write_records(
+                &[record],
+                None,
+                stdout_format,
+                format::Style {
+                    color: cli.color.stdout(),
+                    ..cli.style.style(stdout_format)?
+                },
+            )
+        }
+        Command::ListPorts => list_ports(),
+        Command::Discover { scan_time } => {
+            #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+            {
+                discover(*scan_time).await
+            }
+            #[cfg(not(any(feature = "bluebus", feature = "btleplug")))]
+            {
+                let _ = scan_time;
+                Err(anyhow!(connect::NO_BLE_SUPPORT))
+            }
+        }
+        Command::Selftest(connect) => selftest(connect).await,
+        Command::Calibrate {
+            channel,
+            average,
+            connect,
+        } => calibrate::run(connect, *channel, *average).await,
+        Command::Stats {
+            path,
+            gap,
+            drift,
+            sync,
+        } => stats(path, &sync.unwrap_or(Reading::SYNC), *gap, *drift),
+        Command::Merge {
+            paths,
+            output,
+            align,
+            tolerance,
+            sync,
+        } => merge(
+            paths,
+            &sync.unwrap_or(Reading::SYNC),
+            output.as_deref(),
+            *align,
+            *tolerance,
+        ),
+        Command::Resample {
+            path,
+            output,
+            period,
+            agg,
+            sync,
+        } => {
+            let format = match output {
+                Some(_) => format,
+                None => stdout_format,
+            };
+            resample(
+                path,
+                &sync.unwrap_or(Reading::SYNC),
+                output.as_deref(),
+                *period,
+                *agg,
+                format,
+                format::Style {
+                    color: output.is_none() && cli.color.stdout(),
+                    ..cli.style.style(format)?
+                },
+            )
+        }
+        Command::Completions { shell } => {
+            use clap::CommandFactory;
+            // clap_complete panics on a write error; buffer the script
+            // so one surfaces as an error instead.
+            let mut script = Vec::new();
+            clap_complete::generate(*shell, &mut Cli::command(), "ut325f", &mut script);
+            std::io::stdout().write_all(&script)?;
+            Ok(())
+        }
+        Command::Export {
+            path,
+            output,
+            progress,
+            sync,
+        } => {
+            let to = cli
+                .format
+                .ok_or_else(|| anyhow!("export needs --format (or --to)"))?;
+            export(
+                path,
+                &sync.unwrap_or(Reading::SYNC),
+                output.as_deref(),
+                to,
+                &cli.style,
+                *progress,
+            )
+        }
+        Command::Import { path, output, sync } => {
+            import(path, &sync.unwrap_or(Reading::SYNC), output.as_deref())
+        }
+        Command::Schema { kind } => {
+            let format = match cli.format {
+                Some(format::Format::JsonFlat) => format::Format::JsonFlat,
+                _ => format::Format::Ndjson,
+            };
+            let schema = schema::schema(*kind, format, &cli.style.style(format)?);
+            let mut text = serde_json::to_string_pretty(&schema)?;
+            text.push('\n');
+            std::io::Write::write_all(&mut std::io::stdout(), text.as_bytes())?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+    use ut325f_rs::StreamTransport;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_open_skips_window() {
+        let schedule = schedule::Schedule::Periodic {
+            window: Duration::from_secs(10),
+            every: Duration::from_secs(60),
+        };
+        // The start of a window, on a clock that follows tokio's.
+        let base = UNIX_EPOCH + Duration::from_secs(60 * 1_000_000);
+        let started = tokio::time::Instant::now();
+        let now = || base + started.elapsed();
+        let mut opens = 0;
+        let mut reads = Vec::new();
+        let (meter, result) = sample_windows(
+            &schedule,
+            false,
+            now,
+            async || {
+                opens += 1;
+                match opens {
+                    1 => Err(anyhow!("no meter")),
+                    _ => Ok(Meter::new(StreamTransport::new(tokio::io::empty()))),
+                }
+            },
+            async |_: &mut Meter<StreamTransport<tokio::io::Empty>>| {
+                reads.push(started.elapsed());
+                match reads.len() {
+                    // Read until the window closes.
+                    1 => std::future::pending().await,
+                    _ => Ok(()),
+                }
+            },
+            &mut LinkStats::default(),
+        )
+        .await;
+        assert!(result.is_ok());
+        assert!(meter.is_some());
+        assert_eq!(opens, 3);
+        assert_eq!(reads, [Duration::from_secs(60), Duration::from_secs(120)]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::io;
+
+use crate::records::Record;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum Alignment {
+    /// Take the closest record within the tolerance.
+    Nearest,
+    /// Interpolate linearly between the records either side, both
+    /// within the tolerance.
+    Interpolate,
+}
+
+/// Returns `other`'s temperatures at each of `times`, NaN where no
+/// record lies within `tolerance_s`. `other` must be sorted by
+/// timestamp.
+pub fn align(
+    times: &[f64],
+    other: &[Record],
+    alignment: Alignment,
+    tolerance_s: f64,
+) -> Vec<[f32; 4]> {
+    let within = |record: &Record, time: f64| (record.timestamp - time).abs() <= tolerance_s;
+    times
+        .iter()
+        .map(|&time| {
+            let after = other.partition_point(|r| r.timestamp < time);
+            let next = other.get(after).filter(|r| within(r, time));
+            let prev = after
+                .checked_sub(1)
+                .map(|i| &other[i])
+                .filter(|r| within(r, time));
+            match (alignment, prev, next) {
+                (_, _, Some(next)) if next.timestamp == time => next.temps_c,
+                (Alignment::Nearest, Some(prev), Some(next)) => {
+                    if time - prev.timestamp <= next.timestamp - time {
+                        prev.temps_c
+                    } else {
+                        next.temps_c
+                    }
+                }
+                (Alignment::Nearest, Some(only), None) | (Alignment::Nearest, None, Some(only)) => {
+                    only.temps_c
+                }
+                (Alignment::Interpolate, Some(prev), Some(next)) => {
+                    let fraction =
+                        ((time - prev.timestamp) / (next.timestamp - prev.timestamp)) as f32;
+                    std::array::from_fn(|channel| {
+                        let (a, b) = (prev.temps_c[channel], next.temps_c[channel]);
+                        a + (b - a) * fraction
+                    })
+                }
+                _ => [f32::NAN; 4],
+            }
+        })
+        .collect()
+}
+
+/// Writes one row per entry of `base`, followed by each aligned
+/// session's four temperatures.
+pub fn write_merged(
+    writer: &mut impl io::Write,
+    base: &[Record],
+    aligned: &[Vec<[f32; 4]>],
+) -> io::Result<()> {
+    for (row, record) in base.iter().enumerate() {
+        write!(writer, "{:.3}", record.timestamp)?;
+        for temps in std::iter::once(&record.temps_c).chain(aligned.iter().map(|a| &a[row])) {
+            for temp in temps {
+                write!(writer, " {:7.3}", temp)?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: f64, t1: f32) -> Record {
+        Record::new(timestamp, [t1; 4])
+    }
+
+    #[test]
+    fn test_align_nearest() {
+        let other = [record(1.0, 10.0), record(2.0, 20.0)];
+        let aligned = align(&[0.5, 1.25, 1.75, 4.0], &other, Alignment::Nearest, 0.5);
+        assert_eq!(aligned[0][0], 10.0);
+        assert_eq!(aligned[1][0], 10.0);
+        assert_eq!(aligned[2][0], 20.0);
+        assert!(aligned[3][0].is_nan());
+    }
+
+    #[test]
+    fn test_align_interpolate() {
+        let other = [record(1.0, 10.0), record(2.0, 20.0), record(3.0, f32::NAN)];
+        let aligned = align(&[1.25, 2.0, 2.5, 0.75], &other, Alignment::Interpolate, 1.0);
+        assert_eq!(aligned[0][0], 12.5);
+        assert_eq!(aligned[1][0], 20.0);
+        assert!(aligned[2][0].is_nan());
+        // Only one neighbour: nothing to interpolate between.
+        assert!(aligned[3][0].is_nan());
+    }
+}
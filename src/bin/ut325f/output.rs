@@ -0,0 +1,219 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A log destination: stdout, or a file compressed according to its
+/// extension (`.gz`, `.zst`).
+pub enum Output {
+    Plain(Box<dyn Write + Send>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<Box<dyn Write + Send>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, Box<dyn Write + Send>>),
+}
+
+/// Opens `path` for writing, or stdout if `None`.
+pub fn create(path: Option<&Path>) -> Result<Output> {
+    let Some(path) = path else {
+        return Ok(Output::Plain(Box::new(BufWriter::new(io::stdout()))));
+    };
+    create_with(path, || {
+        let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        Ok(Box::new(BufWriter::new(file)))
+    })
+}
+
+/// Like [`create`], writing the file through the writer `open` returns.
+pub fn create_with(
+    path: &Path,
+    open: impl FnOnce() -> Result<Box<dyn Write + Send>>,
+) -> Result<Output> {
+    let extension = path.extension().and_then(|e| e.to_str());
+    if !matches!(extension, Some("gz" | "zst")) {
+        return Ok(Output::Plain(open()?));
+    }
+    #[cfg(feature = "gzip")]
+    if extension == Some("gz") {
+        return Ok(Output::Gzip(flate2::write::GzEncoder::new(
+            open()?,
+            flate2::Compression::default(),
+        )));
+    }
+    #[cfg(feature = "zstd")]
+    if extension == Some("zst") {
+        return Ok(Output::Zstd(zstd::Encoder::new(open()?, 0)?));
+    }
+    let feature = if extension == Some("gz") {
+        "gzip"
+    } else {
+        "zstd"
+    };
+    Err(anyhow!(
+        "Built without {feature} support, needed for {}; rebuild with `--features {feature}`",
+        path.display()
+    ))
+}
+
+/// Like [`create`], but stdout stays line-buffered so readings appear
+/// as they arrive.
+pub fn create_live(path: Option<&Path>) -> Result<Output> {
+    match path {
+        Some(path) => create(Some(path)),
+        None => Ok(Output::Plain(Box::new(io::stdout()))),
+    }
+}
+
+impl Output {
+    /// Flushes everything written and, for compressed files, writes the
+    /// stream trailer. Dropping without finishing can leave a
+    /// compressed file truncated.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Plain(mut writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.finish()?.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Reads the whole file at `path`, decompressing it if it starts with
+/// a gzip or zstd header.
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        #[cfg(feature = "gzip")]
+        {
+            let mut decompressed = Vec::new();
+            flate2::read::MultiGzDecoder::new(&bytes[..])
+                .read_to_end(&mut decompressed)
+                .with_context(|| format!("decompressing {}", path.display()))?;
+            return Ok(decompressed);
+        }
+        #[cfg(not(feature = "gzip"))]
+        return Err(anyhow!(
+            "{} is gzip-compressed; rebuild with `--features gzip`",
+            path.display()
+        ));
+    }
+    if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        #[cfg(feature = "zstd")]
+        {
+            let mut decompressed = Vec::new();
+            zstd::Decoder::new(&bytes[..])?
+                .read_to_end(&mut decompressed)
+                .with_context(|| format!("decompressing {}", path.display()))?;
+            return Ok(decompressed);
+        }
+        #[cfg(not(feature = "zstd"))]
+        return Err(anyhow!(
+            "{} is zstd-compressed; rebuild with `--features zstd`",
+            path.display()
+        ));
+    }
+    Ok(bytes)
+}
+
+/// Opens the file at `path` for reading a piece at a time, decompressing
+/// it if it starts with a gzip or zstd header. `consumed` counts the
+/// bytes read from the file itself, for progress against its size.
+pub fn open(path: &Path, consumed: Arc<AtomicU64>) -> Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut file = BufReader::new(Counted {
+        inner: file,
+        consumed,
+    });
+    let head = file.fill_buf()?;
+    if head.starts_with(&[0x1f, 0x8b]) {
+        #[cfg(feature = "gzip")]
+        return Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(file),
+        )));
+        #[cfg(not(feature = "gzip"))]
+        return Err(anyhow!(
+            "{} is gzip-compressed; rebuild with `--features gzip`",
+            path.display()
+        ));
+    }
+    if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        #[cfg(feature = "zstd")]
+        return Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(file)?)));
+        #[cfg(not(feature = "zstd"))]
+        return Err(anyhow!(
+            "{} is zstd-compressed; rebuild with `--features zstd`",
+            path.display()
+        ));
+    }
+    Ok(Box::new(file))
+}
+
+/// A reader that counts the bytes read through it.
+struct Counted<R> {
+    inner: R,
+    consumed: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.consumed.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+#[cfg(all(test, any(feature = "gzip", feature = "zstd")))]
+mod tests {
+    use super::*;
+
+    fn round_trip(extension: &str) -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "ut325f-output-test-{}.{extension}",
+            std::process::id()
+        ));
+        let mut output = create(Some(&path))?;
+        output.write_all(b"1.000 1 2 3 4\n")?;
+        output.finish()?;
+        let bytes = read(&path);
+        std::fs::remove_file(&path)?;
+        assert_eq!(bytes?, b"1.000 1 2 3 4\n");
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_round_trip() -> Result<()> {
+        round_trip("gz")
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() -> Result<()> {
+        round_trip("zst")
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use parquet::basic::Compression;
+use parquet::data_type::{FloatType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+
+use crate::records::Record;
+
+/// Records per row group.
+const ROW_GROUP_LEN: usize = 1 << 16;
+
+/// Columns as in CSV, in degrees Celsius; a NaN temperature is null.
+const SCHEMA: &str = "
+    message reading {
+        required int64 timestamp (TIMESTAMP(MICROS, true));
+        optional float t1;
+        optional float t2;
+        optional float t3;
+        optional float t4;
+        optional float meter_temp;
+    }
+";
+
+/// Writes records as an Apache Parquet file, a row group at a time.
+pub struct ParquetWriter<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+    pending: Vec<Record>,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    pub fn new(writer: W) -> Result<Self> {
+        let schema = Arc::new(parse_message_type(SCHEMA)?);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(Self {
+            writer: SerializedFileWriter::new(writer, schema, Arc::new(properties))?,
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, record: &Record) -> Result<()> {
+        self.pending.push(record.clone());
+        if self.pending.len() >= ROW_GROUP_LEN {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the records not yet written and the file footer, and
+    /// returns the writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.write_row_group()?;
+        Ok(self.writer.into_inner()?)
+    }
+
+    fn write_row_group(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        let mut column = row_group.next_column()?.context("no timestamp column")?;
+        let micros: Vec<i64> = self
+            .pending
+            .iter()
+            .map(|record| (record.timestamp * 1e6).round() as i64)
+            .collect();
+        column
+            .typed::<Int64Type>()
+            .write_batch(&micros, None, None)?;
+        column.close()?;
+        for index in 0..5 {
+            let temps: Vec<f32> = self
+                .pending
+                .iter()
+                .map(|record| match index {
+                    4 => record.meter_temp_c.unwrap_or(f32::NAN),
+                    channel => record.temps_c[channel],
+                })
+                .collect();
+            let values: Vec<f32> = temps.iter().copied().filter(|t| !t.is_nan()).collect();
+            let levels: Vec<i16> = temps.iter().map(|t| i16::from(!t.is_nan())).collect();
+            let mut column = row_group.next_column()?.context("too few columns")?;
+            column
+                .typed::<FloatType>()
+                .write_batch(&values, Some(&levels), None)?;
+            column.close()?;
+        }
+        row_group.close()?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("ut325f-{}.parquet", std::process::id()));
+        let mut writer = ParquetWriter::new(std::fs::File::create(&path)?)?;
+        writer.write(&Record::new(1.5, [1.0, f32::NAN, 3.0, 4.0]))?;
+        writer.write(&Record {
+            meter_temp_c: Some(25.5),
+            ..Record::new(2.0, [5.0; 4])
+        })?;
+        writer.finish()?;
+        let reader = SerializedFileReader::new(std::fs::File::open(&path)?)?;
+        let rows: Vec<Vec<Field>> = reader
+            .get_row_iter(None)?
+            .map(|row| row.map(|row| row.into_columns().into_iter().map(|(_, f)| f).collect()))
+            .collect::<std::result::Result<_, _>>()?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][0], Field::TimestampMicros(1_500_000));
+        assert_eq!(
+            rows[0][1..5],
+            [
+                Field::Float(1.0),
+                Field::Null,
+                Field::Float(3.0),
+                Field::Float(4.0)
+            ]
+        );
+        assert_eq!(rows[0][5], Field::Null);
+        assert_eq!(rows[1][5], Field::Float(25.5));
+        Ok(())
+    }
+}
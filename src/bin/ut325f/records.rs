@@ -0,0 +1,871 @@
+use anyhow::{Context, Result, anyhow};
+use std::io::{BufRead, Read};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ut325f_rs::{FrameDecoder, HoldType, Quality, Reading, Validity};
+
+/// One reading as recorded in a log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Unix time in seconds.
+    pub timestamp: f64,
+    pub temps_c: [f32; 4],
+    /// Hold type and held temperatures, present in logs written with
+    /// `--held-temps` and in decoded raw captures.
+    pub held: Option<(HoldType, [f32; 4])>,
+    /// The meter's internal temperature; the text format omits it.
+    pub meter_temp_c: Option<f32>,
+    /// Position in the session's output, counting from 0, so a gap or
+    /// a step backwards shows a record was lost or reordered on the way.
+    /// The text format omits it.
+    pub seq: Option<u64>,
+    /// The checks the reading's frame failed, in logs written with
+    /// `--tolerant`. The text format omits it.
+    pub validity: Option<Validity>,
+    /// How the reading arrived, in JSON logs written with `--quality`.
+    pub quality: Option<Quality>,
+}
+
+impl Record {
+    /// A record of just `temps_c`, for tests.
+    #[cfg(test)]
+    pub fn new(timestamp: f64, temps_c: [f32; 4]) -> Self {
+        Self {
+            timestamp,
+            temps_c,
+            held: None,
+            meter_temp_c: None,
+            seq: None,
+            validity: None,
+            quality: None,
+        }
+    }
+
+    pub fn from_reading(reading: &Reading, held_temps: bool) -> Self {
+        Self {
+            timestamp: unix_seconds(reading.timestamp),
+            temps_c: reading.current_temps_c,
+            held: held_temps.then_some((reading.hold_type, reading.held_temps_c)),
+            meter_temp_c: Some(reading.meter_temp_c),
+            seq: None,
+            validity: None,
+            quality: None,
+        }
+    }
+}
+
+/// Unix time in seconds, negative before the epoch.
+pub fn unix_seconds(time: SystemTime) -> f64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    }
+}
+
+/// Reads every record in the file at `path`, which may be a log in any
+/// format this tool writes (optionally compressed), a raw capture of
+/// the meter's byte stream, or a third-party capture (see
+/// [`crate::import`]). Raw captures carry no timing, so their records
+/// are stamped with the time of decoding; third-party captures keep
+/// their own timestamps. Frames in captures start with `sync`.
+pub fn read_records(path: &Path, sync: &[u8; Reading::N_SYNC_BYTES]) -> Result<Vec<Record>> {
+    decode_file(&crate::output::read(path)?, path, sync)
+}
+
+fn decode_file(
+    bytes: &[u8],
+    path: &Path,
+    sync: &[u8; Reading::N_SYNC_BYTES],
+) -> Result<Vec<Record>> {
+    // Checked first: a USB capture also contains the meter's sync bytes.
+    if let Some(chunks) = crate::import::extract(bytes) {
+        let chunks = chunks.with_context(|| path.display().to_string())?;
+        return Ok(decode_chunks(&chunks, sync));
+    }
+    if bytes.windows(Reading::N_SYNC_BYTES).any(|w| w == sync) {
+        return Ok(decode_raw(bytes, sync));
+    }
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| anyhow!("{}: neither a log nor a raw capture", path.display()))?;
+    parse_log(text).with_context(|| path.display().to_string())
+}
+
+/// Records per batch from [`stream_records`].
+const BATCH: usize = 1024;
+
+/// Like [`read_records`], but hands the records to `send` in batches as
+/// they are decoded, holding only a piece of the input at a time, until
+/// the input ends or `send` returns false. Third-party captures are
+/// still read whole. `consumed` counts the bytes of the file read.
+pub fn stream_records(
+    path: &Path,
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    consumed: Arc<AtomicU64>,
+    mut send: impl FnMut(Vec<Record>) -> bool,
+) -> Result<()> {
+    let mut input = crate::output::open(path, consumed)?;
+    let head = input.fill_buf()?;
+    // A log is text; anything else is taken for a raw capture, as
+    // read_records would find sync bytes in it.
+    let text = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if crate::import::is_capture(head) {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        for batch in decode_file(&bytes, path, sync)?.chunks(BATCH) {
+            if !send(batch.to_vec()) {
+                break;
+            }
+        }
+        Ok(())
+    } else if text && !head.windows(Reading::N_SYNC_BYTES).any(|w| w == sync) {
+        stream_log(input, send).with_context(|| path.display().to_string())
+    } else {
+        stream_raw(input, sync, send).with_context(|| path.display().to_string())
+    }
+}
+
+fn stream_raw(
+    mut input: impl BufRead,
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    mut send: impl FnMut(Vec<Record>) -> bool,
+) -> Result<()> {
+    let mut decoder = FrameDecoder::new().with_sync(*sync);
+    let mut batch = Vec::new();
+    loop {
+        let bytes = input.fill_buf()?;
+        if bytes.is_empty() {
+            break;
+        }
+        decoder.push(bytes);
+        let n = bytes.len();
+        input.consume(n);
+        let readings = std::iter::from_fn(|| decoder.next_frame())
+            .filter_map(|frame| Reading::parse_with_sync(&frame, sync).ok());
+        batch.extend(readings.map(|reading| Record::from_reading(&reading, true)));
+        if batch.len() >= BATCH && !send(std::mem::take(&mut batch)) {
+            return Ok(());
+        }
+    }
+    if !batch.is_empty() {
+        send(batch);
+    }
+    Ok(())
+}
+
+fn stream_log(input: impl BufRead, mut send: impl FnMut(Vec<Record>) -> bool) -> Result<()> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().is_ok_and(|line| !is_skipped(line)));
+    let Some((_, first)) = lines.next() else {
+        return Ok(());
+    };
+    let first = first?;
+    let (parse, header) = line_parser(&first);
+    let first = (!header).then_some((0, Ok(first)));
+    let mut batch = Vec::new();
+    for (index, line) in first.into_iter().chain(lines) {
+        let line = line?;
+        batch.push(parse(&line).with_context(|| format!("line {}", index + 1))?);
+        if batch.len() >= BATCH && !send(std::mem::take(&mut batch)) {
+            return Ok(());
+        }
+    }
+    if !batch.is_empty() {
+        send(batch);
+    }
+    Ok(())
+}
+
+type LineParser = Box<dyn Fn(&str) -> Result<Record>>;
+
+fn parse_log(text: &str) -> Result<Vec<Record>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !is_skipped(line));
+    let Some((_, first)) = lines.clone().next() else {
+        return Ok(Vec::new());
+    };
+    let (parse, header) = line_parser(first);
+    if header {
+        lines.next();
+    }
+    lines
+        .map(|(index, line)| parse(line).with_context(|| format!("line {}", index + 1)))
+        .collect()
+}
+
+/// The parser for the lines of a log whose first non-blank line is
+/// `first`, and whether that line is a header rather than a record.
+fn line_parser(first: &str) -> (LineParser, bool) {
+    if first.starts_with('{') {
+        (Box::new(parse_json_line), false)
+    } else if let Some(delimiter) = csv_delimiter(first) {
+        let columns = split_csv(first, delimiter);
+        (
+            Box::new(move |line| parse_csv_line(&columns, delimiter, line)),
+            true,
+        )
+    } else {
+        (Box::new(parse_text_line), false)
+    }
+}
+
+/// True for a line of a log that holds no record: a blank line, a
+/// heartbeat or annotation in an NDJSON log (see
+/// [`RecordWriter::write_heartbeat`](crate::format::RecordWriter::write_heartbeat)),
+/// or an annotation in a text log (see
+/// [`RecordWriter::write_annotation`](crate::format::RecordWriter::write_annotation)).
+fn is_skipped(line: &str) -> bool {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return true;
+    }
+    line.starts_with('{')
+        && (line.contains(r#""heartbeat""#) || line.contains(r#""annotation""#))
+        && serde_json::from_str::<serde_json::Value>(line).is_ok_and(|value| {
+            value.get("heartbeat").is_some() || value.get("annotation").is_some()
+        })
+}
+
+#[cfg(not(feature = "parallel"))]
+fn decode_raw(bytes: &[u8], sync: &[u8; Reading::N_SYNC_BYTES]) -> Vec<Record> {
+    decode_slice(bytes, sync)
+}
+
+/// Decodes a large capture in pieces on all cores.
+#[cfg(feature = "parallel")]
+fn decode_raw(bytes: &[u8], sync: &[u8; Reading::N_SYNC_BYTES]) -> Vec<Record> {
+    use rayon::prelude::*;
+    split_at_frames(bytes, sync, PARALLEL_CHUNK)
+        .par_iter()
+        .flat_map_iter(|piece| decode_slice(piece, sync))
+        .collect()
+}
+
+fn decode_slice(bytes: &[u8], sync: &[u8; Reading::N_SYNC_BYTES]) -> Vec<Record> {
+    Reading::parse_all_with_sync(bytes, sync)
+        .filter_map(Result::ok)
+        .map(|reading| Record::from_reading(&reading, true))
+        .collect()
+}
+
+/// The size of the pieces a raw capture is decoded in.
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK: usize = 4 << 20;
+
+/// Splits `bytes` into pieces of about `len` bytes that decode to the
+/// same frames separately as together. Each piece but the first starts
+/// at a valid frame with no valid frame starting in the bytes before it
+/// that could overlap it, so a sequential scan reaches that offset too:
+/// it is either past the end of an earlier frame or still looking.
+#[cfg(feature = "parallel")]
+fn split_at_frames<'a>(
+    bytes: &'a [u8],
+    sync: &[u8; Reading::N_SYNC_BYTES],
+    len: usize,
+) -> Vec<&'a [u8]> {
+    let valid_at = |at: usize| {
+        bytes[at..]
+            .first_chunk()
+            .is_some_and(|frame| Reading::validate_frame_with_sync(frame, sync))
+    };
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut at = len;
+    while at < bytes.len() {
+        if valid_at(at) && !(at.saturating_sub(Reading::N_BYTES - 1).max(start)..at).any(valid_at) {
+            pieces.push(&bytes[start..at]);
+            start = at;
+            at += len;
+        } else {
+            at += 1;
+        }
+    }
+    pieces.push(&bytes[start..]);
+    pieces
+}
+
+/// Decodes a third-party capture, stamping each record with the time of
+/// the chunk that completed its frame.
+fn decode_chunks(
+    chunks: &[crate::import::Chunk],
+    sync: &[u8; Reading::N_SYNC_BYTES],
+) -> Vec<Record> {
+    let mut decoder = FrameDecoder::new().with_sync(*sync);
+    let mut records = Vec::new();
+    for chunk in chunks {
+        decoder.push(&chunk.bytes);
+        while let Some(frame) = decoder.next_frame() {
+            if let Ok(reading) = Reading::parse_with_sync(&frame, sync) {
+                records.push(Record {
+                    timestamp: chunk.timestamp,
+                    ..Record::from_reading(&reading, true)
+                });
+            }
+        }
+    }
+    records
+}
+
+fn parse_temp(field: &str) -> Result<f32> {
+    match field.trim() {
+        // Written for NaN with --nan empty or --nan null.
+        "" | "null" => Ok(f32::NAN),
+        field => field
+            .parse()
+            .map_err(|_| anyhow!("bad temperature '{field}'")),
+    }
+}
+
+fn parse_timestamp(field: &str) -> Result<f64> {
+    field
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("bad timestamp '{field}'"))
+}
+
+fn parse_temps(fields: &[&str]) -> Result<[f32; 4]> {
+    let mut temps = [0.0; 4];
+    for (temp, field) in temps.iter_mut().zip(fields) {
+        *temp = parse_temp(field)?;
+    }
+    Ok(temps)
+}
+
+/// Parses a line written by `Reading::write_current_temps` or
+/// `Reading::write_all_temps`, or in the text format, where a meter
+/// tag may follow the timestamp and derived channels end the line.
+fn parse_text_line(line: &str) -> Result<Record> {
+    let mut fields: Vec<&str> = line.split_whitespace().collect();
+    if fields
+        .get(1)
+        .is_some_and(|field| parse_temp(field).is_err())
+    {
+        fields.remove(1);
+    }
+    if fields.len() < 5 {
+        return Err(anyhow!(
+            "expected at least 5 fields, found {}",
+            fields.len()
+        ));
+    }
+    let held = match fields.get(5) {
+        Some(field) if fields.len() >= 10 && parse_hold_type(field).is_ok() => {
+            Some((parse_hold_type(field)?, parse_temps(&fields[6..10])?))
+        }
+        _ => None,
+    };
+    Ok(Record {
+        timestamp: parse_timestamp(fields[0])?,
+        temps_c: parse_temps(&fields[1..5])?,
+        held,
+        meter_temp_c: None,
+        seq: None,
+        validity: None,
+        quality: None,
+    })
+}
+
+/// The delimiter of a CSV log, from its header row.
+fn csv_delimiter(header: &str) -> Option<char> {
+    let rest = header.trim_start_matches('"').strip_prefix("timestamp")?;
+    let rest = rest.strip_prefix('"').unwrap_or(rest);
+    rest.chars().next().filter(|c| [',', ';', '\t'].contains(c))
+}
+
+/// Splits a CSV line, unquoting fields and turning decimal commas into
+/// points (no field written by this tool has any other comma).
+fn split_csv(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(String::new()),
+            ',' => fields.last_mut().unwrap().push('.'),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+fn parse_csv_line(columns: &[String], delimiter: char, line: &str) -> Result<Record> {
+    let fields = split_csv(line, delimiter);
+    if fields.len() != columns.len() {
+        return Err(anyhow!(
+            "expected {} fields, found {}",
+            columns.len(),
+            fields.len()
+        ));
+    }
+    let field = |name: &str| {
+        columns
+            .iter()
+            .position(|c| c == name)
+            .map(|index| fields[index].as_str())
+    };
+    // Temperatures are in degrees, or in millidegrees under an `_mc`
+    // column name.
+    let temp = |name: &str| -> Result<Option<f32>> {
+        if let Some(value) = field(name) {
+            return parse_temp(value).map(Some);
+        }
+        field(&format!("{name}_mc"))
+            .map(|value| parse_temp(value).map(|mc| mc / 1000.0))
+            .transpose()
+    };
+    let temps = |prefix: &str| -> Result<Option<[f32; 4]>> {
+        let mut temps = [0.0; 4];
+        for (channel, t) in temps.iter_mut().enumerate() {
+            let Some(value) = temp(&format!("{prefix}{}", channel + 1))? else {
+                return Ok(None);
+            };
+            *t = value;
+        }
+        Ok(Some(temps))
+    };
+    let held = match (field("hold_type"), temps("held")?) {
+        (Some(hold_type), Some(held)) => Some((parse_hold_type(hold_type)?, held)),
+        _ => None,
+    };
+    Ok(Record {
+        timestamp: parse_timestamp(field("timestamp").unwrap_or_default())?,
+        temps_c: temps("t")?.ok_or_else(|| anyhow!("missing temperature columns"))?,
+        held,
+        meter_temp_c: temp("meter_temp")?,
+        seq: field("seq")
+            .map(|seq| seq.trim().parse().map_err(|_| anyhow!("bad seq '{seq}'")))
+            .transpose()?,
+        validity: field("validity").map(parse_validity).transpose()?,
+        quality: None,
+    })
+}
+
+fn parse_json_line(line: &str) -> Result<Record> {
+    let mut value: serde_json::Value = serde_json::from_str(line)?;
+    millidegrees_to_degrees(&mut value);
+    // Non-finite temperatures are written as null.
+    let temp = |value: &serde_json::Value| match value {
+        serde_json::Value::Null => Ok(f32::NAN),
+        value => value
+            .as_f64()
+            .map(|v| v as f32)
+            .ok_or_else(|| anyhow!("bad temperature {value}")),
+    };
+    let temps = |value: &serde_json::Value| -> Result<[f32; 4]> {
+        let values = value
+            .as_array()
+            .filter(|values| values.len() == 4)
+            .ok_or_else(|| anyhow!("expected 4 temperatures, found {value}"))?;
+        let mut temps = [0.0; 4];
+        for (t, v) in temps.iter_mut().zip(values) {
+            *t = temp(v)?;
+        }
+        Ok(temps)
+    };
+    let held = match (value.get("hold_type"), value.get("held_temps_c")) {
+        (Some(hold_type), Some(held)) => {
+            let hold_type = hold_type
+                .as_str()
+                .ok_or_else(|| anyhow!("bad hold type {hold_type}"))?;
+            Some((parse_hold_type(hold_type)?, temps(held)?))
+        }
+        _ => None,
+    };
+    Ok(Record {
+        timestamp: value
+            .get("timestamp")
+            .and_then(serde_json::Value::as_f64)
+            .ok_or_else(|| anyhow!("missing timestamp"))?,
+        temps_c: temps(value.get("temps_c").unwrap_or(&serde_json::Value::Null))?,
+        held,
+        meter_temp_c: value.get("meter_temp_c").map(temp).transpose()?,
+        seq: value
+            .get("seq")
+            .map(|seq| seq.as_u64().ok_or_else(|| anyhow!("bad seq {seq}")))
+            .transpose()?,
+        validity: value
+            .get("validity")
+            .map(|validity| {
+                let validity = validity
+                    .as_str()
+                    .ok_or_else(|| anyhow!("bad validity {validity}"))?;
+                parse_validity(validity)
+            })
+            .transpose()?,
+        quality: value.get("quality").map(parse_quality).transpose()?,
+    })
+}
+
+/// Rewrites the `_mc` fields of a line written with `--millidegrees`
+/// as the equivalent `_c` fields.
+fn millidegrees_to_degrees(value: &mut serde_json::Value) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    let to_degrees = |value: serde_json::Value| match value {
+        serde_json::Value::Number(mc) => mc
+            .as_f64()
+            .map_or(serde_json::Value::Null, |mc| (mc / 1000.0).into()),
+        serde_json::Value::Array(values) => values
+            .into_iter()
+            .map(|value| match value.as_f64() {
+                Some(mc) => (mc / 1000.0).into(),
+                None => value,
+            })
+            .collect(),
+        value => value,
+    };
+    for name in ["temps", "held_temps", "meter_temp"] {
+        if let Some(mc) = object.remove(&format!("{name}_mc")) {
+            object.insert(format!("{name}_c"), to_degrees(mc));
+        }
+    }
+}
+
+fn parse_hold_type(field: &str) -> Result<HoldType> {
+    match field {
+        "Current" => Ok(HoldType::Current),
+        "Maximum" => Ok(HoldType::Maximum),
+        "Minimum" => Ok(HoldType::Minimum),
+        "Average" => Ok(HoldType::Average),
+        _ => Err(anyhow!("bad hold type '{field}'")),
+    }
+}
+
+/// Parses the `Display` form of [`Validity`]: `ok`, or flag names
+/// joined by `+`.
+fn parse_validity(field: &str) -> Result<Validity> {
+    let field = field.trim();
+    if field == "ok" {
+        return Ok(Validity::VALID);
+    }
+    field
+        .split('+')
+        .try_fold(Validity::VALID, |validity, name| {
+            let (_, flag) = Validity::FLAGS
+                .iter()
+                .find(|(flag_name, _)| *flag_name == name)
+                .ok_or_else(|| anyhow!("bad validity '{field}'"))?;
+            Ok(validity | *flag)
+        })
+}
+
+/// Parses the `quality` object written by
+/// [`quality_json`](crate::format::quality_json).
+fn parse_quality(value: &serde_json::Value) -> Result<Quality> {
+    let bad = || anyhow!("bad quality {value}");
+    let field = |name: &str| value.get(name).ok_or_else(bad);
+    Ok(Quality {
+        checksum_ok: field("checksum_ok")?.as_bool().ok_or_else(bad)?,
+        resync_bytes: field("resync_bytes")?.as_u64().ok_or_else(bad)?,
+        jitter_s: match field("jitter_s")? {
+            serde_json::Value::Null => None,
+            jitter => Some(jitter.as_f64().ok_or_else(bad)? as f32),
+        },
+        filtered_channels: field("filtered_channels")?
+            .as_u64()
+            .and_then(|n| u8::try_from(n).ok())
+            .ok_or_else(bad)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_current_line() -> Result<()> {
+        let record = parse_text_line("1700000000.123  26.698     NaN  26.626  66.573")?;
+        assert_eq!(record.timestamp, 1700000000.123);
+        assert_eq!(record.temps_c[0], 26.698);
+        assert!(record.temps_c[1].is_nan());
+        assert!(record.held.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_all_temps_line() -> Result<()> {
+        let record = parse_text_line(
+            "1.5  1.000   2.000   3.000   4.000 Maximum   5.000   6.000   7.000   8.000",
+        )?;
+        assert_eq!(record.held, Some((HoldType::Maximum, [5.0, 6.0, 7.0, 8.0])));
+        let record = parse_text_line("1.5 ttyUSB0   1.000   2.000   3.000   4.000")?;
+        assert_eq!(record.temps_c, [1.0, 2.0, 3.0, 4.0]);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_split_at_frames() {
+        let frame = |temp| {
+            Reading::builder()
+                .current_temp_c(0, temp)
+                .build()
+                .to_frame()
+        };
+        let mut bytes = vec![0x55, 0x13];
+        for i in 0..20 {
+            bytes.extend_from_slice(&frame(i as f32));
+            bytes.extend(std::iter::repeat_n(0xaa, i % 3));
+        }
+        // A valid frame hidden inside the payload of another, which a
+        // sequential scan never sees: no piece may start there.
+        let mut inner = frame(-1.0);
+        inner[45] = 0;
+        let sum = Reading::SYNC
+            .iter()
+            .chain(&inner[..46])
+            .fold(0u16, |sum, &b| sum.wrapping_add(b.into()));
+        inner[46..48].copy_from_slice(&sum.to_be_bytes());
+        Reading::write_checksum(&mut inner);
+        let mut outer = [0; Reading::N_BYTES];
+        outer[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
+        outer[8..].copy_from_slice(&inner[..48]);
+        assert!(Reading::validate_frame(&outer));
+        bytes.extend_from_slice(&outer);
+        bytes.extend_from_slice(&inner[48..]);
+        bytes.extend_from_slice(&frame(100.0));
+
+        let temps = |records: Vec<Record>| -> Vec<u32> {
+            records
+                .iter()
+                .map(|record| record.temps_c[0].to_bits())
+                .collect()
+        };
+        let whole = temps(decode_slice(&bytes, &Reading::SYNC));
+        assert_eq!(whole.len(), 22);
+        assert!(!whole.contains(&(-1.0f32).to_bits()));
+        for len in (1..=120).chain([1000]) {
+            let pieces = split_at_frames(&bytes, &Reading::SYNC, len);
+            assert_eq!(pieces.concat(), bytes);
+            let pieced = pieces
+                .iter()
+                .flat_map(|piece| decode_slice(piece, &Reading::SYNC))
+                .collect();
+            assert_eq!(temps(pieced), whole, "{len}");
+        }
+    }
+
+    #[test]
+    fn test_decode_chunks_keeps_capture_time() {
+        let frame = Reading::builder()
+            .timestamp(UNIX_EPOCH)
+            .current_temps_c([1.0, 2.0, 3.0, 4.0])
+            .meter_temp_c(25.0)
+            .build()
+            .to_frame();
+        let (head, tail) = frame.split_at(10);
+        let chunks = [
+            crate::import::Chunk {
+                timestamp: 1.0,
+                bytes: head.to_vec(),
+            },
+            crate::import::Chunk {
+                timestamp: 2.0,
+                bytes: tail.to_vec(),
+            },
+        ];
+        let records = decode_chunks(&chunks, &Reading::SYNC);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].timestamp, 2.0);
+        assert_eq!(records[0].temps_c, [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_decode_with_sync() -> Result<()> {
+        let sync = [0xaa, 0x55, 0x00, 0x34, 0x02];
+        let mut frame = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .build()
+            .to_frame();
+        frame[..Reading::N_SYNC_BYTES].copy_from_slice(&sync);
+        Reading::write_checksum(&mut frame);
+        let bytes = [&[0x13][..], &frame, &frame].concat();
+        let path = Path::new("capture.bin");
+        let records = decode_file(&bytes, path, &sync)?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].temps_c[0], 21.5);
+        // With the usual sync the capture holds no frames, nor is it a
+        // log.
+        assert!(decode_file(&bytes, path, &Reading::SYNC).is_err());
+        let mut streamed = 0;
+        stream_raw(&bytes[..], &sync, |batch| {
+            streamed += batch.len();
+            true
+        })?;
+        assert_eq!(streamed, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rejects_short_line() {
+        assert!(parse_text_line("1.5 1.0 2.0").is_err());
+    }
+
+    #[test]
+    fn test_parse_csv_log() -> Result<()> {
+        let records = parse_log("timestamp,t1,t2,t3,t4,meter_temp\n1.5,1,NaN,3,4,25.5\n")?;
+        assert_eq!(records.len(), 1);
+        let empty = parse_log("timestamp,t1,t2,t3,t4\n1.5,1,,3,4\n")?;
+        assert!(empty[0].temps_c[1].is_nan());
+        assert_eq!(records[0].timestamp, 1.5);
+        assert!(records[0].temps_c[1].is_nan());
+        assert_eq!(records[0].meter_temp_c, Some(25.5));
+        assert!(records[0].held.is_none());
+        Ok(())
+    }
+
+    fn batch_sizes(
+        stream: impl FnOnce(&mut dyn FnMut(Vec<Record>) -> bool) -> Result<()>,
+    ) -> Result<Vec<usize>> {
+        let mut sizes = Vec::new();
+        stream(&mut |batch| {
+            sizes.push(batch.len());
+            true
+        })?;
+        Ok(sizes)
+    }
+
+    #[test]
+    fn test_stream_records() -> Result<()> {
+        let mut log = String::from("timestamp,t1,t2,t3,t4\n\n");
+        for i in 0..BATCH + 10 {
+            log.push_str(&format!("{i},1,2,3,4\n"));
+        }
+        assert_eq!(
+            batch_sizes(|send| stream_log(log.as_bytes(), send))?,
+            [BATCH, 10]
+        );
+        assert!(batch_sizes(|send| stream_log(&b"1.0 1 2 3\n"[..], send)).is_err());
+
+        let frame = Reading::builder().build().to_frame();
+        let mut raw = vec![0x13; 7];
+        for _ in 0..BATCH + 1 {
+            raw.extend_from_slice(&frame);
+        }
+        assert_eq!(decode_slice(&raw, &Reading::SYNC).len(), BATCH + 1);
+        // A batch takes all the frames in a buffer of input.
+        assert_eq!(
+            batch_sizes(|send| stream_raw(&raw[..], &Reading::SYNC, send))?,
+            [BATCH + 1]
+        );
+        let mut input = std::io::BufReader::with_capacity(Reading::N_BYTES, &raw[..]);
+        assert_eq!(
+            batch_sizes(|send| stream_raw(&mut input, &Reading::SYNC, send))?,
+            [BATCH, 1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_csv_dialects() -> Result<()> {
+        for log in [
+            "timestamp;t1;t2;t3;t4\n1,5;1,25;NaN;3;4\n",
+            "timestamp\tt1\tt2\tt3\tt4\n1,5\t1,25\tNaN\t3\t4\n",
+            "\"timestamp\",\"t1\",\"t2\",\"t3\",\"t4\"\n\"1,5\",\"1,25\",\"NaN\",\"3\",\"4\"\n",
+        ] {
+            let records = parse_log(log)?;
+            assert_eq!(records[0].timestamp, 1.5, "{log}");
+            assert_eq!(records[0].temps_c[0], 1.25, "{log}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_skips_heartbeats_and_annotations() -> Result<()> {
+        let records = parse_log(concat!(
+            r#"{"timestamp":1.5,"temps_c":[1,2,3,4]}"#,
+            "\n",
+            r#"{"timestamp":9.5,"heartbeat":true,"last_reading":1.5}"#,
+            "\n",
+            r#"{"timestamp":9.6,"annotation":"door"}"#,
+            "\n",
+        ))?;
+        assert_eq!(records.len(), 1);
+        let records = parse_log("# 1.0 start\ntimestamp,t1,t2,t3,t4\n1.5,1,2,3,4\n# 1.6 door\n")?;
+        assert_eq!(records.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_seq() -> Result<()> {
+        let csv = parse_log("timestamp,seq,t1,t2,t3,t4\n1.5,41,1,2,3,4\n1.6,43,1,2,3,4\n")?;
+        assert_eq!(
+            csv.iter().map(|r| r.seq).collect::<Vec<_>>(),
+            [Some(41), Some(43)]
+        );
+        let json = parse_log(r#"{"timestamp":1.5,"seq":41,"temps_c":[1,2,3,4]}"#)?;
+        assert_eq!(json[0].seq, Some(41));
+        assert_eq!(parse_log("1.5 1 2 3 4\n")?[0].seq, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_validity() -> Result<()> {
+        let csv = parse_log(
+            "timestamp,t1,t2,t3,t4,validity\n1.5,1,2,3,4,ok\n1.6,1,2,3,4,checksum+anomaly\n",
+        )?;
+        assert_eq!(csv[0].validity, Some(Validity::VALID));
+        assert_eq!(
+            csv[1].validity,
+            Some(Validity::CHECKSUM | Validity::ANOMALY)
+        );
+        let json = parse_log(r#"{"timestamp":1.5,"temps_c":[1,2,3,4],"validity":"hold_type"}"#)?;
+        assert_eq!(json[0].validity, Some(Validity::HOLD_TYPE));
+        assert!(parse_log("timestamp,t1,t2,t3,t4,validity\n1.5,1,2,3,4,bad\n").is_err());
+        let json = parse_log(
+            r#"{"timestamp":1.5,"temps_c":[1,2,3,4],"quality":{"checksum_ok":false,"resync_bytes":7,"jitter_s":null,"filtered_channels":1}}"#,
+        )?;
+        assert_eq!(
+            json[0].quality,
+            Some(Quality {
+                checksum_ok: false,
+                resync_bytes: 7,
+                jitter_s: None,
+                filtered_channels: 1,
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_log() -> Result<()> {
+        let records = parse_log(concat!(
+            r#"{"timestamp":1.5,"temps_c":[1,null,3,4],"#,
+            r#""hold_type":"Average","held_temps_c":[5,6,7,8]}"#,
+            "\n"
+        ))?;
+        assert!(records[0].temps_c[1].is_nan());
+        assert_eq!(
+            records[0].held,
+            Some((HoldType::Average, [5.0, 6.0, 7.0, 8.0]))
+        );
+        assert_eq!(records[0].meter_temp_c, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_millidegree_logs() -> Result<()> {
+        let csv = parse_log(
+            "timestamp,t1_mc,t2_mc,t3_mc,t4_mc,meter_temp_mc\n1.5,1500,NaN,0,-250,25500\n",
+        )?;
+        assert_eq!(csv[0].temps_c[0], 1.5);
+        assert_eq!(csv[0].temps_c[3], -0.25);
+        assert_eq!(csv[0].meter_temp_c, Some(25.5));
+        let json =
+            parse_log(r#"{"timestamp":1.5,"temps_mc":[1500,null,0,-250],"meter_temp_mc":25500}"#)?;
+        assert_eq!(json[0].temps_c[0], 1.5);
+        assert!(json[0].temps_c[1].is_nan());
+        assert_eq!(json[0].meter_temp_c, Some(25.5));
+        Ok(())
+    }
+}
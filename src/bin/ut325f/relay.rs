@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use ut325f_rs::AlarmEvent;
+
+/// The output switched by --relay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelaySpec {
+    /// A GPIO line by its sysfs number, as on a Raspberry Pi.
+    Gpio(u32),
+    /// A USB relay board of the LCUS type (a CH340 serial adapter
+    /// taking 4-byte commands), and the relay on it, from 1.
+    Serial { port: String, channel: u8 },
+}
+
+impl FromStr for RelaySpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid relay '{s}'; expected gpio:17, serial:/dev/ttyUSB1 or serial:/dev/ttyUSB1:2"
+            )
+        };
+        match s.split_once(':') {
+            Some(("gpio", line)) => line.parse().map(Self::Gpio).map_err(|_| invalid()),
+            Some(("serial", port)) => {
+                let (port, channel) = match port.rsplit_once(':') {
+                    Some((port, channel)) => (port, channel.parse().map_err(|_| invalid())?),
+                    None => (port, 1),
+                };
+                if port.is_empty() || channel == 0 {
+                    return Err(invalid());
+                }
+                Ok(Self::Serial {
+                    port: port.to_owned(),
+                    channel,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A relay held on while any alarm is raised, for --relay.
+pub struct Relay {
+    spec: RelaySpec,
+    raised: usize,
+    on: bool,
+}
+
+impl Relay {
+    /// Opens the relay and switches it off, so a broken relay shows
+    /// before it is needed.
+    pub fn open(spec: RelaySpec) -> Result<Self> {
+        if let RelaySpec::Gpio(line) = spec {
+            export_gpio(line)?;
+        }
+        let relay = Self {
+            spec,
+            raised: 0,
+            on: false,
+        };
+        relay
+            .switch(false)
+            .with_context(|| format!("Cannot switch --relay {}", relay.describe()))?;
+        Ok(relay)
+    }
+
+    /// Follows an alarm changing state: on with the first alarm raised,
+    /// off when the last one clears.
+    pub fn update(&mut self, event: &AlarmEvent) {
+        self.raised = match event.raised {
+            true => self.raised + 1,
+            false => self.raised.saturating_sub(1),
+        };
+        let on = self.raised > 0;
+        // A failed switch is retried on the next alarm change.
+        if on != self.on {
+            match self.switch(on) {
+                Ok(()) => self.on = on,
+                Err(e) => tracing::error!(
+                    "Failed to switch relay {} {}: {e:#}",
+                    self.describe(),
+                    if on { "on" } else { "off" }
+                ),
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.spec {
+            RelaySpec::Gpio(line) => format!("gpio:{line}"),
+            RelaySpec::Serial { port, channel } => format!("serial:{port}:{channel}"),
+        }
+    }
+
+    fn switch(&self, on: bool) -> Result<()> {
+        match &self.spec {
+            RelaySpec::Gpio(line) => {
+                std::fs::write(gpio_path(*line).join("value"), if on { "1" } else { "0" })?;
+                Ok(())
+            }
+            RelaySpec::Serial { port, channel } => switch_serial(port, *channel, on),
+        }
+    }
+}
+
+fn gpio_path(line: u32) -> PathBuf {
+    PathBuf::from(format!("/sys/class/gpio/gpio{line}"))
+}
+
+/// Makes `line` an output through the sysfs interface, exporting it if
+/// need be.
+fn export_gpio(line: u32) -> Result<()> {
+    if !gpio_path(line).exists() {
+        std::fs::write("/sys/class/gpio/export", line.to_string())
+            .with_context(|| format!("Cannot export GPIO {line}"))?;
+    }
+    // "low" sets the direction and the level together, without a
+    // glitch high.
+    std::fs::write(gpio_path(line).join("direction"), "low")
+        .with_context(|| format!("Cannot make GPIO {line} an output"))
+}
+
+/// The command switching relay `channel` of an LCUS board.
+#[cfg(any(feature = "serial", test))]
+fn serial_command(channel: u8, on: bool) -> [u8; 4] {
+    let state = u8::from(on);
+    [
+        0xa0,
+        channel,
+        state,
+        0xa0u8.wrapping_add(channel).wrapping_add(state),
+    ]
+}
+
+#[cfg(feature = "serial")]
+fn switch_serial(port: &str, channel: u8, on: bool) -> Result<()> {
+    use std::io::Write;
+
+    let mut port = serialport::new(port, 9600)
+        .timeout(std::time::Duration::from_secs(1))
+        .open()?;
+    port.write_all(&serial_command(channel, on))?;
+    port.flush()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "serial"))]
+fn switch_serial(_port: &str, _channel: u8, _on: bool) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Built without serial support; rebuild with `--features serial`"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!("gpio:17".parse(), Ok(RelaySpec::Gpio(17)));
+        assert_eq!(
+            "serial:/dev/ttyUSB1:2".parse(),
+            Ok(RelaySpec::Serial {
+                port: "/dev/ttyUSB1".to_owned(),
+                channel: 2
+            })
+        );
+        assert_eq!(
+            "serial:/dev/ttyUSB1".parse::<RelaySpec>().unwrap(),
+            RelaySpec::Serial {
+                port: "/dev/ttyUSB1".to_owned(),
+                channel: 1
+            }
+        );
+        assert!("gpio:x".parse::<RelaySpec>().is_err());
+        assert!("serial:/dev/ttyUSB1:0".parse::<RelaySpec>().is_err());
+        assert!("usb:1".parse::<RelaySpec>().is_err());
+        assert_eq!(serial_command(1, true), [0xa0, 0x01, 0x01, 0xa2]);
+        assert_eq!(serial_command(1, false), [0xa0, 0x01, 0x00, 0xa1]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ut325f_rs::AlarmEvent;
+
+#[cfg(feature = "email")]
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    pub server: String,
+    pub from: String,
+    pub to: Vec<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Sends alarm messages to a webhook and/or by email, at most one per
+/// alarm per `min_interval`. Suppressed events are counted and reported
+/// in the alarm's next message.
+pub struct RemoteNotifier {
+    limiter: RateLimiter,
+    /// Client and URL to POST each alarm to as JSON.
+    #[cfg(feature = "webhook")]
+    webhook: Option<(reqwest::Client, String)>,
+    #[cfg(feature = "email")]
+    email: Option<EmailConfig>,
+}
+
+impl RemoteNotifier {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            limiter: RateLimiter::new(min_interval),
+            #[cfg(feature = "webhook")]
+            webhook: None,
+            #[cfg(feature = "email")]
+            email: None,
+        }
+    }
+
+    #[cfg(feature = "webhook")]
+    pub fn with_webhook(mut self, url: String) -> Self {
+        self.webhook = Some((reqwest::Client::new(), url));
+        self
+    }
+
+    #[cfg(feature = "email")]
+    pub fn with_email(mut self, email: EmailConfig) -> Self {
+        self.email = Some(email);
+        self
+    }
+
+    /// Sends `event` in the background unless rate-limited. Delivery
+    /// failures are reported on stderr.
+    pub fn send(&mut self, event: &AlarmEvent, message: &str) {
+        let Some(suppressed) = self.limiter.allow(&event.rule.to_string(), Instant::now()) else {
+            return;
+        };
+        let mut text = message.to_owned();
+        if suppressed > 0 {
+            text.push_str(&format!(" [{suppressed} earlier changes suppressed]"));
+        }
+        #[cfg(feature = "webhook")]
+        if let Some((client, url)) = &self.webhook {
+            let body = payload(event, &text, suppressed);
+            let request = client.post(url).json(&body);
+            tokio::spawn(async move {
+                let result = request.send().await.and_then(|r| r.error_for_status());
+                if let Err(e) = result {
+                    tracing::warn!("Webhook delivery failed: {e}");
+                }
+            });
+        }
+        #[cfg(feature = "email")]
+        if let Some(email) = &self.email {
+            let email = email.clone();
+            tokio::spawn(async move {
+                if let Err(e) = send_email(&email, &text).await {
+                    tracing::warn!("Email delivery failed: {e}");
+                }
+            });
+        }
+        #[cfg(not(feature = "email"))]
+        let _ = text;
+    }
+}
+
+/// The webhook body. `text` makes it usable as-is with Slack and Teams
+/// incoming webhooks; the other fields are for generic receivers.
+#[cfg(feature = "webhook")]
+fn payload(event: &AlarmEvent, text: &str, suppressed: u32) -> serde_json::Value {
+    let timestamp = event
+        .timestamp
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    serde_json::json!({
+        "text": text,
+        "alarm": event.rule.to_string(),
+        "state": if event.raised { "raised" } else { "cleared" },
+        "temp_c": event.temp_c,
+        "timestamp": timestamp,
+        "suppressed": suppressed,
+    })
+}
+
+#[cfg(feature = "email")]
+async fn send_email(config: &EmailConfig, text: &str) -> anyhow::Result<()> {
+    use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+    let mut builder = Message::builder()
+        .from(config.from.parse()?)
+        .subject(format!("UT325F: {text}"));
+    for to in &config.to {
+        builder = builder.to(to.parse()?);
+    }
+    let message = builder.body(text.to_owned())?;
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&config.server)?;
+    if let (Some(user), Some(password)) = (&config.user, &config.password) {
+        transport =
+            transport.credentials(lettre::transport::smtp::authentication::Credentials::new(
+                user.clone(),
+                password.clone(),
+            ));
+    }
+    transport.build().send(message).await?;
+    Ok(())
+}
+
+/// Allows one message per key per interval, counting the rest.
+struct RateLimiter {
+    min_interval: Duration,
+    keys: HashMap<String, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of messages suppressed since the last allowed
+    /// one, or `None` if this one is suppressed.
+    fn allow(&mut self, key: &str, now: Instant) -> Option<u32> {
+        match self.keys.get_mut(key) {
+            Some((last, suppressed)) if now.duration_since(*last) < self.min_interval => {
+                *suppressed += 1;
+                None
+            }
+            Some((last, suppressed)) => {
+                *last = now;
+                Some(std::mem::take(suppressed))
+            }
+            None => {
+                self.keys.insert(key.to_owned(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(limiter.allow("T1>80", start), Some(0));
+        assert_eq!(limiter.allow("T2>80", start), Some(0));
+        assert_eq!(
+            limiter.allow("T1>80", start + Duration::from_secs(10)),
+            None
+        );
+        assert_eq!(
+            limiter.allow("T1>80", start + Duration::from_secs(20)),
+            None
+        );
+        assert_eq!(
+            limiter.allow("T1>80", start + Duration::from_secs(60)),
+            Some(2)
+        );
+        assert_eq!(
+            limiter.allow("T1>80", start + Duration::from_secs(120)),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::{Context, Result, anyhow};
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use ut325f_rs::{Error, Reading, Transport};
+
+use crate::import::Chunk;
+
+/// The disconnect reason reported when a capture or stdin runs out.
+pub const END_OF_CAPTURE: &str = ut325f_rs::transport::END_OF_INPUT;
+
+/// Bytes handed to the meter per read, about what a serial read yields.
+const CHUNK_LEN: usize = 64;
+
+/// Serves a capture to a `Meter` as if it were arriving from the
+/// meter; the capture running out reads as a disconnect.
+pub struct CaptureTransport {
+    chunks: std::vec::IntoIter<Chunk>,
+    /// True if the chunks carry the times they were captured.
+    timed: bool,
+    pace: Option<Pace>,
+}
+
+/// Replays chunks at their captured times, scaled by `speed`.
+struct Pace {
+    speed: f64,
+    /// When the first chunk was delivered, and its captured time.
+    start: Option<(Instant, f64)>,
+}
+
+impl CaptureTransport {
+    /// Serves a raw capture, which carries no timing.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let chunks: Vec<Chunk> = bytes
+            .chunks(CHUNK_LEN)
+            .map(|bytes| Chunk {
+                timestamp: 0.0,
+                bytes: bytes.to_vec(),
+            })
+            .collect();
+        Self {
+            chunks: chunks.into_iter(),
+            timed: false,
+            pace: None,
+        }
+    }
+
+    /// Serves a third-party capture chunk by chunk.
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
+        Self {
+            chunks: chunks.into_iter(),
+            timed: true,
+            pace: None,
+        }
+    }
+
+    /// Opens a raw capture of frames starting with `sync`,
+    /// decompressing it if needed, or a third-party capture.
+    pub fn open(path: &Path, sync: &[u8; Reading::N_SYNC_BYTES]) -> Result<Self> {
+        let bytes = crate::output::read(path)?;
+        if let Some(chunks) = crate::import::extract(&bytes) {
+            let chunks = chunks.with_context(|| path.display().to_string())?;
+            return Ok(Self::from_chunks(chunks));
+        }
+        if !bytes.windows(Reading::N_SYNC_BYTES).any(|w| w == sync) {
+            return Err(anyhow!("{}: not a raw capture", path.display()));
+        }
+        Ok(Self::new(bytes))
+    }
+
+    /// Reproduces the capture's original timing, `speed` times faster.
+    /// Fails for a capture that carries no timing.
+    pub fn with_speed(mut self, speed: f64) -> Result<Self> {
+        if !self.timed {
+            return Err(anyhow!(
+                "a raw capture carries no timing to reproduce; --speed needs a \
+                 timestamped capture (pcap, pcapng or Saleae CSV)"
+            ));
+        }
+        self.pace = Some(Pace { speed, start: None });
+        Ok(self)
+    }
+}
+
+impl Transport for CaptureTransport {
+    async fn recv(&mut self) -> ut325f_rs::Result<Vec<u8>> {
+        while self
+            .chunks
+            .as_slice()
+            .first()
+            .is_some_and(|chunk| chunk.bytes.is_empty())
+        {
+            self.chunks.next();
+        }
+        let timestamp = self
+            .chunks
+            .as_slice()
+            .first()
+            .ok_or(Error::Disconnected(END_OF_CAPTURE))?
+            .timestamp;
+        if let Some(pace) = &mut self.pace {
+            let &mut (start, first) = pace
+                .start
+                .get_or_insert_with(|| (Instant::now(), timestamp));
+            // Captures are not always in time order; late chunks go
+            // out at once.
+            let offset = ((timestamp - first) / pace.speed).max(0.0);
+            tokio::time::sleep_until(start + Duration::from_secs_f64(offset)).await;
+        }
+        // Taken only once due, so that a cancelled recv loses nothing.
+        let chunk = self
+            .chunks
+            .next()
+            .ok_or(Error::Disconnected(END_OF_CAPTURE))?;
+        Ok(chunk.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ut325f_rs::{Meter, StreamTransport};
+
+    #[tokio::test]
+    async fn test_replay_capture() -> Result<()> {
+        let frame = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .build()
+            .to_frame();
+        let mut meter = Meter::new(CaptureTransport::new(frame.repeat(3)));
+        for _ in 0..3 {
+            assert_eq!(meter.read().await?.current_temps_c[0], 21.5);
+        }
+        assert!(matches!(meter.read().await, Err(Error::Disconnected(_))));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_at_speed() -> Result<()> {
+        let frame = Reading::builder().build().to_frame();
+        let chunks = [10.0, 12.0, 16.0]
+            .map(|timestamp| Chunk {
+                timestamp,
+                bytes: frame.to_vec(),
+            })
+            .to_vec();
+        let mut meter = Meter::new(CaptureTransport::from_chunks(chunks).with_speed(2.0)?);
+        let start = Instant::now();
+        meter.read().await?;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        meter.read().await?;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        meter.read().await?;
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+        assert!(
+            CaptureTransport::new(frame.to_vec())
+                .with_speed(1.0)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_replay_loses_nothing() -> Result<()> {
+        let frame = Reading::builder().build().to_frame();
+        let chunks = (0..4)
+            .map(|i| Chunk {
+                timestamp: f64::from(i),
+                bytes: frame.to_vec(),
+            })
+            .collect();
+        let mut meter = Meter::new(CaptureTransport::from_chunks(chunks).with_speed(1.0)?);
+        let mut readings = 0;
+        loop {
+            match tokio::time::timeout(Duration::from_millis(300), meter.read()).await {
+                Ok(Ok(_)) => readings += 1,
+                Ok(Err(_)) => break,
+                Err(_) => {}
+            }
+        }
+        assert_eq!(readings, 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream() -> Result<()> {
+        let frame = Reading::builder()
+            .current_temp_c(0, 21.5)
+            .build()
+            .to_frame();
+        let bytes = [&[0x00, 0x13][..], &frame, &frame[..10]].concat();
+        let mut meter = Meter::new(StreamTransport::new(&bytes[..]));
+        assert_eq!(meter.read().await?.current_temps_c[0], 21.5);
+        assert!(matches!(meter.read().await, Err(Error::Disconnected(_))));
+        Ok(())
+    }
+}
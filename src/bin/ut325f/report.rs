@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use serde_json::json;
+use ut325f_rs::{AlarmEvent, LinkStats, ThermocoupleType};
+
+use crate::compare::Comparison;
+use crate::records::{Record, unix_seconds};
+use crate::stats::ChannelStats;
+
+/// Everything recorded about a session for `--report`.
+pub struct RunReport {
+    source: String,
+    thermocouples: [Option<ThermocoupleType>; 4],
+    comparison: Option<Comparison>,
+    start: SystemTime,
+    temps: Vec<[f32; 4]>,
+    alarms: Vec<AlarmEvent>,
+}
+
+impl RunReport {
+    pub fn new(source: String) -> Self {
+        Self {
+            source,
+            thermocouples: [None; 4],
+            comparison: None,
+            start: SystemTime::now(),
+            temps: Vec::new(),
+            alarms: Vec::new(),
+        }
+    }
+
+    /// Lists each channel's thermocouple type, given as zero-based
+    /// channel numbers.
+    pub fn with_thermocouples(mut self, types: &[(usize, ThermocoupleType)]) -> Self {
+        for &(channel, kind) in types {
+            self.thermocouples[channel] = Some(kind);
+        }
+        self
+    }
+
+    /// Adds each channel's deviation from `reference` (zero-based), if
+    /// given.
+    pub fn with_reference(mut self, reference: Option<usize>) -> Self {
+        self.comparison = reference.map(Comparison::new);
+        self
+    }
+
+    pub fn record(&mut self, record: &Record) {
+        self.temps.push(record.temps_c);
+        if let Some(comparison) = self.comparison.as_mut() {
+            comparison.push(record);
+        }
+    }
+
+    pub fn alarm(&mut self, event: &AlarmEvent) {
+        self.alarms.push(event.clone());
+    }
+
+    /// Writes the report as JSON to `path`. `error` is the error that
+    /// ended the session, if any.
+    pub fn write(&self, path: &Path, link: LinkStats, error: Option<&anyhow::Error>) -> Result<()> {
+        let end = SystemTime::now();
+        let channels: Vec<_> = (0..4)
+            .map(|channel| {
+                let stats = ChannelStats::new(self.temps.iter().map(|t| t[channel]));
+                let mut entry = json!({
+                    "channel": format!("T{}", channel + 1),
+                    "count": stats.count,
+                    "missing": stats.missing,
+                    "min": crate::format::json_number(stats.min.into()),
+                    "max": crate::format::json_number(stats.max.into()),
+                    "mean": crate::format::json_number(stats.mean.into()),
+                    "p95": crate::format::json_number(stats.p95.into()),
+                });
+                if let Some(kind) = self.thermocouples[channel] {
+                    entry["thermocouple"] = kind.to_string().into();
+                }
+                if let Some(summary) = self.comparison.as_ref().and_then(|c| c.summary(channel)) {
+                    entry["deviation"] = json!({
+                        "count": summary.count,
+                        "bias": summary.bias,
+                        "drift_per_h": summary.drift_per_h,
+                        "max": summary.max_deviation,
+                    });
+                }
+                entry
+            })
+            .collect();
+        let alarms: Vec<_> = self
+            .alarms
+            .iter()
+            .map(|event| {
+                json!({
+                    "alarm": event.rule.to_string(),
+                    "state": if event.raised { "raised" } else { "cleared" },
+                    "temp_c": crate::format::json_number(event.temp_c.into()),
+                    "timestamp": unix_seconds(event.timestamp),
+                })
+            })
+            .collect();
+        let mut report = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "source": self.source,
+            "start": unix_seconds(self.start),
+            "end": unix_seconds(end),
+            "duration_s": end.duration_since(self.start).unwrap_or_default().as_secs_f64(),
+            "outcome": match error {
+                Some(e) => format!("{e:#}"),
+                None => "ok".to_owned(),
+            },
+            "readings": self.temps.len(),
+            "channels": channels,
+            "alarms": alarms,
+            "link": {
+                "readings": link.readings,
+                "discarded_bytes": link.discarded_bytes,
+                "rejected_frames": link.rejected_frames,
+                "timeouts": link.timeouts,
+                "sync_acquisitions": link.sync_acquisitions,
+                "mean_sync_acquisition_s": link
+                    .mean_sync_acquisition_time()
+                    .map(|mean| mean.as_secs_f64()),
+            },
+        });
+        if let Some(comparison) = &self.comparison {
+            report["reference"] = format!("T{}", comparison.reference() + 1).into();
+        }
+        let mut text = serde_json::to_string_pretty(&report)?;
+        text.push('\n');
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_report() -> Result<()> {
+        let mut report = RunReport::new("serial:/dev/ttyUSB0".to_owned())
+            .with_thermocouples(&[(0, ThermocoupleType::K)])
+            .with_reference(Some(2));
+        for t1 in [1.0, 2.0, 3.0] {
+            report.record(&Record::new(0.0, [t1, f32::NAN, 0.0, 0.0]));
+        }
+        let path = std::env::temp_dir().join(format!("ut325f-report-{}.json", std::process::id()));
+        report.write(&path, LinkStats::default(), None)?;
+        let value: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(value["outcome"], "ok");
+        assert_eq!(value["readings"], 3);
+        assert_eq!(value["channels"][0]["mean"], 2.0);
+        assert!(value["channels"][1]["mean"].is_null());
+        assert_eq!(value["channels"][0]["thermocouple"], "K");
+        assert!(value["channels"][1].get("thermocouple").is_none());
+        assert_eq!(value["reference"], "T3");
+        assert_eq!(value["channels"][0]["deviation"]["bias"], 2.0);
+        assert!(value["channels"][2].get("deviation").is_none());
+        assert_eq!(value["link"]["rejected_frames"], 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Result, bail};
+
+use crate::records::Record;
+
+/// The most bins `resample` will emit; a finer grid, or a stray
+/// timestamp far from the rest, is refused rather than filled in.
+const MAX_BINS: i64 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum Aggregation {
+    Mean,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+impl Aggregation {
+    /// Aggregates the non-NaN `values`; NaN if there are none.
+    fn apply(self, values: impl Iterator<Item = f32>) -> f32 {
+        let mut values = values.filter(|v| !v.is_nan());
+        let Some(first) = values.next() else {
+            return f32::NAN;
+        };
+        match self {
+            Self::Mean => {
+                let (sum, count) = values.fold((f64::from(first), 1), |(sum, count), v| {
+                    (sum + f64::from(v), count + 1)
+                });
+                (sum / f64::from(count)) as f32
+            }
+            Self::Min => values.fold(first, f32::min),
+            Self::Max => values.fold(first, f32::max),
+            Self::First => first,
+            Self::Last => values.last().unwrap_or(first),
+        }
+    }
+}
+
+/// Aggregates `records` (sorted by timestamp) into consecutive bins of
+/// `period_s` seconds aligned to multiples of the period. Every bin from
+/// the first record's to the last's is emitted, timestamped at its
+/// start; empty bins and channels with no valid samples are NaN. Fails
+/// if that is more than [`MAX_BINS`].
+pub fn resample(
+    records: &[Record],
+    period_s: f64,
+    aggregation: Aggregation,
+) -> Result<Vec<Record>> {
+    let (Some(first), Some(last)) = (records.first(), records.last()) else {
+        return Ok(Vec::new());
+    };
+    let bin_of = |timestamp: f64| (timestamp / period_s).floor() as i64;
+    let (first_bin, last_bin) = (bin_of(first.timestamp), bin_of(last.timestamp));
+    let bins = last_bin.saturating_sub(first_bin).saturating_add(1);
+    if bins > MAX_BINS {
+        bail!(
+            "{:.0}s to {:.0}s at this period is {bins} bins, more than {MAX_BINS}; \
+             use a longer --period",
+            first.timestamp,
+            last.timestamp
+        );
+    }
+    let mut remaining = records;
+    Ok((first_bin..=last_bin)
+        .map(|bin| {
+            let end = remaining.partition_point(|r| bin_of(r.timestamp) <= bin);
+            let (members, rest) = remaining.split_at(end);
+            remaining = rest;
+            Record {
+                timestamp: bin as f64 * period_s,
+                temps_c: std::array::from_fn(|channel| {
+                    aggregation.apply(members.iter().map(|r| r.temps_c[channel]))
+                }),
+                held: None,
+                // Keep the field in every bin, so CSV columns stay
+                // consistent.
+                meter_temp_c: first
+                    .meter_temp_c
+                    .map(|_| aggregation.apply(members.iter().filter_map(|r| r.meter_temp_c))),
+                seq: None,
+                validity: None,
+                quality: None,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: f64, t1: f32) -> Record {
+        Record::new(timestamp, [t1, f32::NAN, t1, t1])
+    }
+
+    #[test]
+    fn test_aggregations() {
+        let values = [3.0, f32::NAN, 1.0, 2.0];
+        assert_eq!(Aggregation::Mean.apply(values.into_iter()), 2.0);
+        assert_eq!(Aggregation::Min.apply(values.into_iter()), 1.0);
+        assert_eq!(Aggregation::Max.apply(values.into_iter()), 3.0);
+        assert_eq!(Aggregation::First.apply(values.into_iter()), 3.0);
+        assert_eq!(Aggregation::Last.apply(values.into_iter()), 2.0);
+        assert!(Aggregation::Mean.apply([f32::NAN].into_iter()).is_nan());
+    }
+
+    #[test]
+    fn test_resample_fills_empty_bins() {
+        let records = [record(60.5, 1.0), record(90.0, 3.0), record(185.0, 5.0)];
+        let resampled = resample(&records, 60.0, Aggregation::Mean).unwrap();
+        let times: Vec<_> = resampled.iter().map(|r| r.timestamp).collect();
+        assert_eq!(times, [60.0, 120.0, 180.0]);
+        assert_eq!(resampled[0].temps_c[0], 2.0);
+        assert!(resampled[0].temps_c[1].is_nan());
+        assert!(resampled[1].temps_c[0].is_nan());
+        assert_eq!(resampled[2].temps_c[0], 5.0);
+    }
+
+    #[test]
+    fn test_resample_refuses_too_many_bins() {
+        let records = [record(0.0, 1.0), record(86_400.0, 1.0)];
+        assert!(resample(&records, 0.001, Aggregation::Mean).is_err());
+        assert!(resample(&records, 1.0, Aggregation::Mean).is_ok());
+    }
+}
@@ -0,0 +1,467 @@
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A stretch of time during which a live session reads the meter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+/// When a live session reads the meter.
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// The first `window` of every `every`, aligned to UTC like
+    /// --rotate.
+    Periodic { window: Duration, every: Duration },
+    /// The same `windows` every day, their times `utc_offset` seconds
+    /// ahead of UTC, or in the system's local time if `None`.
+    Daily {
+        windows: Vec<DailyWindow>,
+        utc_offset: Option<i64>,
+    },
+}
+
+/// The last midnight at or before `now` in the time zone `utc_offset`
+/// seconds ahead of UTC.
+pub fn midnight(now: SystemTime, utc_offset: i64) -> SystemTime {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let local = since_epoch.as_nanos() as i128 + i128::from(utc_offset) * 1_000_000_000;
+    now - Duration::from_nanos(local.rem_euclid(DAY.as_nanos() as i128) as u64)
+}
+
+/// The system's offset from UTC at `time` in seconds, following
+/// daylight saving time; 0 where it cannot be found.
+#[cfg(unix)]
+pub fn local_offset(time: SystemTime) -> i64 {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as libc::time_t;
+    // SAFETY: localtime_r only reads the time and fills in the tm it is
+    // given.
+    unsafe {
+        let mut tm = std::mem::zeroed::<libc::tm>();
+        if libc::localtime_r(&secs, &mut tm).is_null() {
+            return 0;
+        }
+        tm.tm_gmtoff as i64
+    }
+}
+
+#[cfg(not(unix))]
+pub fn local_offset(_time: SystemTime) -> i64 {
+    0
+}
+
+impl Schedule {
+    /// The window in progress at `now`, or else the next one.
+    pub fn window(&self, now: SystemTime) -> Window {
+        match *self {
+            Self::Periodic { window, every } => {
+                let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let into_period = since_epoch.as_nanos() % every.as_nanos();
+                let mut start = now - Duration::from_nanos(into_period as u64);
+                if now >= start + window {
+                    start += every;
+                }
+                Window {
+                    start,
+                    end: start + window,
+                }
+            }
+            Self::Daily {
+                ref windows,
+                utc_offset,
+            } => {
+                let utc_offset = utc_offset.unwrap_or_else(|| local_offset(now));
+                let midnight = midnight(now, utc_offset);
+                let mut upcoming: Vec<Window> = [midnight - DAY, midnight, midnight + DAY]
+                    .into_iter()
+                    .flat_map(|day| windows.iter().map(move |window| window.on(day)))
+                    .filter(|window| window.end > now)
+                    .collect();
+                upcoming.sort_by_key(|window| window.start);
+                // Overlapping or adjoining windows are one: the meter
+                // stays open across them.
+                let mut window = upcoming[0];
+                for next in &upcoming[1..] {
+                    if next.start > window.end {
+                        break;
+                    }
+                    window.end = window.end.max(next.end);
+                }
+                window
+            }
+        }
+    }
+}
+
+/// A `--between` window, e.g. 08:00-18:00; one ending before it starts
+/// runs past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailyWindow {
+    /// Seconds after midnight.
+    start: u32,
+    end: u32,
+}
+
+impl DailyWindow {
+    /// This window on the day starting at `midnight`.
+    fn on(&self, midnight: SystemTime) -> Window {
+        let start = midnight + Duration::from_secs(self.start.into());
+        let mut end = midnight + Duration::from_secs(self.end.into());
+        if end <= start {
+            end += DAY;
+        }
+        Window { start, end }
+    }
+}
+
+impl FromStr for DailyWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| "expected START-END, e.g. 08:00-18:00".to_owned())?;
+        let window = Self {
+            start: parse_time_of_day(start)?,
+            end: parse_time_of_day(end)?,
+        };
+        if window.start % DAY.as_secs() as u32 == window.end % DAY.as_secs() as u32 {
+            return Err("window must not start and end at the same time".to_owned());
+        }
+        Ok(window)
+    }
+}
+
+/// Parses HH:MM, 00:00 to 24:00, into seconds after midnight.
+fn parse_time_of_day(s: &str) -> Result<u32, String> {
+    let invalid = || format!("invalid time '{s}'; expected HH:MM");
+    let (hours, minutes) = s.split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if minutes >= 60 || hours > 24 || (hours == 24 && minutes > 0) {
+        return Err(invalid());
+    }
+    Ok((hours * 60 + minutes) * 60)
+}
+
+/// Parses a fixed offset from UTC such as +01:00, -05:30 or +9 into
+/// seconds.
+pub fn parse_utc_offset(s: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid offset '{s}'; expected e.g. +01:00 or -05:00");
+    let (sign, rest) = match s.as_bytes().first() {
+        Some(b'+') => (1, &s[1..]),
+        Some(b'-') => (-1, &s[1..]),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i64 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i64 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(sign * (hours * 60 + minutes) * 60)
+}
+
+/// A cron expression for `--schedule`: minute, hour, day of month,
+/// month and day of week, each `*`, a value, a range `a-b` or a list of
+/// them, optionally with a step (`*/15`); months and days of the week
+/// may be named (`jan`, `mon`). As in cron, a day matches either of
+/// the two day fields when both are restricted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of month and day of week fields are restricted
+    /// (do not start with `*`).
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead to look for a match, enough for any leap day.
+const MAX_DAYS_AHEAD: i64 = 8 * 366;
+
+impl Cron {
+    /// The first minute after `now` the expression matches, in a time
+    /// zone `utc_offset` seconds ahead of UTC; `None` if it never does
+    /// (e.g. on 30 February).
+    pub fn next_after(&self, now: SystemTime, utc_offset: i64) -> Option<SystemTime> {
+        let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let local = since_epoch.as_secs() as i64 + utc_offset;
+        let mut minute = local.div_euclid(60) + 1;
+        let first_day = minute.div_euclid(24 * 60);
+        for day in first_day..first_day + MAX_DAYS_AHEAD {
+            if self.matches_day(day) {
+                for of_day in minute.rem_euclid(24 * 60)..24 * 60 {
+                    if bit(self.hours, of_day / 60) && bit(self.minutes, of_day % 60) {
+                        let secs = (day * 24 * 60 + of_day) * 60 - utc_offset;
+                        return Some(UNIX_EPOCH + Duration::from_secs(secs.try_into().ok()?));
+                    }
+                }
+            }
+            minute = (day + 1) * 24 * 60;
+        }
+        None
+    }
+
+    /// Whether the expression matches `day`, counted from 1970-01-01.
+    fn matches_day(&self, day: i64) -> bool {
+        let (month, day_of_month) = civil_from_days(day);
+        // 1970-01-01 was a Thursday.
+        let weekday = (day + 4).rem_euclid(7);
+        let by_month = bit(self.days, day_of_month);
+        let by_week = bit(self.weekdays, weekday);
+        let by_day = if self.days_restricted && self.weekdays_restricted {
+            by_month || by_week
+        } else {
+            by_month && by_week
+        };
+        bit(self.months, month) && by_day
+    }
+}
+
+fn bit(set: u64, n: i64) -> bool {
+    set & (1 << n) != 0
+}
+
+/// The month (1-12) and day of month of `day`, counted from
+/// 1970-01-01, in the proleptic Gregorian calendar.
+fn civil_from_days(day: i64) -> (i64, i64) {
+    // Howard Hinnant's algorithm, with years starting in March.
+    let z = day + 719_468;
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    (month, day_of_month)
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            s => s,
+        };
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err("expected 5 fields: minute hour day-of-month month day-of-week".to_owned());
+        };
+        let cron = Self {
+            minutes: parse_field(minutes, 0, 59, &[])?,
+            hours: parse_field(hours, 0, 23, &[])?,
+            days: parse_field(days, 1, 31, &[])?,
+            months: parse_field(months, 1, 12, &MONTHS)?,
+            // Both 0 and 7 are Sunday.
+            weekdays: {
+                let set = parse_field(weekdays, 0, 7, &WEEKDAYS)?;
+                (set | set >> 7) & 0x7f
+            },
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        };
+        if cron.next_after(UNIX_EPOCH, 0).is_none() {
+            return Err("never matches".to_owned());
+        }
+        Ok(cron)
+    }
+}
+
+/// Parses one field of a cron expression into a set of values between
+/// `min` and `max`. `names` name the values from `min` up.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let n = match names.iter().position(|name| name.eq_ignore_ascii_case(s)) {
+            Some(i) => min + i as u32,
+            None => s.parse().map_err(|_| format!("invalid value '{s}'"))?,
+        };
+        if !(min..=max).contains(&n) {
+            return Err(format!("{n} is not between {min} and {max}"));
+        }
+        Ok(n)
+    };
+    let mut set = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("invalid step '{step}'")),
+            },
+            None => (item, 1),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (value(first)?, value(last)?),
+            // `a/step` runs from a to the end, as in Vixie cron.
+            None if item.contains('/') => (value(range)?, max),
+            None => {
+                let n = value(range)?;
+                (n, n)
+            }
+        };
+        if first > last {
+            return Err(format!("empty range '{range}'"));
+        }
+        for n in (first..=last).step_by(step as usize) {
+            set |= 1 << n;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_periodic_window() {
+        let schedule = Schedule::Periodic {
+            window: Duration::from_secs(10),
+            every: Duration::from_secs(300),
+        };
+        let current = Window {
+            start: at(600),
+            end: at(610),
+        };
+        assert_eq!(schedule.window(at(600)), current);
+        assert_eq!(schedule.window(at(605)), current);
+        let next = Window {
+            start: at(900),
+            end: at(910),
+        };
+        assert_eq!(schedule.window(at(610)), next);
+        assert_eq!(schedule.window(at(899)), next);
+    }
+
+    #[test]
+    fn test_daily_window() {
+        let day = DAY.as_secs();
+        let hour = 3600;
+        let schedule = Schedule::Daily {
+            windows: vec!["08:00-18:00".parse().unwrap()],
+            utc_offset: Some(0),
+        };
+        let today = Window {
+            start: at(day + 8 * hour),
+            end: at(day + 18 * hour),
+        };
+        assert_eq!(schedule.window(at(day + 3 * hour)), today);
+        assert_eq!(schedule.window(at(day + 12 * hour)), today);
+        assert_eq!(
+            schedule.window(at(day + 18 * hour)).start,
+            at(2 * day + 8 * hour)
+        );
+
+        // 08:00 at UTC+02:00 is 06:00 UTC.
+        let schedule = Schedule::Daily {
+            windows: vec!["08:00-18:00".parse().unwrap()],
+            utc_offset: Some(2 * hour as i64),
+        };
+        assert_eq!(
+            schedule.window(at(day + 7 * hour)).start,
+            at(day + 6 * hour)
+        );
+
+        // Overnight, and merged with a window it adjoins.
+        let schedule = Schedule::Daily {
+            windows: vec![
+                "22:00-06:00".parse().unwrap(),
+                "06:00-07:00".parse().unwrap(),
+            ],
+            utc_offset: Some(0),
+        };
+        let night = Window {
+            start: at(day - 2 * hour),
+            end: at(day + 7 * hour),
+        };
+        assert_eq!(schedule.window(at(day + hour)), night);
+
+        // Without an offset, the system's at the time.
+        let windows = vec!["08:00-18:00".parse().unwrap()];
+        let local = Schedule::Daily {
+            windows: windows.clone(),
+            utc_offset: None,
+        };
+        let now = at(1_735_711_170);
+        let fixed = Schedule::Daily {
+            windows,
+            utc_offset: Some(local_offset(now)),
+        };
+        assert_eq!(local.window(now), fixed.window(now));
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let cron: Cron = "0 */6 * * *".parse().unwrap();
+        // 2025-01-01T05:59:30Z, then on the hour and not again at once.
+        let now = at(1_735_711_170);
+        let next = cron.next_after(now, 0).unwrap();
+        assert_eq!(next, at(1_735_711_200));
+        assert_eq!(cron.next_after(next, 0), Some(at(1_735_711_200 + 6 * 3600)));
+        // 06:00 at UTC-05:00 is 11:00 UTC.
+        assert_eq!(cron.next_after(now, -5 * 3600), Some(at(1_735_729_200)));
+
+        // Mondays (2025-01-06 is the next) and the 15th, at 09:30.
+        let cron: Cron = "30 9 15 * mon".parse().unwrap();
+        assert_eq!(cron.next_after(now, 0), Some(at(1_736_155_800)));
+        // Leap days only: 2028-02-29.
+        let cron: Cron = "0 0 29 feb *".parse().unwrap();
+        assert_eq!(cron.next_after(now, 0), Some(at(1_835_395_200)));
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert_eq!("@daily".parse::<Cron>(), "0 0 * * *".parse::<Cron>());
+        assert_eq!("0 0 * * 7".parse::<Cron>(), "0 0 * * sun".parse::<Cron>());
+        assert!("0 0 * *".parse::<Cron>().is_err());
+        assert!("60 * * * *".parse::<Cron>().is_err());
+        assert!("*/0 * * * *".parse::<Cron>().is_err());
+        assert!("0 0 30 2 *".parse::<Cron>().is_err());
+        assert_eq!(
+            parse_field("10-20/5,1", 0, 59, &[]),
+            Ok(1 << 1 | 1 << 10 | 1 << 15 | 1 << 20)
+        );
+        assert_eq!(parse_field("50/5", 0, 59, &[]), Ok(1 << 50 | 1 << 55));
+    }
+
+    #[test]
+    fn test_parse_between() {
+        assert!("08:00-24:00".parse::<DailyWindow>().is_ok());
+        assert!("08:00-08:00".parse::<DailyWindow>().is_err());
+        assert!("00:00-24:00".parse::<DailyWindow>().is_err());
+        assert!("8-18".parse::<DailyWindow>().is_err());
+        assert!("08:60-18:00".parse::<DailyWindow>().is_err());
+        assert_eq!(parse_utc_offset("-05:30"), Ok(-19800));
+        assert_eq!(parse_utc_offset("+9"), Ok(32400));
+        assert!(parse_utc_offset("05:00").is_err());
+    }
+}
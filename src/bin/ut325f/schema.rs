@@ -0,0 +1,351 @@
+use serde_json::{Value, json};
+
+use crate::format::{Format, Style};
+
+/// Which output contract `schema` describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum Kind {
+    /// A line of NDJSON or json-flat output: a reading, a heartbeat or
+    /// an annotation.
+    Record,
+    /// A --webhook alarm message.
+    Alarm,
+    /// A --report session report.
+    Report,
+    /// A line of a --daily-summary file.
+    DailySummary,
+}
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// The JSON Schema of `kind`, for records written in `format` and
+/// `style` (which decide the field names).
+pub fn schema(kind: Kind, format: Format, style: &Style) -> Value {
+    match kind {
+        Kind::Record => json!({
+            "$schema": DRAFT,
+            "title": "UT325F record",
+            "oneOf": [reading(format, style), heartbeat(), annotation()],
+        }),
+        Kind::Alarm => json!({
+            "$schema": DRAFT,
+            "title": "UT325F alarm message",
+            "type": "object",
+            "properties": {
+                "text": {"type": "string"},
+                "alarm": {"type": "string", "description": "The limit, e.g. T1>80"},
+                "state": {"enum": ["raised", "cleared"]},
+                "temp_c": {
+                    "type": ["number", "null"],
+                    "description": "Null for a condition over several channels",
+                },
+                "timestamp": timestamp(),
+                "suppressed": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Changes not sent since the last message",
+                },
+            },
+            "required": ["text", "alarm", "state", "temp_c", "timestamp", "suppressed"],
+        }),
+        Kind::Report => report(),
+        Kind::DailySummary => daily_summary(),
+    }
+}
+
+fn timestamp() -> Value {
+    json!({"type": "number", "description": "Seconds since the Unix epoch"})
+}
+
+/// A temperature, null when the channel has no value.
+fn temp(millidegrees: bool) -> Value {
+    match millidegrees {
+        true => json!({"type": ["integer", "null"], "description": "Millidegrees C"}),
+        false => json!({"type": ["number", "null"], "description": "Degrees C"}),
+    }
+}
+
+fn reading(format: Format, style: &Style) -> Value {
+    let unit = if style.millidegrees { "mc" } else { "c" };
+    let temp = temp(style.millidegrees);
+    let mut properties = serde_json::Map::new();
+    properties.insert("timestamp".into(), timestamp());
+    properties.insert(
+        "meter".into(),
+        json!({"type": "string", "description": "The meter, when several are read at once"}),
+    );
+    properties.insert(
+        "seq".into(),
+        json!({"type": "integer", "minimum": 0, "description": "Per-output record number"}),
+    );
+    properties.insert(
+        "validity".into(),
+        json!({
+            "type": "string",
+            "description": "With --tolerant: ok, or the checks the frame failed joined by +",
+        }),
+    );
+    properties.insert(
+        "quality".into(),
+        json!({
+            "type": "object",
+            "description": "With --quality: how the reading arrived",
+            "properties": {
+                "checksum_ok": {"type": "boolean"},
+                "resync_bytes": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Bytes skipped looking for the frame",
+                },
+                "jitter_s": {
+                    "type": ["number", "null"],
+                    "description": "Change in the interval between frames",
+                },
+                "filtered_channels": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Channels the plausibility filter blanked",
+                },
+            },
+            "required": ["checksum_ok", "resync_bytes", "jitter_s", "filtered_channels"],
+        }),
+    );
+    let hold_type = json!({"enum": ["Current", "Maximum", "Minimum", "Average"]});
+    let mut required = vec![json!("timestamp")];
+    if format == Format::JsonFlat {
+        let suffix = if style.millidegrees { "_mc" } else { "" };
+        for label in &style.labels {
+            properties.insert(format!("{label}{suffix}"), temp.clone());
+            properties.insert(format!("{label}_held{suffix}"), temp.clone());
+            required.push(format!("{label}{suffix}").into());
+        }
+        properties.insert("hold_type".into(), hold_type);
+        properties.insert(format!("meter_temp{suffix}"), temp.clone());
+        for derived in &style.derived {
+            properties.insert(format!("{}{suffix}", derived.name), temp.clone());
+            required.push(format!("{}{suffix}", derived.name).into());
+        }
+    } else {
+        let temps = json!({"type": "array", "items": temp, "minItems": 4, "maxItems": 4});
+        properties.insert(format!("temps_{unit}"), temps.clone());
+        properties.insert("hold_type".into(), hold_type);
+        properties.insert(format!("held_temps_{unit}"), temps);
+        properties.insert(format!("meter_temp_{unit}"), temp.clone());
+        required.push(format!("temps_{unit}").into());
+        for derived in &style.derived {
+            properties.insert(format!("{}_{unit}", derived.name), temp.clone());
+            required.push(format!("{}_{unit}", derived.name).into());
+        }
+    }
+    if style.validity {
+        required.push("validity".into());
+    }
+    if style.quality {
+        required.push("quality".into());
+    }
+    json!({
+        "title": "Reading",
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn heartbeat() -> Value {
+    json!({
+        "title": "Heartbeat",
+        "type": "object",
+        "properties": {
+            "timestamp": timestamp(),
+            "heartbeat": {"const": true},
+            "last_reading": {
+                "type": ["number", "null"],
+                "description": "Timestamp of the last reading, if any",
+            },
+        },
+        "required": ["timestamp", "heartbeat", "last_reading"],
+        "additionalProperties": false,
+    })
+}
+
+fn annotation() -> Value {
+    json!({
+        "title": "Annotation",
+        "type": "object",
+        "properties": {
+            "timestamp": timestamp(),
+            "meter": {"type": "string"},
+            "annotation": {"type": "string", "description": "The note marking this moment"},
+        },
+        "required": ["timestamp", "annotation"],
+        "additionalProperties": false,
+    })
+}
+
+fn report() -> Value {
+    let number = json!({"type": ["number", "null"]});
+    let count = json!({"type": "integer", "minimum": 0});
+    json!({
+        "$schema": DRAFT,
+        "title": "UT325F session report",
+        "type": "object",
+        "properties": {
+            "version": {"type": "string"},
+            "source": {"type": "string"},
+            "start": timestamp(),
+            "end": timestamp(),
+            "duration_s": {"type": "number"},
+            "outcome": {"type": "string", "description": "ok, or the error that ended the session"},
+            "reference": {
+                "type": "string",
+                "description": "The --reference channel the others are compared against",
+            },
+            "readings": count,
+            "channels": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "channel": {"type": "string"},
+                        "thermocouple": {
+                            "enum": ["K", "J", "T", "E", "R", "S", "N"],
+                            "description": "As given by --thermocouple",
+                        },
+                        "count": count,
+                        "missing": count,
+                        "min": number,
+                        "max": number,
+                        "mean": number,
+                        "p95": number,
+                        "deviation": {
+                            "type": "object",
+                            "description": "From the reference channel, in degrees C",
+                            "properties": {
+                                "count": count,
+                                "bias": {"type": "number"},
+                                "drift_per_h": number,
+                                "max": {"type": "number"},
+                            },
+                        },
+                    },
+                },
+            },
+            "alarms": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "alarm": {"type": "string"},
+                        "state": {"enum": ["raised", "cleared"]},
+                        "temp_c": number,
+                        "timestamp": timestamp(),
+                    },
+                },
+            },
+            "link": {
+                "type": "object",
+                "properties": {
+                    "readings": count,
+                    "discarded_bytes": count,
+                    "rejected_frames": count,
+                    "timeouts": count,
+                    "sync_acquisitions": count,
+                    "mean_sync_acquisition_s": number,
+                },
+            },
+        },
+        "required": ["version", "source", "start", "end", "outcome", "readings", "channels"],
+    })
+}
+
+fn daily_summary() -> Value {
+    let number = json!({"type": ["number", "null"]});
+    let count = json!({"type": "integer", "minimum": 0});
+    json!({
+        "$schema": DRAFT,
+        "title": "UT325F daily summary",
+        "type": "object",
+        "properties": {
+            "date": {"type": "string", "description": "The day, as YYYY-MM-DD at --utc-offset"},
+            "start": timestamp(),
+            "end": timestamp(),
+            "partial": {
+                "type": "boolean",
+                "description": "Written when the session ended, before the day did",
+            },
+            "uptime_s": {"type": "number"},
+            "readings": count,
+            "completeness_pct": {
+                "type": ["number", "null"],
+                "description": "Share of the seconds up in which a reading arrived",
+            },
+            "alarms": count,
+            "channels": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "channel": {"type": "string"},
+                        "count": count,
+                        "missing": count,
+                        "min": number,
+                        "max": number,
+                        "mean": number,
+                    },
+                },
+            },
+        },
+        "required": [
+            "date", "start", "end", "partial", "uptime_s", "readings",
+            "completeness_pct", "alarms", "channels",
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::RecordWriter;
+    use crate::records::Record;
+    use ut325f_rs::{HoldType, Quality, Validity};
+
+    /// Checks a written record against the reading schema's field
+    /// names and required fields.
+    fn check(format: Format, style: Style) {
+        let record = Record {
+            timestamp: 1.5,
+            temps_c: [1.0, f32::NAN, 3.0, 4.0],
+            held: Some((HoldType::Maximum, [5.0, 6.0, 7.0, 8.0])),
+            meter_temp_c: Some(25.5),
+            seq: Some(3),
+            validity: style.validity.then_some(Validity::CHECKSUM),
+            quality: style.quality.then_some(Quality::default()),
+        };
+        let mut writer = RecordWriter::new(Vec::new(), format, style.clone());
+        writer.write(&record).unwrap();
+        let written: Value = serde_json::from_slice(&writer.into_inner()).unwrap();
+        let schema = reading(format, &style);
+        for key in written.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{key}");
+        }
+        for key in schema["required"].as_array().unwrap() {
+            assert!(written.get(key.as_str().unwrap()).is_some(), "{key}");
+        }
+    }
+
+    #[test]
+    fn test_reading_schema_matches_output() {
+        check(Format::Ndjson, Style::default());
+        let mut style = Style {
+            millidegrees: true,
+            validity: true,
+            quality: true,
+            ..Style::default()
+        };
+        style.labels[0] = "oven".to_owned();
+        style.derived = vec!["delta = t1 - t2".parse().unwrap()];
+        check(Format::Ndjson, style.clone());
+        check(Format::JsonFlat, style);
+    }
+}
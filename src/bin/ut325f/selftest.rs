@@ -0,0 +1,58 @@
+use anyhow::{Result, anyhow};
+
+use ut325f_rs::{FrameDecoder, HoldType, Reading};
+
+/// Checks that frames survive encoding, chunking, line noise and
+/// corruption the way the decoder promises.
+pub fn check_decoder() -> Result<()> {
+    let reading = Reading::builder()
+        .current_temps_c([21.5, f32::NAN, -40.0, 1000.0])
+        .held_temps_c([30.0, f32::NAN, f32::NAN, f32::NAN])
+        .hold_type(HoldType::Maximum)
+        .meter_temp_c(25.0)
+        .build();
+    let frame = reading.to_frame();
+    let mut corrupted = frame;
+    corrupted[10] ^= 0x01;
+
+    let mut stream = vec![0x00, 0xaa, 0x55, 0x13];
+    stream.extend_from_slice(&frame);
+    stream.extend_from_slice(&corrupted);
+    stream.extend_from_slice(&frame);
+
+    let mut decoder = FrameDecoder::new();
+    let mut decoded = Vec::new();
+    for chunk in stream.chunks(7) {
+        decoder.push(chunk);
+        while let Some(frame) = decoder.next_frame() {
+            decoded.push(Reading::parse(&frame)?);
+        }
+    }
+    if decoded.len() != 2 {
+        return Err(anyhow!("expected 2 frames, decoded {}", decoded.len()));
+    }
+    if decoded.iter().any(|parsed| {
+        let mut expected = reading;
+        expected.timestamp = parsed.timestamp;
+        *parsed != expected
+    }) {
+        return Err(anyhow!("decoded reading differs from the one encoded"));
+    }
+    if decoder.rejected_frames() != 1 {
+        return Err(anyhow!(
+            "expected 1 rejected frame, counted {}",
+            decoder.rejected_frames()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_decoder() -> Result<()> {
+        check_decoder()
+    }
+}
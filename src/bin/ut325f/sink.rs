@@ -0,0 +1,472 @@
+use anyhow::{Context, Result, anyhow};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ut325f_rs::{Annotation, Heartbeat, Reading, Sink};
+
+use crate::background::{Files, FlushPolicy};
+use crate::duration;
+use crate::format::{Format, RecordWriter, Style};
+use crate::interactive;
+use crate::output::{self, Output};
+use crate::records::{Record, unix_seconds};
+
+/// One `--output`: `PATH[,format=FORMAT][,every=INTERVAL]`, with `-`
+/// for stdout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputSpec {
+    /// `None` for stdout.
+    pub path: Option<PathBuf>,
+    /// Overrides `--format` for this output.
+    pub format: Option<Format>,
+    /// Write at most one reading per interval.
+    pub every: Option<Duration>,
+}
+
+impl FromStr for OutputSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let path = match parts.next() {
+            Some("") | None => return Err("missing path".to_owned()),
+            Some("-") => None,
+            Some(path) => Some(PathBuf::from(path)),
+        };
+        let mut spec = Self {
+            path,
+            format: None,
+            every: None,
+        };
+        for option in parts {
+            match option.split_once('=') {
+                Some(("format", format)) => {
+                    spec.format = Some(clap::ValueEnum::from_str(format, true)?);
+                }
+                Some(("every", every)) => {
+                    spec.every = Some(duration::parse(every)?);
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown option '{option}'; expected format= or every="
+                    ));
+                }
+            }
+        }
+        Ok(spec)
+    }
+}
+
+/// When to start a new log file and which old ones to delete.
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    /// Segment length; segments start at multiples of it since the
+    /// Unix epoch (so 1d rotates at midnight UTC).
+    pub interval: Duration,
+    /// Keep at most this many segments, including the current one.
+    pub keep: Option<usize>,
+    /// Delete the oldest segments while all of them together exceed
+    /// this many bytes. The current segment is never deleted.
+    pub max_total_size: Option<u64>,
+}
+
+/// The monitor's log: stdout or a file, optionally rotated. Files are
+/// written in the background (see [`Files`]).
+pub struct FileSink {
+    writer: RecordWriter<Output>,
+    /// `None` for stdout.
+    files: Option<Files>,
+    /// Flush after every this many records.
+    flush_every: Option<u64>,
+    format: Format,
+    style: Style,
+    held_temps: bool,
+    path: Option<PathBuf>,
+    rotation: Option<Rotation>,
+    segment: Option<u64>,
+    /// Sequence number of the next reading written.
+    seq: u64,
+}
+
+impl FileSink {
+    /// Opens the log. With rotation, segments are named after `path`
+    /// with their UTC start time inserted before the extensions
+    /// (`session.csv` becomes `session-20250101T000000Z.csv`), and the
+    /// first one is created with the first record.
+    pub fn create(
+        path: Option<&Path>,
+        format: Format,
+        style: Style,
+        held_temps: bool,
+        rotation: Option<Rotation>,
+        flush: FlushPolicy,
+    ) -> Result<Self> {
+        if rotation.is_some() && path.is_none() {
+            return Err(anyhow!("rotation requires an output file"));
+        }
+        let files = path.map(|_| Files::spawn(flush));
+        let output = match (path, &files, &rotation) {
+            (Some(path), Some(files), None) => open(files, path)?,
+            // Block-buffered only if it is to be flushed every so often.
+            (None, _, _) if flush.records.is_some_and(|n| n > 1) => output::create(None)?,
+            (None, _, _) => output::create_live(None)?,
+            _ => Output::Plain(Box::new(std::io::sink())),
+        };
+        Ok(Self {
+            writer: RecordWriter::new(output, format, style.clone()),
+            files,
+            flush_every: flush.records,
+            format,
+            style,
+            held_temps,
+            path: path.map(Path::to_owned),
+            rotation,
+            segment: None,
+            seq: 0,
+        })
+    }
+
+    pub fn write_record(&mut self, record: &Record) -> Result<()> {
+        if let (Some(rotation), Some(path)) = (&self.rotation, &self.path) {
+            let segment = (record.timestamp.max(0.0) / rotation.interval.as_secs_f64()) as u64;
+            if self.segment != Some(segment) {
+                let start = UNIX_EPOCH
+                    + Duration::from_secs_f64(segment as f64 * rotation.interval.as_secs_f64());
+                let segment_path = segment_path(path, start);
+                let retention =
+                    apply_retention(path.clone(), segment_path.clone(), rotation.clone());
+                let files = self.files.clone().expect("a file sink has files");
+                // The last segment's data goes ahead of the switch.
+                self.take_writer().into_inner().finish()?;
+                let output = output::create_with(&segment_path, || {
+                    Ok(Box::new(
+                        files.create(segment_path.clone(), Some(Box::pin(retention))),
+                    ))
+                })?;
+                self.writer = RecordWriter::new(output, self.format, self.style.clone());
+                self.segment = Some(segment);
+            }
+        }
+        self.writer.write(record)?;
+        Ok(())
+    }
+
+    /// The log's background writer, to [`close`](Files::close) once
+    /// the sink has finished; `None` for stdout.
+    pub fn files(&self) -> Option<Files> {
+        self.files.clone()
+    }
+
+    /// Takes the current writer, leaving one that discards.
+    fn take_writer(&mut self) -> RecordWriter<Output> {
+        std::mem::replace(
+            &mut self.writer,
+            RecordWriter::new(
+                Output::Plain(Box::new(std::io::sink())),
+                self.format,
+                self.style.clone(),
+            ),
+        )
+    }
+}
+
+/// Creates the file at `path` and opens it for writing through `files`.
+fn open(files: &Files, path: &Path) -> Result<Output> {
+    output::create_with(path, || {
+        let file =
+            std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        Ok(Box::new(files.open(file)))
+    })
+}
+
+impl Sink for FileSink {
+    /// Numbers the readings this sink writes, across rotated segments.
+    /// A display on stdout follows the [`interactive`] controls.
+    fn write(&mut self, reading: &Reading) -> ut325f_rs::Result<()> {
+        let mut held_temps = self.held_temps;
+        if self.path.is_none() && matches!(self.format, Format::Text | Format::Pretty) {
+            let controls = interactive::controls();
+            if controls.paused {
+                self.seq += 1;
+                return Ok(());
+            }
+            held_temps ^= controls.held_flipped;
+            self.writer.set_unit(controls.unit);
+        }
+        let record = Record {
+            seq: Some(self.seq),
+            validity: self.style.validity.then_some(reading.validity),
+            quality: self.style.quality.then_some(reading.quality),
+            ..Record::from_reading(reading, held_temps)
+        };
+        self.seq += 1;
+        self.write_record(&record).map_err(sink_error)?;
+        if self.flush_every.is_some_and(|n| self.seq.is_multiple_of(n)) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn heartbeat(&mut self, heartbeat: &Heartbeat) -> ut325f_rs::Result<()> {
+        self.writer.write_heartbeat(
+            unix_seconds(heartbeat.timestamp),
+            heartbeat.last_reading.map(unix_seconds),
+        )?;
+        if self.flush_every.is_some() {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushed at once, so the mark is in the file as it is made.
+    fn annotate(&mut self, annotation: &Annotation) -> ut325f_rs::Result<()> {
+        self.writer
+            .write_annotation(unix_seconds(annotation.timestamp), &annotation.note)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Hands the rest of a file to its background writer; see
+    /// [`files`](Self::files).
+    fn finish(&mut self) -> ut325f_rs::Result<()> {
+        Ok(self.take_writer().into_inner().finish()?)
+    }
+}
+
+/// Keeps I/O errors recognizable (e.g. a broken pipe) when they cross
+/// into the library's sink error type.
+pub fn sink_error(e: anyhow::Error) -> ut325f_rs::Error {
+    match e.downcast::<std::io::Error>() {
+        Ok(e) => ut325f_rs::Error::Io(e),
+        Err(e) => ut325f_rs::Error::Sink(e.into()),
+    }
+}
+
+/// Splits a file name into the part before its first dot and the rest
+/// (`session.csv.zst` into `session` and `.csv.zst`).
+fn split_name(path: &Path) -> (String, String) {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match name.find('.') {
+        Some(dot) if dot > 0 => (name[..dot].to_owned(), name[dot..].to_owned()),
+        _ => (name, String::new()),
+    }
+}
+
+/// `path` with `start` inserted before its extensions, e.g.
+/// `session-20250101T000000Z.csv`.
+pub fn segment_path(path: &Path, start: SystemTime) -> PathBuf {
+    let (stem, extensions) = split_name(path);
+    let stamp: String = humantime::format_rfc3339_seconds(start)
+        .to_string()
+        .chars()
+        .filter(|c| !matches!(c, '-' | ':'))
+        .collect();
+    path.with_file_name(format!("{stem}-{stamp}{extensions}"))
+}
+
+/// `path` with `tag` inserted before its extensions, e.g.
+/// `session-ttyUSB0.csv`.
+pub fn tagged_path(path: &Path, tag: &str) -> PathBuf {
+    let (stem, extensions) = split_name(path);
+    path.with_file_name(format!("{stem}-{tag}{extensions}"))
+}
+
+/// Deletes the oldest segments of the log at `path` beyond the
+/// rotation's limits, never `current`. Runs on the background writer
+/// once `current` has been created.
+async fn apply_retention(path: PathBuf, current: PathBuf, rotation: Rotation) -> io::Result<()> {
+    if rotation.keep.is_none() && rotation.max_total_size.is_none() {
+        return Ok(());
+    }
+    let (stem, extensions) = split_name(&path);
+    let prefix = format!("{stem}-");
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let listing =
+        |e: io::Error| io::Error::new(e.kind(), format!("listing {}: {e}", dir.display()));
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(listing)?;
+    let mut segments = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(listing)? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(&prefix) && name.ends_with(&extensions) {
+            segments.push((name, entry.metadata().await?.len()));
+        }
+    }
+    // Segment names embed their start time, so they sort by age.
+    segments.sort();
+    let current_name = current
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut total: u64 = segments.iter().map(|(_, size)| size).sum();
+    let mut count = segments.len();
+    for (name, size) in segments {
+        let over_count = rotation.keep.is_some_and(|keep| count > keep);
+        let over_size = rotation.max_total_size.is_some_and(|max| total > max);
+        if !(over_count || over_size) || name == current_name {
+            break;
+        }
+        let old = dir.join(&name);
+        tokio::fs::remove_file(&old)
+            .await
+            .map_err(|e| io::Error::new(e.kind(), format!("removing {}: {e}", old.display())))?;
+        total -= size;
+        count -= 1;
+    }
+    Ok(())
+}
+
+/// Parses a byte count with an optional binary suffix (e.g. 500M, 2G).
+pub fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let s = s.trim();
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let shift = match c.to_ascii_uppercase() {
+                'K' => 10,
+                'M' => 20,
+                'G' => 30,
+                'T' => 40,
+                _ => return Err(format!("unknown size suffix '{c}'")),
+            };
+            (&s[..i], shift)
+        }
+        _ => (s, 0),
+    };
+    let value: u64 = digits.parse().map_err(|_| format!("invalid size '{s}'"))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size '{s}' is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_path() {
+        let start = UNIX_EPOCH + Duration::from_secs(86400 + 3600);
+        assert_eq!(
+            segment_path(Path::new("logs/session.csv.zst"), start),
+            Path::new("logs/session-19700102T010000Z.csv.zst")
+        );
+        assert_eq!(
+            segment_path(Path::new("session"), start),
+            Path::new("session-19700102T010000Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_output_spec() {
+        assert_eq!(
+            "-".parse(),
+            Ok(OutputSpec {
+                path: None,
+                format: None,
+                every: None
+            })
+        );
+        assert_eq!(
+            "minutes.ndjson,format=ndjson,every=1m".parse(),
+            Ok(OutputSpec {
+                path: Some(PathBuf::from("minutes.ndjson")),
+                format: Some(Format::Ndjson),
+                every: Some(Duration::from_secs(60)),
+            })
+        );
+        assert!("a.csv,rate=1s".parse::<OutputSpec>().is_err());
+        assert!("a.csv,format=xml".parse::<OutputSpec>().is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert_eq!(parse_size("10k"), Ok(10 << 10));
+        assert!(parse_size("2X").is_err());
+        assert!(parse_size("G").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_flush_every() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("ut325f-flush-{}.csv", std::process::id()));
+        let flush = FlushPolicy {
+            interval: Duration::from_secs(3600),
+            records: Some(2),
+            ..FlushPolicy::default()
+        };
+        let mut sink = FileSink::create(
+            Some(&path),
+            Format::Csv,
+            Style::default(),
+            false,
+            None,
+            flush,
+        )?;
+        let lines = || std::fs::read_to_string(&path).map(|log| log.lines().count());
+        let reading = Reading::builder().build();
+        sink.write(&reading)?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(lines()?, 0);
+        sink.write(&reading)?;
+        let mut waited = 0;
+        while lines()? < 3 && waited < 100 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            waited += 1;
+        }
+        assert_eq!(lines()?, 3);
+        sink.finish()?;
+        sink.files().expect("a file sink has files").close().await?;
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rotation_and_retention() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("ut325f-sink-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let rotation = Rotation {
+            interval: Duration::from_secs(60),
+            keep: Some(2),
+            max_total_size: None,
+        };
+        let mut sink = FileSink::create(
+            Some(&dir.join("log.csv")),
+            Format::Csv,
+            Style::default(),
+            false,
+            Some(rotation),
+            FlushPolicy::default(),
+        )?;
+        for minute in 0..4 {
+            sink.write_record(&Record::new(f64::from(minute * 60 + 1), [1.0; 4]))?;
+        }
+        sink.finish()?;
+        sink.files().expect("a file sink has files").close().await?;
+        let mut names: Vec<_> = std::fs::read_dir(&dir)?
+            .map(|e| e.map(|e| e.file_name().to_string_lossy().into_owned()))
+            .collect::<std::io::Result<_>>()?;
+        names.sort();
+        let logs = names
+            .iter()
+            .map(|name| std::fs::read_to_string(dir.join(name)))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(
+            names,
+            ["log-19700101T000200Z.csv", "log-19700101T000300Z.csv"]
+        );
+        // Each segment holds its own header and record, and no other.
+        for log in logs {
+            assert!(log.starts_with("timestamp,"));
+            assert_eq!(log.lines().count(), 2);
+        }
+        Ok(())
+    }
+}
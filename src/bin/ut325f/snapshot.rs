@@ -0,0 +1,60 @@
+use anyhow::{Context, Result, anyhow};
+use ut325f_rs::Reading;
+
+use crate::cli::ConnectArgs;
+use crate::connect;
+use crate::records::Record;
+use crate::stats::ChannelStats;
+
+/// Each channel's mean over `readings`, leaving out NaN, with the time
+/// of the last. A channel with no value in any of them is NaN.
+fn average(readings: &[Reading]) -> Option<Record> {
+    let mean = |temps: &dyn Fn(&Reading) -> f32| ChannelStats::new(readings.iter().map(temps)).mean;
+    Some(Record {
+        temps_c: std::array::from_fn(|channel| mean(&|reading| reading.current_temps_c[channel])),
+        meter_temp_c: Some(mean(&|reading| reading.meter_temp_c)),
+        ..Record::from_reading(readings.last()?, false)
+    })
+}
+
+/// Reads `samples` readings for `snapshot` and returns their average.
+pub async fn read(connect_args: &ConnectArgs, samples: usize) -> Result<Record> {
+    let mut meter = connect::open(connect_args).await?;
+    let mut readings = Vec::with_capacity(samples);
+    let mut result = Ok(());
+    while readings.len() < samples {
+        match meter.read().await {
+            Ok(reading) => readings.push(reading),
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+    let torn_down = connect::teardown(meter, connect_args.disconnect).await;
+    result.context("Error reading data")?;
+    torn_down?;
+    average(&readings).ok_or_else(|| anyhow!("no readings to average"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average() {
+        let reading = |seconds, t1, t2| {
+            Reading::builder()
+                .timestamp(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+                .current_temp_c(0, t1)
+                .current_temp_c(1, t2)
+                .current_temp_c(2, f32::NAN)
+                .build()
+        };
+        let record = average(&[reading(1, 20.0, f32::NAN), reading(2, 22.0, 5.0)]).unwrap();
+        assert_eq!(record.timestamp, 2.0);
+        assert_eq!(record.temps_c[..2], [21.0, 5.0]);
+        assert!(record.temps_c[2].is_nan());
+        assert!(average(&[]).is_none());
+    }
+}
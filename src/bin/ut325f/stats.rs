@@ -0,0 +1,166 @@
+use std::io;
+
+use crate::records::Record;
+
+/// Summary statistics for one channel, over its non-NaN samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelStats {
+    pub count: usize,
+    pub missing: usize,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub p95: f32,
+}
+
+impl ChannelStats {
+    pub fn new(samples: impl Iterator<Item = f32>) -> Self {
+        let mut missing = 0;
+        let mut values: Vec<f32> = samples
+            .filter(|v| {
+                missing += usize::from(v.is_nan());
+                !v.is_nan()
+            })
+            .collect();
+        values.sort_by(f32::total_cmp);
+        let count = values.len();
+        if count == 0 {
+            return Self {
+                count,
+                missing,
+                min: f32::NAN,
+                max: f32::NAN,
+                mean: f32::NAN,
+                p95: f32::NAN,
+            };
+        }
+        let sum: f64 = values.iter().map(|&v| f64::from(v)).sum();
+        // Nearest-rank percentile.
+        let rank = (0.95 * count as f64).ceil() as usize;
+        Self {
+            count,
+            missing,
+            min: values[0],
+            max: values[count - 1],
+            mean: (sum / count as f64) as f32,
+            p95: values[rank.max(1) - 1],
+        }
+    }
+}
+
+/// Summary of a recorded session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionStats {
+    pub records: usize,
+    pub duration_s: f64,
+    /// Intervals between consecutive records longer than the gap
+    /// threshold.
+    pub gaps: usize,
+    pub longest_gap_s: f64,
+    pub total_gap_s: f64,
+    pub channels: [ChannelStats; 4],
+}
+
+impl SessionStats {
+    pub fn new(records: &[Record], gap_threshold_s: f64) -> Self {
+        let duration_s = match (records.first(), records.last()) {
+            (Some(first), Some(last)) => last.timestamp - first.timestamp,
+            _ => 0.0,
+        };
+        let mut gaps = 0;
+        let mut longest_gap_s: f64 = 0.0;
+        let mut total_gap_s = 0.0;
+        for pair in records.windows(2) {
+            let interval = pair[1].timestamp - pair[0].timestamp;
+            if interval > gap_threshold_s {
+                gaps += 1;
+                longest_gap_s = longest_gap_s.max(interval);
+                total_gap_s += interval;
+            }
+        }
+        Self {
+            records: records.len(),
+            duration_s,
+            gaps,
+            longest_gap_s,
+            total_gap_s,
+            channels: std::array::from_fn(|channel| {
+                ChannelStats::new(records.iter().map(|r| r.temps_c[channel]))
+            }),
+        }
+    }
+
+    pub fn write(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        writeln!(writer, "records   {}", self.records)?;
+        writeln!(writer, "duration  {:.3} s", self.duration_s)?;
+        writeln!(
+            writer,
+            "gaps      {} (longest {:.3} s, total {:.3} s)",
+            self.gaps, self.longest_gap_s, self.total_gap_s
+        )?;
+        writeln!(
+            writer,
+            "channel  count  missing      min      max     mean      p95"
+        )?;
+        for (index, channel) in self.channels.iter().enumerate() {
+            writeln!(
+                writer,
+                "T{:<6} {:6} {:8} {:8.3} {:8.3} {:8.3} {:8.3}",
+                index + 1,
+                channel.count,
+                channel.missing,
+                channel.min,
+                channel.max,
+                channel.mean,
+                channel.p95
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: f64, t1: f32) -> Record {
+        Record::new(timestamp, [t1, f32::NAN, 0.0, 0.0])
+    }
+
+    #[test]
+    fn test_channel_stats() {
+        let stats = ChannelStats::new((1..=20).map(|v| v as f32).chain([f32::NAN]));
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.missing, 1);
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.max, 20.0);
+        assert_eq!(stats.mean, 10.5);
+        assert_eq!(stats.p95, 19.0);
+    }
+
+    #[test]
+    fn test_all_nan_channel() {
+        let stats = ChannelStats::new([f32::NAN, f32::NAN].into_iter());
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.missing, 2);
+        assert!(stats.mean.is_nan());
+    }
+
+    #[test]
+    fn test_session_gaps() {
+        let records = [
+            record(0.0, 1.0),
+            record(0.25, 2.0),
+            record(5.25, 3.0),
+            record(5.5, 4.0),
+            record(8.5, 5.0),
+        ];
+        let stats = SessionStats::new(&records, 2.0);
+        assert_eq!(stats.records, 5);
+        assert_eq!(stats.duration_s, 8.5);
+        assert_eq!(stats.gaps, 2);
+        assert_eq!(stats.longest_gap_s, 5.0);
+        assert_eq!(stats.channels[0].mean, 3.0);
+        assert_eq!(stats.channels[1].count, 0);
+    }
+}
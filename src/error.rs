@@ -14,6 +14,32 @@ pub enum Error {
     #[error("malformed frame: {0}")]
     MalformedFrame(&'static str),
 
+    #[error("unexpected unknown bytes {0:02x?}")]
+    UnexpectedUnknownBytes([u8; 4]),
+
+    #[error(
+        "invalid alarm '{0}'; expected e.g. T1>80, T2<5, 'T1>80 clear=75 for=10s' or 't1 > 80 && t2 < 5 for 30s'"
+    )]
+    InvalidAlarm(String),
+
+    #[error("unknown thermocouple type '{0}'; expected K, J, T, E, R, S or N")]
+    InvalidThermocoupleType(String),
+
+    #[error("invalid expression '{0}': {1}")]
+    InvalidExpression(String, String),
+
+    /// Strict reading only: a frame that failed to parse, with the
+    /// bytes at fault.
+    #[error(transparent)]
+    InvalidFrame(Box<ut325f_protocol::FrameError>),
+
+    #[error("invalid source '{0}'; expected serial:PORT, ble:ADDRESS, tcp:HOST:PORT or file:PATH")]
+    InvalidSource(String),
+
+    /// A source that needs a feature this build lacks.
+    #[error("built without {0} support")]
+    Unsupported(&'static str),
+
     #[error("timeout reading data")]
     ReadTimeout,
 
@@ -23,13 +49,29 @@ pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
-    #[cfg(feature = "serial")]
+    /// A supervised reader panicked, with the panic's message.
+    #[error("reader panicked: {0}")]
+    Panicked(String),
+
+    /// Every sink of a [`Tee`](crate::Tee) found its destination closed.
+    #[error("every output was closed")]
+    SinksClosed,
+
+    /// A [`Sink`](crate::Sink) failed in a way other than I/O.
+    #[error(transparent)]
+    Sink(Box<dyn std::error::Error + Send + Sync>),
+
+    #[cfg(any(feature = "serial", feature = "serial-async-io"))]
     #[error("failed to open serial port {port}: {source}")]
     SerialOpen {
         port: String,
-        source: tokio_serial::Error,
+        source: serialport::Error,
     },
 
+    #[cfg(feature = "serial")]
+    #[error("no frames from {port} at any of {rates:?} baud")]
+    NoBaudRate { port: String, rates: Vec<u32> },
+
     #[cfg(any(feature = "bluebus", feature = "btleplug"))]
     #[error("timeout connecting to {0}")]
     ConnectTimeout(String),
@@ -96,4 +138,19 @@ pub enum Error {
     Btleplug(#[from] btleplug::Error),
 }
 
+impl From<ut325f_protocol::Error> for Error {
+    fn from(e: ut325f_protocol::Error) -> Self {
+        match e {
+            ut325f_protocol::Error::BadSyncHeader => Self::BadSyncHeader,
+            ut325f_protocol::Error::ChecksumMismatch => Self::ChecksumMismatch,
+            ut325f_protocol::Error::InvalidHoldType(hold_type) => Self::InvalidHoldType(hold_type),
+            ut325f_protocol::Error::UnexpectedUnknownBytes(bytes) => {
+                Self::UnexpectedUnknownBytes(bytes)
+            }
+            ut325f_protocol::Error::Anomaly(anomaly) => Self::MalformedFrame(anomaly),
+            _ => Self::MalformedFrame("unrecognized frame error"),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
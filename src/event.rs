@@ -0,0 +1,140 @@
+use std::time::SystemTime;
+
+use crate::alarm::{AlarmEvent, Alarms};
+use crate::{HoldType, Reading};
+
+/// A discrete change in what the meter reports.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum MeterEvent {
+    /// A thermocouple was plugged into `channel` (0-based).
+    ChannelConnected {
+        channel: usize,
+        timestamp: SystemTime,
+    },
+    /// The thermocouple on `channel` (0-based) was unplugged or broke.
+    ChannelDisconnected {
+        channel: usize,
+        timestamp: SystemTime,
+    },
+    /// The meter's hold mode was changed.
+    HoldTypeChanged {
+        from: HoldType,
+        to: HoldType,
+        timestamp: SystemTime,
+    },
+    AlarmRaised(AlarmEvent),
+    AlarmCleared(AlarmEvent),
+    /// A supervised reader lost the meter to `error` and is opening it
+    /// again, for the `attempt`th time in a row.
+    Restarted {
+        error: String,
+        attempt: u32,
+        timestamp: SystemTime,
+    },
+    /// The `sink`th sink added to a [`Tee`](crate::Tee) (0-based) found
+    /// its destination closed, e.g. stdout's reader exited, and gets no
+    /// more readings.
+    SinkClosed {
+        sink: usize,
+        timestamp: SystemTime,
+    },
+}
+
+/// Turns a stream of readings into [`MeterEvent`]s by comparing each
+/// reading with the one before. The first reading is the baseline and
+/// produces only alarm events.
+#[derive(Debug, Clone, Default)]
+pub struct EventDetector {
+    previous: Option<Reading>,
+    alarms: Alarms,
+}
+
+impl EventDetector {
+    pub fn new(alarms: Alarms) -> Self {
+        Self {
+            previous: None,
+            alarms,
+        }
+    }
+
+    /// Returns the events `reading` gives rise to.
+    pub fn update(&mut self, reading: &Reading) -> Vec<MeterEvent> {
+        let mut events = Vec::new();
+        if let Some(previous) = &self.previous {
+            let timestamp = reading.timestamp;
+            for (channel, (before, now)) in previous
+                .current_temps_c
+                .iter()
+                .zip(&reading.current_temps_c)
+                .enumerate()
+            {
+                match (before.is_nan(), now.is_nan()) {
+                    (true, false) => {
+                        events.push(MeterEvent::ChannelConnected { channel, timestamp })
+                    }
+                    (false, true) => {
+                        events.push(MeterEvent::ChannelDisconnected { channel, timestamp })
+                    }
+                    _ => {}
+                }
+            }
+            if previous.hold_type != reading.hold_type {
+                events.push(MeterEvent::HoldTypeChanged {
+                    from: previous.hold_type,
+                    to: reading.hold_type,
+                    timestamp,
+                });
+            }
+        }
+        events.extend(self.alarms.update(reading).into_iter().map(|event| {
+            if event.raised {
+                MeterEvent::AlarmRaised(event)
+            } else {
+                MeterEvent::AlarmCleared(event)
+            }
+        }));
+        self.previous = Some(*reading);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(temps: [f32; 4], hold_type: HoldType) -> Reading {
+        Reading::builder()
+            .timestamp(SystemTime::UNIX_EPOCH)
+            .current_temps_c(temps)
+            .hold_type(hold_type)
+            .meter_temp_c(25.0)
+            .build()
+    }
+
+    #[test]
+    fn test_events() {
+        let alarms = Alarms::new(vec!["T1>80".parse().unwrap()]);
+        let mut detector = EventDetector::new(alarms);
+        let nan = f32::NAN;
+        assert!(
+            detector
+                .update(&reading([20.0, nan, 1.0, nan], HoldType::Current))
+                .is_empty()
+        );
+        let events = detector.update(&reading([90.0, 2.0, nan, nan], HoldType::Maximum));
+        assert!(matches!(
+            events[..],
+            [
+                MeterEvent::ChannelConnected { channel: 1, .. },
+                MeterEvent::ChannelDisconnected { channel: 2, .. },
+                MeterEvent::HoldTypeChanged {
+                    from: HoldType::Current,
+                    to: HoldType::Maximum,
+                    ..
+                },
+                MeterEvent::AlarmRaised(_),
+            ]
+        ));
+    }
+}
@@ -0,0 +1,482 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Reading;
+use crate::error::Error;
+
+// Longest first, so that `<=` is not read as `<` and `=`.
+const SYMBOLS: [&str; 17] = [
+    "..", "&&", "||", "<=", ">=", "==", "!=", "<", ">", "!", "+", "-", "*", "/", "(", ")", ",",
+];
+
+/// An expression over a reading's current temperatures, e.g.
+/// `t1 - t2`, `mean(t1..t4)` or `t1 > 80 && t2 - t3 > 5`.
+///
+/// Channels are `t1` to `t4`; operators are `+ - * /`, the comparisons
+/// `< <= > >= == !=`, `&& || !` and parentheses. Comparisons and logic
+/// yield 1 for true and 0 for false, and any non-zero value counts as
+/// true. The functions `mean`, `min` and `max` take channels, ranges of
+/// channels (`t1..t3`) and expressions, and skip NaN (an open channel);
+/// `abs` takes one argument. Anywhere else a NaN makes the result NaN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    source: String,
+    root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Channel(usize),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Func {
+    Mean,
+    Min,
+    Max,
+    Abs,
+}
+
+impl Expr {
+    /// Evaluates the expression on a reading's current temperatures.
+    pub fn eval(&self, temps_c: &[f32; 4]) -> f32 {
+        self.root.eval(temps_c) as f32
+    }
+
+    /// Whether the expression reads any channel at all.
+    pub fn uses_channels(&self) -> bool {
+        self.root.uses_channels()
+    }
+}
+
+impl Node {
+    fn uses_channels(&self) -> bool {
+        match self {
+            Self::Number(_) => false,
+            Self::Channel(_) => true,
+            Self::Neg(node) | Self::Not(node) => node.uses_channels(),
+            Self::Binary(_, left, right) => left.uses_channels() || right.uses_channels(),
+            Self::Call(_, args) => args.iter().any(Self::uses_channels),
+        }
+    }
+
+    fn eval(&self, temps_c: &[f32; 4]) -> f64 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Channel(channel) => temps_c[*channel].into(),
+            Self::Neg(node) => -node.eval(temps_c),
+            Self::Not(node) => truth(node.eval(temps_c), |v| v == 0.0),
+            Self::Binary(op, left, right) => {
+                let (left, right) = (left.eval(temps_c), right.eval(temps_c));
+                let both = |f: fn(f64, f64) -> bool| match left.is_nan() || right.is_nan() {
+                    true => f64::NAN,
+                    false => f64::from(u8::from(f(left, right))),
+                };
+                match op {
+                    Op::Add => left + right,
+                    Op::Sub => left - right,
+                    Op::Mul => left * right,
+                    Op::Div => left / right,
+                    Op::Less => both(|l, r| l < r),
+                    Op::LessEqual => both(|l, r| l <= r),
+                    Op::Greater => both(|l, r| l > r),
+                    Op::GreaterEqual => both(|l, r| l >= r),
+                    Op::Equal => both(|l, r| l == r),
+                    Op::NotEqual => both(|l, r| l != r),
+                    Op::And => both(|l, r| l != 0.0 && r != 0.0),
+                    Op::Or => both(|l, r| l != 0.0 || r != 0.0),
+                }
+            }
+            Self::Call(Func::Abs, args) => args[0].eval(temps_c).abs(),
+            Self::Call(func, args) => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.eval(temps_c))
+                    .filter(|v| !v.is_nan());
+                let (count, sum, min, max) = values.fold(
+                    (0, 0.0, f64::INFINITY, f64::NEG_INFINITY),
+                    |(count, sum, min, max), v| (count + 1, sum + v, min.min(v), max.max(v)),
+                );
+                match (count, func) {
+                    (0, _) => f64::NAN,
+                    (_, Func::Mean) => sum / f64::from(count),
+                    (_, Func::Min) => min,
+                    (_, Func::Max) => max,
+                    (_, Func::Abs) => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+/// 1 if `f` holds for `value`, else 0; NaN stays NaN.
+fn truth(value: f64, f: fn(f64) -> bool) -> f64 {
+    match value.is_nan() {
+        true => f64::NAN,
+        false => f64::from(u8::from(f(value))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Ident(name) => f.write_str(name),
+            Self::Symbol(symbol) => f.write_str(symbol),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if rest.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            // A `.` belongs to the number only if a digit follows, so
+            // that `1..` is not read as `1.` and `.`.
+            let mut end = 0;
+            let bytes = rest.as_bytes();
+            while end < bytes.len()
+                && (bytes[end].is_ascii_digit()
+                    || bytes[end] == b'.' && bytes.get(end + 1).is_some_and(u8::is_ascii_digit))
+            {
+                end += 1;
+            }
+            let number = rest[..end.max(1)].parse().map_err(|_| "bad number")?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end.max(1)..];
+        } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_ascii_lowercase()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("unexpected '{}'", rest.chars().next().unwrap()));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// A recursive-descent parser over the tokens of an expression.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        let matched = self.peek() == Some(&Token::Symbol(symbol));
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        match self.eat(symbol) {
+            true => Ok(()),
+            false => Err(format!("expected '{symbol}'")),
+        }
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.eat("||") {
+            node = Node::Binary(Op::Or, Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.not()?;
+        while self.eat("&&") {
+            node = Node::Binary(Op::And, Box::new(node), Box::new(self.not()?));
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> Result<Node, String> {
+        if self.eat("!") {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        let node = self.sum()?;
+        let ops = [
+            ("<", Op::Less),
+            ("<=", Op::LessEqual),
+            (">", Op::Greater),
+            (">=", Op::GreaterEqual),
+            ("==", Op::Equal),
+            ("!=", Op::NotEqual),
+        ];
+        match ops.into_iter().find(|(symbol, _)| self.eat(symbol)) {
+            Some((_, op)) => Ok(Node::Binary(op, Box::new(node), Box::new(self.sum()?))),
+            None => Ok(node),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                Op::Add
+            } else if self.eat("-") {
+                Op::Sub
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                Op::Mul
+            } else if self.eat("/") {
+                Op::Div
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat("-") {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Node::Number(value)),
+            Some(Token::Symbol("(")) => {
+                let node = self.or()?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::Symbol("(")) => {
+                self.position += 1;
+                self.call(&name)
+            }
+            Some(Token::Ident(name)) => channel(&name).map(Node::Channel),
+            Some(token) => Err(format!("unexpected '{token}'")),
+            None => Err("unexpected end".to_owned()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Node, String> {
+        let func = match name {
+            "mean" | "avg" => Func::Mean,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "abs" => Func::Abs,
+            _ => return Err(format!("unknown function '{name}'")),
+        };
+        let mut args = Vec::new();
+        loop {
+            args.extend(self.argument()?);
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect(")")?;
+        if func == Func::Abs && args.len() != 1 {
+            return Err("abs takes one argument".to_owned());
+        }
+        Ok(Node::Call(func, args))
+    }
+
+    /// An expression, or a range of channels such as `t1..t4`.
+    fn argument(&mut self) -> Result<Vec<Node>, String> {
+        if let (Some(Token::Ident(first)), Some(Token::Symbol(".."))) = (
+            self.tokens.get(self.position),
+            self.tokens.get(self.position + 1),
+        ) {
+            let first = channel(first)?;
+            self.position += 2;
+            let last = match self.next() {
+                Some(Token::Ident(last)) => channel(&last)?,
+                _ => return Err("expected a channel after '..'".to_owned()),
+            };
+            if last < first {
+                return Err("empty channel range".to_owned());
+            }
+            return Ok((first..=last).map(Node::Channel).collect());
+        }
+        Ok(vec![self.or()?])
+    }
+}
+
+/// The zero-based index of a channel name such as `t1`.
+fn channel(name: &str) -> Result<usize, String> {
+    match name.strip_prefix('t').map(str::parse::<usize>) {
+        Some(Ok(n @ 1..=4)) => Ok(n - 1),
+        _ => Err(format!("unknown channel '{name}'; expected t1 to t4")),
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| Error::InvalidExpression(s.to_owned(), reason);
+        let mut parser = Parser {
+            tokens: tokenize(s).map_err(invalid)?,
+            position: 0,
+        };
+        let root = parser.or().map_err(invalid)?;
+        if let Some(token) = parser.peek() {
+            return Err(invalid(format!("unexpected '{token}'")));
+        }
+        Ok(Self {
+            source: s.trim().to_owned(),
+            root,
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// A computed channel, written as `NAME = EXPR`, e.g.
+/// `delta = t1 - t2` or `avg_all = mean(t1..t4)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedChannel {
+    pub name: String,
+    pub expr: Expr,
+}
+
+impl DerivedChannel {
+    /// The channel's value for `reading`.
+    pub fn eval(&self, reading: &Reading) -> f32 {
+        self.expr.eval(&reading.current_temps_c)
+    }
+}
+
+impl FromStr for DerivedChannel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Error::InvalidExpression(s.to_owned(), reason.to_owned());
+        let (name, expr) = s
+            .split_once('=')
+            .ok_or_else(|| invalid("expected NAME = EXPR"))?;
+        let name = name.trim();
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(invalid(
+                "the name must be a letter then letters, digits or _",
+            ));
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            expr: expr.trim().parse()?,
+        })
+    }
+}
+
+impl fmt::Display for DerivedChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.name, self.expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expr: &str, temps_c: [f32; 4]) -> f32 {
+        expr.parse::<Expr>().unwrap().eval(&temps_c)
+    }
+
+    #[test]
+    fn test_eval() {
+        let temps = [10.0, 4.0, f32::NAN, 2.5];
+        assert_eq!(eval("t1 - t2", temps), 6.0);
+        assert_eq!(eval("T1 - t2 * 2 + 1", temps), 3.0);
+        assert_eq!(eval("(t1 - t2) / -2", temps), -3.0);
+        assert_eq!(eval("abs(t2 - t1)", temps), 6.0);
+        assert_eq!(eval("mean(t1..t4)", temps), 5.5);
+        assert_eq!(eval("max(t2, t4, 0.5)", temps), 4.0);
+        assert_eq!(eval("min(t1..t2, t4 * 10)", temps), 4.0);
+        assert!(eval("t3 + 1", temps).is_nan());
+        assert!(eval("mean(t3..t3)", temps).is_nan());
+        assert_eq!(eval("t1 > 8 && t2 - t4 >= 1.5", temps), 1.0);
+        assert_eq!(eval("t1 > 8 && !(t2 < t4) && t1 != 10", temps), 0.0);
+        assert_eq!(eval("t1 < 0 || t4 == 2.5", temps), 1.0);
+        assert_eq!(eval("(t1 > t2) * 5", temps), 5.0);
+        assert!(eval("t3 > 0 || t1 > 0", temps).is_nan());
+    }
+
+    #[test]
+    fn test_parse() {
+        for bad in [
+            "",
+            "t5",
+            "t1 +",
+            "t1 t2",
+            "(t1",
+            "sum(t1)",
+            "abs(t1, t2)",
+            "mean(t4..t1)",
+            "t1 $ 2",
+        ] {
+            assert!(bad.parse::<Expr>().is_err(), "{bad}");
+        }
+        let derived: DerivedChannel = "delta = t1 - t2".parse().unwrap();
+        assert_eq!(derived.name, "delta");
+        assert_eq!(derived.to_string(), "delta = t1 - t2");
+        assert!("1x = t1".parse::<DerivedChannel>().is_err());
+        assert!("t1 - t2".parse::<DerivedChannel>().is_err());
+    }
+}
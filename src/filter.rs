@@ -0,0 +1,189 @@
+use std::fmt;
+
+use crate::Reading;
+use crate::thermocouple::ThermocoupleType;
+
+/// A step that persists for this many samples in a row is taken as
+/// real (e.g. a probe moved into an oven) rather than rejected forever.
+const MAX_CONSECUTIVE_REJECTIONS: u32 = 3;
+
+/// Why a sample was rejected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RejectReason {
+    /// Outside the configured absolute range.
+    OutOfRange,
+    /// Too far from the channel's previous accepted sample.
+    Step,
+    /// Outside the range the channel's thermocouple type is defined
+    /// for.
+    OutsideType,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::OutOfRange => "out of range",
+            Self::Step => "implausible step",
+            Self::OutsideType => "outside the thermocouple type's range",
+        })
+    }
+}
+
+/// A sample removed by [`PlausibilityFilter`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Rejection {
+    /// Zero-based channel index.
+    pub channel: usize,
+    /// The rejected temperature.
+    pub temp_c: f32,
+    pub reason: RejectReason,
+}
+
+/// Replaces physically implausible current temperatures with NaN, so a
+/// corrupted value reaches neither logs nor alarms.
+#[derive(Debug, Clone, Default)]
+pub struct PlausibilityFilter {
+    min_c: Option<f32>,
+    max_c: Option<f32>,
+    max_step_c: Option<f32>,
+    types: [Option<ThermocoupleType>; 4],
+    last_accepted: [Option<f32>; 4],
+    consecutive_rejections: [u32; 4],
+}
+
+impl PlausibilityFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects temperatures outside `min_c..=max_c`.
+    pub fn with_range(mut self, min_c: Option<f32>, max_c: Option<f32>) -> Self {
+        self.min_c = min_c;
+        self.max_c = max_c;
+        self
+    }
+
+    /// Rejects a change of more than `max_step_c` from a channel's
+    /// previous accepted sample, unless it persists for three samples.
+    pub fn with_max_step(mut self, max_step_c: f32) -> Self {
+        self.max_step_c = Some(max_step_c);
+        self
+    }
+
+    /// Rejects temperatures on `channel` (zero-based) that a
+    /// thermocouple of type `kind` cannot produce.
+    pub fn with_thermocouple(mut self, channel: usize, kind: ThermocoupleType) -> Self {
+        self.types[channel] = Some(kind);
+        self
+    }
+
+    /// Filters `reading` in place, counting the channels it blanks in
+    /// its [`Quality`](crate::Quality), and returns what was rejected.
+    pub fn apply(&mut self, reading: &mut Reading) -> Vec<Rejection> {
+        let mut rejections = Vec::new();
+        for (channel, temp_c) in reading.current_temps_c.iter_mut().enumerate() {
+            if temp_c.is_nan() {
+                // A reconnected probe may start anywhere.
+                self.last_accepted[channel] = None;
+                self.consecutive_rejections[channel] = 0;
+                continue;
+            }
+            let out_of_range = self.min_c.is_some_and(|min| *temp_c < min)
+                || self.max_c.is_some_and(|max| *temp_c > max);
+            let step = match (self.max_step_c, self.last_accepted[channel]) {
+                (Some(max_step), Some(last)) => (*temp_c - last).abs() > max_step,
+                _ => false,
+            };
+            let outside_type = self.types[channel].is_some_and(|kind| !kind.contains(*temp_c));
+            let reason = if out_of_range {
+                Some(RejectReason::OutOfRange)
+            } else if outside_type {
+                Some(RejectReason::OutsideType)
+            } else if step && self.consecutive_rejections[channel] + 1 < MAX_CONSECUTIVE_REJECTIONS
+            {
+                Some(RejectReason::Step)
+            } else {
+                None
+            };
+            match reason {
+                Some(reason) => {
+                    rejections.push(Rejection {
+                        channel,
+                        temp_c: *temp_c,
+                        reason,
+                    });
+                    if reason == RejectReason::Step {
+                        self.consecutive_rejections[channel] += 1;
+                    }
+                    *temp_c = f32::NAN;
+                }
+                None => {
+                    self.last_accepted[channel] = Some(*temp_c);
+                    self.consecutive_rejections[channel] = 0;
+                }
+            }
+        }
+        reading.quality.filtered_channels += rejections.len() as u8;
+        rejections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn reading(t1: f32) -> Reading {
+        Reading::builder()
+            .timestamp(SystemTime::UNIX_EPOCH)
+            .current_temp_c(0, t1)
+            .meter_temp_c(25.0)
+            .build()
+    }
+
+    fn apply(filter: &mut PlausibilityFilter, t1: f32) -> (f32, Option<RejectReason>) {
+        let mut reading = reading(t1);
+        let rejections = filter.apply(&mut reading);
+        assert_eq!(
+            usize::from(reading.quality.filtered_channels),
+            rejections.len()
+        );
+        (
+            reading.current_temps_c[0],
+            rejections.first().map(|r| r.reason),
+        )
+    }
+
+    #[test]
+    fn test_range() {
+        let mut filter = PlausibilityFilter::new().with_range(Some(-200.0), Some(1372.0));
+        assert_eq!(apply(&mut filter, 20.0), (20.0, None));
+        let (temp, reason) = apply(&mut filter, 3000.0);
+        assert!(temp.is_nan());
+        assert_eq!(reason, Some(RejectReason::OutOfRange));
+    }
+
+    #[test]
+    fn test_thermocouple() {
+        let mut filter = PlausibilityFilter::new().with_thermocouple(0, ThermocoupleType::T);
+        assert_eq!(apply(&mut filter, 300.0), (300.0, None));
+        let (temp, reason) = apply(&mut filter, 450.0);
+        assert!(temp.is_nan());
+        assert_eq!(reason, Some(RejectReason::OutsideType));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut filter = PlausibilityFilter::new().with_max_step(10.0);
+        assert_eq!(apply(&mut filter, 20.0), (20.0, None));
+        assert_eq!(apply(&mut filter, 25.0).0, 25.0);
+        assert_eq!(apply(&mut filter, 500.0).1, Some(RejectReason::Step));
+        assert_eq!(apply(&mut filter, 26.0), (26.0, None));
+        // A persistent step is accepted on its third sample.
+        assert_eq!(apply(&mut filter, 200.0).1, Some(RejectReason::Step));
+        assert_eq!(apply(&mut filter, 201.0).1, Some(RejectReason::Step));
+        assert_eq!(apply(&mut filter, 202.0), (202.0, None));
+    }
+}
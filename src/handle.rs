@@ -0,0 +1,364 @@
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::Poll;
+use std::time::{Duration, Instant, SystemTime};
+
+use tokio::sync::{broadcast, oneshot, watch};
+use tokio::task::JoinHandle;
+
+use crate::Reading;
+use crate::alarm::Alarms;
+use crate::error::{Error, Result};
+use crate::event::{EventDetector, MeterEvent};
+use crate::history::History;
+use crate::meter::{ConnectionState, Meter};
+use crate::retry::RetryPolicy;
+use crate::transport::Transport;
+
+const EVENT_CAPACITY: usize = 64;
+
+/// The most recent reading from a spawned meter.
+#[derive(Debug, Clone, Copy)]
+pub struct Latest {
+    pub reading: Reading,
+    pub(crate) received: Instant,
+}
+
+impl Latest {
+    /// Time since the reading arrived.
+    pub fn age(&self) -> Duration {
+        self.received.elapsed()
+    }
+}
+
+/// A meter read continuously by a background task; see
+/// [`Meter::spawn`].
+pub struct MeterHandle<T: Transport> {
+    latest: watch::Receiver<Option<Latest>>,
+    state: watch::Receiver<ConnectionState>,
+    events: broadcast::Sender<MeterEvent>,
+    history: Arc<Mutex<History>>,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<(Meter<T>, Result<()>)>,
+}
+
+/// Opens a meter again for a supervised handle.
+type Reopen<T> = Box<dyn FnMut() -> Pin<Box<dyn Future<Output = Result<Meter<T>>> + Send>> + Send>;
+
+impl<T: Transport + Send + 'static> Meter<T> {
+    /// Moves the meter to a background task that reads it continuously,
+    /// keeping the latest reading available through the returned handle.
+    /// The task ends on the first read error; [`MeterHandle::stop`]
+    /// returns the meter and that error.
+    ///
+    /// The task and the handle's channels are tokio's, so this needs a
+    /// tokio runtime even with the `async-io` feature; under other
+    /// executors, read the meter directly.
+    pub fn spawn(self) -> MeterHandle<T> {
+        self.spawn_with_alarms(Alarms::default())
+    }
+
+    /// Like [`spawn`](Self::spawn), also raising and clearing `alarms`
+    /// as [`MeterEvent`]s.
+    pub fn spawn_with_alarms(self, alarms: Alarms) -> MeterHandle<T> {
+        self.spawn_task(alarms, RetryPolicy::never(), None)
+    }
+
+    /// Like [`spawn_with_alarms`](Self::spawn_with_alarms), but
+    /// supervised, for services that must not silently stop: when a
+    /// read fails or panics, the meter is opened again with `reopen`
+    /// (which should set it up like this one) and reading resumes, as
+    /// many times in a row as `policy` allows. Each restart is reported
+    /// as a [`MeterEvent::Restarted`], and the connection's state reads
+    /// `Reconnecting` until the meter is back. The task ends only when
+    /// the policy gives up.
+    pub fn spawn_supervised<F, Fut>(
+        self,
+        alarms: Alarms,
+        policy: RetryPolicy,
+        mut reopen: F,
+    ) -> MeterHandle<T>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Meter<T>>> + Send + 'static,
+    {
+        self.spawn_task(alarms, policy, Some(Box::new(move || Box::pin(reopen()))))
+    }
+
+    fn spawn_task(
+        mut self,
+        alarms: Alarms,
+        policy: RetryPolicy,
+        mut reopen: Option<Reopen<T>>,
+    ) -> MeterHandle<T> {
+        let (latest_tx, latest) = watch::channel(None);
+        let (state_tx, state) = watch::channel(self.state());
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let events_tx = events.clone();
+        let mut detector = EventDetector::new(alarms);
+        let history = Arc::new(Mutex::new(std::mem::take(&mut self.history)));
+        let history_tx = history.clone();
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let publish = |state: ConnectionState| {
+                state_tx.send_if_modified(|current| std::mem::replace(current, state) != state);
+            };
+            // Failures since the last reading.
+            let mut failures = 0;
+            loop {
+                let result = tokio::select! {
+                    result = catch_panic(self.read()) => result,
+                    _ = &mut stopped => return (self, Ok(())),
+                };
+                publish(self.state());
+                let error = match result {
+                    Ok(reading) => {
+                        failures = 0;
+                        for event in detector.update(&reading) {
+                            // No subscribers is not an error.
+                            let _ = events_tx.send(event);
+                        }
+                        let latest = Latest {
+                            reading,
+                            received: Instant::now(),
+                        };
+                        let mut history = history_tx.lock().unwrap_or_else(PoisonError::into_inner);
+                        if history.is_enabled() {
+                            history.push(latest);
+                        }
+                        drop(history);
+                        latest_tx.send_replace(Some(latest));
+                        continue;
+                    }
+                    Err(e) => e,
+                };
+                failures += 1;
+                let Some(reopen) = reopen.as_mut().filter(|_| policy.allows(failures)) else {
+                    return (self, Err(error));
+                };
+                tracing::warn!("reading the meter failed: {error}; reopening it");
+                let _ = events_tx.send(MeterEvent::Restarted {
+                    error: error.to_string(),
+                    attempt: failures,
+                    timestamp: SystemTime::now(),
+                });
+                publish(ConnectionState::Reconnecting);
+                loop {
+                    let delay = policy.delay(failures);
+                    let reopened = tokio::select! {
+                        reopened = async {
+                            tokio::time::sleep(delay).await;
+                            reopen().await
+                        } => reopened,
+                        _ = &mut stopped => return (self, Ok(())),
+                    };
+                    match reopened {
+                        Ok(meter) => {
+                            // The old transport has failed; closing it
+                            // only releases what it holds.
+                            let _ = std::mem::replace(&mut self, meter).close().await;
+                            break;
+                        }
+                        Err(e) if policy.allows(failures + 1) => {
+                            failures += 1;
+                            tracing::debug!("reopening the meter failed: {e}");
+                        }
+                        Err(e) => {
+                            publish(ConnectionState::Disconnected);
+                            return (self, Err(e));
+                        }
+                    }
+                }
+                publish(self.state());
+            }
+        });
+        MeterHandle {
+            latest,
+            state,
+            events,
+            history,
+            stop,
+            task,
+        }
+    }
+}
+
+/// Runs a read, turning a panic while polling it into an error so that
+/// a supervised task can recover.
+async fn catch_panic(read: impl Future<Output = Result<Reading>>) -> Result<Reading> {
+    let mut read = std::pin::pin!(read);
+    std::future::poll_fn(|cx| {
+        std::panic::catch_unwind(AssertUnwindSafe(|| read.as_mut().poll(cx))).unwrap_or_else(
+            |panic| {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                Poll::Ready(Err(Error::Panicked(message)))
+            },
+        )
+    })
+    .await
+}
+
+impl<T: Transport> MeterHandle<T> {
+    /// Returns the most recent reading without waiting, or `None` if
+    /// none has arrived yet.
+    pub fn latest(&self) -> Option<Latest> {
+        *self.latest.borrow()
+    }
+
+    /// Returns a receiver to await new readings on. Its `changed`
+    /// fails once the background task has ended.
+    pub fn subscribe(&self) -> watch::Receiver<Option<Latest>> {
+        self.latest.clone()
+    }
+
+    /// The connection's state as of the background task's last read.
+    pub fn state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Returns a receiver to await changes of the connection's state
+    /// on, e.g. `Syncing` to `Streaming`.
+    pub fn state_changes(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
+    }
+
+    /// Returns a receiver for the events of readings from now on. A
+    /// receiver that falls more than 64 events behind loses the oldest
+    /// ones and is told how many.
+    pub fn events(&self) -> broadcast::Receiver<MeterEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns a copy of the readings kept by
+    /// [`Meter::with_history`], oldest first; empty without it.
+    pub fn history(&self) -> History {
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        // Readings age out even while none arrive.
+        history.prune();
+        history.clone()
+    }
+
+    /// Returns true once the background task has ended on a read error.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stops the background task and returns the meter, for
+    /// [`close`](Meter::close) or [`detach`](Meter::detach), together
+    /// with the error that ended reading early, if any.
+    pub async fn stop(self) -> (Meter<T>, Result<()>) {
+        let _ = self.stop.send(());
+        match self.task.await {
+            Ok(stopped) => stopped,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::History;
+    use crate::alarm::Alarms;
+    use crate::error::{Error, Result};
+    use crate::event::MeterEvent;
+    use crate::meter::ConnectionState;
+    use crate::meter::Meter;
+    use crate::meter::tests::{meter_with, valid_frame};
+    use crate::retry::RetryPolicy;
+    use crate::transport::Transport;
+
+    #[tokio::test]
+    async fn test_latest() {
+        let handle = meter_with(vec![valid_frame().to_vec()]).spawn();
+        let mut latest = handle.subscribe();
+        latest.wait_for(Option::is_some).await.ok();
+        assert!(handle.latest().is_some_and(|l| l.age().as_secs() < 5));
+        let (_meter, result) = handle.stop().await;
+        assert!(matches!(result, Err(Error::Disconnected(_))));
+    }
+
+    #[tokio::test]
+    async fn test_state_changes() {
+        let handle = meter_with(vec![valid_frame().to_vec()]).spawn();
+        let mut states = handle.state_changes();
+        assert_eq!(*states.borrow_and_update(), ConnectionState::Syncing);
+        states
+            .wait_for(|&state| state == ConnectionState::Disconnected)
+            .await
+            .unwrap();
+        assert_eq!(handle.state(), ConnectionState::Disconnected);
+        let (_meter, _) = handle.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_supervised_restarts() {
+        let policy = RetryPolicy::new().with_max_attempts(3);
+        let mut reopened = 0;
+        let handle = meter_with(vec![valid_frame().to_vec()]).spawn_supervised(
+            Alarms::default(),
+            policy,
+            move || {
+                reopened += 1;
+                // One meter with a reading, then one that cannot open.
+                let result = match reopened {
+                    1 => Ok(meter_with(vec![valid_frame().to_vec()])),
+                    _ => Err(Error::Disconnected("unplugged")),
+                };
+                std::future::ready(result)
+            },
+        );
+        let mut events = handle.events();
+        // Fails once the task has ended.
+        let mut latest = handle.subscribe();
+        while latest.changed().await.is_ok() {}
+        let mut attempts = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let MeterEvent::Restarted { attempt, .. } = event {
+                attempts.push(attempt);
+            }
+        }
+        // The reading from the reopened meter resets the count.
+        assert_eq!(attempts, [1, 1]);
+        assert_eq!(handle.state(), ConnectionState::Disconnected);
+        let (_meter, result) = handle.stop().await;
+        assert!(matches!(result, Err(Error::Disconnected("unplugged"))));
+    }
+
+    #[tokio::test]
+    async fn test_supervised_catches_panics() {
+        struct PanickingTransport;
+
+        impl Transport for PanickingTransport {
+            async fn recv(&mut self) -> Result<Vec<u8>> {
+                panic!("transport bug")
+            }
+        }
+
+        let handle = Meter::new(PanickingTransport).spawn_supervised(
+            Alarms::default(),
+            RetryPolicy::never(),
+            || std::future::ready(Ok(Meter::new(PanickingTransport))),
+        );
+        while !handle.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        let (_meter, result) = handle.stop().await;
+        assert!(matches!(result, Err(Error::Panicked(message)) if message == "transport bug"));
+    }
+
+    #[tokio::test]
+    async fn test_history() {
+        let frames = vec![valid_frame().to_vec(); 3];
+        let handle = meter_with(frames).with_history(History::last(2)).spawn();
+        while !handle.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(handle.history().len(), 2);
+        let (_meter, _) = handle.stop().await;
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::handle::Latest;
+
+/// The most recent readings of a spawned meter, oldest first, bounded
+/// by count and/or age; see [`Meter::with_history`].
+///
+/// [`Meter::with_history`]: crate::Meter::with_history
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    readings: VecDeque<Latest>,
+    max_len: Option<usize>,
+    max_age: Option<Duration>,
+}
+
+impl History {
+    /// Keeps the last `n` readings.
+    pub fn last(n: usize) -> Self {
+        Self {
+            max_len: Some(n),
+            ..Self::default()
+        }
+    }
+
+    /// Keeps the readings of the last `age` (about 3 per second).
+    pub fn spanning(age: Duration) -> Self {
+        Self {
+            max_age: Some(age),
+            ..Self::default()
+        }
+    }
+
+    /// Also drops readings older than `age`.
+    pub fn with_max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Whether this keeps any readings at all.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_len.is_some() || self.max_age.is_some()
+    }
+
+    pub(crate) fn push(&mut self, latest: Latest) {
+        self.push_at(latest, Instant::now());
+    }
+
+    fn push_at(&mut self, latest: Latest, now: Instant) {
+        self.readings.push_back(latest);
+        if let Some(max_len) = self.max_len {
+            while self.readings.len() > max_len {
+                self.readings.pop_front();
+            }
+        }
+        self.prune_at(now);
+    }
+
+    /// Drops readings past the maximum age.
+    pub(crate) fn prune(&mut self) {
+        self.prune_at(Instant::now());
+    }
+
+    fn prune_at(&mut self, now: Instant) {
+        if let Some(max_age) = self.max_age {
+            while self.readings.front().is_some_and(|l| age(l, now) > max_age) {
+                self.readings.pop_front();
+            }
+        }
+    }
+
+    /// The readings kept, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Latest> {
+        self.readings.iter()
+    }
+
+    /// The readings of the last `age`, oldest first.
+    pub fn since(&self, age: Duration) -> impl Iterator<Item = &Latest> {
+        self.since_at(age, Instant::now())
+    }
+
+    fn since_at(&self, max_age: Duration, now: Instant) -> impl Iterator<Item = &Latest> {
+        let start = self.readings.partition_point(|l| age(l, now) > max_age);
+        self.readings.range(start..)
+    }
+
+    /// The readings taken from `from` up to but not including `to`,
+    /// oldest first.
+    pub fn range(&self, from: SystemTime, to: SystemTime) -> impl Iterator<Item = &Latest> {
+        // Filtered rather than bisected: the wall clock may have been
+        // set back while readings were kept.
+        self.readings
+            .iter()
+            .filter(move |l| (from..to).contains(&l.reading.timestamp))
+    }
+
+    /// Like [`range`](Self::range), but at most one reading per
+    /// `every`: each one kept is at least `every` after the one before.
+    pub fn range_every(
+        &self,
+        from: SystemTime,
+        to: SystemTime,
+        every: Duration,
+    ) -> impl Iterator<Item = &Latest> {
+        let mut next = from;
+        self.range(from, to).filter(move |l| {
+            let due = l.reading.timestamp >= next;
+            if due {
+                next = l.reading.timestamp + every;
+            }
+            due
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+}
+
+/// How long before `now` `latest` arrived.
+fn age(latest: &Latest, now: Instant) -> Duration {
+    now.saturating_duration_since(latest.received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reading;
+
+    fn received(at: Instant) -> Latest {
+        Latest {
+            reading: Reading::builder().build(),
+            received: at,
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut history = History::last(2);
+        for _ in 0..3 {
+            history.push(received(Instant::now()));
+        }
+        assert_eq!(history.len(), 2);
+
+        // Readings 90, 30 and 10 s old, counted forward from a fixed
+        // base rather than back from now, which a freshly booted host
+        // may not reach.
+        let base = Instant::now();
+        let now = base + Duration::from_secs(90);
+        let mut history = History::spanning(Duration::from_secs(60));
+        for secs in [0, 60, 80] {
+            history.push_at(received(base + Duration::from_secs(secs)), now);
+        }
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.since_at(Duration::from_secs(20), now).count(), 1);
+    }
+
+    #[test]
+    fn test_range() {
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let mut history = History::last(10);
+        for secs in 0..10 {
+            let mut latest = received(Instant::now());
+            latest.reading.timestamp = at(secs);
+            history.push(latest);
+        }
+        let timestamps = |readings: Vec<&Latest>| {
+            readings
+                .iter()
+                .map(|l| l.reading.timestamp)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            timestamps(history.range(at(2), at(5)).collect()),
+            [at(2), at(3), at(4)]
+        );
+        assert_eq!(
+            timestamps(
+                history
+                    .range_every(at(1), at(9), Duration::from_secs(3))
+                    .collect()
+            ),
+            [at(1), at(4), at(7)]
+        );
+    }
+}
@@ -1,20 +1,59 @@
-mod decoder;
+mod alarm;
 mod error;
+mod event;
+mod expr;
+mod filter;
+#[cfg(feature = "tokio-rt")]
+mod handle;
+#[cfg(feature = "tokio-rt")]
+mod history;
 mod meter;
-mod reading;
+mod poll;
+pub mod prelude;
+mod retry;
+mod rt;
+mod sink;
+#[cfg(feature = "tokio-rt")]
+mod source;
+mod thermocouple;
 pub mod transport;
-mod utils;
 
-pub use decoder::FrameDecoder;
+pub use alarm::{AlarmEvent, Alarms, Condition, Direction, Rule, Threshold};
 pub use error::{Error, Result};
-pub use meter::Meter;
-pub use reading::{HoldType, Reading};
+pub use event::{EventDetector, MeterEvent};
+pub use expr::{DerivedChannel, Expr};
+pub use filter::{PlausibilityFilter, RejectReason, Rejection};
+#[cfg(feature = "tokio-rt")]
+pub use handle::{Latest, MeterHandle};
+#[cfg(feature = "tokio-rt")]
+pub use history::History;
+pub use meter::{ConnectionState, LinkStats, Meter};
+pub use poll::PollMeter;
+pub use retry::RetryPolicy;
+pub use sink::{Annotation, Heartbeat, Sink, Tee};
+#[cfg(feature = "tokio-rt")]
+pub use source::{DynMeter, MeterSource, NextReading, ReadingSource};
+pub use thermocouple::ThermocoupleType;
+#[cfg(all(feature = "serial-async-io", unix))]
+pub use transport::AsyncIoSerialTransport;
 #[cfg(feature = "bluebus")]
 pub use transport::BluebusTransport;
 #[cfg(feature = "btleplug")]
 pub use transport::BtleplugTransport;
 #[cfg(feature = "serial")]
 pub use transport::SerialTransport;
-pub use transport::Transport;
 #[cfg(any(feature = "bluebus", feature = "btleplug"))]
 pub use transport::{BleTransport, DiscoveredMeter};
+#[cfg(any(feature = "serial", feature = "serial-async-io"))]
+pub use transport::{Parity, SerialConfig, StopBits};
+pub use transport::{StreamTransport, Transport};
+/// The `uom` version the typed temperature accessors use.
+#[cfg(feature = "uom")]
+pub use ut325f_protocol::uom;
+#[cfg(feature = "embedded-io")]
+pub use ut325f_protocol::{EmbeddedIoReader, ReadError};
+pub use ut325f_protocol::{Event as ProtocolEvent, Mode, Protocol};
+pub use ut325f_protocol::{
+    FrameDecoder, FrameError, HoldType, Quality, Reading, ReadingBuilder, Validity, round_temp,
+    to_millidegrees,
+};
@@ -1,38 +1,290 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::decoder::FrameDecoder;
+use crate::Reading;
 use crate::error::{Error, Result};
-use crate::reading::Reading;
+#[cfg(feature = "tokio-rt")]
+use crate::history::History;
+use crate::retry::RetryPolicy;
 use crate::transport::Transport;
+use crate::{Mode, Protocol, ProtocolEvent};
 
 const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// Counters describing the health of a meter's link since it was
+/// opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LinkStats {
+    /// Readings returned by [`Meter::read`].
+    pub readings: u64,
+    /// Bytes skipped while looking for a valid frame.
+    pub discarded_bytes: u64,
+    /// Candidate frames rejected as corrupt.
+    pub rejected_frames: u64,
+    /// Reads that timed out.
+    pub timeouts: u64,
+    /// Times the decoder found its way to a valid frame: once at the
+    /// start and again after each run of discarded bytes.
+    pub sync_acquisitions: u64,
+    /// Total time spent acquiring sync, from the first read (or the
+    /// first discarded byte) to the valid frame that ended it.
+    pub sync_acquisition_time: Duration,
+}
+
+impl LinkStats {
+    /// Mean time to acquire sync; `None` before the first valid frame.
+    pub fn mean_sync_acquisition_time(&self) -> Option<Duration> {
+        u32::try_from(self.sync_acquisitions)
+            .ok()
+            .filter(|&n| n > 0)
+            .map(|n| self.sync_acquisition_time / n)
+    }
+}
+
+/// Sums the counters of several links, e.g. of a meter reopened for
+/// each sampling window.
+impl std::ops::AddAssign for LinkStats {
+    fn add_assign(&mut self, other: Self) {
+        self.readings += other.readings;
+        self.discarded_bytes += other.discarded_bytes;
+        self.rejected_frames += other.rejected_frames;
+        self.timeouts += other.timeouts;
+        self.sync_acquisitions += other.sync_acquisitions;
+        self.sync_acquisition_time += other.sync_acquisition_time;
+    }
+}
+
+/// What a meter's connection is doing, for a GUI or health check to
+/// show rather than infer from errors. A [`Meter`] reports the states
+/// it can see itself; `Opening` and `Reconnecting` are for the code
+/// that opens it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConnectionState {
+    /// Not connected: the transport failed or was closed.
+    Disconnected,
+    /// Being opened for the first time.
+    Opening,
+    /// Connected, but waiting for a valid frame: at first, after line
+    /// noise or a corrupt frame, or while the meter is silent.
+    Syncing,
+    /// Delivering readings.
+    Streaming,
+    /// Being opened again after the connection was lost.
+    Reconnecting,
+}
+
+impl std::fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Disconnected => "disconnected",
+            Self::Opening => "opening",
+            Self::Syncing => "syncing",
+            Self::Streaming => "streaming",
+            Self::Reconnecting => "reconnecting",
+        })
+    }
+}
+
 /// A UT325F meter on some transport.
 ///
 /// The meter streams readings unsolicited (roughly 3 per second); `read`
 /// returns the next one.
 pub struct Meter<T: Transport> {
     transport: T,
-    decoder: FrameDecoder,
+    protocol: Protocol,
     read_timeout: Duration,
+    retry: RetryPolicy,
+    strict: bool,
+    tolerant: bool,
+    state: ConnectionState,
+    stats: LinkStats,
+    /// When the decoder lost (or had not yet found) sync; `None` while
+    /// in sync.
+    unsynced_since: Option<Instant>,
+    /// The decoder's discarded byte count at the last reading.
+    discarded_at_reading: u64,
+    /// When the last reading arrived, and how long after the one
+    /// before it.
+    last_received: Option<Instant>,
+    last_interval: Option<Duration>,
+    last_frame: Option<[u8; Reading::N_BYTES]>,
+    /// Handed to the handle by [`spawn`](Self::spawn).
+    #[cfg(feature = "tokio-rt")]
+    pub(crate) history: History,
 }
 
 impl<T: Transport> Meter<T> {
     pub fn new(transport: T) -> Self {
         Meter {
             transport,
-            decoder: FrameDecoder::new(),
+            protocol: Protocol::new(),
             read_timeout: DEFAULT_READ_TIMEOUT,
+            retry: RetryPolicy::never(),
+            strict: false,
+            tolerant: false,
+            state: ConnectionState::Syncing,
+            stats: LinkStats::default(),
+            unsynced_since: None,
+            discarded_at_reading: 0,
+            last_received: None,
+            last_interval: None,
+            last_frame: None,
+            #[cfg(feature = "tokio-rt")]
+            history: History::default(),
         }
     }
 
+    /// In strict mode, `read` fails on a corrupted frame or one the
+    /// meter is not known to send (see [`Reading::parse_strict`])
+    /// instead of skipping it. Reading again carries on with the next
+    /// frame.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self.set_mode()
+    }
+
+    /// In tolerant mode, `read` keeps a frame that fails its checksum
+    /// or holds values the meter is not known to send, noting the
+    /// failed checks in the reading's `validity` (see
+    /// [`Reading::parse_tolerant`]), instead of skipping it. Ignored in
+    /// strict mode.
+    pub fn with_tolerant(mut self, tolerant: bool) -> Self {
+        self.tolerant = tolerant;
+        self.set_mode()
+    }
+
+    fn set_mode(mut self) -> Self {
+        let mode = match (self.strict, self.tolerant) {
+            (true, _) => Mode::Strict,
+            (false, true) => Mode::Tolerant,
+            (false, false) => Mode::Lenient,
+        };
+        self.protocol = std::mem::take(&mut self.protocol).with_mode(mode);
+        self
+    }
+
+    /// Retries a read that times out as `policy` says before `read`
+    /// gives up with [`Error::ReadTimeout`]. By default it gives up at
+    /// the first timeout.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Once [spawned](Self::spawn), keeps the readings in `history`,
+    /// within its bounds, for [`MeterHandle::history`]. Unused by a
+    /// meter read directly.
+    ///
+    /// [`MeterHandle::history`]: crate::MeterHandle::history
+    #[cfg(feature = "tokio-rt")]
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Reads frames starting with `sync` instead of [`Reading::SYNC`],
+    /// for firmware that sends another header; see
+    /// [`FrameDecoder::with_sync`](crate::FrameDecoder::with_sync).
+    pub fn with_sync(mut self, sync: [u8; Reading::N_SYNC_BYTES]) -> Self {
+        self.protocol = std::mem::take(&mut self.protocol).with_sync(sync);
+        self
+    }
+
+    /// Keeps the bytes skipped while looking for frames, for
+    /// [`take_discarded`](Self::take_discarded).
+    pub fn with_discarded_kept(mut self) -> Self {
+        self.protocol = std::mem::take(&mut self.protocol).with_discarded_kept();
+        self
+    }
+
+    /// Returns the bytes skipped since the last call: line noise and
+    /// rejected frames. Always empty unless the meter was built
+    /// [`with_discarded_kept`](Self::with_discarded_kept).
+    pub fn take_discarded(&mut self) -> Vec<u8> {
+        self.protocol.take_discarded()
+    }
+
+    /// The raw frame behind the reading `read` last returned, e.g. for
+    /// a hex dump.
+    pub fn last_frame(&self) -> Option<&[u8; Reading::N_BYTES]> {
+        self.last_frame.as_ref()
+    }
+
     /// Returns the next reading, skipping corrupted frames. Errors only
     /// on transport failure or when no valid frame arrives within the
-    /// read timeout.
+    /// read timeout, as often as the retry policy allows.
+    ///
+    /// Cancel safe, given a cancel-safe transport: bytes received
+    /// before the future is dropped stay buffered in the meter, so a
+    /// read abandoned in `select!` or under a timeout loses no frame and
+    /// the next read carries on where it stopped.
     pub async fn read(&mut self) -> Result<Reading> {
-        tokio::time::timeout(self.read_timeout, self.read_frame())
-            .await
-            .map_err(|_| Error::ReadTimeout)?
+        let mut failures = 0;
+        loop {
+            let result = self.read_attempt().await;
+            self.set_state(match &result {
+                Ok(_) => ConnectionState::Streaming,
+                Err(
+                    Error::ReadTimeout
+                    | Error::BadSyncHeader
+                    | Error::ChecksumMismatch
+                    | Error::InvalidHoldType(_)
+                    | Error::MalformedFrame(_)
+                    | Error::UnexpectedUnknownBytes(_)
+                    | Error::InvalidFrame(_),
+                ) => ConnectionState::Syncing,
+                Err(_) => ConnectionState::Disconnected,
+            });
+            match result {
+                Err(Error::ReadTimeout) if self.retry.allows(failures + 1) => {
+                    failures += 1;
+                    let delay = self.retry.delay(failures);
+                    tracing::debug!("retrying the read in {delay:.1?}");
+                    crate::rt::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn read_attempt(&mut self) -> Result<Reading> {
+        if self.stats.sync_acquisitions == 0 && self.unsynced_since.is_none() {
+            self.unsynced_since = Some(Instant::now());
+        }
+        let read_timeout = self.read_timeout;
+        match crate::rt::timeout(read_timeout, self.read_frame()).await {
+            Some(result) => result.map(|reading| self.assess(reading)),
+            None => {
+                tracing::debug!("no valid frame within {read_timeout:?}");
+                self.stats.timeouts += 1;
+                Err(Error::ReadTimeout)
+            }
+        }
+    }
+
+    /// The connection's state as of the last read: `Syncing` until the
+    /// first reading, then `Streaming`, back to `Syncing` when reads
+    /// stop yielding frames, and `Disconnected` once the transport
+    /// fails.
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    fn set_state(&mut self, state: ConnectionState) {
+        if state != self.state {
+            tracing::debug!("connection {} -> {state}", self.state);
+            self.state = state;
+        }
+    }
+
+    /// Returns the link counters accumulated since the meter was opened.
+    pub fn link_stats(&self) -> LinkStats {
+        LinkStats {
+            discarded_bytes: self.protocol.decoder().discarded_bytes(),
+            rejected_frames: self.protocol.decoder().rejected_frames(),
+            ..self.stats
+        }
     }
 
     /// Gracefully shuts down the transport, disconnecting a BLE
@@ -55,16 +307,55 @@ impl<T: Transport> Meter<T> {
 
     async fn read_frame(&mut self) -> Result<Reading> {
         loop {
-            // The decoder yields only checksum-valid frames; parse can
-            // still reject one (e.g. an unknown hold type) — skip it.
-            if let Some(frame) = self.decoder.next_frame() {
-                if let Ok(reading) = Reading::parse(&frame) {
-                    return Ok(reading);
+            while let Some(event) = self.protocol.next_event() {
+                match event {
+                    ProtocolEvent::Reading { reading, frame } => {
+                        if !reading.validity.is_valid() {
+                            tracing::debug!("kept frame failing {}", reading.validity);
+                        }
+                        self.last_frame = Some(frame);
+                        self.synced();
+                        return Ok(reading);
+                    }
+                    ProtocolEvent::Discarded(n) => {
+                        tracing::debug!("discarded {n} bytes looking for a frame");
+                        self.unsynced_since.get_or_insert_with(Instant::now);
+                    }
+                    ProtocolEvent::Skipped(e) => tracing::debug!("skipped frame: {e}"),
+                    ProtocolEvent::Invalid(e) => return Err(Error::InvalidFrame(Box::new(e))),
+                    _ => {}
                 }
-                continue;
             }
             let chunk = self.transport.recv().await?;
-            self.decoder.push(&chunk);
+            tracing::trace!("received {chunk:02x?}");
+            self.protocol.push(&chunk);
+        }
+    }
+
+    /// Fills in the resync distance and jitter of a reading's
+    /// [`Quality`](crate::Quality).
+    fn assess(&mut self, mut reading: Reading) -> Reading {
+        let discarded_bytes = self.protocol.decoder().discarded_bytes();
+        reading.quality.resync_bytes = discarded_bytes - self.discarded_at_reading;
+        self.discarded_at_reading = discarded_bytes;
+        let now = Instant::now();
+        let interval = self.last_received.map(|last| now - last);
+        if let (Some(interval), Some(last_interval)) = (interval, self.last_interval) {
+            reading.quality.jitter_s = Some(interval.abs_diff(last_interval).as_secs_f32());
+        }
+        self.last_received = Some(now);
+        self.last_interval = interval;
+        reading
+    }
+
+    /// Counts a reading, ending any sync acquisition in progress.
+    fn synced(&mut self) {
+        self.stats.readings += 1;
+        if let Some(since) = self.unsynced_since.take() {
+            let elapsed = since.elapsed();
+            tracing::debug!("acquired sync in {elapsed:?}");
+            self.stats.sync_acquisitions += 1;
+            self.stats.sync_acquisition_time += elapsed;
         }
     }
 }
@@ -77,6 +368,18 @@ impl Meter<crate::transport::SerialTransport> {
             crate::transport::SerialTransport::open(port).await?,
         ))
     }
+
+    /// Opens the meter on a USB serial port, reads one reading and
+    /// closes the port again, for a quick check that should not hold
+    /// it open.
+    pub async fn read_once(port: &str) -> Result<Reading> {
+        let mut meter = Self::open_serial(port).await?;
+        let result = meter.read().await;
+        let closed = meter.close().await;
+        let reading = result?;
+        closed?;
+        Ok(reading)
+    }
 }
 
 #[cfg(any(feature = "bluebus", feature = "btleplug"))]
@@ -101,12 +404,12 @@ impl Meter<crate::transport::BleTransport> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use crate::reading::tests::fix_checksum;
+    use crate::Validity;
     use std::collections::VecDeque;
 
-    struct ChunkTransport {
+    pub(crate) struct ChunkTransport {
         chunks: VecDeque<Vec<u8>>,
     }
 
@@ -118,16 +421,16 @@ mod tests {
         }
     }
 
-    fn meter_with(chunks: Vec<Vec<u8>>) -> Meter<ChunkTransport> {
+    pub(crate) fn meter_with(chunks: Vec<Vec<u8>>) -> Meter<ChunkTransport> {
         Meter::new(ChunkTransport {
             chunks: chunks.into(),
         })
     }
 
-    fn valid_frame() -> [u8; Reading::N_BYTES] {
+    pub(crate) fn valid_frame() -> [u8; Reading::N_BYTES] {
         let mut frame = [0u8; Reading::N_BYTES];
         frame[..Reading::N_SYNC_BYTES].copy_from_slice(&Reading::SYNC);
-        fix_checksum(&mut frame);
+        Reading::write_checksum(&mut frame);
         frame
     }
 
@@ -136,7 +439,7 @@ mod tests {
         let frame = valid_frame();
         let mut meter = meter_with(vec![frame[..30].to_vec(), frame[30..].to_vec()]);
         let reading = meter.read().await?;
-        assert_eq!(reading.hold_type, crate::reading::HoldType::Current);
+        assert_eq!(reading.hold_type, crate::HoldType::Current);
         Ok(())
     }
 
@@ -155,9 +458,75 @@ mod tests {
         // to the following good frame rather than fail.
         let mut bad_hold = valid_frame();
         bad_hold[Reading::N_BYTES - 3] = 0xff;
-        fix_checksum(&mut bad_hold);
+        Reading::write_checksum(&mut bad_hold);
         let mut meter = meter_with(vec![bad_hold.to_vec(), valid_frame().to_vec()]);
         assert!(meter.read().await.is_ok());
+        let stats = meter.link_stats();
+        assert_eq!(stats.readings, 1);
+        assert_eq!(stats.rejected_frames, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_strict_read_reports_corrupt_frame() -> Result<()> {
+        let mut corrupted = valid_frame();
+        corrupted[10] ^= 0x01;
+        let mut meter =
+            meter_with(vec![corrupted.to_vec(), valid_frame().to_vec()]).with_strict(true);
+        let Err(Error::InvalidFrame(error)) = meter.read().await else {
+            panic!("expected an invalid frame");
+        };
+        assert_eq!(error.error, ut325f_protocol::Error::ChecksumMismatch);
+        assert_eq!(error.field(), "checksum");
+        assert!(meter.read().await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tolerant_read_flags_corrupt_frame() -> Result<()> {
+        let mut corrupted = valid_frame();
+        corrupted[10] ^= 0x01;
+        let mut meter =
+            meter_with(vec![corrupted.to_vec(), valid_frame().to_vec()]).with_tolerant(true);
+        let reading = meter.read().await?;
+        assert_eq!(reading.validity, Validity::CHECKSUM);
+        assert!(!reading.quality.checksum_ok);
+        assert!(meter.read().await?.validity.is_valid());
+        assert_eq!(meter.link_stats().rejected_frames, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_acquisitions() -> Result<()> {
+        let mut meter = meter_with(vec![
+            valid_frame().to_vec(),
+            valid_frame().to_vec(),
+            vec![0x00; 7],
+            valid_frame().to_vec(),
+        ]);
+        assert_eq!(meter.link_stats().mean_sync_acquisition_time(), None);
+        let mut readings = Vec::new();
+        for _ in 0..3 {
+            readings.push(meter.read().await?);
+        }
+        let resync_bytes = readings.iter().map(|r| r.quality.resync_bytes);
+        assert_eq!(resync_bytes.collect::<Vec<_>>(), [0, 0, 7]);
+        assert_eq!(readings[1].quality.jitter_s, None);
+        assert!(readings[2].quality.jitter_s.is_some());
+        let stats = meter.link_stats();
+        assert_eq!(stats.sync_acquisitions, 2);
+        assert!(stats.mean_sync_acquisition_time().is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_raw_bytes() -> Result<()> {
+        let frame = valid_frame();
+        let mut meter = meter_with(vec![vec![0x01, 0x02], frame.to_vec()]).with_discarded_kept();
+        assert_eq!(meter.last_frame(), None);
+        meter.read().await?;
+        assert_eq!(meter.last_frame(), Some(&frame));
+        assert_eq!(meter.take_discarded(), [0x01, 0x02]);
         Ok(())
     }
 
@@ -166,4 +535,101 @@ mod tests {
         let mut meter = meter_with(vec![]);
         assert!(meter.read().await.is_err());
     }
+
+    #[tokio::test]
+    async fn test_state() {
+        let mut corrupt = valid_frame();
+        corrupt[20] ^= 0x01;
+        let mut meter =
+            meter_with(vec![valid_frame().to_vec(), corrupt.to_vec()]).with_strict(true);
+        assert_eq!(meter.state(), ConnectionState::Syncing);
+        meter.read().await.unwrap();
+        assert_eq!(meter.state(), ConnectionState::Streaming);
+        assert!(meter.read().await.is_err());
+        assert_eq!(meter.state(), ConnectionState::Syncing);
+        assert!(matches!(meter.read().await, Err(Error::Disconnected(_))));
+        assert_eq!(meter.state(), ConnectionState::Disconnected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_reads_lose_no_frame() -> Result<()> {
+        /// Delivers a byte stream a few bytes at a time, slowly.
+        struct TrickleTransport(VecDeque<u8>);
+
+        impl Transport for TrickleTransport {
+            async fn recv(&mut self) -> Result<Vec<u8>> {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let n = self.0.len().min(7);
+                match n {
+                    0 => Err(Error::Disconnected("test transport closed")),
+                    n => Ok(self.0.drain(..n).collect()),
+                }
+            }
+        }
+
+        let frames: Vec<u8> = (0..5u8)
+            .flat_map(|i| {
+                Reading::builder()
+                    .current_temp_c(0, f32::from(i))
+                    .build()
+                    .to_frame()
+            })
+            .collect();
+        let mut meter = Meter::new(TrickleTransport(frames.into()));
+        let mut temps = Vec::new();
+        // Each read is abandoned several times mid-frame.
+        loop {
+            let read = tokio::time::timeout(Duration::from_millis(250), meter.read());
+            match read.await {
+                Ok(Ok(reading)) => temps.push(reading.current_temps_c[0]),
+                Ok(Err(_)) => break,
+                Err(_) => {}
+            }
+        }
+        assert_eq!(temps, [0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(meter.link_stats().discarded_bytes, 0);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_retries_timeouts() -> Result<()> {
+        /// Silent until the third time it is asked.
+        struct WakingTransport(u32);
+
+        impl Transport for WakingTransport {
+            async fn recv(&mut self) -> Result<Vec<u8>> {
+                self.0 += 1;
+                if self.0 < 3 {
+                    std::future::pending::<()>().await;
+                }
+                Ok(valid_frame().to_vec())
+            }
+        }
+
+        let policy = RetryPolicy::new().with_max_attempts(3);
+        let mut meter = Meter::new(WakingTransport(0)).with_retry(policy);
+        meter.read().await?;
+        assert_eq!(meter.link_stats().timeouts, 2);
+        let mut meter = Meter::new(WakingTransport(0)).with_retry(policy.with_max_attempts(2));
+        assert!(matches!(meter.read().await, Err(Error::ReadTimeout)));
+        Ok(())
+    }
+
+    #[cfg(feature = "async-io")]
+    #[test]
+    fn test_read_timeout_without_tokio() {
+        struct SilentTransport;
+
+        impl Transport for SilentTransport {
+            async fn recv(&mut self) -> Result<Vec<u8>> {
+                std::future::pending().await
+            }
+        }
+
+        let mut meter = Meter::new(SilentTransport);
+        meter.read_timeout = Duration::from_millis(10);
+        let result = futures_lite::future::block_on(meter.read());
+        assert!(matches!(result, Err(Error::ReadTimeout)));
+        assert_eq!(meter.link_stats().timeouts, 1);
+    }
 }
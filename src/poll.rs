@@ -0,0 +1,107 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use crate::Reading;
+use crate::error::Result;
+use crate::meter::Meter;
+use crate::transport::Transport;
+
+/// A read in progress, which owns the meter until it completes.
+type PendingRead<T> = Pin<Box<dyn Future<Output = (Meter<T>, Result<Reading>)> + Send>>;
+
+/// A meter read by polling rather than awaiting, for event loops that
+/// drive futures by hand (e.g. around mio) instead of through an
+/// executor; see [`Meter::into_poll`].
+pub struct PollMeter<T: Transport> {
+    meter: Option<Meter<T>>,
+    pending: Option<PendingRead<T>>,
+}
+
+impl<T: Transport + Send + 'static> Meter<T> {
+    /// Turns the meter into one read with
+    /// [`poll_next_reading`](PollMeter::poll_next_reading).
+    pub fn into_poll(self) -> PollMeter<T> {
+        PollMeter {
+            meter: Some(self),
+            pending: None,
+        }
+    }
+}
+
+impl<T: Transport + Send + 'static> PollMeter<T> {
+    /// Polls for the next reading, as [`Meter::read`] would return it,
+    /// arranging for `cx`'s waker to be woken when it may be ready.
+    /// A read left pending carries on at the next poll.
+    pub fn poll_next_reading(&mut self, cx: &mut Context<'_>) -> Poll<Result<Reading>> {
+        let meter = &mut self.meter;
+        let pending = self.pending.get_or_insert_with(|| {
+            let mut meter = meter
+                .take()
+                .expect("the meter is idle when no read is pending");
+            Box::pin(async move {
+                let result = meter.read().await;
+                (meter, result)
+            })
+        });
+        let (meter, result) = ready!(pending.as_mut().poll(cx));
+        self.pending = None;
+        self.meter = Some(meter);
+        Poll::Ready(result)
+    }
+
+    /// The meter, unless a read is pending.
+    pub fn meter(&self) -> Option<&Meter<T>> {
+        self.meter.as_ref()
+    }
+
+    /// Returns the meter, unless a read is pending: the read owns it
+    /// until it completes.
+    pub fn into_inner(self) -> Option<Meter<T>> {
+        self.meter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use crate::meter::tests::valid_frame;
+    use std::task::Waker;
+    use tokio::sync::mpsc;
+
+    struct ChannelTransport(mpsc::UnboundedReceiver<Vec<u8>>);
+
+    impl Transport for ChannelTransport {
+        async fn recv(&mut self) -> Result<Vec<u8>> {
+            self.0
+                .recv()
+                .await
+                .ok_or(Error::Disconnected("test transport closed"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_next_reading() {
+        let (bytes, received) = mpsc::unbounded_channel();
+        let mut meter = Meter::new(ChannelTransport(received)).into_poll();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(meter.poll_next_reading(&mut cx).is_pending());
+        assert!(meter.meter().is_none());
+        let frame = valid_frame();
+        bytes.send(frame[..10].to_vec()).unwrap();
+        assert!(meter.poll_next_reading(&mut cx).is_pending());
+        bytes.send(frame[10..].to_vec()).unwrap();
+        assert!(matches!(
+            meter.poll_next_reading(&mut cx),
+            Poll::Ready(Ok(_))
+        ));
+        assert_eq!(meter.meter().map(|m| m.link_stats().readings), Some(1));
+        drop(bytes);
+        assert!(matches!(
+            meter.poll_next_reading(&mut cx),
+            Poll::Ready(Err(Error::Disconnected(_)))
+        ));
+        assert!(meter.into_inner().is_some());
+    }
+}
@@ -0,0 +1,15 @@
+//! The types most programs need, for a glob import:
+//!
+//! ```
+//! use ut325f_rs::prelude::*;
+//! ```
+
+pub use crate::error::{Error, Result};
+pub use crate::event::MeterEvent;
+#[cfg(feature = "tokio-rt")]
+pub use crate::handle::{Latest, MeterHandle};
+pub use crate::meter::{ConnectionState, Meter};
+#[cfg(feature = "tokio-rt")]
+pub use crate::source::MeterSource;
+pub use crate::transport::Transport;
+pub use crate::{HoldType, Reading};
@@ -0,0 +1,173 @@
+use std::hash::{BuildHasher, RandomState};
+use std::time::Duration;
+
+use crate::error::Result;
+
+/// How often, and how patiently, to retry an operation that failed,
+/// such as opening a port or a read that timed out. The delay before
+/// each retry doubles from `base_delay` up to `max_delay`, varied by up
+/// to `jitter` of itself either way so that several clients do not
+/// retry in step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: Option<u32>,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f32,
+}
+
+/// Five attempts, from a quarter of a second apart.
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: Some(5),
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.1,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A single attempt: fail fast.
+    pub fn never() -> Self {
+        Self::new().with_max_attempts(1)
+    }
+
+    /// Retries until the operation succeeds.
+    pub fn forever() -> Self {
+        Self {
+            max_attempts: None,
+            ..Self::new()
+        }
+    }
+
+    /// Gives up after `attempts` in all, the first included.
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts.max(1));
+        self
+    }
+
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Varies each delay by up to `jitter` (0 to 1) of itself.
+    pub fn with_jitter(mut self, jitter: f32) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The attempts allowed in all; `None` for no limit.
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    /// Whether another attempt may follow `failures` failed ones.
+    pub fn allows(&self, failures: u32) -> bool {
+        self.max_attempts.is_none_or(|max| failures < max)
+    }
+
+    /// How long to wait after `failures` failed attempts (1 after the
+    /// first) before the next.
+    pub fn delay(&self, failures: u32) -> Duration {
+        let doublings = failures.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << doublings)
+            .min(self.max_delay);
+        let spread = (2.0 * random_unit() - 1.0) * self.jitter;
+        delay.mul_f32(1.0 + spread).min(self.max_delay)
+    }
+
+    /// Runs `operation` until it succeeds or the policy gives up, then
+    /// returns its last error.
+    pub async fn retry<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut failures = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    failures += 1;
+                    if !self.allows(failures) {
+                        return Err(e);
+                    }
+                    let delay = self.delay(failures);
+                    tracing::debug!("{e}; retrying in {delay:.1?}");
+                    crate::rt::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+/// A number in [0, 1) that differs between calls, which is all jitter
+/// needs.
+fn random_unit() -> f32 {
+    (RandomState::new().hash_one(std::time::Instant::now()) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new()
+            .with_base_delay(Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(5))
+            .with_jitter(0.0);
+        let delays: Vec<_> = (1..=5).map(|n| policy.delay(n).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(5));
+        let jittered = policy.with_jitter(0.5);
+        for _ in 0..100 {
+            let delay = jittered.delay(1).as_secs_f32();
+            assert!((0.5..=1.5).contains(&delay), "{delay}");
+        }
+        assert!(!RetryPolicy::never().allows(1));
+        assert!(RetryPolicy::forever().allows(u32::MAX));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry() {
+        let policy = RetryPolicy::new().with_max_attempts(3).with_jitter(0.0);
+        let mut calls = 0;
+        let result = policy
+            .retry(|| {
+                calls += 1;
+                let result = if calls < 3 {
+                    Err(Error::ReadTimeout)
+                } else {
+                    Ok(calls)
+                };
+                std::future::ready(result)
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+        calls = 0;
+        let failed: Result<()> = policy
+            .retry(|| {
+                calls += 1;
+                std::future::ready(Err(Error::ReadTimeout))
+            })
+            .await;
+        assert!(matches!(failed, Err(Error::ReadTimeout)));
+        assert_eq!(calls, 3);
+    }
+}
@@ -0,0 +1,39 @@
+//! The few runtime services the library needs outside of a transport.
+//!
+//! The timer goes with the runtime polling the meter, not with the
+//! features enabled: inside a tokio runtime it is always tokio's, so
+//! `tokio::time::pause` and friends apply. With the `async-io` feature
+//! it is async-io's anywhere else (smol, async-std or a plain
+//! `block_on`); without it, a tokio runtime is needed.
+
+use std::time::Duration;
+
+/// Whether to time with async-io's timer rather than tokio's.
+#[cfg(feature = "async-io")]
+fn outside_tokio() -> bool {
+    tokio::runtime::Handle::try_current().is_err()
+}
+
+/// Runs `future` to completion, or returns `None` if `duration` elapses
+/// first.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    #[cfg(feature = "async-io")]
+    if outside_tokio() {
+        return futures_lite::future::or(async { Some(future.await) }, async {
+            async_io::Timer::after(duration).await;
+            None
+        })
+        .await;
+    }
+    tokio::time::timeout(duration, future).await.ok()
+}
+
+/// Waits for `duration`.
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "async-io")]
+    if outside_tokio() {
+        async_io::Timer::after(duration).await;
+        return;
+    }
+    tokio::time::sleep(duration).await;
+}
@@ -0,0 +1,326 @@
+use std::time::{Duration, SystemTime};
+
+use crate::error::{Error, Result};
+use crate::{MeterEvent, Reading};
+
+/// A sign of life written while no readings arrive, so a consumer can
+/// tell a quiet meter from a dead logger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Heartbeat {
+    pub timestamp: SystemTime,
+    /// When the last reading arrived; `None` if none has yet.
+    pub last_reading: Option<SystemTime>,
+}
+
+impl Heartbeat {
+    pub fn new(timestamp: SystemTime, last_reading: Option<SystemTime>) -> Self {
+        Self {
+            timestamp,
+            last_reading,
+        }
+    }
+}
+
+/// A note marking a moment in the data, e.g. "opened chamber door",
+/// written between the readings around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Annotation {
+    pub timestamp: SystemTime,
+    pub note: String,
+}
+
+impl Annotation {
+    /// Control characters in `note`, such as newlines, become spaces so
+    /// it fits on one line of any log.
+    pub fn new(timestamp: SystemTime, note: &str) -> Self {
+        Self {
+            timestamp,
+            note: note.replace(char::is_control, " "),
+        }
+    }
+}
+
+/// A destination for readings, such as a log file or a network push.
+pub trait Sink {
+    fn write(&mut self, reading: &Reading) -> Result<()>;
+
+    /// Records that the link is alive but no reading has arrived for a
+    /// while. Sinks with nowhere to put one ignore it.
+    fn heartbeat(&mut self, _heartbeat: &Heartbeat) -> Result<()> {
+        Ok(())
+    }
+
+    /// Marks a moment in the data. Sinks with nowhere to put one
+    /// ignore it.
+    fn annotate(&mut self, _annotation: &Annotation) -> Result<()> {
+        Ok(())
+    }
+
+    /// Flushes whatever is buffered and closes the destination. Called
+    /// once, at the end of a session.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Fans each reading out to several sinks, each optionally limited to
+/// one reading per interval. A sink whose destination closes (a broken
+/// pipe) is dropped with a [`MeterEvent::SinkClosed`] while the others
+/// carry on; once all are closed, writes fail with
+/// [`Error::SinksClosed`].
+#[derive(Default)]
+pub struct Tee {
+    sinks: Vec<Throttled>,
+    events: Vec<MeterEvent>,
+}
+
+struct Throttled {
+    sink: Box<dyn Sink + Send>,
+    every: Option<Duration>,
+    last: Option<SystemTime>,
+    closed: bool,
+}
+
+impl Throttled {
+    /// True if `timestamp` is at least `every` after the last reading
+    /// written. A clock step backwards also counts as due.
+    fn due(&self, timestamp: SystemTime) -> bool {
+        match (self.every, self.last) {
+            (Some(every), Some(last)) => timestamp
+                .duration_since(last)
+                .map_or(true, |elapsed| elapsed >= every),
+            _ => true,
+        }
+    }
+}
+
+impl Tee {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sink that receives every reading.
+    pub fn with(self, sink: impl Sink + Send + 'static) -> Self {
+        self.with_every(sink, None)
+    }
+
+    /// Adds a sink that receives at most one reading per `every`, by
+    /// reading timestamp; `None` passes every reading.
+    pub fn with_every(mut self, sink: impl Sink + Send + 'static, every: Option<Duration>) -> Self {
+        self.sinks.push(Throttled {
+            sink: Box::new(sink),
+            every,
+            last: None,
+            closed: false,
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Returns the [`MeterEvent::SinkClosed`] events since the last call.
+    pub fn take_events(&mut self) -> Vec<MeterEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Drops sink `index` if `result` says its destination closed.
+    fn check(&mut self, index: usize, result: Result<()>) -> Result<()> {
+        match result {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                self.sinks[index].closed = true;
+                self.events.push(MeterEvent::SinkClosed {
+                    sink: index,
+                    timestamp: SystemTime::now(),
+                });
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Fails with [`Error::SinksClosed`] if there were sinks and all of
+    /// them have closed.
+    fn open(&self) -> Result<()> {
+        if !self.sinks.is_empty() && self.sinks.iter().all(|throttled| throttled.closed) {
+            Err(Error::SinksClosed)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Sink for Tee {
+    /// Writes to every sink that is due. One sink failing does not
+    /// starve the others; the first error is returned once all have
+    /// been tried.
+    fn write(&mut self, reading: &Reading) -> Result<()> {
+        let mut result = Ok(());
+        for index in 0..self.sinks.len() {
+            let throttled = &mut self.sinks[index];
+            if throttled.closed || !throttled.due(reading.timestamp) {
+                continue;
+            }
+            throttled.last = Some(reading.timestamp);
+            let written = throttled.sink.write(reading);
+            result = result.and(self.check(index, written));
+        }
+        result.and(self.open())
+    }
+
+    /// Passes the heartbeat to every sink, unthrottled.
+    fn heartbeat(&mut self, heartbeat: &Heartbeat) -> Result<()> {
+        let mut result = Ok(());
+        for index in 0..self.sinks.len() {
+            if self.sinks[index].closed {
+                continue;
+            }
+            let written = self.sinks[index].sink.heartbeat(heartbeat);
+            result = result.and(self.check(index, written));
+        }
+        result.and(self.open())
+    }
+
+    /// Passes the annotation to every sink, unthrottled.
+    fn annotate(&mut self, annotation: &Annotation) -> Result<()> {
+        let mut result = Ok(());
+        for index in 0..self.sinks.len() {
+            if self.sinks[index].closed {
+                continue;
+            }
+            let written = self.sinks[index].sink.annotate(annotation);
+            result = result.and(self.check(index, written));
+        }
+        result.and(self.open())
+    }
+
+    /// Finishes the sinks that are still open.
+    fn finish(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for index in 0..self.sinks.len() {
+            if self.sinks[index].closed {
+                continue;
+            }
+            let finished = self.sinks[index].sink.finish();
+            result = result.and(self.check(index, finished));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<f32>>>);
+
+    impl Sink for Recorder {
+        fn write(&mut self, reading: &Reading) -> Result<()> {
+            self.0.lock().unwrap().push(reading.current_temps_c[0]);
+            Ok(())
+        }
+
+        fn heartbeat(&mut self, _: &Heartbeat) -> Result<()> {
+            self.0.lock().unwrap().push(-1.0);
+            Ok(())
+        }
+
+        fn annotate(&mut self, annotation: &Annotation) -> Result<()> {
+            self.0.lock().unwrap().push(annotation.note.len() as f32);
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl Sink for Failing {
+        fn write(&mut self, _: &Reading) -> Result<()> {
+            Err(Error::Disconnected("test sink"))
+        }
+    }
+
+    struct Closed;
+
+    impl Sink for Closed {
+        fn write(&mut self, _: &Reading) -> Result<()> {
+            Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe).into())
+        }
+    }
+
+    fn reading_at(seconds: u64) -> Reading {
+        Reading::builder()
+            .timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .current_temp_c(0, seconds as f32)
+            .build()
+    }
+
+    #[test]
+    fn test_tee_throttles_each_sink() -> Result<()> {
+        let all = Recorder::default();
+        let slow = Recorder::default();
+        let mut tee = Tee::new()
+            .with(all.clone())
+            .with_every(slow.clone(), Some(Duration::from_secs(10)));
+        for seconds in 0..25 {
+            tee.write(&reading_at(seconds))?;
+        }
+        assert_eq!(all.0.lock().unwrap().len(), 25);
+        assert_eq!(*slow.0.lock().unwrap(), [0.0, 10.0, 20.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee_heartbeats_bypass_throttling() -> Result<()> {
+        let slow = Recorder::default();
+        let mut tee = Tee::new().with_every(slow.clone(), Some(Duration::from_secs(10)));
+        tee.write(&reading_at(0))?;
+        tee.heartbeat(&Heartbeat::new(SystemTime::UNIX_EPOCH, None))?;
+        tee.write(&reading_at(1))?;
+        assert_eq!(*slow.0.lock().unwrap(), [0.0, -1.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee_annotations_bypass_throttling() -> Result<()> {
+        let slow = Recorder::default();
+        let mut tee = Tee::new().with_every(slow.clone(), Some(Duration::from_secs(10)));
+        tee.write(&reading_at(0))?;
+        let annotation = Annotation::new(SystemTime::UNIX_EPOCH, "door\nopen");
+        assert_eq!(annotation.note, "door open");
+        tee.annotate(&annotation)?;
+        tee.write(&reading_at(1))?;
+        assert_eq!(*slow.0.lock().unwrap(), [0.0, 9.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee_error_does_not_starve_others() {
+        let recorder = Recorder::default();
+        let mut tee = Tee::new().with(Failing).with(recorder.clone());
+        assert!(tee.write(&reading_at(0)).is_err());
+        assert_eq!(recorder.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_tee_drops_closed_sinks() -> Result<()> {
+        let recorder = Recorder::default();
+        let mut tee = Tee::new().with(Closed).with(recorder.clone());
+        tee.write(&reading_at(0))?;
+        tee.write(&reading_at(1))?;
+        assert_eq!(recorder.0.lock().unwrap().len(), 2);
+        assert!(matches!(
+            tee.take_events()[..],
+            [MeterEvent::SinkClosed { sink: 0, .. }]
+        ));
+        assert!(tee.take_events().is_empty());
+
+        let mut tee = Tee::new().with(Closed);
+        assert!(matches!(tee.write(&reading_at(0)), Err(Error::SinksClosed)));
+        tee.finish()
+    }
+}
@@ -0,0 +1,174 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::Reading;
+use crate::error::{Error, Result};
+use crate::meter::Meter;
+use crate::transport::{StreamTransport, Transport};
+
+/// The future returned by [`MeterSource::next_reading`].
+pub type NextReading<'a> = Pin<Box<dyn Future<Output = Result<Reading>> + Send + 'a>>;
+
+/// Anything that yields readings: a [`Meter`] on any transport, canned
+/// readings in tests ([`ReadingSource`]), or an application's own
+/// source. Object safe, so code written against it can take a
+/// `Box<dyn MeterSource + Send>` and have the source chosen at run time.
+pub trait MeterSource {
+    /// Returns the next reading. A source that has run out reports
+    /// [`Error::Disconnected`].
+    fn next_reading(&mut self) -> NextReading<'_>;
+}
+
+impl<T: Transport + Send> MeterSource for Meter<T> {
+    fn next_reading(&mut self) -> NextReading<'_> {
+        Box::pin(self.read())
+    }
+}
+
+impl<S: MeterSource + ?Sized> MeterSource for Box<S> {
+    fn next_reading(&mut self) -> NextReading<'_> {
+        (**self).next_reading()
+    }
+}
+
+/// A [`MeterSource`] picked at run time, e.g. from a configuration
+/// string with [`open`](Self::open).
+pub struct DynMeter(Box<dyn MeterSource + Send>);
+
+impl DynMeter {
+    pub fn new(source: impl MeterSource + Send + 'static) -> Self {
+        Self(Box::new(source))
+    }
+
+    /// Opens the source `url` names:
+    ///
+    /// - `serial:PORT`, e.g. `serial:/dev/ttyUSB0` (feature `serial`)
+    /// - `ble:ADDRESS`, or `ble:` for the only meter discovered
+    ///   (feature `bluebus` or `btleplug`)
+    /// - `tcp:HOST:PORT`, the meter's byte stream from a serial server
+    ///   such as ser2net
+    /// - `file:PATH`, a raw capture, read as fast as it decodes
+    pub async fn open(url: &str) -> Result<Self> {
+        let invalid = || Error::InvalidSource(url.to_owned());
+        let (scheme, rest) = url.split_once(':').ok_or_else(invalid)?;
+        match scheme {
+            "serial" if !rest.is_empty() => Self::open_serial(rest).await,
+            "ble" => Self::open_ble(rest).await,
+            "tcp" if !rest.is_empty() => {
+                let stream = tokio::net::TcpStream::connect(rest).await?;
+                Ok(Self::new(Meter::new(StreamTransport::new(stream))))
+            }
+            "file" if !rest.is_empty() => {
+                let file = tokio::fs::File::open(rest).await?;
+                Ok(Self::new(Meter::new(StreamTransport::new(file))))
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    #[cfg(feature = "serial")]
+    async fn open_serial(port: &str) -> Result<Self> {
+        Ok(Self::new(Meter::open_serial(port).await?))
+    }
+
+    #[cfg(not(feature = "serial"))]
+    async fn open_serial(_port: &str) -> Result<Self> {
+        Err(Error::Unsupported("serial"))
+    }
+
+    #[cfg(any(feature = "bluebus", feature = "btleplug"))]
+    async fn open_ble(address: &str) -> Result<Self> {
+        let meter = match address {
+            "" => Meter::open_ble_only(std::time::Duration::from_secs(8)).await?,
+            address => Meter::open_ble(address).await?,
+        };
+        Ok(Self::new(meter))
+    }
+
+    #[cfg(not(any(feature = "bluebus", feature = "btleplug")))]
+    async fn open_ble(_address: &str) -> Result<Self> {
+        Err(Error::Unsupported("Bluetooth"))
+    }
+}
+
+impl MeterSource for DynMeter {
+    fn next_reading(&mut self) -> NextReading<'_> {
+        self.0.next_reading()
+    }
+}
+
+/// Serves readings from an iterator, e.g. canned ones standing in for a
+/// meter in tests; running out reads as a disconnect.
+pub struct ReadingSource<I> {
+    readings: I,
+}
+
+impl<I: Iterator<Item = Reading>> ReadingSource<I> {
+    pub fn new(readings: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            readings: readings.into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Reading> + Send> MeterSource for ReadingSource<I> {
+    fn next_reading(&mut self) -> NextReading<'_> {
+        let reading = self
+            .readings
+            .next()
+            .ok_or(Error::Disconnected("no more readings"));
+        Box::pin(std::future::ready(reading))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meter::tests::{meter_with, valid_frame};
+
+    async fn temps(source: &mut dyn MeterSource) -> Vec<f32> {
+        let mut temps = Vec::new();
+        while let Ok(reading) = source.next_reading().await {
+            temps.push(reading.current_temps_c[0]);
+        }
+        temps
+    }
+
+    #[tokio::test]
+    async fn test_open_url() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("ut325f-source-{}.raw", std::process::id()));
+        std::fs::write(&path, valid_frame().repeat(2))?;
+        let mut meter = DynMeter::open(&format!("file:{}", path.display())).await?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(temps(&mut meter).await, [0.0, 0.0]);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("tcp:{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            tokio::io::AsyncWriteExt::write_all(&mut stream, &valid_frame()).await
+        });
+        let mut meter = DynMeter::open(&url).await?;
+        server.await.unwrap()?;
+        assert_eq!(temps(&mut meter).await, [0.0]);
+
+        for url in ["/dev/ttyUSB0", "tcp:", "modbus:1", "serial:"] {
+            assert!(matches!(
+                DynMeter::open(url).await.err(),
+                Some(Error::InvalidSource(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sources_are_interchangeable() {
+        let canned = (0..3).map(|i| Reading::builder().current_temp_c(0, i as f32).build());
+        let mut sources: Vec<Box<dyn MeterSource + Send>> = vec![
+            Box::new(ReadingSource::new(canned)),
+            Box::new(meter_with(vec![valid_frame().to_vec()])),
+        ];
+        assert_eq!(temps(&mut sources[0]).await, [0.0, 1.0, 2.0]);
+        assert_eq!(temps(&mut sources[1]).await, [0.0]);
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// A thermocouple type the meter can be set to measure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermocoupleType {
+    K,
+    J,
+    T,
+    E,
+    R,
+    S,
+    N,
+}
+
+impl ThermocoupleType {
+    pub const ALL: [Self; 7] = [
+        Self::K,
+        Self::J,
+        Self::T,
+        Self::E,
+        Self::R,
+        Self::S,
+        Self::N,
+    ];
+
+    /// The temperatures the type is defined for, in °C, per the
+    /// reference tables of IEC 60584-1. A reading outside them cannot
+    /// come from a working thermocouple of the type.
+    pub fn range_c(self) -> (f32, f32) {
+        match self {
+            Self::K => (-270.0, 1372.0),
+            Self::J => (-210.0, 1200.0),
+            Self::T => (-270.0, 400.0),
+            Self::E => (-270.0, 1000.0),
+            Self::R | Self::S => (-50.0, 1768.1),
+            Self::N => (-270.0, 1300.0),
+        }
+    }
+
+    /// Whether `temp_c` lies within [`range_c`](Self::range_c).
+    pub fn contains(self, temp_c: f32) -> bool {
+        let (min, max) = self.range_c();
+        (min..=max).contains(&temp_c)
+    }
+}
+
+impl FromStr for ThermocoupleType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| Error::InvalidThermocoupleType(s.to_owned()))
+    }
+}
+
+impl fmt::Display for ThermocoupleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_range() {
+        assert_eq!(
+            "k".parse::<ThermocoupleType>().ok(),
+            Some(ThermocoupleType::K)
+        );
+        assert_eq!(ThermocoupleType::T.to_string(), "T");
+        assert!("X".parse::<ThermocoupleType>().is_err());
+        assert!(ThermocoupleType::K.contains(1000.0));
+        assert!(!ThermocoupleType::T.contains(500.0));
+        assert!(!ThermocoupleType::S.contains(-100.0));
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::os::fd::{FromRawFd, IntoRawFd};
+use std::time::{Duration, Instant};
+
+use async_io::Async;
+use futures_lite::AsyncReadExt;
+
+use super::Transport;
+use super::serial_config::{OPEN_RETRY, SerialConfig};
+use crate::error::{Error, Result};
+
+/// Transport over the meter's USB serial interface for executors other
+/// than tokio (smol, async-std), driven by async-io's reactor.
+pub struct AsyncIoSerialTransport {
+    serial: Async<File>,
+}
+
+impl AsyncIoSerialTransport {
+    pub async fn open(port: &str) -> Result<Self> {
+        Self::open_with(port, &SerialConfig::default()).await
+    }
+
+    /// Opens `port` with other line settings than the meter's; see
+    /// [`SerialTransport::open_with`](crate::SerialTransport::open_with).
+    pub async fn open_with(port: &str, config: &SerialConfig) -> Result<Self> {
+        let builder = serialport::new(port, config.baud_rate)
+            .data_bits(serialport::DataBits::Eight)
+            .parity(config.parity)
+            .stop_bits(config.stop_bits)
+            .flow_control(serialport::FlowControl::None)
+            .timeout(Duration::from_secs(1));
+        let deadline = Instant::now() + config.open_timeout;
+        let tty = loop {
+            match builder.clone().open_native() {
+                Ok(tty) => break tty,
+                Err(e) if Instant::now() < deadline => {
+                    tracing::debug!("{port}: {e}; retrying");
+                    crate::rt::sleep(OPEN_RETRY).await;
+                }
+                Err(e) => {
+                    return Err(Error::SerialOpen {
+                        port: port.to_owned(),
+                        source: e,
+                    });
+                }
+            }
+        };
+        // SAFETY: the descriptor is open, having just come from a
+        // successful open, and into_raw_fd consumes the port, so the
+        // File becomes its sole owner and the only one to close it.
+        let file = unsafe { File::from_raw_fd(tty.into_raw_fd()) };
+        Ok(Self {
+            serial: Async::new(file)?,
+        })
+    }
+}
+
+impl Transport for AsyncIoSerialTransport {
+    async fn recv(&mut self) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; 256];
+        let n = self.serial.read(&mut buf).await?;
+        if n == 0 {
+            return Err(Error::Disconnected("serial port closed"));
+        }
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_with_retries_until_timeout() {
+        let config = SerialConfig::new().with_open_timeout(Duration::from_millis(300));
+        let started = Instant::now();
+        let opened = futures_lite::future::block_on(AsyncIoSerialTransport::open_with(
+            "/dev/ut325f-missing",
+            &config,
+        ));
+        assert!(matches!(opened, Err(Error::SerialOpen { .. })));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+}
@@ -1,18 +1,28 @@
 use crate::error::Result;
 
+#[cfg(all(feature = "serial-async-io", unix))]
+mod async_io_serial;
 #[cfg(feature = "bluebus")]
 mod bluebus;
 #[cfg(feature = "btleplug")]
 mod btleplug;
 #[cfg(feature = "serial")]
 mod serial;
+#[cfg(any(feature = "serial", feature = "serial-async-io"))]
+mod serial_config;
+mod stream;
 
+#[cfg(all(feature = "serial-async-io", unix))]
+pub use async_io_serial::AsyncIoSerialTransport;
 #[cfg(feature = "bluebus")]
 pub use bluebus::BluebusTransport;
 #[cfg(feature = "btleplug")]
 pub use btleplug::BtleplugTransport;
 #[cfg(feature = "serial")]
 pub use serial::SerialTransport;
+#[cfg(any(feature = "serial", feature = "serial-async-io"))]
+pub use serial_config::{Parity, SerialConfig, StopBits};
+pub use stream::{END_OF_INPUT, StreamTransport};
 
 /// UUID of the meter's BLE UART bridge "Data Out" characteristic. The
 /// meter streams its readings here as GATT notifications, one frame per
@@ -27,6 +37,7 @@ pub const METER_NAME_PREFIX: &str = "UT325F";
 /// A meter found by a BLE backend's `discover`.
 #[cfg(any(feature = "bluebus", feature = "btleplug"))]
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct DiscoveredMeter {
     /// Bluetooth address, suitable for [`Meter::open_ble`](crate::Meter).
     pub address: String,
@@ -120,6 +131,13 @@ pub type BleTransport = BtleplugTransport;
 /// [`FrameDecoder`](crate::FrameDecoder).
 pub trait Transport {
     /// Receives the next non-empty chunk of bytes from the meter.
+    ///
+    /// Must be cancel safe: if the future is dropped before it
+    /// completes, no bytes may be lost, since [`Meter::read`] passes
+    /// that guarantee on to callers using it in `select!` or under a
+    /// timeout.
+    ///
+    /// [`Meter::read`]: crate::Meter::read
     fn recv(&mut self) -> impl Future<Output = Result<Vec<u8>>> + Send;
 
     /// Gracefully shuts the transport down, releasing what it holds
@@ -1,34 +1,128 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::io::AsyncReadExt;
 use tokio_serial::{SerialPortBuilderExt, SerialStream};
 
 use super::Transport;
+use super::serial_config::{OPEN_RETRY, SerialConfig};
+use crate::Reading;
 use crate::error::{Error, Result};
 
+/// How long [`SerialTransport::open_auto_baud`] listens at each rate;
+/// the meter sends about 3 frames a second.
+const AUTO_BAUD_PROBE: Duration = Duration::from_millis(1500);
+
 /// Transport over the meter's USB serial interface.
 pub struct SerialTransport {
     serial: SerialStream,
+    /// Bytes read while detecting the baud rate, handed out by the
+    /// first `recv`.
+    pending: Vec<u8>,
 }
 
 impl SerialTransport {
+    /// The meter's baud rate.
+    pub const BAUD_RATE: u32 = super::serial_config::BAUD_RATE;
+    /// The rates [`open_auto_baud`](Self::open_auto_baud) is usually
+    /// given: the meter's own first, then those clones and other
+    /// firmware are likeliest to use.
+    pub const AUTO_BAUD_RATES: [u32; 6] = [115200, 9600, 19200, 38400, 57600, 230400];
+
     pub async fn open(port: &str) -> Result<Self> {
-        let builder = tokio_serial::new(port, 115200)
+        Self::open_with(port, &SerialConfig::default()).await
+    }
+
+    /// Opens `port` with other line settings than the meter's.
+    pub async fn open_with(port: &str, config: &SerialConfig) -> Result<Self> {
+        let builder = tokio_serial::new(port, config.baud_rate)
             .data_bits(tokio_serial::DataBits::Eight)
-            .parity(tokio_serial::Parity::None)
-            .stop_bits(tokio_serial::StopBits::One)
+            .parity(config.parity)
+            .stop_bits(config.stop_bits)
             .flow_control(tokio_serial::FlowControl::None)
             .timeout(Duration::from_secs(1));
 
-        let serial = builder.open_native_async().map_err(|e| Error::SerialOpen {
+        let deadline = Instant::now() + config.open_timeout;
+        loop {
+            match builder.clone().open_native_async() {
+                Ok(serial) => {
+                    return Ok(Self {
+                        serial,
+                        pending: Vec::new(),
+                    });
+                }
+                Err(e) if Instant::now() < deadline => {
+                    tracing::debug!("{port}: {e}; retrying");
+                    tokio::time::sleep(OPEN_RETRY).await;
+                }
+                Err(e) => {
+                    return Err(Error::SerialOpen {
+                        port: port.to_owned(),
+                        source: e,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Opens `port` at each of `rates` in turn until a valid frame
+    /// arrives, for clones and firmware that do not run at 115200 baud,
+    /// and returns the transport with the rate found and the frame's
+    /// header. Any header with the usual `aa 55 00 34` start counts,
+    /// whatever its command byte; build the meter with
+    /// [`Meter::with_sync`](crate::Meter::with_sync) and the header
+    /// returned to read frames like the one found. The bytes read while
+    /// listening are not lost: the first `recv` returns them.
+    pub async fn open_auto_baud(
+        port: &str,
+        config: &SerialConfig,
+        rates: &[u32],
+    ) -> Result<(Self, u32, [u8; Reading::N_SYNC_BYTES])> {
+        for &rate in rates {
+            let config = config.clone().with_baud_rate(rate);
+            let mut transport = Self::open_with(port, &config).await?;
+            let mut received = Vec::new();
+            let found = crate::rt::timeout(AUTO_BAUD_PROBE, async {
+                loop {
+                    if let Some(sync) = find_sync(&received) {
+                        return Ok::<_, Error>(sync);
+                    }
+                    received.extend(transport.recv().await?);
+                }
+            })
+            .await;
+            match found {
+                Some(Ok(sync)) => {
+                    transport.pending = received;
+                    return Ok((transport, rate, sync));
+                }
+                Some(Err(e)) => return Err(e),
+                None => tracing::debug!("no frame at {rate} baud"),
+            }
+        }
+        Err(Error::NoBaudRate {
             port: port.to_owned(),
-            source: e,
-        })?;
-        Ok(Self { serial })
+            rates: rates.to_vec(),
+        })
     }
 }
 
+/// The header of the first checksum-valid frame in `bytes` with any
+/// command byte.
+fn find_sync(bytes: &[u8]) -> Option<[u8; Reading::N_SYNC_BYTES]> {
+    bytes
+        .windows(Reading::N_BYTES)
+        .filter_map(|window| window.first_chunk::<{ Reading::N_BYTES }>())
+        .find_map(|frame| {
+            let sync = frame.first_chunk::<{ Reading::N_SYNC_BYTES }>().unwrap();
+            (sync[..4] == Reading::SYNC[..4] && Reading::validate_frame_with_sync(frame, sync))
+                .then_some(*sync)
+        })
+}
+
 impl Transport for SerialTransport {
     async fn recv(&mut self) -> Result<Vec<u8>> {
+        if !self.pending.is_empty() {
+            return Ok(std::mem::take(&mut self.pending));
+        }
         let mut buf = vec![0u8; 256];
         let n = self.serial.read(&mut buf).await?;
         if n == 0 {
@@ -38,3 +132,22 @@ impl Transport for SerialTransport {
         Ok(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_sync() {
+        let mut frame = Reading::builder().build().to_frame();
+        let mut bytes = vec![0x12; 7];
+        bytes.extend_from_slice(&frame);
+        assert_eq!(find_sync(&bytes), Some(Reading::SYNC));
+        assert_eq!(find_sync(&bytes[..bytes.len() - 1]), None);
+        frame[4] = 0x02;
+        Reading::write_checksum(&mut frame);
+        assert_eq!(find_sync(&frame), Some([0xaa, 0x55, 0x00, 0x34, 0x02]));
+        frame[20] ^= 0x01;
+        assert_eq!(find_sync(&frame), None);
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+pub use serialport::{Parity, StopBits};
+
+/// The meter's baud rate.
+pub(super) const BAUD_RATE: u32 = 115200;
+
+/// How often a port that fails to open is retried within the open
+/// timeout.
+pub(super) const OPEN_RETRY: Duration = Duration::from_millis(250);
+
+/// Serial line settings. The default is what the meter uses: 115200
+/// baud, 8N1, giving up at once if the port does not open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialConfig {
+    pub(super) baud_rate: u32,
+    pub(super) parity: Parity,
+    pub(super) stop_bits: StopBits,
+    pub(super) open_timeout: Duration,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: BAUD_RATE,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            open_timeout: Duration::ZERO,
+        }
+    }
+}
+
+impl SerialConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    pub fn with_parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    pub fn with_stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Keeps retrying a port that fails to open (e.g. one still being
+    /// enumerated after the adapter is plugged in) for up to `timeout`.
+    pub fn with_open_timeout(mut self, timeout: Duration) -> Self {
+        self.open_timeout = timeout;
+        self
+    }
+
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+}
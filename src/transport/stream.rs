@@ -0,0 +1,37 @@
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::Transport;
+use crate::error::{Error, Result};
+
+/// The [`Error::Disconnected`] reason a [`StreamTransport`] gives at
+/// end of input.
+pub const END_OF_INPUT: &str = "end of input";
+
+/// Reads the meter's byte stream from any tokio reader: a TCP
+/// connection to a serial server such as ser2net, a pipe, or a raw
+/// capture file. End of input reads as a disconnect.
+pub struct StreamTransport<R> {
+    reader: R,
+}
+
+impl<R> StreamTransport<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send> Transport for StreamTransport<R> {
+    async fn recv(&mut self) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; 256];
+        let n = self.reader.read(&mut buf).await?;
+        if n == 0 {
+            return Err(Error::Disconnected(END_OF_INPUT));
+        }
+        buf.truncate(n);
+        Ok(buf)
+    }
+}